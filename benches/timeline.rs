@@ -0,0 +1,115 @@
+//! Benchmarks for the hot paths of drawing a timeline: fetching (and
+//! slicing) tiles, and laying out a frame, which culls items to the view.
+//! Profiles are generated with RandomDataSource at a few densities.
+//!
+//! Run with `cargo bench`, or e.g. `cargo bench -- frame` for one group.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use egui::Vec2;
+
+use legion_prof_viewer::app::Headless;
+use legion_prof_viewer::data::{DataSource, EntryID, EntryInfo, TileID};
+use legion_prof_viewer::synthetic::{RandomDataSource, RandomOptions};
+use legion_prof_viewer::timestamp::Interval;
+
+// Items per row
+const DENSITIES: &[u64] = &[100, 1_000, 10_000];
+
+fn options(items_per_row: u64) -> RandomOptions {
+    RandomOptions {
+        nodes: 2,
+        procs_per_kind: 2,
+        max_rows: 8,
+        items_per_row,
+        ..Default::default()
+    }
+}
+
+// The first slot with any rows
+fn first_slot(data_source: &mut RandomDataSource) -> EntryID {
+    let info = data_source.fetch_info().unwrap();
+    info.data_entries()
+        .into_iter()
+        .find(|(_, entry)| matches!(entry, EntryInfo::Slot { max_rows, .. } if *max_rows > 0))
+        .map(|(entry_id, _)| entry_id)
+        .expect("profile has a slot")
+}
+
+// Part of the interval, from the start
+fn fraction(interval: Interval, fraction: f64) -> Interval {
+    Interval::new(interval.start, interval.lerp(fraction))
+}
+
+fn fetch_slot_tile(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fetch_slot_tile");
+    for &density in DENSITIES {
+        let mut data_source = RandomDataSource::new(options(density));
+        let entry_id = first_slot(&mut data_source);
+        let interval = data_source.interval().unwrap();
+        // Generate the slot up front, so only fetching is measured
+        data_source
+            .fetch_slot_tile(&entry_id, TileID(interval))
+            .unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(density), &density, |b, _| {
+            b.iter(|| {
+                for tile_id in data_source.request_tiles(&entry_id, interval).unwrap() {
+                    data_source.fetch_slot_tile(&entry_id, tile_id).unwrap();
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+// Narrow tiles mean most items are skipped and the rest sliced at the edges
+fn slice_tiles(c: &mut Criterion) {
+    let mut group = c.benchmark_group("slice_tiles");
+    let mut data_source = RandomDataSource::new(options(10_000));
+    let entry_id = first_slot(&mut data_source);
+    let interval = data_source.interval().unwrap();
+    for &width in &[1.0, 0.1, 0.01] {
+        let tile_id = TileID(fraction(interval, width));
+        data_source.fetch_slot_tile(&entry_id, tile_id).unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(width), &width, |b, _| {
+            b.iter(|| data_source.fetch_slot_tile(&entry_id, tile_id).unwrap())
+        });
+    }
+    group.finish();
+}
+
+// Laying out a frame (with everything already fetched) culls items outside
+// the view and merges the ones too small to see
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    for &density in DENSITIES {
+        let data_source = RandomDataSource::new(options(density));
+        let mut headless = Headless::new(Box::new(data_source), Vec2::new(1600.0, 900.0)).unwrap();
+        headless.expand_all(true);
+        let interval = headless.view_interval();
+        for &(name, width) in &[("all", 1.0), ("zoomed", 0.01)] {
+            headless.set_view_interval(fraction(interval, width));
+            headless.run(); // fetch
+
+            let id = BenchmarkId::new(name, density);
+            group.bench_with_input(id, &density, |b, _| b.iter(|| headless.run()));
+        }
+    }
+    group.finish();
+}
+
+// Same as above, plus tessellating and rasterizing the result
+fn render(c: &mut Criterion) {
+    let data_source = RandomDataSource::new(options(1_000));
+    let mut headless = Headless::new(Box::new(data_source), Vec2::new(1600.0, 900.0)).unwrap();
+    headless.expand_all(true);
+    let interval = headless.view_interval();
+    headless.set_view_interval(fraction(interval, 0.1));
+    headless.render();
+
+    c.bench_function("render", |b| b.iter(|| headless.render()));
+}
+
+criterion_group!(benches, fetch_slot_tile, slice_tiles, frame, render);
+criterion_main!(benches);
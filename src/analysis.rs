@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::data::{DataSource, DataSourceResult, EntryID, Item, ItemLinkKind, ItemUID};
+use crate::timestamp::Interval;
+
+#[derive(Debug, Clone)]
+pub struct PathItem {
+    pub item_uid: ItemUID,
+    pub entry_id: EntryID,
+    pub row: u64,
+    pub interval: Interval,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CriticalPath {
+    pub items: Vec<PathItem>, // in order of execution
+    uids: BTreeSet<ItemUID>,
+}
+
+impl CriticalPath {
+    pub fn contains(&self, item_uid: ItemUID) -> bool {
+        self.uids.contains(&item_uid)
+    }
+
+    // Total span of the path, from the start of the first item to the end
+    // of the last
+    pub fn interval(&self) -> Option<Interval> {
+        let first = self.items.first()?;
+        let last = self.items.last()?;
+        Some(first.interval.union(last.interval))
+    }
+
+    // Time spent executing items on the path (as opposed to waiting in
+    // between them)
+    pub fn busy_ns(&self) -> i64 {
+        self.items.iter().map(|i| i.interval.duration_ns()).sum()
+    }
+}
+
+// Summary of all items sharing a name, similar to legion_prof's statistics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameStats {
+    pub name: String,
+    pub count: u64,
+    pub total_ns: i64,
+    pub max_ns: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsColumn {
+    Name,
+    Count,
+    Total,
+    Mean,
+    Max,
+}
+
+impl NameStats {
+    pub fn mean_ns(&self) -> i64 {
+        self.total_ns / self.count.max(1) as i64
+    }
+}
+
+// Groups items by name (i.e., title). Each item should appear once, with
+// its full interval. Sorted by total time, largest first.
+pub fn name_stats<'a>(items: impl IntoIterator<Item = &'a Item>) -> Vec<NameStats> {
+    let mut by_name: BTreeMap<&str, NameStats> = BTreeMap::new();
+    for item in items {
+        let duration = item.interval.duration_ns();
+        let stats = by_name.entry(&item.title).or_insert_with(|| NameStats {
+            name: item.title.clone(),
+            count: 0,
+            total_ns: 0,
+            max_ns: 0,
+        });
+        stats.count += 1;
+        stats.total_ns += duration;
+        stats.max_ns = stats.max_ns.max(duration);
+    }
+    let mut result: Vec<_> = by_name.into_values().collect();
+    sort_stats(&mut result, StatsColumn::Total, true);
+    result
+}
+
+pub fn sort_stats(stats: &mut [NameStats], column: StatsColumn, descending: bool) {
+    match column {
+        StatsColumn::Name => stats.sort_by(|a, b| a.name.cmp(&b.name)),
+        StatsColumn::Count => stats.sort_by_key(|s| s.count),
+        StatsColumn::Total => stats.sort_by_key(|s| s.total_ns),
+        StatsColumn::Mean => stats.sort_by_key(|s| s.mean_ns()),
+        StatsColumn::Max => stats.sort_by_key(|s| s.max_ns),
+    }
+    if descending {
+        stats.reverse();
+    }
+}
+
+// Total time covered by the intervals, counting overlapping parts once
+pub fn covered_ns(intervals: &mut [Interval]) -> i64 {
+    intervals.sort_by_key(|i| i.start);
+    let mut total = 0;
+    let mut current: Option<Interval> = None;
+    for interval in intervals.iter() {
+        match &mut current {
+            Some(c) if interval.start <= c.stop => c.stop = c.stop.max(interval.stop),
+            _ => {
+                total += current.map_or(0, |c| c.duration_ns());
+                current = Some(*interval);
+            }
+        }
+    }
+    total + current.map_or(0, |c| c.duration_ns())
+}
+
+// Computes the critical path ending at the given item, by repeatedly
+// walking back to the predecessor that finished last (i.e., the one that
+// actually gated the start of its successor). Predecessors entirely
+// outside of the interval are ignored.
+pub fn critical_path(
+    data_source: &mut dyn DataSource,
+    interval: Interval,
+    end: PathItem,
+) -> DataSourceResult<CriticalPath> {
+    let mut result = CriticalPath::default();
+    let mut current = Some(end);
+    while let Some(item) = current.take() {
+        // Guard against cycles in malformed dependency data
+        if !result.uids.insert(item.item_uid) {
+            break;
+        }
+
+        current = data_source
+            .fetch_item_links(item.item_uid)?
+            .into_iter()
+            .filter(|link| link.kind == ItemLinkKind::Predecessor)
+            .filter(|link| interval.overlaps(link.interval))
+            .max_by_key(|link| link.interval.stop)
+            .map(|link| PathItem {
+                item_uid: link.item_uid,
+                entry_id: link.entry_id,
+                row: link.row,
+                interval: link.interval,
+                title: link.title,
+            });
+
+        result.items.push(item);
+    }
+    result.items.reverse();
+    Ok(result)
+}
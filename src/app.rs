@@ -1,17 +1,54 @@
 use egui::{Align2, Color32, NumExt, Pos2, Rect, ScrollArea, Slider, Stroke, TextStyle, Vec2};
 use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
-
-use crate::data::{DataSource, EntryID, EntryInfo, Field, SlotTile, UtilPoint};
-use crate::timestamp::Interval;
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+use crate::analysis::{self, CriticalPath, NameStats, PathItem, StatsColumn};
+use crate::console::{self, ConsoleLayer};
+use crate::data::{
+    CopyEndpoints, CounterStyle, CounterTile, DataSource, DataSourceError, DataSourceResult,
+    Description, EntryID, EntryInfo, Event, EventTile, Field, Item, ItemLink, ItemLinkKind,
+    ItemState, ItemUID, SearchResult, SlotTile, TileID, UtilPoint, UtilSeries, Warning,
+};
+#[cfg(target_arch = "wasm32")]
+use crate::deep_link::DeepLink;
+use crate::export;
+use crate::filter::{self, Filter, FilterMode, Op, Query, Subject, Value};
+use crate::loader;
+use crate::node_set::NodeSet;
+use crate::render::{Image, Rasterizer};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::settings;
+use crate::svg::{SvgOptions, SvgWriter, TextAnchor};
+use crate::theme::{Appearance, ColorOverrides, Palette, Theme};
+use crate::timestamp::{Interval, TimeFormat, TimeUnit, Timestamp};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::watch;
+
+// A scope for the puffin profiler (see the Debug menu), for profiling the
+// viewer itself. Without the "puffin" feature, this is nothing at all.
+macro_rules! profile_scope {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "puffin")]
+        puffin::profile_scope!($($arg)*);
+    };
+}
 
 /// Overview:
 ///   ProfApp -> Context, Window *
 ///   Window -> Config, Panel
 ///   Panel -> Summary, { Panel | Slot } *
 ///   Summary
-///   Slot -> Item *
+///   Slot -> Item * | Counter | Event *
 ///
 /// Context:
 ///   * Global configuration state (i.e., for all profiles)
@@ -30,17 +67,133 @@ use crate::timestamp::Interval;
 ///   * Each row contains: label, content
 ///
 /// Summary:
-///   * Utilization widget
+///   * Utilization widget (optionally stacked by kind)
 ///
 /// Slot:
 ///   * One Slot for each processor, channel, memory
 ///   * Viewer widget for items
+///
+/// Counter:
+///   * Line or bar track of arbitrary values (drawn by a Slot)
+///
+/// Events:
+///   * Thin track of instantaneous events (drawn by a Slot)
+///
+/// FlameGraph:
+///   * Alternate layout of a Slot's items, stacked by nesting depth
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    ResetView,
+    ExpandAll,
+    CollapseAll,
+    Search,
+    ToggleHelp,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Trigger {
+    Key(egui::Modifiers, egui::Key),
+    // Matched on the typed character, so it works on any keyboard layout
+    Text(&'static str),
+}
+
+struct Keybinding {
+    trigger: Trigger,
+    action: Action,
+    name: &'static str, // as shown in the help overlay
+}
+
+const fn key(key: egui::Key, action: Action, name: &'static str) -> Keybinding {
+    Keybinding {
+        trigger: Trigger::Key(egui::Modifiers::NONE, key),
+        action,
+        name,
+    }
+}
+
+const fn text(text: &'static str, action: Action, name: &'static str) -> Keybinding {
+    Keybinding {
+        trigger: Trigger::Text(text),
+        action,
+        name,
+    }
+}
+
+// All keyboard shortcuts, in the order shown in the help overlay
+const KEYBINDINGS: &[Keybinding] = &[
+    key(egui::Key::ArrowLeft, Action::PanLeft, "Left"),
+    key(egui::Key::ArrowRight, Action::PanRight, "Right"),
+    text("+", Action::ZoomIn, "+"),
+    text("=", Action::ZoomIn, "="),
+    text("-", Action::ZoomOut, "-"),
+    key(egui::Key::Num0, Action::ResetView, "0"),
+    key(egui::Key::Home, Action::ResetView, "Home"),
+    key(egui::Key::E, Action::ExpandAll, "E"),
+    key(egui::Key::C, Action::CollapseAll, "C"),
+    Keybinding {
+        trigger: Trigger::Key(egui::Modifiers::COMMAND, egui::Key::F),
+        action: Action::Search,
+        name: "Ctrl+F",
+    },
+    key(egui::Key::F1, Action::ToggleHelp, "F1"),
+    text("?", Action::ToggleHelp, "?"),
+];
+
+// Colors used for SVG export, which always uses a light theme
+const SVG_SLOT_FILL: Color32 = Color32::from_gray(248);
+const SVG_SLOT_STROKE: Color32 = Color32::from_gray(200);
+const SVG_TEXT: Color32 = Color32::BLACK;
+
+// Opacity of the min-max band drawn around summary utilization
+const BAND_OPACITY: f32 = 0.25;
+
+// Items that are filtered out (or off the critical path) are drawn faded
+const DIM_FACTOR: f32 = 0.2;
+
+// The shortest view, in ns, so that zooming all the way in still leaves
+// room to tell items apart (and never divides by zero)
+const MIN_VIEW_NS: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+enum ColorMode {
+    #[default]
+    Data, // whatever the data source says
+    Duration,
+}
+
+// Color ramps for ColorMode::Duration, from short to long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+enum ColorRamp {
+    #[default]
+    Viridis,
+    Inferno,
+    BlueRed,
+    Grayscale,
+}
+
+// What nodes (and the slots of each kind) are sorted by, in the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SortKey {
+    #[default]
+    Original, // as given by the data source
+    BusyTime,
+    PeakUtilization,
+}
 
 struct Summary {
     entry_id: EntryID,
     color: Color32,
+    layers: Vec<(String, Color32)>, // only for stacked summaries
     utilization: Vec<UtilPoint>,
+    series: Vec<UtilSeries>,
+    pending_tiles: Option<VecDeque<TileID>>, // None until requested
     last_view_interval: Option<Interval>,
+    error: Option<DataSourceError>, // from the last fetch
 }
 
 struct Slot {
@@ -50,7 +203,76 @@ struct Slot {
     expanded: bool,
     max_rows: u64,
     tiles: Vec<SlotTile>,
+    counter: Option<Counter>,     // drawn instead of items, if present
+    events: Option<Events>,       // likewise
+    flame: Option<FlameGraph>,    // items are laid out by depth, if present
+    memory: Option<MemoryLayout>, // or by address, likewise
+    density: Vec<UtilPoint>,      // fetched instead of items while collapsed
+    fetched_expanded: bool,       // whether the tiles fetched are for items
+    pending_tiles: Option<VecDeque<TileID>>, // None until requested
     last_view_interval: Option<Interval>,
+    error: Option<DataSourceError>, // from the last fetch
+}
+
+// Instantaneous events, drawn as flags
+struct Events {
+    color: Color32,
+    global: bool,
+    tiles: Vec<EventTile>,
+}
+
+// Items laid out by nesting depth instead of rows, so that each item sits
+// on top of the one enclosing it (as with call stacks). Rebuilt whenever
+// more tiles arrive.
+#[derive(Default)]
+struct FlameGraph {
+    items: Vec<(u64, Item)>, // (depth, item), sorted by start time
+    depth: u64,
+    tile_count: usize,
+}
+
+// Items that are allocations (instances in a memory), laid out by address
+// from the bottom up instead of by row, each as tall as its size. Rebuilt
+// whenever more tiles arrive.
+#[derive(Default)]
+struct MemoryLayout {
+    items: Vec<(u64, Item)>, // (row, item), sorted by start time
+    capacity: u64,           // highest address in use, as far as is loaded
+    tile_count: usize,
+}
+
+// Values over time in some unit, auto-scaled to the values in view
+struct Counter {
+    unit: String,
+    color: Color32,
+    style: CounterStyle,
+    tiles: Vec<CounterTile>,
+}
+
+// Items of a slot, painted as one mesh rather than a shape each, since
+// dense slots have thousands of them. Outlines and labels go on top.
+#[derive(Default)]
+struct ItemMesh {
+    mesh: egui::Mesh,
+    outlines: Vec<(Rect, Stroke)>,
+    labels: Vec<(Rect, String, Color32)>, // clipped to the rect
+}
+
+// A run of adjacent sub-pixel items in a row, drawn as one density block
+struct ItemBlock {
+    rect: Rect,
+    interval: Interval,
+    count: u64,
+    busy: f32,
+    color: [f32; 4],
+    highlight: bool,
+}
+
+// Right edges of the items placed so far in a row, to find where the next
+// one overlaps them (e.g., in an over-packed row) and how deeply
+#[derive(Default)]
+struct RowOverlap {
+    ends: Vec<f32>,
 }
 
 struct Panel<S: Entry> {
@@ -61,24 +283,303 @@ struct Panel<S: Entry> {
 
     summary: Option<Summary>,
     slots: Vec<S>,
+    order: Vec<usize>, // indices of slots, in the order drawn (empty if unsorted)
+}
+
+// Distribution of item durations, either in the slot of the selected item
+// or with the same name as it. The brush is a range of durations that gets
+// highlighted in the timeline.
+#[derive(Default)]
+struct Histogram {
+    by_name: bool,
+    log_scale: bool,
+    brush: Option<(i64, i64)>,
+    brush_start: Option<f32>, // while dragging, as a fraction of the width
+}
+
+// Summary of what's inside the drag selection, for the side panel
+struct SelectionStats {
+    interval: Interval, // in this profile's time
+    tile_count: usize,  // number of tiles loaded when computed
+    items: u64,
+    busy_ns: i64,
+    kinds: Vec<(String, f32)>, // average utilization of each kind
+}
+
+// One of the longest items in view, for the statistics panel
+struct LongItem {
+    item_uid: ItemUID,
+    entry_id: EntryID,
+    title: String,
+    interval: Interval,
+}
+
+// A note left by the user, at a point in time (if the interval is empty)
+// or over an interval. Attached to a slot if it has an entry_id, otherwise
+// it spans the whole profile.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+struct Annotation {
+    text: String,
+    interval: Interval,
+    entry_id: Option<EntryID>,
+}
+
+struct Selection {
+    entry_id: EntryID,
+    row: u64,
+    item: Item,
+    links: Vec<ItemLink>,
+    graph: Option<DependencyGraph>, // fetched when the graph is first shown
+}
+
+// Items reachable from the selection by following links a few steps in
+// either direction, for the dependency graph panel
+#[derive(Default)]
+struct DependencyGraph {
+    nodes: Vec<GraphNode>,      // the selection first
+    edges: Vec<(usize, usize)>, // from the earlier item to the later one
+    truncated: bool,            // some layers were too wide to show in full
+}
+
+#[derive(Clone)]
+struct GraphNode {
+    item_uid: ItemUID,
+    entry_id: EntryID,
+    interval: Interval,
+    title: String,
+    depth: i32, // steps from the selection: negative upstream, positive downstream
 }
 
 struct Config {
     // Node selection controls
-    min_node: u64,
-    max_node: u64,
+    node_set: NodeSet,
+    node_set_text: String,
+    node_set_error: Option<String>,
 
     // This is just for the local profile
     interval: Interval,
+    epoch: Option<i64>, // wall-clock time of t = 0, if known
+    description: Description,
+    warnings: Vec<Warning>, // not yet dismissed
+
+    // Visible time range of this profile, i.e., the global view shifted by
+    // view_offset (see Context::sync_views)
+    view_interval: Interval,
+    view_offset: i64,
+
+    // Results of the most recent search, in this profile
+    search_results: Vec<SearchResult>,
+
+    // Slot to bring into view on the next frame
+    scroll_target: Option<EntryID>,
+
+    // Panels and slots hidden from the view
+    hidden: BTreeSet<EntryID>,
+
+    // Long names of slots, for naming the memories of copies
+    slot_names: BTreeMap<EntryID, String>,
+    slot_kinds: BTreeMap<EntryID, String>, // short name of the processor kind
+
+    sort_key: SortKey,
+
+    // Slots kept at the top of the view, in the order they were pinned
+    pinned: Vec<EntryID>,
+    // Width of the scroll area's content last frame, for lining them up
+    scroll_width: f32,
+
+    // Width of the labels at each level (dragged to fit long names)
+    label_width: f32,
+
+    selected: Option<Selection>,
+
+    // Item the context menu was opened on
+    context_item: Option<Item>,
+
+    // Highlighted critical path (everything else gets dimmed)
+    critical_path: Option<CriticalPath>,
+
+    // Per-name statistics, for the interval they were computed over
+    name_stats: Option<(Interval, Vec<NameStats>)>,
+    stats_sort: (StatsColumn, bool), // column, descending
+
+    histogram: Histogram,
+
+    selection_stats: Option<SelectionStats>,
+
+    // Screen space layout (rect, rows) of the slots drawn this frame
+    slot_rects: BTreeMap<EntryID, (Rect, u64)>,
+
+    // Events to draw as lines across every slot, from the event tracks
+    // drawn this frame
+    event_lines: Vec<(Timestamp, Color32)>,
+
+    // Average utilization over the whole profile, in evenly sized buckets
+    // (empty until first needed)
+    overview: Vec<f32>,
+
+    // The top_n longest items in the tiles fetched for longest_view, longest
+    // first. Updated as tiles arrive, so it fills in as the view loads.
+    longest: Vec<LongItem>,
+    longest_view: Option<Interval>,
+    top_n: usize,
+
+    annotations: Vec<Annotation>,
+    annotation_text: String, // being composed in the controls
+    annotate_slot: bool,
+    annotation_status: Option<String>, // result of the last export/import
+
+    // Most recent failure outside of slots (which track their own)
+    last_error: Option<DataSourceError>,
+
+    // Number of tiles that may still be fetched this frame. Slow data
+    // sources are spread over several frames so the UI stays responsive.
+    fetch_budget: u64,
+    // Otherwise tiles still on their way are skipped until they arrive
+    wait_for_tiles: bool,
+
+    frame_stats: FrameStats,
+    legend: RefCell<Legend>,
+
+    // Tiles are only requested once the view stops changing for a moment,
+    // so rapid zooming and panning doesn't fetch every intermediate view
+    last_view: Interval,
+    view_changed_at: f64,
+    view_settled: bool,
 
     data_source: Box<dyn DataSource>,
 }
 
+// What a profile cost over the last frame, for the statistics overlay
+#[derive(Default)]
+struct FrameStats {
+    tiles_fetched: u64,
+    items_painted: Cell<u64>, // counted while drawing, when Config isn't mutable
+}
+
+// Adding (and saving) colors in View > Item Colors
+struct ColorEditor {
+    open: bool,
+    by_kind: bool,
+    key: String, // name or kind to add a color for
+    color: Color32,
+    status: Option<String>,
+}
+
+// Names of the items drawn over the last frame, with their colors and how
+// many there were, for the legend
+#[derive(Default)]
+struct Legend {
+    names: BTreeMap<String, (Color32, u64)>,
+    counted: BTreeSet<ItemUID>, // items straddling tiles are drawn once per tile
+}
+
 struct Window {
     panel: Panel<Panel<Panel<Slot>>>, // nodes -> kind -> proc/chan/mem
     index: u64,
     kinds: Vec<String>,
     config: Config,
+
+    // Identifies the profile, for restoring saved state
+    profile_key: u64,
+
+    // Where the profile was opened from (a path or URL), if it can be
+    // opened again, e.g., to pop it out into a viewer of its own
+    location: Option<String>,
+
+    // A second view of the profile, drawn under the first
+    split: Option<SplitView>,
+}
+
+// The other view of a split window: its own slots (with their tiles and
+// expansion state) and view, which are swapped into the window to draw it.
+// Its view never follows the others, so that one view can stay on the whole
+// run while the other zooms in.
+struct SplitView {
+    panel: Panel<Panel<Panel<Slot>>>,
+    view_interval: Interval,
+    last_view: Interval,
+    view_changed_at: f64,
+    longest: Vec<LongItem>,
+    longest_view: Option<Interval>,
+}
+
+// Per-profile state that is saved across sessions
+#[derive(Default, Deserialize, Serialize)]
+struct SavedView {
+    view_interval: Interval,
+
+    // Entries whose expansion state differs from the default
+    toggled: Vec<EntryID>,
+
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+
+    #[serde(default)]
+    hidden: Vec<EntryID>,
+
+    #[serde(default)]
+    label_width: Option<f32>,
+}
+
+// Active filters, plus the one being composed in the filter panel
+struct FilterState {
+    filters: Vec<Query>,
+    mode: FilterMode,
+
+    subject: Subject,
+    op: Op,
+    value: String,
+    query: String, // alternatively, written in the query language
+    error: Option<String>,
+}
+
+// Trades density for readability, e.g., on high-DPI displays
+#[derive(Copy, Clone, Deserialize, Serialize)]
+#[serde(default)]
+struct Density {
+    ui_scale: f32,  // of everything, on top of the display's own scale
+    row_scale: f32, // of the rows of items, relative to the font
+}
+
+impl Density {
+    const SCALES: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+}
+
+impl Default for Density {
+    fn default() -> Self {
+        Self {
+            ui_scale: 1.0,
+            row_scale: 1.0,
+        }
+    }
+}
+
+// Follow mode: keeps the view on the end of a profile that is still growing
+struct FollowState {
+    enabled: bool,
+    window_ns: i64, // length of the view
+    last_poll: f64, // when the data sources were last asked for their interval
+
+    // The view as last set by following, to notice when the user moves it
+    view: Option<Interval>,
+}
+
+// A named point in time that stays put as the view moves
+struct Marker {
+    name: String,
+    time: Timestamp,
+}
+
+#[derive(Default)]
+struct SearchState {
+    query: String,
+
+    // Cached lowercase copy of the query, used for matching plain text
+    query_lower: String,
+
+    // Parsed query, unless the query is plain text
+    parsed: Option<Query>,
+    error: Option<String>,
 }
 
 #[derive(Default, Deserialize, Serialize)]
@@ -95,12 +596,187 @@ struct Context {
 
     drag_origin: Option<Pos2>,
 
+    // Most recent drag selection, for exporting
+    #[serde(skip)]
+    selection: Option<Interval>,
+
+    // When set (or while holding shift), dragging selects an interval and
+    // offers to zoom to it, rather than zooming right away
+    #[serde(default)]
+    select_on_drag: bool,
+
+    // Show times as the time of day they happened at, where profiles know
+    #[serde(default)]
+    wall_clock: bool,
+    // How times are written everywhere (see TimeFormat::set)
+    #[serde(default)]
+    time_format: TimeFormat,
+    // Where the zoom/stats popup for the selection is open, if it is
+    #[serde(skip)]
+    selection_popup: Option<Pos2>,
+    #[serde(skip)]
+    selection_popup_stats: bool,
+
+    #[serde(skip)]
+    search: SearchState,
+
+    #[serde(skip)]
+    filters: FilterState,
+
+    #[serde(skip)]
+    follow: FollowState,
+
+    // Queries saved for reuse in search or as filters
+    #[serde(default)]
+    saved_queries: Vec<String>,
+
+    #[serde(default)]
+    color_mode: ColorMode,
+    #[serde(default)]
+    color_ramp: ColorRamp,
+
+    #[serde(default)]
+    theme: Theme,
+    #[serde(default)]
+    density: Density,
+    #[serde(skip)]
+    dark_mode: bool, // of the current egui visuals
+
+    // Measurement tool: when enabled, pairs of clicks on the timeline
+    // record an interval
+    #[serde(skip)]
+    measure_mode: bool,
+    #[serde(skip)]
+    measure_start: Option<Timestamp>,
+    #[serde(skip)]
+    measurements: Vec<Interval>,
+
+    // Marker tool: when enabled, clicks on the timeline drop a marker
+    #[serde(skip)]
+    marker_mode: bool,
+    #[serde(skip)]
+    markers: Vec<Marker>, // sorted by time
+    #[serde(skip)]
+    markers_placed: u64, // for naming new markers
+
+    #[serde(skip)]
+    show_help: bool,
+
+    // The "Go to" box: a time to center the view on, or an interval to show
+    #[serde(skip)]
+    go_to_text: String,
+    #[serde(skip)]
+    go_to_error: Option<String>,
+
+    #[serde(skip)]
+    show_histogram: bool,
+    #[serde(skip)]
+    show_item_legend: bool,
+    #[serde(skip)]
+    show_dependency_graph: bool,
+    #[serde(skip)]
+    show_warnings: bool,
+    #[serde(skip)]
+    show_log: bool,
+
+    // Colors for items chosen by the user (e.g., in the configuration
+    // file), and the editor for them
+    #[serde(skip)]
+    color_overrides: ColorOverrides,
+    #[serde(skip)]
+    color_file: Option<PathBuf>,
+    #[serde(skip)]
+    color_editor: ColorEditor,
+    #[serde(skip)]
+    log_level: Option<Level>, // the most verbose shown, or None for all
+    #[serde(skip)]
+    show_stats: bool,
+    #[cfg(feature = "puffin")]
+    #[serde(skip)]
+    show_profiler: bool,
+
+    // When set, all profiles follow view_interval (each shifted by its own
+    // offset). Otherwise only the active profile (last hovered) does. The
+    // lower views of split windows (see Window::split) only ever follow it
+    // while active.
+    #[serde(skip)]
+    sync_views: bool,
+    #[serde(skip)]
+    active_window: Option<(u64, bool)>, // index, and whether the lower view
+
+    // Views zoomed into on purpose (e.g., by dragging), each inside the one
+    // before, for the breadcrumbs. The latest zoom is added on the next
+    // frame, once it's in global time.
+    #[serde(skip)]
+    crumbs: Vec<Interval>,
+    #[serde(skip)]
+    new_crumb: bool,
+
+    // Show one profile at a time (the current tab), rather than stacking
+    // them to compare
+    #[serde(default)]
+    tabbed: bool,
+    #[serde(skip)]
+    current_tab: Option<u64>,
+
+    // Time under the pointer (in global time), linking the cursors of the
+    // views of split windows, and where to draw the pointer's own cursor
+    #[serde(skip)]
+    cursor_time: Option<Timestamp>,
+    #[serde(skip)]
+    active_view_rect: Option<Rect>,
+
+    // Position (in ProfApp::windows) of the window being dragged to reorder
+    #[serde(skip)]
+    dragged_window: Option<usize>,
+
+    // Fetch every tile up front rather than a few per frame (for exports)
+    #[serde(skip)]
+    fetch_all: bool,
+
     // Hack: We need to track the screenspace rect where slot/summary
     // data gets drawn. This gets used rendering the cursor, but we
     // only know it when we render slots. So stash it here.
     slot_rect: Option<Rect>,
 }
 
+/// Initial state of the viewer, e.g., from the command line. Anything
+/// left unset falls back to the saved state (if any) or the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct StartOptions {
+    pub view_interval: Option<Interval>,
+    pub node_set: Option<NodeSet>,
+    pub window_size: Option<Vec2>,
+    pub theme: Option<Theme>,
+    pub color_overrides: ColorOverrides,
+    pub color_file: Option<PathBuf>, // where View > Item Colors saves them
+    pub profile: Option<String>,     // where the data source came from
+    pub config_file: Option<PathBuf>, // given on the command line, if any
+}
+
+/// Profiles shown by a ProfViewerWidget, and how they're being viewed.
+pub struct ProfViewerState {
+    windows: Vec<Window>,
+    cx: Context,
+}
+
+/// The timeline, for embedding in other egui applications. Unlike `start`,
+/// this leaves the rest of the window (and the keyboard) to the
+/// application: the view changes by dragging across the timeline to zoom
+/// in, or through the methods on ProfViewerState.
+pub struct ProfViewerWidget;
+
+/// Draws the timeline of a profile offscreen, without the rest of the
+/// viewer (e.g., for benchmarks and image comparisons). Each frame fetches
+/// everything in view first, so what's drawn doesn't depend on timing.
+pub struct Headless {
+    state: ProfViewerState,
+    ctx: egui::Context,
+    rasterizer: Rasterizer, // keeps the font atlas between frames
+    size: Vec2,
+    pointer: Option<Pos2>,
+}
+
 #[derive(Default, Deserialize, Serialize)]
 #[serde(default)] // deserialize missing fields as default value
 struct ProfApp {
@@ -112,9 +788,36 @@ struct ProfApp {
 
     cx: Context,
 
+    svg_options: SvgOptions,
+
+    // Keyed by Window::profile_key
+    saved_views: BTreeMap<u64, SavedView>,
+
+    // Most recently opened first
+    #[cfg(not(target_arch = "wasm32"))]
+    recent_files: Vec<PathBuf>,
+
+    // Result of the last export, shown in the menu bar
+    #[serde(skip)]
+    status: Option<String>,
+
     #[cfg(not(target_arch = "wasm32"))]
     #[serde(skip)]
     last_update: Option<Instant>,
+
+    // Passed on to viewers started to pop profiles out (see open_viewer)
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    config_file: Option<PathBuf>,
+
+    // Where the profile came from, and the URL fragment last written, so
+    // the browser's URL can be shared to reproduce the view
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    source_url: Option<String>,
+    #[cfg(target_arch = "wasm32")]
+    #[serde(skip)]
+    last_fragment: String,
 }
 
 trait Entry {
@@ -124,7 +827,10 @@ trait Entry {
     fn label_text(&self) -> &str;
     fn hover_text(&self) -> &str;
 
-    fn label(&mut self, ui: &mut egui::Ui, rect: Rect) {
+    // Extra entries for the context menu of the label
+    fn label_menu(&mut self, _ui: &mut egui::Ui, _config: &mut Config) {}
+
+    fn label(&mut self, ui: &mut egui::Ui, rect: Rect, config: &mut Config, cx: &mut Context) {
         let response = ui.allocate_rect(
             rect,
             if self.is_expandable() {
@@ -152,12 +858,52 @@ trait Entry {
             visuals.text_color(),
         );
 
-        if response.clicked() {
+        let response = if response.clicked() {
             // This will take effect next frame because we can't redraw this widget now
             self.toggle_expanded();
+            response
         } else if response.hovered() {
-            response.on_hover_text(self.hover_text());
-        }
+            response.on_hover_text(self.hover_text())
+        } else {
+            response
+        };
+        response.context_menu(|ui| {
+            if self.is_expandable() {
+                if ui.button("Expand All").clicked() {
+                    self.expand_all(true);
+                    ui.close_menu();
+                }
+                if ui.button("Collapse All").clicked() {
+                    self.expand_all(false);
+                    ui.close_menu();
+                }
+            }
+            // Summaries go with their panel
+            if self.entry_id().last_slot_index().is_some() && ui.button("Hide").clicked() {
+                config.hidden.insert(self.entry_id().clone());
+                ui.close_menu();
+            }
+            if ui
+                .button("Zoom to Busy Interval")
+                .on_hover_text("From the first to the last time anything runs here")
+                .clicked()
+            {
+                let busy = self.busy_interval(config);
+                if let Some(interval) = config.report_error(busy) {
+                    ProfApp::zoom_to(cx, interval);
+                }
+                ui.close_menu();
+            }
+            if ui
+                .button("Copy Entry Path")
+                .on_hover_text("As used in links to this view")
+                .clicked()
+            {
+                ui.output().copied_text = self.entry_id().to_string();
+                ui.close_menu();
+            }
+            self.label_menu(ui, config);
+        });
     }
 
     fn content(
@@ -169,52 +915,244 @@ trait Entry {
         cx: &mut Context,
     );
 
+    fn export_svg(&mut self, svg: &mut SvgWriter, rect: Rect, config: &mut Config, cx: &Context);
+
     fn height(&self, config: &Config, cx: &Context) -> f32;
 
     fn is_expandable(&self) -> bool;
 
     fn toggle_expanded(&mut self);
+
+    // Expands or collapses the entry and everything in it
+    fn expand_all(&mut self, expanded: bool);
+
+    // From the first to the last time anything runs in the entry, over the
+    // whole profile (None if nothing does)
+    fn busy_interval(&mut self, config: &mut Config) -> DataSourceResult<Option<Interval>>;
+
+    // Forget fetched tiles the data source says are stale, so they are
+    // requested again the next time the entry is drawn
+    fn invalidate(&mut self, entry_id: &EntryID, interval: Interval);
+
+    // Called in place of content while the entry is scrolled out of view.
+    // Stops fetching for it, and forgets its tiles once it's far away
+    fn cull(&mut self, far: bool, config: &mut Config);
 }
 
 impl Summary {
     fn clear(&mut self) {
         self.utilization.clear();
+        self.series.clear();
+        self.pending_tiles = None;
+        self.error = None;
     }
 
-    fn inflate(&mut self, config: &mut Config, cx: &Context) {
-        let interval = config.interval.intersection(cx.view_interval);
-        let tiles = config.data_source.request_tiles(&self.entry_id, interval);
-        for tile_id in tiles {
+    // Let the data source know we won't be fetching the remaining tiles
+    fn cancel(&mut self, config: &mut Config) {
+        if let Some(mut pending) = self.pending_tiles.take() {
+            if !pending.is_empty() {
+                config
+                    .data_source
+                    .cancel_tiles(&self.entry_id, pending.make_contiguous());
+            }
+        }
+    }
+
+    fn inflate(&mut self, config: &mut Config, cx: &Context) -> DataSourceResult<()> {
+        profile_scope!("fetch tiles");
+        let _span = tracing::debug_span!("fetch", entry = %self.entry_id).entered();
+        if self.pending_tiles.is_none() {
+            let interval = config.interval.intersection(cx.view_interval);
+            let tiles = config.data_source.request_tiles(&self.entry_id, interval)?;
+            self.pending_tiles = Some(tiles.into());
+        }
+        let pending = self.pending_tiles.as_mut().unwrap();
+        while config.fetch_budget > 0 {
+            let tile_id = if let Some(tile_id) = pending.front() {
+                *tile_id
+            } else {
+                break;
+            };
+            // Picked up on a later frame, once it arrives
+            if !config.wait_for_tiles && !config.data_source.tile_ready(&self.entry_id, tile_id) {
+                break;
+            }
             let tile = config
                 .data_source
-                .fetch_summary_tile(&self.entry_id, tile_id);
+                .fetch_summary_tile(&self.entry_id, tile_id)?;
             self.utilization.extend(tile.utilization);
+            for series in tile.series {
+                if let Some(s) = self.series.iter_mut().find(|s| s.name == series.name) {
+                    s.utilization.extend(series.utilization);
+                } else {
+                    self.series.push(series);
+                }
+            }
+            pending.pop_front();
+            config.fetch_budget -= 1;
+            config.frame_stats.tiles_fetched += 1;
         }
+        Ok(())
     }
-}
 
-impl Entry for Summary {
-    fn new(info: &EntryInfo, entry_id: EntryID) -> Self {
-        if let EntryInfo::Summary { color } = info {
-            Self {
-                entry_id,
-                color: *color,
-                utilization: Vec::new(),
-                last_view_interval: None,
+    // Drop stale data and fetch anything needed for the current view
+    fn refresh(&mut self, config: &mut Config, cx: &Context) {
+        if self
+            .last_view_interval
+            .map_or(true, |i| i != cx.view_interval)
+        {
+            // Anything still pending was for an old view
+            self.cancel(config);
+            if !config.view_settled {
+                return; // keep drawing what we have until the view settles
+            }
+            self.clear();
+        }
+        self.last_view_interval = Some(cx.view_interval);
+        // Don't retry failed fetches until asked to (or the view changes)
+        if self.error.is_none() {
+            if let Err(error) = self.inflate(config, cx) {
+                tracing::warn!(entry = %self.entry_id, "unable to fetch tiles: {}", error);
+                self.clear();
+                self.error = Some(error);
             }
-        } else {
-            unreachable!()
         }
     }
 
-    fn entry_id(&self) -> &EntryID {
-        &self.entry_id
-    }
-    fn label_text(&self) -> &str {
-        "avg"
+    fn find_series(&self, name: &str) -> Option<&[UtilPoint]> {
+        self.series
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.utilization.as_slice())
+    }
+
+    // Screen space outline of the min-max band (if the data source has
+    // one) as (x, min y, max y) at every point of either curve in view
+    fn band(&self, rect: Rect, cx: &Context) -> Vec<(f32, f32, f32)> {
+        let (min, max) = match (
+            self.find_series(UtilSeries::MIN),
+            self.find_series(UtilSeries::MAX),
+        ) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return Vec::new(),
+        };
+
+        let view = cx.view_interval;
+        sample_times(&[min, max], view)
+            .into_iter()
+            .map(|time| {
+                let x = rect.lerp(Vec2::new(view.unlerp(time) as f32, 0.0)).x;
+                let y = |util: f32| rect.lerp(Vec2::new(0.0, 1.0 - util)).y;
+                (
+                    x,
+                    y(sample_utilization(min, time)),
+                    y(sample_utilization(max, time)),
+                )
+            })
+            .collect()
+    }
+
+    // Screen space outline of each layer of a stacked summary, as
+    // (x, [y at the bottom of each layer..., y at the top of the stack])
+    fn stack(&self, rect: Rect, cx: &Context) -> Vec<(f32, Vec<f32>)> {
+        let layers: Vec<_> = self
+            .layers
+            .iter()
+            .map(|(name, _)| self.find_series(name).unwrap_or(&[]))
+            .collect();
+
+        let view = cx.view_interval;
+        sample_times(&layers, view)
+            .into_iter()
+            .map(|time| {
+                let x = rect.lerp(Vec2::new(view.unlerp(time) as f32, 0.0)).x;
+                let mut total = 0.0;
+                let mut ys = vec![rect.bottom()];
+                for layer in &layers {
+                    total += sample_utilization(layer, time);
+                    ys.push(rect.lerp(Vec2::new(0.0, 1.0 - total)).y);
+                }
+                (x, ys)
+            })
+            .collect()
+    }
+
+    fn show_legend(&self, ui: &mut egui::Ui, rect: Rect, cx: &Context) {
+        const PADDING: f32 = 4.0;
+
+        let font_id = TextStyle::Small.resolve(ui.style());
+        let text_color = ui.visuals().text_color();
+        let mut pos = rect.left_top() + Vec2::splat(PADDING);
+        for (name, color) in self.layers.iter().rev() {
+            let galley = ui
+                .painter()
+                .layout_no_wrap(name.clone(), font_id.clone(), text_color);
+            let swatch = Rect::from_min_size(pos, Vec2::splat(galley.size().y));
+            ui.painter().rect_filled(swatch, 0.0, cx.themed(*color));
+            pos.x = swatch.right() + PADDING;
+            let width = galley.size().x;
+            ui.painter().galley(pos, galley);
+            pos.x += width + 2.0 * PADDING;
+        }
+    }
+}
+
+// Every time in view where one of the curves changes slope, plus the ends
+// of the view
+fn sample_times(curves: &[&[UtilPoint]], view: Interval) -> Vec<Timestamp> {
+    let mut times: Vec<_> = curves
+        .iter()
+        .flat_map(|curve| curve.iter())
+        .map(|p| p.time)
+        .filter(|t| view.contains(*t))
+        .chain([view.start, view.stop])
+        .collect();
+    times.sort();
+    times.dedup();
+    times
+}
+
+impl Entry for Summary {
+    fn new(info: &EntryInfo, entry_id: EntryID) -> Self {
+        let (color, layers) = match info {
+            EntryInfo::Summary { color } => ((*color).into(), Vec::new()),
+            EntryInfo::Aggregate { layers } => (
+                Color32::GRAY,
+                layers
+                    .iter()
+                    .map(|(name, color)| (name.clone(), (*color).into()))
+                    .collect(),
+            ),
+            _ => unreachable!(),
+        };
+        Self {
+            entry_id,
+            color,
+            layers,
+            utilization: Vec::new(),
+            series: Vec::new(),
+            pending_tiles: None,
+            last_view_interval: None,
+            error: None,
+        }
+    }
+
+    fn entry_id(&self) -> &EntryID {
+        &self.entry_id
+    }
+    fn label_text(&self) -> &str {
+        if self.layers.is_empty() {
+            "avg"
+        } else {
+            "all"
+        }
     }
     fn hover_text(&self) -> &str {
-        "Utilization Plot of Average Usage Over Time"
+        if self.layers.is_empty() {
+            "Utilization Plot of Average Usage Over Time"
+        } else {
+            "Utilization Stacked by Kind"
+        }
     }
 
     fn content(
@@ -225,39 +1163,73 @@ impl Entry for Summary {
         config: &mut Config,
         cx: &mut Context,
     ) {
+        profile_scope!("Summary::content");
         cx.slot_rect = Some(rect); // Save slot rect for use later
 
         const TOOLTIP_RADIUS: f32 = 4.0;
         let response = ui.allocate_rect(rect, egui::Sense::hover());
         let hover_pos = response.hover_pos(); // where is the mouse hovering?
 
-        if self
-            .last_view_interval
-            .map_or(true, |i| i != cx.view_interval)
-        {
-            self.clear();
-        }
-        self.last_view_interval = Some(cx.view_interval);
-        if self.utilization.is_empty() {
-            self.inflate(config, cx);
-        }
+        self.refresh(config, cx);
 
         let style = ui.style();
         let visuals = style.interact_selectable(&response, false);
         ui.painter()
             .rect(rect, 0.0, visuals.bg_fill, visuals.bg_stroke);
 
-        let stroke = Stroke::new(visuals.bg_stroke.width, self.color);
+        if let Some(error) = &self.error {
+            if show_error(ui, rect, error) {
+                self.clear();
+            }
+            return;
+        }
+
+        let color = cx.themed(self.color);
+        let stroke = Stroke::new(visuals.bg_stroke.width, color);
+
+        // Shade the min-max band underneath the average
+        let band = self.band(rect, cx);
+        if !band.is_empty() {
+            let fill = color.linear_multiply(BAND_OPACITY);
+            let mut mesh = egui::Mesh::default();
+            for (i, (x, min, max)) in band.into_iter().enumerate() {
+                mesh.colored_vertex(Pos2::new(x, min), fill);
+                mesh.colored_vertex(Pos2::new(x, max), fill);
+                if i > 0 {
+                    let v = 2 * i as u32;
+                    mesh.add_triangle(v - 2, v - 1, v);
+                    mesh.add_triangle(v - 1, v + 1, v);
+                }
+            }
+            ui.painter().add(mesh);
+        }
+
+        // Stacked layers, also underneath the total
+        let stack = self.stack(rect, cx);
+        for (layer, (_, color)) in self.layers.iter().enumerate() {
+            let color = cx.themed(*color);
+            let mut mesh = egui::Mesh::default();
+            for (i, (x, ys)) in stack.iter().enumerate() {
+                mesh.colored_vertex(Pos2::new(*x, ys[layer]), color);
+                mesh.colored_vertex(Pos2::new(*x, ys[layer + 1]), color);
+                if i > 0 {
+                    let v = 2 * i as u32;
+                    mesh.add_triangle(v - 2, v - 1, v);
+                    mesh.add_triangle(v - 1, v + 1, v);
+                }
+            }
+            ui.painter().add(mesh);
+        }
 
         // Conversions to and from screen space coordinates
         let util_to_screen = |util: &UtilPoint| {
-            let time = cx.view_interval.unlerp(util.time);
+            let time = cx.view_interval.unlerp(util.time) as f32;
             rect.lerp(Vec2::new(time, 1.0 - util.util))
         };
         let screen_to_util = |screen: Pos2| UtilPoint {
             time: cx
                 .view_interval
-                .lerp((screen.x - rect.left()) / rect.width()),
+                .lerp(((screen.x - rect.left()) / rect.width()) as f64),
             util: 1.0 - (screen.y - rect.top()) / rect.height(),
         };
 
@@ -303,18 +1275,98 @@ impl Entry for Summary {
             last_util = Some(util);
         }
 
+        show_loading(ui, rect, self.pending_tiles.as_ref(), cx);
+        self.show_legend(ui, rect, cx);
+
         if let Some(util) = hover_util {
-            let time = cx.view_interval.unlerp(util.time);
+            let time = cx.view_interval.unlerp(util.time) as f32;
             let util_rect = Rect::from_min_max(
                 rect.lerp(Vec2::new(time - 0.05, 0.0)),
                 rect.lerp(Vec2::new(time + 0.05, 1.0)),
             );
-            ui.show_tooltip(
-                "utilization_tooltip",
-                &util_rect,
-                format!("{:.0}% Utilization", util.util * 100.0),
+            let mut text = format!("{:.0}% Utilization", util.util * 100.0);
+            if let (Some(min), Some(max)) = (
+                self.find_series(UtilSeries::MIN),
+                self.find_series(UtilSeries::MAX),
+            ) {
+                write!(
+                    text,
+                    " (min {:.0}%, max {:.0}%)",
+                    sample_utilization(min, util.time) * 100.0,
+                    sample_utilization(max, util.time) * 100.0
+                )
+                .unwrap();
+            }
+            for (name, _) in self.layers.iter().rev() {
+                let layer = self.find_series(name).unwrap_or(&[]);
+                let util = sample_utilization(layer, util.time);
+                write!(text, "\n{}: {:.0}%", name, util * 100.0).unwrap();
+            }
+            ui.show_tooltip("utilization_tooltip", &util_rect, text);
+        }
+    }
+
+    fn export_svg(&mut self, svg: &mut SvgWriter, rect: Rect, config: &mut Config, cx: &Context) {
+        self.refresh(config, cx);
+
+        svg.rect(rect, SVG_SLOT_FILL, Some(SVG_SLOT_STROKE), None);
+        let points: Vec<_> = self
+            .utilization
+            .iter()
+            .map(|util| {
+                let time = cx.view_interval.unlerp(util.time) as f32;
+                rect.lerp(Vec2::new(time, 1.0 - util.util))
+            })
+            .collect();
+        svg.begin_clip(rect);
+        let band = self.band(rect, cx);
+        if !band.is_empty() {
+            let outline: Vec<_> = band
+                .iter()
+                .map(|&(x, min, _)| Pos2::new(x, min))
+                .chain(band.iter().rev().map(|&(x, _, max)| Pos2::new(x, max)))
+                .collect();
+            svg.polygon(
+                &outline,
+                cx.themed(self.color).linear_multiply(BAND_OPACITY),
+            );
+        }
+        let stack = self.stack(rect, cx);
+        for (layer, (_, color)) in self.layers.iter().enumerate() {
+            let outline: Vec<_> = stack
+                .iter()
+                .map(|(x, ys)| Pos2::new(*x, ys[layer]))
+                .chain(
+                    stack
+                        .iter()
+                        .rev()
+                        .map(|(x, ys)| Pos2::new(*x, ys[layer + 1])),
+                )
+                .collect();
+            svg.polygon(&outline, cx.themed(*color));
+        }
+        svg.polyline(&points, cx.themed(self.color), 1.0);
+
+        // Legend, same as on screen
+        let font_size = svg.options().label_font_size * 0.8;
+        let mut x = rect.left() + 4.0;
+        for (name, color) in self.layers.iter().rev() {
+            let swatch =
+                Rect::from_min_size(Pos2::new(x, rect.top() + 4.0), Vec2::splat(font_size));
+            svg.rect(swatch, cx.themed(*color), None, None);
+            x = swatch.right() + 4.0;
+            svg.text(
+                Pos2::new(x, swatch.bottom()),
+                font_size,
+                TextAnchor::Start,
+                SVG_TEXT,
+                name,
+                None,
             );
+            // No text metrics here, so guess at the width
+            x += name.len() as f32 * font_size * 0.6 + 8.0;
         }
+        svg.end_clip();
     }
 
     fn height(&self, _config: &Config, cx: &Context) -> f32 {
@@ -329,13 +1381,43 @@ impl Entry for Summary {
     fn toggle_expanded(&mut self) {
         unreachable!();
     }
+
+    fn expand_all(&mut self, _expanded: bool) {}
+
+    fn busy_interval(&mut self, config: &mut Config) -> DataSourceResult<Option<Interval>> {
+        let utilization =
+            fetch_utilization(config.data_source.as_mut(), &self.entry_id, config.interval)?;
+        Ok(nonzero_interval(&utilization))
+    }
+
+    fn invalidate(&mut self, entry_id: &EntryID, interval: Interval) {
+        if covers(entry_id, &self.entry_id)
+            && self
+                .last_view_interval
+                .map_or(false, |i| i.overlaps(interval))
+        {
+            self.last_view_interval = None;
+        }
+    }
+
+    fn cull(&mut self, far: bool, config: &mut Config) {
+        // Start over when it comes back into view, since a new request would
+        // include the tiles already fetched
+        let fetching = self.pending_tiles.as_ref().map_or(false, |p| !p.is_empty());
+        if fetching || far {
+            self.cancel(config);
+            self.clear();
+            self.last_view_interval = None;
+        }
+    }
 }
 
 impl Slot {
     fn rows(&self) -> u64 {
         const UNEXPANDED_ROWS: u64 = 2;
         if self.expanded {
-            self.max_rows.at_least(UNEXPANDED_ROWS)
+            let rows = self.flame.as_ref().map_or(self.max_rows, |f| f.depth);
+            rows.at_least(UNEXPANDED_ROWS)
         } else {
             UNEXPANDED_ROWS
         }
@@ -343,493 +1425,6111 @@ impl Slot {
 
     fn clear(&mut self) {
         self.tiles.clear();
+        self.density.clear();
+        if let Some(memory) = &mut self.memory {
+            *memory = MemoryLayout::default();
+        }
+        if let Some(flame) = &mut self.flame {
+            *flame = FlameGraph::default();
+        }
+        if let Some(counter) = &mut self.counter {
+            counter.tiles.clear();
+        }
+        if let Some(events) = &mut self.events {
+            events.tiles.clear();
+        }
+        self.pending_tiles = None;
+        self.error = None;
     }
 
-    fn inflate(&mut self, config: &mut Config, cx: &Context) {
-        let interval = config.interval.intersection(cx.view_interval);
-        let tiles = config.data_source.request_tiles(&self.entry_id, interval);
-        for tile_id in tiles {
-            let tile = config.data_source.fetch_slot_tile(&self.entry_id, tile_id);
-            self.tiles.push(tile);
+    // Let the data source know we won't be fetching the remaining tiles
+    fn cancel(&mut self, config: &mut Config) {
+        if let Some(mut pending) = self.pending_tiles.take() {
+            if !pending.is_empty() {
+                config
+                    .data_source
+                    .cancel_tiles(&self.entry_id, pending.make_contiguous());
+            }
         }
     }
 
-    fn render_tile(
-        tile: &SlotTile,
-        rows: u64,
-        mut hover_pos: Option<Pos2>,
-        ui: &mut egui::Ui,
-        rect: Rect,
-        viewport: Rect,
-        cx: &mut Context,
-    ) -> Option<Pos2> {
-        if !cx.view_interval.overlaps(tile.tile_id.0) {
-            return hover_pos;
+    fn inflate(&mut self, config: &mut Config, cx: &Context) -> DataSourceResult<()> {
+        profile_scope!("fetch tiles");
+        let _span = tracing::debug_span!("fetch", entry = %self.entry_id).entered();
+        if self.pending_tiles.is_none() {
+            let interval = config.interval.intersection(cx.view_interval);
+            let tiles = config.data_source.request_tiles(&self.entry_id, interval)?;
+            self.pending_tiles = Some(tiles.into());
+        }
+        let pending = self.pending_tiles.as_mut().unwrap();
+        while config.fetch_budget > 0 {
+            let tile_id = if let Some(tile_id) = pending.front() {
+                *tile_id
+            } else {
+                break;
+            };
+            // Picked up on a later frame, once it arrives
+            if !config.wait_for_tiles && !config.data_source.tile_ready(&self.entry_id, tile_id) {
+                break;
+            }
+            if let Some(counter) = &mut self.counter {
+                let tile = config
+                    .data_source
+                    .fetch_counter_tile(&self.entry_id, tile_id)?;
+                counter.tiles.push(tile);
+            } else if let Some(events) = &mut self.events {
+                let tile = config
+                    .data_source
+                    .fetch_event_tile(&self.entry_id, tile_id)?;
+                events.tiles.push(tile);
+            } else if !self.expanded {
+                let tile = config
+                    .data_source
+                    .fetch_slot_density_tile(&self.entry_id, tile_id)?;
+                self.density.extend(tile.utilization);
+            } else {
+                let tile = config
+                    .data_source
+                    .fetch_slot_tile(&self.entry_id, tile_id)?;
+                config.record_longest(&self.entry_id, &tile);
+                self.tiles.push(tile);
+            }
+            pending.pop_front();
+            config.fetch_budget -= 1;
+            config.frame_stats.tiles_fetched += 1;
+        }
+        Ok(())
+    }
+
+    // Drop stale tiles and fetch anything needed for the current view
+    fn refresh(&mut self, config: &mut Config, cx: &Context) {
+        if self
+            .last_view_interval
+            .map_or(true, |i| i != cx.view_interval)
+        {
+            // Anything still pending was for an old view
+            self.cancel(config);
+            if !config.view_settled {
+                return; // keep drawing what we have until the view settles
+            }
+            self.clear();
+        } else if self.fetched_expanded != self.expanded {
+            // Expanding or collapsing switches between items and density
+            self.cancel(config);
+            self.clear();
+        }
+        self.last_view_interval = Some(cx.view_interval);
+        self.fetched_expanded = self.expanded;
+        // Don't retry failed fetches until asked to (or the view changes)
+        if self.error.is_none() {
+            if let Err(error) = self.inflate(config, cx) {
+                tracing::warn!(entry = %self.entry_id, "unable to fetch tiles: {}", error);
+                self.clear();
+                self.error = Some(error);
+            }
         }
+    }
 
-        for (row, row_items) in tile.items.iter().enumerate() {
-            // Need to reverse the rows because we're working in screen space
-            let irow = rows - (row as u64) - 1;
+    // Screen space rect for an item in the given row (clamped to the view)
+    fn item_rect(rect: Rect, rows: u64, row: u64, interval: Interval, cx: &Context) -> Rect {
+        // Need to reverse the rows because we're working in screen space
+        let irow = rows - row - 1;
+
+        // Note: the interval is EXCLUSIVE. This turns out to be what
+        // we want here, because in screen coordinates interval.stop
+        // is the BEGINNING of the interval.stop nanosecond.
+        let start = cx.view_interval.unlerp(interval.start).at_least(0.0) as f32;
+        let stop = cx.view_interval.unlerp(interval.stop).at_most(1.0) as f32;
+        let min = rect.lerp(Vec2::new(start, (irow as f32 + 0.05) / rows as f32));
+        let max = rect.lerp(Vec2::new(stop, (irow as f32 + 0.95) / rows as f32));
+        Rect::from_min_max(min, max)
+    }
+
+    // A notch at each end of a copy, in the color of the memory there
+    fn render_endpoints(endpoints: &CopyEndpoints, item_rect: Rect, items: &mut ItemMesh) {
+        const NOTCH_WIDTH: f32 = 3.0;
+        if item_rect.width() < 4.0 * NOTCH_WIDTH {
+            return;
+        }
+        let notch = Vec2::new(NOTCH_WIDTH, item_rect.height());
+        if let Some(source) = &endpoints.source {
+            let rect = Rect::from_min_size(item_rect.min, notch);
+            items.rect(rect, memory_color(source), Stroke::NONE);
+        }
+        if let Some(target) = &endpoints.target {
+            let rect =
+                Rect::from_min_size(item_rect.right_top() - Vec2::new(NOTCH_WIDTH, 0.0), notch);
+            items.rect(rect, memory_color(target), Stroke::NONE);
+        }
+    }
 
-            // We want to do this first on rows, so that we can cut the
-            // entire row if we don't need it
+    // Screen space rects for the parts of an item spent waiting
+    fn waiting_rects<'a>(
+        rect: Rect,
+        rows: u64,
+        row: u64,
+        item: &'a Item,
+        cx: &'a Context,
+    ) -> impl Iterator<Item = Rect> + 'a {
+        item.segments
+            .iter()
+            .filter(|segment| segment.state == ItemState::Waiting)
+            .map(move |segment| segment.interval.intersection(item.interval))
+            .filter(move |interval| {
+                interval.duration_ns() > 0 && cx.view_interval.overlaps(*interval)
+            })
+            .map(move |interval| Self::item_rect(rect, rows, row, interval, cx))
+    }
 
-            // Compute bounds for the whole row
-            let row_min = rect.lerp(Vec2::new(0.0, (irow as f32 + 0.05) / rows as f32));
-            let row_max = rect.lerp(Vec2::new(1.0, (irow as f32 + 0.95) / rows as f32));
+    // Density over the interval (whether or not the slot is expanded), or
+    // nothing for counters and events
+    fn fetch_density(
+        &self,
+        data_source: &mut dyn DataSource,
+        interval: Interval,
+    ) -> DataSourceResult<Vec<UtilPoint>> {
+        let mut density = Vec::new();
+        if self.counter.is_some() || self.events.is_some() {
+            return Ok(density);
+        }
+        for tile_id in data_source.request_tiles(&self.entry_id, interval)? {
+            let tile = data_source.fetch_slot_density_tile(&self.entry_id, tile_id)?;
+            density.extend(tile.utilization);
+        }
+        Ok(density)
+    }
 
-            // Cull if out of bounds
-            // Note: need to shift by rect.min to get to viewport space
-            if row_max.y - rect.min.y < viewport.min.y {
-                break;
-            } else if row_min.y - rect.min.y > viewport.max.y {
+    // Draws how busy a collapsed slot is as a strip, colored by the ramp
+    fn render_density(&self, ui: &mut egui::Ui, rect: Rect, cx: &Context) {
+        let strip = rect.shrink2(Vec2::new(0.0, rect.height() * 0.25));
+        let mut bins = ItemMesh::default();
+        for points in self.density.windows(2) {
+            let (p1, p2) = (points[0], points[1]);
+            let interval = Interval::new(p1.time, p2.time);
+            if p1.util <= 0.0 || interval.duration_ns() <= 0 {
+                continue;
+            }
+            let start = cx.view_interval.unlerp(p1.time).at_least(0.0) as f32;
+            let stop = cx.view_interval.unlerp(p2.time).at_most(1.0) as f32;
+            if start >= stop {
+                continue;
+            }
+            let bin_rect = Rect::from_min_max(
+                strip.lerp(Vec2::new(start, 0.0)),
+                strip.lerp(Vec2::new(stop, 1.0)),
+            );
+            bins.rect(bin_rect, cx.color_ramp.color(p1.util), Stroke::NONE);
+        }
+        bins.paint(ui);
+    }
+
+    // Renders all loaded tiles, returning the item under the mouse (if any)
+    fn render_tiles(
+        &self,
+        mut hover_pos: Option<Pos2>,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        viewport: Rect,
+        config: &Config,
+        cx: &Context,
+    ) -> Option<(u64, &Item)> {
+        let rows = self.rows();
+        let selected = config.selected.as_ref().map(|s| s.item.item_uid);
+        let path = config.critical_path.as_ref();
+        let mut hovered = None;
+        let mut items = ItemMesh::default();
+
+        for tile in &self.tiles {
+            if !cx.view_interval.overlaps(tile.tile_id.0) {
                 continue;
             }
 
-            // Check if mouse is hovering over this row
-            let row_rect = Rect::from_min_max(row_min, row_max);
-            let row_hover = hover_pos.map_or(false, |h| row_rect.contains(h));
+            for (row, row_items) in tile.items.iter().enumerate() {
+                // Need to reverse the rows because we're working in screen space
+                let irow = rows - (row as u64) - 1;
+
+                // We want to do this first on rows, so that we can cut the
+                // entire row if we don't need it
 
-            // Now handle the items
-            for item in row_items {
-                if !cx.view_interval.overlaps(item.interval) {
+                // Compute bounds for the whole row
+                let row_min = rect.lerp(Vec2::new(0.0, (irow as f32 + 0.05) / rows as f32));
+                let row_max = rect.lerp(Vec2::new(1.0, (irow as f32 + 0.95) / rows as f32));
+
+                // Cull if out of bounds
+                // Note: need to shift by rect.min to get to viewport space
+                if row_max.y - rect.min.y < viewport.min.y {
+                    break;
+                } else if row_min.y - rect.min.y > viewport.max.y {
                     continue;
                 }
 
-                // Note: the interval is EXCLUSIVE. This turns out to be what
-                // we want here, because in screen coordinates interval.stop
-                // is the BEGINNING of the interval.stop nanosecond.
-                let start = cx.view_interval.unlerp(item.interval.start).at_least(0.0);
-                let stop = cx.view_interval.unlerp(item.interval.stop).at_most(1.0);
-                let min = rect.lerp(Vec2::new(start, (irow as f32 + 0.05) / rows as f32));
-                let max = rect.lerp(Vec2::new(stop, (irow as f32 + 0.95) / rows as f32));
-
-                let item_rect = Rect::from_min_max(min, max);
-                if row_hover && hover_pos.map_or(false, |h| item_rect.contains(h)) {
-                    hover_pos = None;
-
-                    ui.show_tooltip_ui("task_tooltip", &item_rect, |ui| {
-                        ui.label(&item.title);
-                        for (name, field) in &item.fields {
-                            match field {
-                                Field::I64(value) => {
-                                    ui.label(format!("{}: {}", name, value));
-                                }
-                                Field::U64(value) => {
-                                    ui.label(format!("{}: {}", name, value));
-                                }
-                                Field::String(value) => {
-                                    ui.label(format!("{}: {}", name, value));
-                                }
-                                Field::Interval(value) => {
-                                    ui.label(format!("{}: {}", name, value));
-                                }
-                                Field::Empty => {
-                                    ui.label(name);
-                                }
+                // Check if mouse is hovering over this row
+                let row_rect = Rect::from_min_max(row_min, row_max);
+                let row_hover = hover_pos.map_or(false, |h| row_rect.contains(h));
+
+                // Look up the item under the mouse directly rather than
+                // testing every item as we go
+                let hover_uid = hover_pos.filter(|_| row_hover).and_then(|h| {
+                    let time = cx
+                        .view_interval
+                        .lerp(((h.x - rect.left()) / rect.width()) as f64);
+                    let point = Interval::new(time, Timestamp(time.0 + 1));
+                    // The last one is drawn on top
+                    SlotTile::items_in(row_items, point)
+                        .last()
+                        .map(|item| item.item_uid)
+                });
+
+                // Items that are too small to see are merged into blocks
+                let mut block: Option<ItemBlock> = None;
+                let mut overlap = RowOverlap::default();
+
+                // Now handle the items
+                for item in SlotTile::items_in(row_items, cx.view_interval) {
+                    let color = if let Some(color) = cx.item_color(&self.entry_id, item, config) {
+                        color
+                    } else {
+                        continue; // filtered out
+                    };
+                    let data_color = cx.data_color(&self.entry_id, item, config);
+                    config.legend.borrow_mut().add(item, data_color);
+                    let item_rect = Self::item_rect(rect, rows, row as u64, item.interval, cx);
+                    let overlapped = overlap.add(item_rect);
+                    let highlight = cx.search.is_match(&self.entry_id, item)
+                        || config.is_brushed(&self.entry_id, item);
+                    let on_path = path.map(|p| p.contains(item.item_uid));
+                    let color = if on_path == Some(false) {
+                        color.linear_multiply(DIM_FACTOR)
+                    } else {
+                        color
+                    };
+
+                    // Sub-pixel items get merged with their (sub-pixel) neighbors
+                    if item_rect.width() < ItemBlock::MIN_WIDTH {
+                        if let Some(b) = &mut block {
+                            if b.can_merge(item_rect) {
+                                b.merge(item.interval, item_rect, color, highlight);
+                                continue;
                             }
                         }
-                    });
+                        if let Some(b) = block.take() {
+                            config.frame_stats.paint();
+                            hover_pos = b.render(row_hover, hover_pos, ui, &mut items);
+                        }
+                        block = Some(ItemBlock::new(item.interval, item_rect, color, highlight));
+                        continue;
+                    }
+                    if let Some(b) = block.take() {
+                        config.frame_stats.paint();
+                        hover_pos = b.render(row_hover, hover_pos, ui, &mut items);
+                    }
+
+                    if hover_pos.is_some() && hover_uid == Some(item.item_uid) {
+                        hover_pos = None;
+                        hovered = Some((row as u64, item));
+
+                        ui.show_tooltip_ui("task_tooltip", &item_rect, |ui| {
+                            ui.label(&item.title);
+                            show_fields(ui, &item.fields);
+                            show_endpoints(ui, item, config);
+                            show_original_color(ui, item, cx);
+                            let waiting = waiting_ns(item);
+                            if waiting > 0 {
+                                ui.label(format!("Waiting: {}", Timestamp(waiting)));
+                            }
+                        });
+                    }
+                    let stroke = if selected == Some(item.item_uid) {
+                        Stroke::new(2.0, ui.visuals().strong_text_color())
+                    } else if on_path == Some(true) {
+                        Stroke::new(2.0, ui.visuals().warn_fg_color)
+                    } else if highlight {
+                        ui.visuals().selection.stroke
+                    } else {
+                        Stroke::NONE
+                    };
+                    items.rect(item_rect, color, stroke);
+                    for segment_rect in Self::waiting_rects(rect, rows, row as u64, item, cx) {
+                        items.rect(segment_rect, waiting_color(color), Stroke::NONE);
+                    }
+                    if let Some((overlap_rect, depth)) = overlapped {
+                        let color = overlap_color(color, depth as f32);
+                        items.rect(overlap_rect, color, Stroke::NONE);
+                    }
+                    if let Some(endpoints) = &item.endpoints {
+                        Self::render_endpoints(endpoints, item_rect, &mut items);
+                    }
+                    config.frame_stats.paint();
+                }
+                if let Some(b) = block.take() {
+                    config.frame_stats.paint();
+                    hover_pos = b.render(row_hover, hover_pos, ui, &mut items);
                 }
-                ui.painter().rect(item_rect, 0.0, item.color, Stroke::NONE);
             }
         }
-        hover_pos
+        items.paint(ui);
+        hovered
     }
 }
 
-impl Entry for Slot {
-    fn new(info: &EntryInfo, entry_id: EntryID) -> Self {
-        if let EntryInfo::Slot {
-            short_name,
-            long_name,
-            max_rows,
-        } = info
-        {
-            Self {
-                entry_id,
-                short_name: short_name.to_owned(),
-                long_name: long_name.to_owned(),
-                expanded: true,
-                max_rows: *max_rows,
-                tiles: Vec::new(),
-                last_view_interval: None,
+impl FlameGraph {
+    fn update(&mut self, tiles: &[SlotTile]) {
+        if self.tile_count == tiles.len() {
+            return;
+        }
+        self.tile_count = tiles.len();
+
+        // Items that straddle a tile boundary show up once per tile
+        let mut items: BTreeMap<ItemUID, Item> = BTreeMap::new();
+        for item in tiles.iter().flat_map(|tile| tile.items.iter().flatten()) {
+            if let Some(existing) = items.get_mut(&item.item_uid) {
+                existing.interval = existing.interval.union(item.interval);
+            } else {
+                items.insert(item.item_uid, item.clone());
             }
-        } else {
-            unreachable!()
         }
+        let mut items: Vec<_> = items.into_values().collect();
+        // Enclosing items come first when they start at the same time
+        items.sort_by_key(|item| (item.interval.start, std::cmp::Reverse(item.interval.stop)));
+
+        // Each item goes on top of whatever is still open when it starts
+        let mut open: Vec<Timestamp> = Vec::new();
+        self.depth = 0;
+        self.items = items
+            .into_iter()
+            .map(|item| {
+                while open
+                    .last()
+                    .map_or(false, |stop| *stop <= item.interval.start)
+                {
+                    open.pop();
+                }
+                let depth = open.len() as u64;
+                open.push(item.interval.stop);
+                self.depth = self.depth.max(depth + 1);
+                (depth, item)
+            })
+            .collect();
     }
 
-    fn entry_id(&self) -> &EntryID {
-        &self.entry_id
-    }
-    fn label_text(&self) -> &str {
-        &self.short_name
-    }
-    fn hover_text(&self) -> &str {
-        &self.long_name
+    fn visible(&self, view: Interval) -> impl Iterator<Item = &(u64, Item)> {
+        let end = self
+            .items
+            .partition_point(|(_, item)| item.interval.start < view.stop);
+        self.items[..end]
+            .iter()
+            .filter(move |(_, item)| view.overlaps(item.interval))
     }
 
-    fn content(
-        &mut self,
+    fn render(
+        &self,
+        slot: &Slot,
+        hover_pos: Option<Pos2>,
         ui: &mut egui::Ui,
         rect: Rect,
-        viewport: Rect,
-        config: &mut Config,
-        cx: &mut Context,
-    ) {
-        cx.slot_rect = Some(rect); // Save slot rect for use later
-
-        let response = ui.allocate_rect(rect, egui::Sense::hover());
-        let mut hover_pos = response.hover_pos(); // where is the mouse hovering?
-
-        if self.expanded {
-            if self
-                .last_view_interval
-                .map_or(true, |i| i != cx.view_interval)
-            {
-                self.clear();
-            }
-            self.last_view_interval = Some(cx.view_interval);
-            if self.tiles.is_empty() {
-                self.inflate(config, cx);
+        config: &Config,
+        cx: &Context,
+    ) -> Option<(u64, &Item)> {
+        // Don't bother labeling items narrower than this
+        const MIN_LABEL_WIDTH: f32 = 24.0;
+
+        let rows = slot.rows();
+        let selected = config.selected.as_ref().map(|s| s.item.item_uid);
+        let mut hovered = None;
+        let mut items = ItemMesh::default();
+        for (depth, item) in self.visible(cx.view_interval) {
+            let color = if let Some(color) = cx.item_color(&slot.entry_id, item, config) {
+                color
+            } else {
+                continue;
+            };
+            let item_rect = Slot::item_rect(rect, rows, *depth, item.interval, cx);
+            let highlight = cx.search.is_match(&slot.entry_id, item);
+            let stroke = if selected == Some(item.item_uid) {
+                Stroke::new(2.0, ui.visuals().strong_text_color())
+            } else if highlight {
+                ui.visuals().selection.stroke
+            } else {
+                Stroke::new(0.5, ui.visuals().extreme_bg_color)
+            };
+            items.rect(item_rect, color, stroke);
+            config.frame_stats.paint();
+            if item_rect.width() >= MIN_LABEL_WIDTH {
+                let label = (item_rect, item.title.clone(), Theme::text_on(color));
+                items.labels.push(label);
             }
 
-            let style = ui.style();
-            let visuals = style.interact_selectable(&response, false);
-            ui.painter()
-                .rect(rect, 0.0, visuals.bg_fill, visuals.bg_stroke);
+            if hover_pos.map_or(false, |h| item_rect.contains(h)) {
+                hovered = Some((*depth, item));
+                ui.show_tooltip_ui("task_tooltip", &item_rect, |ui| {
+                    ui.label(&item.title);
+                    show_fields(ui, &item.fields);
+                    show_original_color(ui, item, cx);
+                });
+            }
+        }
+        items.paint(ui);
+        hovered
+    }
 
-            let rows = self.rows();
-            for tile in &self.tiles {
-                hover_pos = Self::render_tile(tile, rows, hover_pos, ui, rect, viewport, cx);
+    fn export_svg(
+        &self,
+        slot: &Slot,
+        svg: &mut SvgWriter,
+        rect: Rect,
+        config: &Config,
+        cx: &Context,
+    ) {
+        let rows = slot.rows();
+        for (depth, item) in self.visible(cx.view_interval) {
+            if let Some(color) = cx.item_color(&slot.entry_id, item, config) {
+                let item_rect = Slot::item_rect(rect, rows, *depth, item.interval, cx);
+                svg.rect(item_rect, color, Some(SVG_SLOT_FILL), Some(&item.title));
             }
         }
     }
+}
 
-    fn height(&self, _config: &Config, cx: &Context) -> f32 {
-        self.rows() as f32 * cx.row_height
+impl MemoryLayout {
+    fn update(&mut self, tiles: &[SlotTile]) {
+        if self.tile_count == tiles.len() {
+            return;
+        }
+        self.tile_count = tiles.len();
+
+        // Items that straddle a tile boundary show up once per tile
+        let mut items: BTreeMap<ItemUID, (u64, Item)> = BTreeMap::new();
+        for tile in tiles {
+            for (row, row_items) in tile.items.iter().enumerate() {
+                for item in row_items.iter().filter(|item| item.allocation.is_some()) {
+                    if let Some((_, existing)) = items.get_mut(&item.item_uid) {
+                        existing.interval = existing.interval.union(item.interval);
+                    } else {
+                        items.insert(item.item_uid, (row as u64, item.clone()));
+                    }
+                }
+            }
+        }
+        self.items = items.into_values().collect();
+        self.items.sort_by_key(|(_, item)| item.interval.start);
+        self.capacity = self
+            .items
+            .iter()
+            .filter_map(|(_, item)| item.allocation.map(|a| a.offset + a.size))
+            .max()
+            .unwrap_or(0);
     }
 
-    fn is_expandable(&self) -> bool {
-        true
+    fn visible(&self, view: Interval) -> impl Iterator<Item = &(u64, Item)> {
+        let end = self
+            .items
+            .partition_point(|(_, item)| item.interval.start < view.stop);
+        self.items[..end]
+            .iter()
+            .filter(move |(_, item)| view.overlaps(item.interval))
     }
 
-    fn toggle_expanded(&mut self) {
-        self.expanded = !self.expanded;
+    fn item_rect(&self, rect: Rect, item: &Item, cx: &Context) -> Rect {
+        // Even the smallest allocations stay visible
+        const MIN_HEIGHT: f32 = 1.0;
+
+        let span = Slot::item_rect(rect, 1, 0, item.interval, cx).x_range();
+        let allocation = item.allocation.unwrap();
+        let capacity = self.capacity.max(1) as f32;
+        let bottom = rect.bottom() - rect.height() * allocation.offset as f32 / capacity;
+        let height = (rect.height() * allocation.size as f32 / capacity).at_least(MIN_HEIGHT);
+        Rect::from_x_y_ranges(span, (bottom - height)..=bottom)
     }
-}
 
-impl<S: Entry> Panel<S> {
-    fn render<T: Entry>(
+    fn render(
+        &self,
+        slot: &Slot,
+        hover_pos: Option<Pos2>,
         ui: &mut egui::Ui,
         rect: Rect,
-        viewport: Rect,
-        slot: &mut T,
-        y: &mut f32,
-        config: &mut Config,
-        cx: &mut Context,
-    ) -> bool {
-        const LABEL_WIDTH: f32 = 60.0;
-        const COL_PADDING: f32 = 4.0;
-        const ROW_PADDING: f32 = 4.0;
-
-        // Compute the size of this slot
-        // This is in screen (i.e., rect) space
-        let min_y = *y;
-        let max_y = min_y + slot.height(config, cx);
-        *y = max_y + ROW_PADDING;
+        config: &Config,
+        cx: &Context,
+    ) -> Option<(u64, &Item)> {
+        let selected = config.selected.as_ref().map(|s| s.item.item_uid);
+        let mut hovered = None;
+        let mut items = ItemMesh::default();
+        for (row, item) in self.visible(cx.view_interval) {
+            let color = if let Some(color) = cx.item_color(&slot.entry_id, item, config) {
+                color
+            } else {
+                continue;
+            };
+            let item_rect = self.item_rect(rect, item, cx);
+            let highlight = cx.search.is_match(&slot.entry_id, item);
+            let stroke = if selected == Some(item.item_uid) {
+                Stroke::new(2.0, ui.visuals().strong_text_color())
+            } else if highlight {
+                ui.visuals().selection.stroke
+            } else {
+                Stroke::new(0.5, ui.visuals().extreme_bg_color)
+            };
+            items.rect(item_rect, color, stroke);
+            config.frame_stats.paint();
+
+            // Later allocations are drawn on top, so they win
+            if hover_pos.map_or(false, |h| item_rect.contains(h)) {
+                hovered = Some((*row, item));
+            }
+        }
+        items.paint(ui);
+
+        if let Some((_, item)) = hovered {
+            let item_rect = self.item_rect(rect, item, cx);
+            ui.show_tooltip_ui("task_tooltip", &item_rect, |ui| {
+                ui.label(&item.title);
+                let allocation = item.allocation.unwrap();
+                ui.label(format!(
+                    "Address: {:#x}–{:#x} ({})",
+                    allocation.offset,
+                    allocation.offset + allocation.size,
+                    bytes_text(allocation.size)
+                ));
+                show_fields(ui, &item.fields);
+                show_original_color(ui, item, cx);
+            });
+        }
+        hovered
+    }
 
-        // Cull if out of bounds
-        // Note: need to shift by rect.min to get to viewport space
-        if max_y - rect.min.y < viewport.min.y {
-            return false;
-        } else if min_y - rect.min.y > viewport.max.y {
-            return true;
+    fn export_svg(
+        &self,
+        slot: &Slot,
+        svg: &mut SvgWriter,
+        rect: Rect,
+        config: &Config,
+        cx: &Context,
+    ) {
+        for (_, item) in self.visible(cx.view_interval) {
+            if let Some(color) = cx.item_color(&slot.entry_id, item, config) {
+                let item_rect = self.item_rect(rect, item, cx);
+                svg.rect(item_rect, color, Some(SVG_SLOT_FILL), Some(&item.title));
+            }
         }
+    }
+}
 
-        // Draw label and content
-        let label_min = rect.min.x;
-        let label_max = (rect.min.x + LABEL_WIDTH).at_most(rect.max.x);
-        let content_min = (label_max + COL_PADDING).at_most(rect.max.x);
-        let content_max = rect.max.x;
-
-        let label_subrect =
-            Rect::from_min_max(Pos2::new(label_min, min_y), Pos2::new(label_max, max_y));
-        let content_subrect =
-            Rect::from_min_max(Pos2::new(content_min, min_y), Pos2::new(content_max, max_y));
+impl Histogram {
+    const BUCKETS: usize = 32;
 
-        // Shift viewport up by the amount consumed
-        // Invariant: (0, 0) in viewport is rect.min
-        //   (i.e., subtracting rect.min gets us from screen space to viewport space)
-        // Note: viewport.min is NOT necessarily (0, 0)
-        let content_viewport = viewport.translate(Vec2::new(0.0, rect.min.y - min_y));
+    // Position of a duration along the x axis, from 0 to 1
+    fn fraction(&self, duration: i64, (min, max): (i64, i64)) -> f32 {
+        if max <= min {
+            return 0.0;
+        }
+        if self.log_scale {
+            let (min, max) = (min.max(1) as f64, max.max(1) as f64);
+            ((duration.max(1) as f64 / min).ln() / (max / min).ln()) as f32
+        } else {
+            ((duration - min) as f64 / (max - min) as f64) as f32
+        }
+    }
 
-        slot.content(ui, content_subrect, content_viewport, config, cx);
-        slot.label(ui, label_subrect);
+    // Inverse of fraction
+    fn duration(&self, fraction: f32, (min, max): (i64, i64)) -> i64 {
+        let fraction = fraction as f64;
+        if self.log_scale {
+            let (min, max) = (min.max(1) as f64, max.max(1) as f64);
+            (min * (max / min).powf(fraction)).round() as i64
+        } else {
+            min + ((max - min) as f64 * fraction).round() as i64
+        }
+    }
 
-        false
+    fn counts(&self, durations: &[i64], range: (i64, i64)) -> Vec<u64> {
+        let mut counts = vec![0; Self::BUCKETS];
+        for &duration in durations {
+            let bucket = (self.fraction(duration, range) * Self::BUCKETS as f32) as usize;
+            counts[bucket.min(Self::BUCKETS - 1)] += 1;
+        }
+        counts
     }
 
-    fn is_slot_visible(entry_id: &EntryID, config: &Config) -> bool {
-        let index = entry_id.last_slot_index().unwrap();
-        entry_id.level() != 1 || (index >= config.min_node && index <= config.max_node)
+    fn is_brushed(&self, duration: i64) -> bool {
+        self.brush
+            .map_or(false, |(min, max)| min <= duration && duration <= max)
     }
 }
 
-impl<S: Entry> Entry for Panel<S> {
-    fn new(info: &EntryInfo, entry_id: EntryID) -> Self {
-        if let EntryInfo::Panel {
-            short_name,
-            long_name,
-            summary,
-            slots,
-        } = info
-        {
-            let expanded = entry_id.level() != 2;
-            let summary = summary
-                .as_ref()
-                .map(|s| Summary::new(s, entry_id.summary()));
-            let slots = slots
+impl SortKey {
+    const ALL: &'static [SortKey] = &[
+        SortKey::Original,
+        SortKey::BusyTime,
+        SortKey::PeakUtilization,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Original => "Original",
+            SortKey::BusyTime => "Busy Time",
+            SortKey::PeakUtilization => "Peak Utilization",
+        }
+    }
+
+    // How active a utilization curve is over the view, by this key
+    fn activity(self, utilization: &[UtilPoint], view: Interval) -> f64 {
+        match self {
+            SortKey::Original => 0.0,
+            SortKey::BusyTime => utilization
+                .windows(2)
+                .map(|p| {
+                    let overlap = Interval::new(p[0].time, p[1].time).intersection(view);
+                    overlap.duration_ns().max(0) as f64 * (p[0].util + p[1].util) as f64 * 0.5
+                })
+                .sum(),
+            SortKey::PeakUtilization => utilization
                 .iter()
-                .enumerate()
-                .map(|(i, s)| S::new(s, entry_id.child(i as u64)))
-                .collect();
-            Self {
-                entry_id,
-                short_name: short_name.to_owned(),
-                long_name: long_name.to_owned(),
-                expanded,
-                summary,
-                slots,
-            }
-        } else {
-            unreachable!()
+                .filter(|p| view.contains(p.time))
+                .map(|p| p.util as f64)
+                .fold(0.0, f64::max),
         }
     }
+}
 
-    fn entry_id(&self) -> &EntryID {
-        &self.entry_id
+impl ColorRamp {
+    const ALL: &'static [ColorRamp] = &[
+        ColorRamp::Viridis,
+        ColorRamp::Inferno,
+        ColorRamp::BlueRed,
+        ColorRamp::Grayscale,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ColorRamp::Viridis => "Viridis",
+            ColorRamp::Inferno => "Inferno",
+            ColorRamp::BlueRed => "Blue-Red",
+            ColorRamp::Grayscale => "Grayscale",
+        }
     }
-    fn label_text(&self) -> &str {
-        &self.short_name
+
+    // Evenly spaced stops, interpolated in between
+    fn stops(self) -> &'static [[u8; 3]] {
+        match self {
+            ColorRamp::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            ColorRamp::Inferno => &[
+                [0, 0, 4],
+                [87, 16, 110],
+                [188, 55, 84],
+                [249, 142, 9],
+                [252, 255, 164],
+            ],
+            ColorRamp::BlueRed => &[[49, 54, 149], [224, 243, 248], [165, 0, 38]],
+            ColorRamp::Grayscale => &[[32, 32, 32], [224, 224, 224]],
+        }
     }
-    fn hover_text(&self) -> &str {
-        &self.long_name
+
+    // Color at t in [0, 1]
+    fn color(self, t: f32) -> Color32 {
+        let stops = self.stops();
+        let t = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let i = (t as usize).min(stops.len() - 2);
+        let (a, b, f) = (stops[i], stops[i + 1], t - i as f32);
+        let mix = |c: usize| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * f).round() as u8;
+        Color32::from_rgb(mix(0), mix(1), mix(2))
     }
+}
 
-    fn content(
-        &mut self,
-        ui: &mut egui::Ui,
-        rect: Rect,
-        viewport: Rect,
-        config: &mut Config,
-        cx: &mut Context,
-    ) {
-        let mut y = rect.min.y;
-        if let Some(summary) = &mut self.summary {
-            Self::render(ui, rect, viewport, summary, &mut y, config, cx);
+impl Default for ColorEditor {
+    fn default() -> Self {
+        Self {
+            open: false,
+            by_kind: false,
+            key: String::new(),
+            color: Color32::GRAY,
+            status: None,
         }
+    }
+}
 
-        if self.expanded {
-            for slot in &mut self.slots {
-                // Apply visibility settings
-                if !Self::is_slot_visible(slot.entry_id(), config) {
-                    continue;
+impl Legend {
+    fn add(&mut self, item: &Item, color: Color32) {
+        if !self.counted.insert(item.item_uid) {
+            return;
+        }
+        if let Some((_, count)) = self.names.get_mut(&item.title) {
+            *count += 1;
+        } else {
+            self.names.insert(item.title.clone(), (color, 1));
+        }
+    }
+}
+
+impl Context {
+    // Colors from the data source go through here before being drawn
+    fn themed(&self, color: Color32) -> Color32 {
+        self.theme.apply(color, self.dark_mode)
+    }
+
+    // Color the item is drawn in when colored by the data
+    fn data_color(&self, entry_id: &EntryID, item: &Item, config: &Config) -> Color32 {
+        // Chosen by the user, so drawn as is
+        let kind = config.slot_kinds.get(entry_id).map(String::as_str);
+        match self.color_overrides.get(&item.title, kind) {
+            Some(color) => color,
+            None => self.themed(item.color.into()),
+        }
+    }
+
+    // Color to draw the item in, or None if it's filtered out
+    fn item_color(&self, entry_id: &EntryID, item: &Item, config: &Config) -> Option<Color32> {
+        let color = match self.color_mode {
+            ColorMode::Data => self.data_color(entry_id, item, config),
+            ColorMode::Duration => self.color_ramp.color(config.heat(item)),
+        };
+        self.filters.color(entry_id, item, color)
+    }
+}
+
+// Each memory gets a color of its own, so that copies between the same
+// memories can be spotted at a glance
+fn memory_color(entry_id: &EntryID) -> Color32 {
+    const PALETTE: &[Color32] = &[
+        Color32::from_rgb(0x1f, 0x77, 0xb4),
+        Color32::from_rgb(0xff, 0x7f, 0x0e),
+        Color32::from_rgb(0x2c, 0xa0, 0x2c),
+        Color32::from_rgb(0xd6, 0x27, 0x28),
+        Color32::from_rgb(0x94, 0x67, 0xbd),
+        Color32::from_rgb(0x8c, 0x56, 0x4b),
+        Color32::from_rgb(0xe3, 0x77, 0xc2),
+        Color32::from_rgb(0x17, 0xbe, 0xcf),
+    ];
+    let mut hasher = DefaultHasher::new();
+    entry_id.hash(&mut hasher);
+    PALETTE[(hasher.finish() % PALETTE.len() as u64) as usize]
+}
+
+fn show_endpoints(ui: &mut egui::Ui, item: &Item, config: &Config) {
+    let endpoints = if let Some(endpoints) = &item.endpoints {
+        endpoints
+    } else {
+        return;
+    };
+    for (label, memory) in [("From:", &endpoints.source), ("To:", &endpoints.target)] {
+        ui.horizontal(|ui| {
+            ui.weak(label);
+            match memory {
+                Some(memory) => {
+                    ui.colored_label(memory_color(memory), "⏺");
+                    let name = config.slot_names.get(memory);
+                    ui.label(name.map_or_else(|| memory.to_string(), String::clone));
+                }
+                None => {
+                    ui.label("(none)");
                 }
+            }
+        });
+    }
+}
 
-                if Self::render(ui, rect, viewport, slot, &mut y, config, cx) {
-                    break;
+// When items are colored by something else, the tooltip still shows the
+// color the data source gave them
+fn show_original_color(ui: &mut egui::Ui, item: &Item, cx: &Context) {
+    if cx.color_mode != ColorMode::Data {
+        ui.horizontal(|ui| {
+            ui.label("Color:");
+            ui.colored_label(item.color, "⬛");
+        });
+    }
+}
+
+// Where items overlap, the top one is drawn darker the more are under it,
+// rather than simply hiding them. Depth is how many are under it (on
+// average, for merged items), so 0 leaves the color as it is.
+fn overlap_color(color: Color32, depth: f32) -> Color32 {
+    const SHADE_PER_ITEM: f32 = 0.35;
+    // Ignore rounding in the widths of items that only touch
+    if depth < 0.01 {
+        return color;
+    }
+    let factor = 1.0 / (1.0 + SHADE_PER_ITEM * depth);
+    let [r, g, b, a] = color.to_array();
+    let shade = |c: u8| (c as f32 * factor).round() as u8;
+    Color32::from_rgba_premultiplied(shade(r), shade(g), shade(b), a)
+}
+
+// Waiting time is drawn as a darker shade of the item's color
+fn waiting_color(color: Color32) -> Color32 {
+    let [r, g, b, a] = color.to_array();
+    Color32::from_rgba_premultiplied(r / 3, g / 3, b / 3, a)
+}
+
+fn waiting_ns(item: &Item) -> i64 {
+    item.segments
+        .iter()
+        .filter(|segment| segment.state == ItemState::Waiting)
+        .map(|segment| {
+            segment
+                .interval
+                .intersection(item.interval)
+                .duration_ns()
+                .max(0)
+        })
+        .sum()
+}
+
+impl Counter {
+    // Values in view, as (interval, value) steps
+    fn steps(&self, view: Interval) -> Vec<(Interval, f64)> {
+        let mut steps = Vec::new();
+        for tile in &self.tiles {
+            for (i, point) in tile.points.iter().enumerate() {
+                let stop = tile
+                    .points
+                    .get(i + 1)
+                    .map_or(tile.tile_id.0.stop, |p| p.time);
+                let interval = Interval::new(point.time, stop);
+                if view.overlaps(interval) {
+                    steps.push((interval, point.value));
                 }
             }
         }
+        steps
     }
 
-    fn height(&self, config: &Config, cx: &Context) -> f32 {
-        const UNEXPANDED_ROWS: u64 = 2;
-        const ROW_PADDING: f32 = 4.0;
+    // Top of the y-axis: a round number at or above the largest value
+    fn scale(steps: &[(Interval, f64)]) -> f64 {
+        let max = steps.iter().map(|(_, v)| *v).fold(0.0, f64::max);
+        if max <= 0.0 {
+            return 1.0;
+        }
+        let magnitude = 10.0_f64.powf(max.log10().floor());
+        for step in [1.0, 2.0, 5.0, 10.0] {
+            if max <= step * magnitude {
+                return step * magnitude;
+            }
+        }
+        unreachable!()
+    }
 
-        let mut total = 0.0;
-        let mut rows: i64 = 0;
-        if let Some(summary) = &self.summary {
-            total += summary.height(config, cx);
-            rows += 1;
-        } else if !self.expanded {
-            // Need some minimum space if this panel has no summary and is collapsed
-            total += UNEXPANDED_ROWS as f32 * cx.row_height;
-            rows += 1;
+    // Screen space rects for each step, from the baseline up to the value
+    fn step_rects(steps: &[(Interval, f64)], scale: f64, rect: Rect, cx: &Context) -> Vec<Rect> {
+        steps
+            .iter()
+            .map(|(interval, value)| {
+                let start = cx.view_interval.unlerp(interval.start).at_least(0.0) as f32;
+                let stop = cx.view_interval.unlerp(interval.stop).at_most(1.0) as f32;
+                let top = 1.0 - (value / scale) as f32;
+                Rect::from_min_max(
+                    rect.lerp(Vec2::new(start, top)),
+                    rect.lerp(Vec2::new(stop, 1.0)),
+                )
+            })
+            .collect()
+    }
+
+    // Outline of the steps as a line
+    fn outline(rects: &[Rect]) -> Vec<Pos2> {
+        rects
+            .iter()
+            .flat_map(|r| [r.left_top(), r.right_top()])
+            .collect()
+    }
+
+    fn render(&self, hover_pos: Option<Pos2>, ui: &mut egui::Ui, rect: Rect, cx: &Context) {
+        let steps = self.steps(cx.view_interval);
+        let scale = Self::scale(&steps);
+        let rects = Self::step_rects(&steps, scale, rect, cx);
+        let color = cx.themed(self.color);
+
+        match self.style {
+            CounterStyle::Line => {
+                let stroke = Stroke::new(1.5, color);
+                ui.painter()
+                    .add(egui::Shape::line(Self::outline(&rects), stroke));
+            }
+            CounterStyle::Bar => {
+                for step_rect in &rects {
+                    ui.painter().rect_filled(*step_rect, 0.0, color);
+                }
+            }
         }
 
-        if self.expanded {
-            for slot in &self.slots {
-                // Apply visibility settings
-                if !Self::is_slot_visible(slot.entry_id(), config) {
-                    continue;
+        // Label the top of the axis
+        ui.painter().text(
+            rect.left_top() + Vec2::splat(4.0),
+            Align2::LEFT_TOP,
+            format_counter(scale, &self.unit),
+            TextStyle::Small.resolve(ui.style()),
+            ui.visuals().weak_text_color(),
+        );
+
+        if let Some(hover) = hover_pos {
+            let hovered = rects
+                .iter()
+                .position(|r| r.left() <= hover.x && hover.x < r.right());
+            if let Some(i) = hovered {
+                let (interval, value) = steps[i];
+                let marker = Rect::from_min_max(
+                    Pos2::new(hover.x, rect.top()),
+                    Pos2::new(hover.x, rect.bottom()),
+                );
+                ui.painter().line_segment(
+                    [marker.min, marker.max],
+                    Stroke::new(1.0, ui.visuals().weak_text_color()),
+                );
+                ui.show_tooltip_ui("counter_tooltip", &marker, |ui| {
+                    ui.label(format_counter(value, &self.unit));
+                    ui.label(format!("Interval: {}", interval));
+                });
+            }
+        }
+    }
+
+    fn export_svg(&self, svg: &mut SvgWriter, rect: Rect, cx: &Context) {
+        let steps = self.steps(cx.view_interval);
+        let scale = Self::scale(&steps);
+        let rects = Self::step_rects(&steps, scale, rect, cx);
+        let color = cx.themed(self.color);
+
+        match self.style {
+            CounterStyle::Line => svg.polyline(&Self::outline(&rects), color, 1.5),
+            CounterStyle::Bar => {
+                for step_rect in rects {
+                    svg.rect(step_rect, color, None, None);
                 }
+            }
+        }
 
-                total += slot.height(config, cx);
-                rows += 1;
+        let font_size = svg.options().label_font_size * 0.8;
+        svg.text(
+            rect.left_top() + Vec2::new(4.0, 4.0 + font_size),
+            font_size,
+            TextAnchor::Start,
+            SVG_TEXT,
+            &format_counter(scale, &self.unit),
+            None,
+        );
+    }
+}
+
+impl Events {
+    const FLAG_SIZE: f32 = 6.0;
+
+    // Events in view, with their x coordinates
+    fn visible<'a>(
+        &'a self,
+        rect: Rect,
+        cx: &'a Context,
+    ) -> impl Iterator<Item = (f32, &'a Event)> + 'a {
+        self.tiles
+            .iter()
+            .flat_map(|tile| &tile.events)
+            .filter(move |event| cx.view_interval.contains(event.time))
+            .map(move |event| {
+                let x = cx.view_interval.unlerp(event.time) as f32;
+                (rect.lerp(Vec2::new(x, 0.0)).x, event)
+            })
+    }
+
+    fn flag(x: f32, rect: Rect) -> [Pos2; 3] {
+        [
+            Pos2::new(x, rect.top()),
+            Pos2::new(x + Self::FLAG_SIZE, rect.top() + Self::FLAG_SIZE * 0.5),
+            Pos2::new(x, rect.top() + Self::FLAG_SIZE),
+        ]
+    }
+
+    fn render(
+        &self,
+        hover_pos: Option<Pos2>,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        config: &mut Config,
+        cx: &Context,
+    ) {
+        let color = cx.themed(self.color);
+        let stroke = Stroke::new(1.0, color);
+        let mut hovered = None;
+        for (x, event) in self.visible(rect, cx) {
+            ui.painter().line_segment(
+                [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                stroke,
+            );
+            ui.painter().add(egui::Shape::convex_polygon(
+                Self::flag(x, rect).to_vec(),
+                color,
+                Stroke::NONE,
+            ));
+
+            if self.global {
+                config.event_lines.push((event.time, color));
+            }
+
+            // Prefer the event closest to the mouse
+            if let Some(hover) = hover_pos {
+                let distance = (hover.x - x).abs();
+                if distance <= Self::FLAG_SIZE && hovered.map_or(true, |(d, _, _)| distance < d) {
+                    hovered = Some((distance, x, event));
+                }
             }
         }
 
-        total += (rows - 1).at_least(0) as f32 * ROW_PADDING;
+        if let Some((_, x, event)) = hovered {
+            let flag_rect = Rect::from_min_max(
+                Pos2::new(x, rect.top()),
+                Pos2::new(x + Self::FLAG_SIZE, rect.bottom()),
+            );
+            ui.show_tooltip_ui("event_tooltip", &flag_rect, |ui| {
+                ui.label(&event.title);
+                ui.label(format!("Time: {}", event.time));
+                show_fields(ui, &event.fields);
+            });
+        }
+    }
 
-        total
+    fn export_svg(&self, svg: &mut SvgWriter, rect: Rect, cx: &Context) {
+        let color = cx.themed(self.color);
+        for (x, _) in self.visible(rect, cx) {
+            let (top, bottom) = (Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom()));
+            svg.line(top, bottom, color, 1.0);
+            svg.polygon(&Self::flag(x, rect), color);
+        }
     }
+}
 
-    fn is_expandable(&self) -> bool {
-        !self.slots.is_empty()
+// Formats a counter value with a metric prefix (or binary, for bytes)
+fn format_counter(value: f64, unit: &str) -> String {
+    let (base, prefixes) = if unit.starts_with('B') {
+        (1024.0, ["", "Ki", "Mi", "Gi", "Ti", "Pi"])
+    } else {
+        (1000.0, ["", "k", "M", "G", "T", "P"])
+    };
+    let mut value = value;
+    let mut prefix = 0;
+    while value.abs() >= base && prefix + 1 < prefixes.len() {
+        value /= base;
+        prefix += 1;
     }
+    let result = if prefix == 0 && value.fract() == 0.0 {
+        format!("{} {}", value, unit)
+    } else {
+        format!("{:.2} {}{}", value, prefixes[prefix], unit)
+    };
+    result.trim_end().to_owned()
+}
 
-    fn toggle_expanded(&mut self) {
-        self.expanded = !self.expanded;
+// Draws an error badge over a slot. Returns true if the user asked to retry.
+fn show_error(ui: &mut egui::Ui, rect: Rect, error: &DataSourceError) -> bool {
+    const BUTTON_SIZE: Vec2 = Vec2::new(48.0, 20.0);
+    const PADDING: f32 = 4.0;
+
+    let color = ui.visuals().error_fg_color;
+    ui.painter()
+        .rect_filled(rect, 0.0, color.linear_multiply(0.1));
+
+    let button_size = Vec2::new(BUTTON_SIZE.x, BUTTON_SIZE.y.min(rect.height()));
+    let button_rect = Rect::from_center_size(
+        Pos2::new(
+            rect.right() - PADDING - button_size.x * 0.5,
+            rect.center().y,
+        ),
+        button_size,
+    );
+    let text_rect = Rect::from_min_max(rect.min, Pos2::new(button_rect.left(), rect.bottom()));
+    ui.painter().with_clip_rect(text_rect).text(
+        text_rect.left_center() + Vec2::new(PADDING, 0.0),
+        Align2::LEFT_CENTER,
+        format!("⚠ {}", error),
+        TextStyle::Body.resolve(ui.style()),
+        color,
+    );
+
+    ui.put(button_rect, egui::Button::new("Retry").small())
+        .on_hover_text(error.to_string())
+        .clicked()
+}
+
+// Draws a pulsing placeholder over the parts of a slot still being fetched
+fn show_loading(
+    ui: &mut egui::Ui,
+    rect: Rect,
+    pending_tiles: Option<&VecDeque<TileID>>,
+    cx: &Context,
+) {
+    let pending_tiles = if let Some(pending_tiles) = pending_tiles {
+        pending_tiles
+    } else {
+        return;
+    };
+    if pending_tiles.is_empty() {
+        return;
+    }
+
+    let time = ui.input().time as f32;
+    let alpha = 0.15 + 0.1 * (time * 4.0).sin();
+    let color = ui.visuals().weak_text_color().linear_multiply(alpha);
+    for tile_id in pending_tiles {
+        let start = cx.view_interval.unlerp(tile_id.0.start).at_least(0.0) as f32;
+        let stop = cx.view_interval.unlerp(tile_id.0.stop).at_most(1.0) as f32;
+        if start >= stop {
+            continue;
+        }
+        let tile_rect = Rect::from_min_max(
+            rect.lerp(Vec2::new(start, 0.0)),
+            rect.lerp(Vec2::new(stop, 1.0)),
+        );
+        ui.painter().rect_filled(tile_rect, 0.0, color);
     }
+
+    // Keep animating (and fetching) until everything arrives
+    ui.ctx().request_repaint();
 }
 
-impl Config {
-    fn new(mut data_source: Box<dyn DataSource>) -> Self {
-        let max_node = data_source.fetch_info().nodes();
-        Self {
-            min_node: 0,
-            max_node,
+fn fetch_utilization(
+    data_source: &mut dyn DataSource,
+    entry_id: &EntryID,
+    interval: Interval,
+) -> DataSourceResult<Vec<UtilPoint>> {
+    let mut utilization = Vec::new();
+    for tile_id in data_source.request_tiles(entry_id, interval)? {
+        let tile = data_source.fetch_summary_tile(entry_id, tile_id)?;
+        utilization.extend(tile.utilization);
+    }
+    Ok(utilization)
+}
 
-            interval: data_source.interval(),
+// From the first to the last point of a utilization curve that's nonzero
+fn nonzero_interval(utilization: &[UtilPoint]) -> Option<Interval> {
+    let first = utilization.iter().find(|p| p.util > 0.0)?;
+    let last = utilization.iter().rev().find(|p| p.util > 0.0)?;
+    Some(Interval::new(first.time, last.time))
+}
 
-            data_source,
+// Linear interpolation of a utilization curve at the given time
+fn sample_utilization(utilization: &[UtilPoint], time: Timestamp) -> f32 {
+    let index = utilization.partition_point(|p| p.time < time);
+    if let Some(p) = utilization.get(index).filter(|p| p.time == time) {
+        return p.util;
+    }
+    if index == 0 || index == utilization.len() {
+        return 0.0;
+    }
+    let (p1, p2) = (&utilization[index - 1], &utilization[index]);
+    let ratio = Interval::new(p1.time, p2.time).unlerp(time) as f32;
+    p1.util + (p2.util - p1.util) * ratio
+}
+
+fn field_text(name: &str, field: &Field) -> String {
+    match field {
+        Field::Empty => name.to_owned(),
+        _ => format!("{}: {}", name, field_value(field)),
+    }
+}
+
+fn field_value(field: &Field) -> String {
+    match field {
+        Field::I64(value) => value.to_string(),
+        Field::U64(value) => value.to_string(),
+        Field::String(value) => value.clone(),
+        Field::Interval(value) => format!("{} ({})", value, Timestamp(value.duration_ns())),
+        Field::Empty => String::new(),
+        Field::F64(value) => value.to_string(),
+        Field::Bytes(value) => bytes_text(*value),
+        Field::Percent(value) => format!("{:.1}%", value * 100.0),
+        Field::Link { label, url } => format!("{} <{}>", label, url),
+    }
+}
+
+fn bytes_text(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn field_json(field: &Field) -> serde_json::Value {
+    match field {
+        Field::I64(value) => (*value).into(),
+        Field::U64(value) => (*value).into(),
+        Field::String(value) => value.as_str().into(),
+        Field::Interval(value) => serde_json::json!({
+            "start_ns": value.start.0,
+            "stop_ns": value.stop.0,
+        }),
+        Field::Empty => serde_json::Value::Null,
+        Field::F64(value) | Field::Percent(value) => (*value).into(),
+        Field::Bytes(value) => (*value).into(),
+        Field::Link { label, url } => serde_json::json!({ "label": label, "url": url }),
+    }
+}
+
+// Everything about an item (in the slot at path), for the clipboard
+fn item_details(item: &Item, path: &str, json: bool) -> String {
+    if json {
+        let fields: serde_json::Map<_, _> = item
+            .fields
+            .iter()
+            .map(|(name, field)| (name.clone(), field_json(field)))
+            .collect();
+        let details = serde_json::json!({
+            "title": item.title,
+            "slot": path,
+            "start_ns": item.interval.start.0,
+            "stop_ns": item.interval.stop.0,
+            "duration_ns": item.interval.duration_ns(),
+            "fields": fields,
+        });
+        return serde_json::to_string_pretty(&details).expect("JSON values always serialize");
+    }
+    let mut text = String::new();
+    writeln!(text, "{}", item.title).unwrap();
+    writeln!(text, "Slot: {}", path).unwrap();
+    writeln!(text, "Interval: {}", item.interval).unwrap();
+    writeln!(text, "Duration: {}", Timestamp(item.interval.duration_ns())).unwrap();
+    for (name, field) in &item.fields {
+        writeln!(text, "{}", field_text(name, field)).unwrap();
+    }
+    text
+}
+
+fn csv_escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+// Items can have dozens of fields, so tooltips (which can't be scrolled)
+// and the details panel (until asked for more) only show this many
+const MAX_FIELDS_SHOWN: usize = 8;
+
+fn field_grid(ui: &mut egui::Ui, id_source: impl Hash, fields: &[(String, Field)]) {
+    egui::Grid::new(id_source).num_columns(2).show(ui, |ui| {
+        for (name, field) in fields {
+            ui.weak(name);
+            match field {
+                Field::Link { label, url } => ui.hyperlink_to(label, url),
+                _ => ui.label(field_value(field)),
+            };
+            ui.end_row();
         }
+    });
+}
+
+// For tooltips, where holding Shift shows the fields that don't fit
+fn show_fields(ui: &mut egui::Ui, fields: &[(String, Field)]) {
+    let shown = if ui.input().modifiers.shift {
+        fields.len()
+    } else {
+        fields.len().min(MAX_FIELDS_SHOWN)
+    };
+    field_grid(ui, "fields", &fields[..shown]);
+    if shown < fields.len() {
+        ui.weak(format!(
+            "{} more… (hold Shift to show all)",
+            fields.len() - shown
+        ));
     }
 }
 
-impl Window {
-    fn new(data_source: Box<dyn DataSource>, index: u64) -> Self {
-        let mut config = Config::new(data_source);
+// For the details panel, where the rest can be expanded
+fn show_all_fields(ui: &mut egui::Ui, id_source: impl Hash + Copy, fields: &[(String, Field)]) {
+    let shown = fields.len().min(MAX_FIELDS_SHOWN);
+    field_grid(ui, ("fields", id_source), &fields[..shown]);
+    if shown < fields.len() {
+        egui::CollapsingHeader::new(format!("{} more…", fields.len() - shown))
+            .id_source(("more_fields", id_source))
+            .show(ui, |ui| {
+                field_grid(ui, ("more_fields", id_source), &fields[shown..]);
+            });
+    }
+}
 
-        Self {
-            panel: Panel::new(config.data_source.fetch_info(), EntryID::root()),
-            index,
-            kinds: config.data_source.fetch_info().kinds(),
-            config,
+impl ItemMesh {
+    fn rect(&mut self, rect: Rect, color: Color32, stroke: Stroke) {
+        self.mesh.add_colored_rect(rect, color);
+        if stroke.width > 0.0 && stroke.color != Color32::TRANSPARENT {
+            self.outlines.push((rect, stroke));
         }
     }
 
-    fn content(&mut self, ui: &mut egui::Ui, cx: &mut Context) {
-        ui.heading(format!("Profile {}", self.index));
+    fn paint(self, ui: &egui::Ui) {
+        let painter = ui.painter();
+        painter.add(self.mesh);
+        for (rect, stroke) in self.outlines {
+            painter.rect_stroke(rect, 0.0, stroke);
+        }
+        let font_id = TextStyle::Small.resolve(ui.style());
+        for (rect, title, color) in self.labels {
+            painter.with_clip_rect(rect).text(
+                rect.left_center() + Vec2::new(2.0, 0.0),
+                Align2::LEFT_CENTER,
+                title,
+                font_id.clone(),
+                color,
+            );
+        }
+    }
+}
 
-        ScrollArea::vertical()
-            .auto_shrink([false; 2])
-            .show_viewport(ui, |ui, viewport| {
-                let height = self.panel.height(&self.config, cx);
-                ui.set_height(height);
-                ui.set_width(ui.available_width());
+impl RowOverlap {
+    // The part of the item on top of others, and how many deep, if any
+    fn add(&mut self, item_rect: Rect) -> Option<(Rect, usize)> {
+        self.ends.retain(|end| *end > item_rect.min.x);
+        let depth = self.ends.len();
+        let covered = self.ends.iter().copied().fold(item_rect.min.x, f32::max);
+        self.ends.push(item_rect.max.x);
+        if depth == 0 {
+            return None;
+        }
+        let max = Pos2::new(covered.min(item_rect.max.x), item_rect.max.y);
+        Some((Rect::from_min_max(item_rect.min, max), depth))
+    }
+}
+
+impl ItemBlock {
+    // Items narrower than this (in screen space) get merged
+    const MIN_WIDTH: f32 = 1.0;
+
+    fn new(interval: Interval, item_rect: Rect, color: Color32, highlight: bool) -> Self {
+        let mut result = Self {
+            rect: item_rect,
+            interval,
+            count: 0,
+            busy: 0.0,
+            color: [0.0; 4],
+            highlight: false,
+        };
+        result.merge(interval, item_rect, color, highlight);
+        result
+    }
+
+    fn can_merge(&self, item_rect: Rect) -> bool {
+        item_rect.min.x - self.rect.max.x < Self::MIN_WIDTH
+    }
+
+    fn merge(&mut self, interval: Interval, item_rect: Rect, color: Color32, highlight: bool) {
+        self.rect = self.rect.union(item_rect);
+        self.interval = self.interval.union(interval);
+        self.count += 1;
+        self.highlight |= highlight;
+
+        // Weight the color of each item by its (screen space) width
+        let weight = item_rect.width();
+        self.busy += weight;
+        for (c, v) in self.color.iter_mut().zip(color.to_array()) {
+            *c += v as f32 * weight;
+        }
+    }
+
+    fn render(
+        self,
+        row_hover: bool,
+        mut hover_pos: Option<Pos2>,
+        ui: &mut egui::Ui,
+        items: &mut ItemMesh,
+    ) -> Option<Pos2> {
+        let (rect, color) = self.display();
+
+        if row_hover && hover_pos.map_or(false, |h| rect.contains(h)) {
+            hover_pos = None;
+
+            ui.show_tooltip_ui("task_tooltip", &rect, |ui| {
+                ui.label(format!("{} merged items", self.count));
+                ui.label(format!("Interval: {}", self.interval));
+            });
+        }
+
+        let stroke = if self.highlight {
+            ui.visuals().selection.stroke
+        } else {
+            Stroke::NONE
+        };
+        items.rect(rect, color, stroke);
+        hover_pos
+    }
+
+    fn export_svg(self, svg: &mut SvgWriter) {
+        let (rect, color) = self.display();
+        let title = format!("{} merged items\nInterval: {}", self.count, self.interval);
+        svg.rect(rect, color, None, Some(&title));
+    }
+
+    // Rect and color to draw the block with
+    fn display(&self) -> (Rect, Color32) {
+        // Make sure the block is always visible
+        let mut rect = self.rect;
+        if rect.width() < Self::MIN_WIDTH {
+            rect = Rect::from_center_size(rect.center(), Vec2::new(Self::MIN_WIDTH, rect.height()));
+        }
+
+        // Average color, faded by the fraction of the block that is busy.
+        // Past all of it, the items overlap, so it's darkened instead.
+        let [r, g, b, a] = self.color.map(|c| (c / self.busy.max(f32::EPSILON)) as u8);
+        let density = self.busy / rect.width();
+        let color =
+            Color32::from_rgba_premultiplied(r, g, b, a).linear_multiply(density.clamp(0.2, 1.0));
+        (rect, overlap_color(color, density - 1.0))
+    }
+}
+
+impl Entry for Slot {
+    fn new(info: &EntryInfo, entry_id: EntryID) -> Self {
+        const COUNTER_ROWS: u64 = 3;
+        const EVENT_ROWS: u64 = 1;
+
+        let mut events = None;
+        let (short_name, long_name, max_rows, counter) = match info {
+            EntryInfo::Slot {
+                short_name,
+                long_name,
+                max_rows,
+            } => (short_name, long_name, *max_rows, None),
+            EntryInfo::Events {
+                short_name,
+                long_name,
+                color,
+                global,
+            } => {
+                events = Some(Events {
+                    color: (*color).into(),
+                    global: *global,
+                    tiles: Vec::new(),
+                });
+                (short_name, long_name, EVENT_ROWS, None)
+            }
+            EntryInfo::Counter {
+                short_name,
+                long_name,
+                unit,
+                color,
+                style,
+            } => {
+                let counter = Counter {
+                    unit: unit.to_owned(),
+                    color: (*color).into(),
+                    style: *style,
+                    tiles: Vec::new(),
+                };
+                (short_name, long_name, COUNTER_ROWS, Some(counter))
+            }
+            _ => unreachable!(),
+        };
+        Self {
+            expanded: expanded_by_default(&entry_id),
+            entry_id,
+            short_name: short_name.to_owned(),
+            long_name: long_name.to_owned(),
+            max_rows,
+            tiles: Vec::new(),
+            counter,
+            events,
+            flame: None,
+            memory: None,
+            density: Vec::new(),
+            fetched_expanded: false,
+            pending_tiles: None,
+            last_view_interval: None,
+            error: None,
+        }
+    }
+
+    fn entry_id(&self) -> &EntryID {
+        &self.entry_id
+    }
+    fn label_text(&self) -> &str {
+        &self.short_name
+    }
+    fn hover_text(&self) -> &str {
+        &self.long_name
+    }
+
+    fn content(
+        &mut self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        viewport: Rect,
+        config: &mut Config,
+        cx: &mut Context,
+    ) {
+        profile_scope!("Slot::content", &self.long_name);
+        cx.slot_rect = Some(rect); // Save slot rect for use later
+
+        let response = ui.allocate_rect(rect, egui::Sense::click());
+        let hover_pos = response.hover_pos(); // where is the mouse hovering?
+
+        if self.expanded {
+            self.refresh(config, cx);
+
+            let style = ui.style();
+            let visuals = style.interact_selectable(&response, false);
+            ui.painter()
+                .rect(rect, 0.0, visuals.bg_fill, visuals.bg_stroke);
+
+            if let Some(error) = &self.error {
+                if show_error(ui, rect, error) {
+                    self.clear();
+                }
+                return;
+            }
+
+            if let Some(counter) = &self.counter {
+                counter.render(hover_pos, ui, rect, cx);
+                show_loading(ui, rect, self.pending_tiles.as_ref(), cx);
+                return;
+            }
+            if let Some(events) = &self.events {
+                events.render(hover_pos, ui, rect, config, cx);
+                show_loading(ui, rect, self.pending_tiles.as_ref(), cx);
+                return;
+            }
+
+            if let Some(flame) = &mut self.flame {
+                flame.update(&self.tiles);
+            }
+            if let Some(memory) = &mut self.memory {
+                memory.update(&self.tiles);
+            }
+            let hovered = if let Some(flame) = &self.flame {
+                flame.render(self, hover_pos, ui, rect, config, cx)
+            } else if let Some(memory) = &self.memory {
+                memory.render(self, hover_pos, ui, rect, config, cx)
+            } else {
+                self.render_tiles(hover_pos, ui, rect, viewport, config, cx)
+            };
+            show_loading(ui, rect, self.pending_tiles.as_ref(), cx);
+            if response.clicked() {
+                if let Some((row, item)) = hovered {
+                    let item = item.clone();
+                    config.select_item(self.entry_id.clone(), row, item);
+                } else {
+                    config.selected = None;
+                }
+            }
+            if response.double_clicked() {
+                if let Some((_, item)) = hovered {
+                    ProfApp::zoom_to(cx, item.interval);
+                }
+            }
+            if response.secondary_clicked() {
+                config.context_item = hovered.map(|(_, item)| item.clone());
+            }
+
+            // Save the layout so that links to this slot can be drawn later
+            config
+                .slot_rects
+                .insert(self.entry_id.clone(), (rect, self.rows()));
+
+            response.context_menu(|ui| {
+                if let Some(item) = &config.context_item {
+                    ui.label(&item.title);
+                    if ui.button("Zoom to Item").clicked() {
+                        ProfApp::zoom_to(cx, item.interval);
+                        ui.close_menu();
+                    }
+                } else {
+                    ui.close_menu();
+                }
+            });
+        } else if self.counter.is_none() && self.events.is_none() {
+            self.refresh(config, cx);
+            if self.error.is_none() {
+                self.render_density(ui, rect, cx);
+                show_loading(ui, rect, self.pending_tiles.as_ref(), cx);
+            }
+        }
+    }
+
+    fn export_svg(&mut self, svg: &mut SvgWriter, rect: Rect, config: &mut Config, cx: &Context) {
+        if !self.expanded {
+            return;
+        }
+        self.refresh(config, cx);
+        svg.set_timeline_rect(rect);
+
+        svg.rect(rect, SVG_SLOT_FILL, Some(SVG_SLOT_STROKE), None);
+        if let Some(counter) = &self.counter {
+            counter.export_svg(svg, rect, cx);
+            return;
+        }
+        if let Some(events) = &self.events {
+            events.export_svg(svg, rect, cx);
+            return;
+        }
+        if let Some(flame) = &mut self.flame {
+            flame.update(&self.tiles);
+        }
+        if let Some(memory) = &mut self.memory {
+            memory.update(&self.tiles);
+        }
+        let rows = self.rows();
+        if let Some(flame) = &self.flame {
+            flame.export_svg(self, svg, rect, config, cx);
+            return;
+        }
+        if let Some(memory) = &self.memory {
+            memory.export_svg(self, svg, rect, config, cx);
+            return;
+        }
+        for tile in &self.tiles {
+            if !cx.view_interval.overlaps(tile.tile_id.0) {
+                continue;
+            }
+
+            for (row, row_items) in tile.items.iter().enumerate() {
+                let mut block: Option<ItemBlock> = None;
+                let mut overlap = RowOverlap::default();
+                for item in SlotTile::items_in(row_items, cx.view_interval) {
+                    let color = if let Some(color) = cx.item_color(&self.entry_id, item, config) {
+                        color
+                    } else {
+                        continue;
+                    };
+                    let item_rect = Self::item_rect(rect, rows, row as u64, item.interval, cx);
+                    let overlapped = overlap.add(item_rect);
+
+                    // Merge sub-pixel items, same as on screen
+                    if item_rect.width() < ItemBlock::MIN_WIDTH {
+                        if let Some(b) = &mut block {
+                            if b.can_merge(item_rect) {
+                                b.merge(item.interval, item_rect, color, false);
+                                continue;
+                            }
+                        }
+                        if let Some(b) = block.take() {
+                            b.export_svg(svg);
+                        }
+                        block = Some(ItemBlock::new(item.interval, item_rect, color, false));
+                        continue;
+                    }
+                    if let Some(b) = block.take() {
+                        b.export_svg(svg);
+                    }
+
+                    let mut title = item.title.clone();
+                    for (name, field) in &item.fields {
+                        title.push('\n');
+                        title.push_str(&field_text(name, field));
+                    }
+                    svg.rect(item_rect, color, None, Some(&title));
+                    for segment_rect in Self::waiting_rects(rect, rows, row as u64, item, cx) {
+                        svg.rect(segment_rect, waiting_color(color), None, None);
+                    }
+                    if let Some((overlap_rect, depth)) = overlapped {
+                        svg.rect(overlap_rect, overlap_color(color, depth as f32), None, None);
+                    }
+                }
+                if let Some(b) = block.take() {
+                    b.export_svg(svg);
+                }
+            }
+        }
+    }
+
+    fn height(&self, _config: &Config, cx: &Context) -> f32 {
+        self.rows() as f32 * cx.row_height
+    }
+
+    fn is_expandable(&self) -> bool {
+        true
+    }
+
+    fn toggle_expanded(&mut self) {
+        self.expanded = !self.expanded;
+    }
+
+    fn expand_all(&mut self, expanded: bool) {
+        self.expanded = expanded;
+    }
+
+    fn busy_interval(&mut self, config: &mut Config) -> DataSourceResult<Option<Interval>> {
+        let density = self.fetch_density(config.data_source.as_mut(), config.interval)?;
+        Ok(nonzero_interval(&density))
+    }
+
+    fn invalidate(&mut self, entry_id: &EntryID, interval: Interval) {
+        if covers(entry_id, &self.entry_id)
+            && self
+                .last_view_interval
+                .map_or(false, |i| i.overlaps(interval))
+        {
+            self.last_view_interval = None;
+        }
+    }
+
+    fn cull(&mut self, far: bool, config: &mut Config) {
+        // Start over when it comes back into view, since a new request would
+        // include the tiles already fetched
+        let fetching = self.pending_tiles.as_ref().map_or(false, |p| !p.is_empty());
+        if fetching || far {
+            self.cancel(config);
+            self.clear();
+            self.last_view_interval = None;
+        }
+    }
+
+    fn label_menu(&mut self, ui: &mut egui::Ui, config: &mut Config) {
+        let pinned = config.pinned.iter().position(|p| *p == self.entry_id);
+        if let Some(index) = pinned {
+            if ui.button("Unpin").clicked() {
+                config.pinned.remove(index);
+                ui.close_menu();
+            }
+        } else if ui
+            .button("Pin to Top")
+            .on_hover_text("Keep this slot above the others while scrolling")
+            .clicked()
+        {
+            config.pinned.push(self.entry_id.clone());
+            ui.close_menu();
+        }
+        if self.counter.is_some() || self.events.is_some() {
+            return;
+        }
+        ui.separator();
+        let mut flame = self.flame.is_some();
+        if ui
+            .checkbox(&mut flame, "Flame Graph")
+            .on_hover_text("Stack items by nesting depth instead of by row")
+            .changed()
+        {
+            self.flame = flame.then(FlameGraph::default);
+            self.memory = None;
+            ui.close_menu();
+        }
+        let mut tile_items = self
+            .tiles
+            .iter()
+            .flat_map(|tile| tile.items.iter().flatten());
+        if self.memory.is_some() || tile_items.any(|item| item.allocation.is_some()) {
+            let mut memory = self.memory.is_some();
+            if ui
+                .checkbox(&mut memory, "Memory Layout")
+                .on_hover_text("Place instances by address, each as tall as its size")
+                .changed()
+            {
+                self.memory = memory.then(MemoryLayout::default);
+                self.flame = None;
+                ui.close_menu();
+            }
+        }
+    }
+}
+
+impl<S: Entry> Panel<S> {
+    const LABEL_WIDTH: f32 = 60.0;
+    const MIN_LABEL_WIDTH: f32 = 24.0;
+    const MAX_LABEL_WIDTH: f32 = 400.0;
+    const COL_PADDING: f32 = 4.0;
+
+    fn render<T: Entry>(
+        ui: &mut egui::Ui,
+        rect: Rect,
+        viewport: Rect,
+        slot: &mut T,
+        y: &mut f32,
+        config: &mut Config,
+        cx: &mut Context,
+    ) {
+        const ROW_PADDING: f32 = 4.0;
+        // Off-screen slots keep their tiles (for scrolling back) up to this
+        // many screens away
+        const KEEP_SCREENS: f32 = 2.0;
+
+        // Compute the size of this slot
+        // This is in screen (i.e., rect) space
+        let min_y = *y;
+        let max_y = min_y + slot.height(config, cx);
+        *y = max_y + ROW_PADDING;
+
+        // Cull if out of bounds
+        // Note: need to shift by rect.min to get to viewport space
+        let above = max_y - rect.min.y < viewport.min.y;
+        let below = min_y - rect.min.y > viewport.max.y;
+
+        // Bring the slot into view if requested (e.g., from search). If the
+        // target is nested inside an off-screen slot, scroll to the
+        // enclosing slot first; the target will be found on a later frame
+        if let Some(target) = &config.scroll_target {
+            let exact = target == slot.entry_id();
+            if exact || ((above || below) && slot.entry_id().is_ancestor_of(target)) {
+                let target =
+                    Rect::from_min_max(Pos2::new(rect.min.x, min_y), Pos2::new(rect.max.x, max_y));
+                ui.scroll_to_rect(target, Some(egui::Align::Center));
+                if exact {
+                    config.scroll_target = None;
+                }
+            }
+        }
+
+        if above || below {
+            let distance = if above {
+                viewport.min.y - (max_y - rect.min.y)
+            } else {
+                (min_y - rect.min.y) - viewport.max.y
+            };
+            slot.cull(distance > KEEP_SCREENS * viewport.height(), config);
+            return;
+        }
+
+        // Draw label and content
+        let label_min = rect.min.x;
+        let label_max = (rect.min.x + config.label_width).at_most(rect.max.x);
+        let content_min = (label_max + Self::COL_PADDING).at_most(rect.max.x);
+        let content_max = rect.max.x;
+
+        let label_subrect =
+            Rect::from_min_max(Pos2::new(label_min, min_y), Pos2::new(label_max, max_y));
+        let content_subrect =
+            Rect::from_min_max(Pos2::new(content_min, min_y), Pos2::new(content_max, max_y));
+
+        // Shift viewport up by the amount consumed
+        // Invariant: (0, 0) in viewport is rect.min
+        //   (i.e., subtracting rect.min gets us from screen space to viewport space)
+        // Note: viewport.min is NOT necessarily (0, 0)
+        let content_viewport = viewport.translate(Vec2::new(0.0, rect.min.y - min_y));
+
+        slot.content(ui, content_subrect, content_viewport, config, cx);
+        slot.label(ui, label_subrect, config, cx);
+        Self::resize_labels(
+            ui,
+            slot.entry_id(),
+            label_max,
+            content_min,
+            min_y,
+            max_y,
+            config,
+        );
+    }
+
+    // Drag the gap after a label to resize the labels of every level. The
+    // gap moves by the width times its level, so that it follows the pointer.
+    fn resize_labels(
+        ui: &mut egui::Ui,
+        entry_id: &EntryID,
+        label_max: f32,
+        content_min: f32,
+        min_y: f32,
+        max_y: f32,
+        config: &mut Config,
+    ) {
+        let gap = Rect::from_min_max(Pos2::new(label_max, min_y), Pos2::new(content_min, max_y));
+        let id = ui.id().with(("resize_labels", entry_id));
+        let response = ui.interact(gap, id, egui::Sense::drag());
+        if response.hovered() || response.dragged() {
+            ui.output().cursor_icon = egui::CursorIcon::ResizeHorizontal;
+        }
+        if response.dragged() {
+            let level = entry_id.level().max(1) as f32;
+            config.label_width = (config.label_width + response.drag_delta().x / level)
+                .clamp(Self::MIN_LABEL_WIDTH, Self::MAX_LABEL_WIDTH);
+        }
+    }
+
+    // Same layout as render, but without culling (everything gets exported)
+    fn render_svg<T: Entry>(
+        svg: &mut SvgWriter,
+        rect: Rect,
+        slot: &mut T,
+        y: &mut f32,
+        config: &mut Config,
+        cx: &Context,
+    ) {
+        const COL_PADDING: f32 = 4.0;
+        const ROW_PADDING: f32 = 4.0;
+
+        let font_size = svg.options().label_font_size;
+        let label_width = font_size * 5.0;
+
+        let min_y = *y;
+        let max_y = min_y + slot.height(config, cx);
+        *y = max_y + ROW_PADDING;
+
+        let label_max = (rect.min.x + label_width).at_most(rect.max.x);
+        let content_min = (label_max + COL_PADDING).at_most(rect.max.x);
+        let content_subrect =
+            Rect::from_min_max(Pos2::new(content_min, min_y), Pos2::new(rect.max.x, max_y));
+
+        svg.text(
+            Pos2::new(rect.min.x, min_y + font_size),
+            font_size,
+            TextAnchor::Start,
+            SVG_TEXT,
+            slot.label_text(),
+            Some(slot.hover_text()),
+        );
+        slot.export_svg(svg, content_subrect, config, cx);
+    }
+
+    fn is_slot_visible(entry_id: &EntryID, config: &Config) -> bool {
+        let index = entry_id.last_slot_index().unwrap();
+        (entry_id.level() != 1 || config.node_set.contains(index))
+            && !config.hidden.contains(entry_id)
+    }
+
+    fn order(&self) -> Vec<usize> {
+        if self.order.is_empty() {
+            (0..self.slots.len()).collect()
+        } else {
+            self.order.clone()
+        }
+    }
+
+    // Sorts the slots by activity (most active first), or back into their
+    // original order if there's no activity to go by
+    fn sort(&mut self, activity: Option<Vec<f64>>) {
+        self.order.clear();
+        if let Some(activity) = activity {
+            let mut order = self.order();
+            order.sort_by(|a, b| activity[*b].total_cmp(&activity[*a]));
+            self.order = order;
+        }
+    }
+
+    // Pinned slots are drawn above the scroll area, rather than in it
+    fn is_slot_scrolled(entry_id: &EntryID, config: &Config) -> bool {
+        Self::is_slot_visible(entry_id, config) && !config.pinned.contains(entry_id)
+    }
+}
+
+// Everything except kinds (e.g., CPU/GPU/etc.) starts expanded
+fn expanded_by_default(entry_id: &EntryID) -> bool {
+    entry_id.level() != 2
+}
+
+// Whether something said about one entry applies to another
+fn covers(entry_id: &EntryID, other: &EntryID) -> bool {
+    entry_id == other || entry_id.is_ancestor_of(other)
+}
+
+impl<S: Entry> Entry for Panel<S> {
+    fn new(info: &EntryInfo, entry_id: EntryID) -> Self {
+        if let EntryInfo::Panel {
+            short_name,
+            long_name,
+            summary,
+            slots,
+        } = info
+        {
+            let expanded = expanded_by_default(&entry_id);
+            let summary = summary
+                .as_ref()
+                .map(|s| Summary::new(s, entry_id.summary()));
+            let slots = slots
+                .iter()
+                .enumerate()
+                .map(|(i, s)| S::new(s, entry_id.child(i as u64)))
+                .collect();
+            Self {
+                entry_id,
+                short_name: short_name.to_owned(),
+                long_name: long_name.to_owned(),
+                expanded,
+                summary,
+                slots,
+                order: Vec::new(),
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    fn entry_id(&self) -> &EntryID {
+        &self.entry_id
+    }
+    fn label_text(&self) -> &str {
+        &self.short_name
+    }
+    fn hover_text(&self) -> &str {
+        &self.long_name
+    }
+
+    fn content(
+        &mut self,
+        ui: &mut egui::Ui,
+        rect: Rect,
+        viewport: Rect,
+        config: &mut Config,
+        cx: &mut Context,
+    ) {
+        profile_scope!("Panel::content", &self.long_name);
+        let mut y = rect.min.y;
+        if let Some(summary) = &mut self.summary {
+            Self::render(ui, rect, viewport, summary, &mut y, config, cx);
+        }
+
+        if self.expanded {
+            for index in self.order() {
+                let slot = &mut self.slots[index];
+                // Apply visibility settings
+                if !Self::is_slot_scrolled(slot.entry_id(), config) {
+                    continue;
+                }
+
+                // Slots past the viewport are only culled
+                Self::render(ui, rect, viewport, slot, &mut y, config, cx);
+            }
+        }
+    }
+
+    fn export_svg(&mut self, svg: &mut SvgWriter, rect: Rect, config: &mut Config, cx: &Context) {
+        let mut y = rect.min.y;
+        if let Some(summary) = &mut self.summary {
+            Self::render_svg(svg, rect, summary, &mut y, config, cx);
+        }
+
+        if self.expanded {
+            for index in self.order() {
+                let slot = &mut self.slots[index];
+                if Self::is_slot_visible(slot.entry_id(), config) {
+                    Self::render_svg(svg, rect, slot, &mut y, config, cx);
+                }
+            }
+        }
+    }
+
+    fn height(&self, config: &Config, cx: &Context) -> f32 {
+        const UNEXPANDED_ROWS: u64 = 2;
+        const ROW_PADDING: f32 = 4.0;
+
+        let mut total = 0.0;
+        let mut rows: i64 = 0;
+        if let Some(summary) = &self.summary {
+            total += summary.height(config, cx);
+            rows += 1;
+        } else if !self.expanded {
+            // Need some minimum space if this panel has no summary and is collapsed
+            total += UNEXPANDED_ROWS as f32 * cx.row_height;
+            rows += 1;
+        }
+
+        if self.expanded {
+            for slot in &self.slots {
+                // Apply visibility settings
+                if !Self::is_slot_scrolled(slot.entry_id(), config) {
+                    continue;
+                }
+
+                total += slot.height(config, cx);
+                rows += 1;
+            }
+        }
+
+        total += (rows - 1).at_least(0) as f32 * ROW_PADDING;
+
+        total
+    }
+
+    fn is_expandable(&self) -> bool {
+        !self.slots.is_empty()
+    }
+
+    fn toggle_expanded(&mut self) {
+        self.expanded = !self.expanded;
+    }
+
+    fn expand_all(&mut self, expanded: bool) {
+        self.expanded = expanded;
+        for slot in &mut self.slots {
+            slot.expand_all(expanded);
+        }
+    }
+
+    fn busy_interval(&mut self, config: &mut Config) -> DataSourceResult<Option<Interval>> {
+        if let Some(summary) = &mut self.summary {
+            return summary.busy_interval(config);
+        }
+        let mut result: Option<Interval> = None;
+        for slot in &mut self.slots {
+            if let Some(busy) = slot.busy_interval(config)? {
+                result = Some(result.map_or(busy, |r| r.union(busy)));
+            }
+        }
+        Ok(result)
+    }
+
+    fn invalidate(&mut self, entry_id: &EntryID, interval: Interval) {
+        if !covers(entry_id, &self.entry_id) && !self.entry_id.is_ancestor_of(entry_id) {
+            return;
+        }
+        if let Some(summary) = &mut self.summary {
+            summary.invalidate(entry_id, interval);
+        }
+        for slot in &mut self.slots {
+            slot.invalidate(entry_id, interval);
+        }
+    }
+
+    fn cull(&mut self, far: bool, config: &mut Config) {
+        if let Some(summary) = &mut self.summary {
+            summary.cull(far, config);
+        }
+        for slot in &mut self.slots {
+            slot.cull(far, config);
+        }
+    }
+}
+
+impl Config {
+    // Hands the data source back on failure, so the caller can keep using it
+    fn new(
+        mut data_source: Box<dyn DataSource>,
+    ) -> Result<Self, (DataSourceError, Box<dyn DataSource>)> {
+        let read = |data_source: &mut dyn DataSource| -> DataSourceResult<_> {
+            let interval = data_source.interval()?;
+            let info = data_source.fetch_info()?;
+            let slot_names = info
+                .data_entries()
+                .into_iter()
+                .filter_map(|(entry_id, info)| match info {
+                    EntryInfo::Slot { long_name, .. } => Some((entry_id, long_name.clone())),
+                    _ => None,
+                })
+                .collect();
+            // Slots sit under a node and then a kind
+            let slot_kinds = info
+                .data_entries()
+                .into_iter()
+                .filter_map(|(entry_id, _)| {
+                    let node = entry_id.slot_index(0)?;
+                    let kind = EntryID::root().child(node).child(entry_id.slot_index(1)?);
+                    match info.get(&kind)? {
+                        EntryInfo::Panel { short_name, .. } => {
+                            Some((entry_id, short_name.to_lowercase()))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect();
+            let epoch = data_source.epoch()?;
+            let description = data_source.fetch_description()?;
+            Ok((interval, slot_names, slot_kinds, epoch, description))
+        };
+        let (interval, slot_names, slot_kinds, epoch, description) =
+            match read(data_source.as_mut()) {
+                Ok(parts) => parts,
+                Err(error) => return Err((error, data_source)),
+            };
+        Ok(Self {
+            node_set: NodeSet::all(),
+            node_set_text: NodeSet::all().to_string(),
+            node_set_error: None,
+
+            interval,
+            epoch,
+            description,
+            warnings: Vec::new(),
+            view_interval: interval,
+            view_offset: 0,
+
+            search_results: Vec::new(),
+            scroll_target: None,
+            hidden: BTreeSet::new(),
+            slot_names,
+            slot_kinds,
+            sort_key: SortKey::Original,
+            pinned: Vec::new(),
+            scroll_width: 0.0,
+            label_width: Panel::<Slot>::LABEL_WIDTH,
+
+            selected: None,
+            context_item: None,
+            critical_path: None,
+            name_stats: None,
+            stats_sort: (StatsColumn::Total, true),
+            histogram: Histogram::default(),
+            selection_stats: None,
+            slot_rects: BTreeMap::new(),
+            event_lines: Vec::new(),
+
+            overview: Vec::new(),
+
+            longest: Vec::new(),
+            longest_view: None,
+            top_n: 10,
+
+            annotations: Vec::new(),
+            annotation_text: String::new(),
+            annotate_slot: false,
+            annotation_status: None,
+
+            last_error: None,
+
+            fetch_budget: 0,
+            wait_for_tiles: false,
+
+            frame_stats: FrameStats::default(),
+            legend: RefCell::default(),
+
+            last_view: interval,
+            view_changed_at: f64::NEG_INFINITY,
+            view_settled: true,
+
+            data_source,
+        })
+    }
+}
+
+impl Config {
+    // Stash an error to be shown in the UI, and carry on with a default
+    fn report_error<T: Default>(&mut self, result: DataSourceResult<T>) -> T {
+        result.unwrap_or_else(|error| {
+            tracing::warn!("{}", error);
+            self.last_error = Some(error);
+            T::default()
+        })
+    }
+
+    fn record_longest(&mut self, entry_id: &EntryID, tile: &SlotTile) {
+        for item in tile.items.iter().flatten() {
+            // Items that straddle a tile boundary show up once per tile
+            let existing = self
+                .longest
+                .iter_mut()
+                .find(|long| long.item_uid == item.item_uid);
+            if let Some(long) = existing {
+                long.interval = long.interval.union(item.interval);
+                continue;
+            }
+            let duration = item.interval.duration_ns();
+            let index = self
+                .longest
+                .partition_point(|long| long.interval.duration_ns() >= duration);
+            if index < self.top_n {
+                self.longest.insert(
+                    index,
+                    LongItem {
+                        item_uid: item.item_uid,
+                        entry_id: entry_id.clone(),
+                        title: item.title.clone(),
+                        interval: item.interval,
+                    },
+                );
+                self.longest.truncate(self.top_n);
+            }
+        }
+        self.longest
+            .sort_by_key(|long| std::cmp::Reverse(long.interval.duration_ns()));
+    }
+
+    // Position of the item's duration on the heat color ramp. The ramp
+    // covers four orders of magnitude (on a log scale) up to the longest
+    // item in view, so outliers stand out.
+    fn heat(&self, item: &Item) -> f32 {
+        const DECADES: f64 = 4.0;
+        let max = self
+            .longest
+            .first()
+            .map_or(self.view_interval.duration_ns(), |long| {
+                long.interval.duration_ns()
+            });
+        let ratio = item.interval.duration_ns().max(1) as f64 / max.max(1) as f64;
+        (1.0 + ratio.log10() / DECADES) as f32
+    }
+
+    // Is the item part of the histogram's source, within the brushed range?
+    fn is_brushed(&self, entry_id: &EntryID, item: &Item) -> bool {
+        let (selected, (min, max)) = match (&self.selected, self.histogram.brush) {
+            (Some(selected), Some(brush)) => (selected, brush),
+            _ => return false,
+        };
+        let source = if self.histogram.by_name {
+            selected.item.title == item.title
+        } else {
+            selected.entry_id == *entry_id
+        };
+        let duration = item.interval.duration_ns();
+        source && min <= duration && duration <= max
+    }
+
+    fn select_item(&mut self, entry_id: EntryID, row: u64, item: Item) {
+        let links = self.data_source.fetch_item_links(item.item_uid);
+        let links = self.report_error(links);
+        self.selected = Some(Selection {
+            entry_id,
+            row,
+            item,
+            links,
+            graph: None,
+        });
+    }
+}
+
+impl DependencyGraph {
+    const MAX_DEPTH: i32 = 3;
+    const MAX_LAYER_WIDTH: usize = 12;
+
+    fn new(data_source: &mut dyn DataSource, selected: &Selection) -> DataSourceResult<Self> {
+        let mut graph = Self::default();
+        graph.nodes.push(GraphNode {
+            item_uid: selected.item.item_uid,
+            entry_id: selected.entry_id.clone(),
+            interval: selected.item.interval,
+            title: selected.item.title.clone(),
+            depth: 0,
+        });
+        let mut index = BTreeMap::new();
+        index.insert(selected.item.item_uid, 0);
+
+        for (kind, step) in [
+            (ItemLinkKind::Predecessor, -1),
+            (ItemLinkKind::Successor, 1),
+        ] {
+            let mut frontier = vec![0];
+            for depth in 1..=Self::MAX_DEPTH {
+                let mut next = Vec::new();
+                for n in frontier {
+                    let links = if n == 0 {
+                        selected.links.clone()
+                    } else {
+                        data_source.fetch_item_links(graph.nodes[n].item_uid)?
+                    };
+                    for link in links.into_iter().filter(|l| l.kind == kind) {
+                        let m = match index.get(&link.item_uid) {
+                            Some(m) => *m,
+                            None if next.len() >= Self::MAX_LAYER_WIDTH => {
+                                graph.truncated = true;
+                                continue;
+                            }
+                            None => {
+                                index.insert(link.item_uid, graph.nodes.len());
+                                next.push(graph.nodes.len());
+                                graph.nodes.push(GraphNode {
+                                    item_uid: link.item_uid,
+                                    entry_id: link.entry_id,
+                                    interval: link.interval,
+                                    title: link.title,
+                                    depth: depth * step,
+                                });
+                                graph.nodes.len() - 1
+                            }
+                        };
+                        let edge = if step < 0 { (m, n) } else { (n, m) };
+                        if !graph.edges.contains(&edge) {
+                            graph.edges.push(edge);
+                        }
+                    }
+                }
+                frontier = next;
+            }
+        }
+        Ok(graph)
+    }
+}
+
+impl FrameStats {
+    fn paint(&self) {
+        self.items_painted.set(self.items_painted.get() + 1);
+    }
+}
+
+impl Window {
+    fn new(data_source: Box<dyn DataSource>, index: u64) -> DataSourceResult<Self> {
+        Self::open(data_source, index).map_err(|(error, _)| error)
+    }
+
+    // Like `new`, but hands the data source back if it can't be read
+    fn open(
+        data_source: Box<dyn DataSource>,
+        index: u64,
+    ) -> Result<Self, (DataSourceError, Box<dyn DataSource>)> {
+        let mut config = Config::new(data_source)?;
+        let info = match config.data_source.fetch_info() {
+            Ok(info) => info,
+            Err(error) => return Err((error, config.data_source)),
+        };
+
+        // Assume that profiles with identical structure are the same
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(info).unwrap().hash(&mut hasher);
+        config.interval.hash(&mut hasher);
+
+        let panel = Panel::new(info, EntryID::root());
+        let kinds = info.kinds();
+        Ok(Self {
+            panel,
+            index,
+            kinds,
+            config,
+            profile_key: hasher.finish(),
+            location: None,
+            split: None,
+        })
+    }
+
+    fn for_each_expandable(&mut self, mut f: impl FnMut(&EntryID, &mut bool)) {
+        for node in &mut self.panel.slots {
+            f(&node.entry_id, &mut node.expanded);
+            for kind in &mut node.slots {
+                f(&kind.entry_id, &mut kind.expanded);
+                for slot in &mut kind.slots {
+                    f(&slot.entry_id, &mut slot.expanded);
+                }
+            }
+        }
+    }
+
+    // Entries whose expansion state differs from the default
+    fn toggled(&mut self) -> Vec<EntryID> {
+        let mut toggled = Vec::new();
+        self.for_each_expandable(|entry_id, expanded| {
+            if *expanded != expanded_by_default(entry_id) {
+                toggled.push(entry_id.clone());
+            }
+        });
+        toggled
+    }
+
+    fn set_toggled(&mut self, toggled: &[EntryID]) {
+        let toggled: BTreeSet<_> = toggled.iter().collect();
+        self.for_each_expandable(|entry_id, expanded| {
+            *expanded = expanded_by_default(entry_id) != toggled.contains(entry_id);
+        });
+    }
+
+    fn save_view(&mut self, cx: &Context) -> SavedView {
+        SavedView {
+            view_interval: cx.view_interval,
+            toggled: self.toggled(),
+            annotations: self.config.annotations.clone(),
+            hidden: self.config.hidden.iter().cloned().collect(),
+            label_width: Some(self.config.label_width),
+        }
+    }
+
+    fn restore_view(&mut self, saved: &SavedView) {
+        self.set_toggled(&saved.toggled);
+        self.config.annotations = saved.annotations.clone();
+        self.config.hidden = saved.hidden.iter().cloned().collect();
+        if let Some(width) = saved.label_width {
+            self.config.label_width = width.clamp(
+                Panel::<Slot>::MIN_LABEL_WIDTH,
+                Panel::<Slot>::MAX_LABEL_WIDTH,
+            );
+        }
+    }
+
+    fn content(&mut self, ui: &mut egui::Ui, cx: &mut Context) {
+        profile_scope!("Window::content");
+        let _span = tracing::debug_span!("render", window = self.index).entered();
+        ui.heading(self.title());
+
+        // Shared by both views of a split window
+        const MAX_FETCHES_PER_FRAME: u64 = 16;
+        self.config.fetch_budget = if cx.fetch_all {
+            u64::MAX
+        } else {
+            MAX_FETCHES_PER_FRAME
+        };
+        self.config.wait_for_tiles = cx.fetch_all;
+        self.config.frame_stats = FrameStats::default();
+        self.config.legend = RefCell::default();
+
+        if self.split.is_none() {
+            self.view(ui, cx, false);
+            return;
+        }
+        let spacing = ui.spacing().item_spacing.y;
+        let height = ((ui.available_height() - 2.0 * spacing) / 2.0).at_least(0.0);
+        ui.allocate_ui(Vec2::new(ui.available_width(), height), |ui| {
+            ui.set_height(height);
+            self.view(ui, cx, false);
+        });
+        ui.separator();
+        self.swap_split();
+        ui.push_id("split", |ui| self.view(ui, cx, true));
+        self.swap_split();
+    }
+
+    fn view(&mut self, ui: &mut egui::Ui, cx: &mut Context, lower: bool) {
+        let id = (self.index, lower);
+        let offset = self.config.view_offset;
+        let hovered = ui.ui_contains_pointer();
+        if hovered && cx.active_window != Some(id) {
+            let was_lower = cx.active_window.map_or(false, |(_, lower)| lower);
+            cx.active_window = Some(id);
+            if !cx.sync_views || lower || was_lower {
+                // Take over the global view from the previously active profile
+                cx.view_interval = self.config.view_interval.translate(-offset);
+            }
+        }
+        let lower_active = cx.active_window.map_or(false, |(_, lower)| lower);
+        if (cx.sync_views && !lower && !lower_active) || cx.active_window == Some(id) {
+            self.config.view_interval = cx.view_interval.translate(offset);
+        }
+
+        // Everything below works in terms of this profile's view
+        let global_view = std::mem::replace(&mut cx.view_interval, self.config.view_interval);
+
+        const SETTLE_SECONDS: f64 = 0.15;
+        let now = ui.input().time;
+        if self.config.view_interval != self.config.last_view {
+            self.config.last_view = self.config.view_interval;
+            self.config.view_changed_at = now;
+        }
+        self.config.view_settled =
+            cx.fetch_all || now - self.config.view_changed_at >= SETTLE_SECONDS;
+        if !self.config.view_settled {
+            ui.ctx().request_repaint(); // to notice when it settles
+        } else if self.config.longest_view != Some(self.config.view_interval) {
+            // Slots are about to refetch for the new view
+            self.config.longest.clear();
+            self.config.longest_view = Some(self.config.view_interval);
+        }
+
+        self.config.slot_rects.clear();
+        self.config.event_lines.clear();
+        self.pinned_slots(ui, cx);
+
+        ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show_viewport(ui, |ui, viewport| {
+                let height = self.panel.height(&self.config, cx);
+                ui.set_height(height);
+                ui.set_width(ui.available_width());
+
+                let rect = Rect::from_min_size(ui.min_rect().min, viewport.size());
+                self.config.scroll_width = rect.width();
+
+                // Root panel has no label
+                self.panel.content(ui, rect, viewport, &mut self.config, cx);
+
+                self.render_event_lines(ui, rect, cx);
+                self.render_annotations(ui, rect, cx);
+                self.render_links(ui, cx);
+            });
+
+        if self.split.is_some() {
+            if hovered {
+                cx.active_view_rect = Some(ui.min_rect());
+            } else {
+                self.render_linked_cursor(ui, cx);
+            }
+        }
+
+        // The view may have been changed while rendering (e.g., zooming to an
+        // item), in which case that change was made in local time
+        let local_view = std::mem::replace(&mut cx.view_interval, global_view);
+        if local_view != self.config.view_interval {
+            self.config.view_interval = local_view;
+            cx.view_interval = local_view.translate(-offset);
+            cx.active_window = Some(id);
+        }
+    }
+
+    // The time under the pointer in the other view, as a dashed line
+    fn render_linked_cursor(&self, ui: &mut egui::Ui, cx: &Context) {
+        let (time, slot_rect) = match (cx.cursor_time, cx.slot_rect) {
+            (Some(time), Some(slot_rect)) => {
+                (time.0.saturating_add(self.config.view_offset), slot_rect)
+            }
+            _ => return,
+        };
+        let view = cx.view_interval; // this view, while drawing it
+        if !view.contains(Timestamp(time)) {
+            return;
+        }
+        let x = slot_rect.left() + view.unlerp(Timestamp(time)) as f32 * slot_rect.width();
+        let rect = ui.min_rect();
+        let stroke = ui.visuals().selection.stroke;
+        ui.painter().extend(egui::Shape::dashed_line(
+            &[Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+            stroke,
+            6.0,
+            4.0,
+        ));
+    }
+
+    // Swap the lower view of a split window in (or back out)
+    fn swap_split(&mut self) {
+        if let Some(split) = &mut self.split {
+            std::mem::swap(&mut self.panel, &mut split.panel);
+            let config = &mut self.config;
+            std::mem::swap(&mut config.view_interval, &mut split.view_interval);
+            std::mem::swap(&mut config.last_view, &mut split.last_view);
+            std::mem::swap(&mut config.view_changed_at, &mut split.view_changed_at);
+            std::mem::swap(&mut config.longest, &mut split.longest);
+            std::mem::swap(&mut config.longest_view, &mut split.longest_view);
+        }
+    }
+
+    fn toggle_split(&mut self, cx: &mut Context) {
+        if self.split.take().is_some() {
+            if cx.active_window == Some((self.index, true)) {
+                cx.active_window = None;
+            }
+            return;
+        }
+        let toggled = self.toggled();
+        let info = self.config.data_source.fetch_info();
+        let panel = match info {
+            Ok(info) => Panel::new(info, EntryID::root()),
+            Err(error) => {
+                self.config.last_error = Some(error);
+                return;
+            }
+        };
+        // Start out the same as the upper view
+        let view = self.config.view_interval;
+        self.split = Some(SplitView {
+            panel,
+            view_interval: view,
+            last_view: view,
+            view_changed_at: 0.0,
+            longest: Vec::new(),
+            longest_view: None,
+        });
+        self.swap_split();
+        self.set_toggled(&toggled);
+        self.swap_split();
+    }
+
+    // Zoom to an interval in this profile's time
+    fn zoom_to(&self, cx: &mut Context, interval: Interval) {
+        cx.active_window = Some((self.index, false));
+        ProfApp::zoom_to(cx, interval.translate(-self.config.view_offset));
+    }
+
+    fn export_svg(
+        &mut self,
+        svg: &mut SvgWriter,
+        left: f32,
+        right: f32,
+        y: &mut f32,
+        cx: &Context,
+    ) {
+        const PADDING: f32 = 8.0;
+
+        let heading_size = svg.options().label_font_size * 1.5;
+        *y += heading_size;
+        svg.text(
+            Pos2::new(left, *y),
+            heading_size,
+            TextAnchor::Start,
+            SVG_TEXT,
+            &self.title(),
+            None,
+        );
+        *y += PADDING;
+
+        // Root panel has no label
+        let height = self.panel.height(&self.config, cx);
+        let rect = Rect::from_min_max(Pos2::new(left, *y), Pos2::new(right, *y + height));
+        self.config.fetch_budget = u64::MAX;
+        self.config.view_settled = true;
+        self.panel.export_svg(svg, rect, &mut self.config, cx);
+        *y += height + PADDING;
+    }
+
+    // Every item overlapping the (global) interval in expanded slots, along
+    // with the path of the slot it's in. Fetches tiles as needed.
+    fn items_in(&mut self, interval: Interval) -> DataSourceResult<Vec<(String, Item)>> {
+        let interval = interval.translate(self.config.view_offset);
+        let config = &mut self.config;
+        let mut result = Vec::new();
+        for node in &self.panel.slots {
+            if !node.expanded || !Panel::<Slot>::is_slot_visible(&node.entry_id, config) {
+                continue;
+            }
+            for kind in node.slots.iter().filter(|kind| kind.expanded) {
+                if !Panel::<Slot>::is_slot_visible(&kind.entry_id, config) {
+                    continue;
+                }
+                for slot in &kind.slots {
+                    if !slot.expanded || slot.counter.is_some() || slot.events.is_some() {
+                        continue;
+                    }
+                    if !Panel::<Slot>::is_slot_visible(&slot.entry_id, config) {
+                        continue;
+                    }
+                    let path = format!(
+                        "{}/{}/{}",
+                        node.short_name, kind.short_name, slot.short_name
+                    );
+
+                    // Items that straddle tiles come back in pieces
+                    let mut items: BTreeMap<ItemUID, Item> = BTreeMap::new();
+                    for tile_id in config.data_source.request_tiles(&slot.entry_id, interval)? {
+                        let tile = config
+                            .data_source
+                            .fetch_slot_tile(&slot.entry_id, tile_id)?;
+                        for item in tile.items.into_iter().flatten() {
+                            if !interval.overlaps(item.interval) {
+                                continue;
+                            }
+                            if let Some(existing) = items.get_mut(&item.item_uid) {
+                                existing.interval = existing.interval.union(item.interval);
+                            } else {
+                                items.insert(item.item_uid, item);
+                            }
+                        }
+                    }
+
+                    result.extend(items.into_values().map(|item| (path.clone(), item)));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    // Appends a CSV row for every item overlapping the interval (in global
+    // time), in the slots currently shown
+    fn export_csv(&mut self, out: &mut String, interval: Interval) -> DataSourceResult<()> {
+        for (path, item) in self.items_in(interval)? {
+            let fields: Vec<_> = item
+                .fields
+                .iter()
+                .map(|(name, field)| field_text(name, field))
+                .collect();
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                self.index,
+                csv_escape(&item.title),
+                csv_escape(&path),
+                item.interval.start.0,
+                item.interval.stop.0,
+                item.interval.duration_ns(),
+                csv_escape(&fields.join("; "))
+            )
+            .unwrap();
+        }
+        Ok(())
+    }
+
+    fn expand_all(&mut self, expanded: bool) {
+        for node in &mut self.panel.slots {
+            for kind in &mut node.slots {
+                kind.expanded = expanded;
+            }
+        }
+    }
+
+    fn overview(&mut self) -> &[f32] {
+        if self.config.overview.is_empty() {
+            self.inflate_overview();
+        }
+        &self.config.overview
+    }
+
+    fn inflate_overview(&mut self) {
+        const BUCKETS: usize = 512;
+
+        // Use the top-most summaries available (which depends on the
+        // data source)
+        let mut entry_ids: Vec<_> = self
+            .panel
+            .summary
+            .iter()
+            .map(|s| s.entry_id.clone())
+            .collect();
+        if entry_ids.is_empty() {
+            entry_ids = self
+                .panel
+                .slots
+                .iter()
+                .filter_map(|node| node.summary.as_ref())
+                .map(|s| s.entry_id.clone())
+                .collect();
+        }
+        if entry_ids.is_empty() {
+            entry_ids = self
+                .panel
+                .slots
+                .iter()
+                .flat_map(|node| &node.slots)
+                .filter_map(|kind| kind.summary.as_ref())
+                .map(|s| s.entry_id.clone())
+                .collect();
+        }
+
+        let interval = self.config.interval;
+        let mut overview = vec![0.0; BUCKETS];
+        for entry_id in &entry_ids {
+            let utilization =
+                fetch_utilization(self.config.data_source.as_mut(), entry_id, interval);
+            let utilization = self.config.report_error(utilization);
+            for (i, value) in overview.iter_mut().enumerate() {
+                let time = interval.lerp((i as f64 + 0.5) / BUCKETS as f64);
+                *value += sample_utilization(&utilization, time);
+            }
+        }
+        if !entry_ids.is_empty() {
+            for value in &mut overview {
+                *value /= entry_ids.len() as f32;
+            }
+        }
+        self.config.overview = overview;
+    }
+
+    // Lines for global events, spanning every slot in view
+    fn render_event_lines(&self, ui: &mut egui::Ui, rect: Rect, cx: &Context) {
+        let slot_rect = if let Some(slot_rect) = cx.slot_rect {
+            slot_rect
+        } else {
+            return;
+        };
+        for (time, color) in &self.config.event_lines {
+            let x = slot_rect
+                .lerp(Vec2::new(cx.view_interval.unlerp(*time) as f32, 0.0))
+                .x;
+            ui.painter().line_segment(
+                [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                Stroke::new(1.0, color.linear_multiply(0.5)),
+            );
+        }
+    }
+
+    fn render_annotations(&self, ui: &mut egui::Ui, rect: Rect, cx: &Context) {
+        let slot_rect = if let Some(slot_rect) = cx.slot_rect {
+            slot_rect
+        } else {
+            return;
+        };
+        let color = ui.visuals().warn_fg_color;
+        let hover_pos = ui.input().pointer.hover_pos();
+        let mut hovered = None;
+        for annotation in &self.config.annotations {
+            if !annotation.interval.overlaps(cx.view_interval) {
+                continue;
+            }
+            // Annotations on slots that aren't drawn are hidden with them
+            let (top, bottom) = match &annotation.entry_id {
+                Some(entry_id) => match self.config.slot_rects.get(entry_id) {
+                    Some((slot, _)) => (slot.top(), slot.bottom()),
+                    None => continue,
+                },
+                None => (rect.top(), rect.bottom()),
+            };
+            let x = |time| {
+                let time = cx.view_interval.unlerp(time).clamp(0.0, 1.0) as f32;
+                slot_rect.lerp(Vec2::new(time, 0.0)).x
+            };
+            let (start, stop) = (x(annotation.interval.start), x(annotation.interval.stop));
+            let span = Rect::from_x_y_ranges(start..=stop, top..=bottom);
+            ui.painter()
+                .rect_filled(span, 0.0, color.linear_multiply(0.1));
+            ui.painter().line_segment(
+                [Pos2::new(start, top), Pos2::new(start, bottom)],
+                Stroke::new(1.0, color),
+            );
+            let flag = Events::flag(start, span);
+            ui.painter().add(egui::Shape::convex_polygon(
+                flag.to_vec(),
+                color,
+                Stroke::NONE,
+            ));
+
+            let flag_rect = Rect::from_min_max(
+                Pos2::new(start, top),
+                Pos2::new(start + Events::FLAG_SIZE, top + Events::FLAG_SIZE),
+            );
+            if hover_pos.map_or(false, |pos| flag_rect.expand(2.0).contains(pos)) {
+                hovered = Some((flag_rect, annotation));
+            }
+        }
+
+        if let Some((flag_rect, annotation)) = hovered {
+            ui.show_tooltip_ui("annotation_tooltip", &flag_rect, |ui| {
+                ui.label(&annotation.text);
+                ui.label(format!("Time: {}", annotation.interval));
+                if let Some(slot) = annotation.entry_id.as_ref().and_then(|e| self.find_slot(e)) {
+                    ui.label(format!("Slot: {}", slot.long_name));
+                }
+            });
+        }
+    }
+
+    // Draw arrows between the selected item and its dependencies
+    fn render_links(&self, ui: &mut egui::Ui, cx: &Context) {
+        let selected = if let Some(selected) = &self.config.selected {
+            selected
+        } else {
+            return;
+        };
+
+        let item_rect = |entry_id: &EntryID, row: u64, interval: Interval| {
+            if !cx.view_interval.overlaps(interval) {
+                return None;
+            }
+            let (rect, rows) = self.config.slot_rects.get(entry_id)?;
+            if row >= *rows {
+                return None;
+            }
+            Some(Slot::item_rect(*rect, *rows, row, interval, cx))
+        };
+
+        let source = item_rect(&selected.entry_id, selected.row, selected.item.interval);
+        let source = if let Some(source) = source {
+            source
+        } else {
+            return;
+        };
+
+        let stroke = Stroke::new(2.0, ui.visuals().strong_text_color());
+        for link in &selected.links {
+            let target = if let Some(target) = item_rect(&link.entry_id, link.row, link.interval) {
+                target
+            } else {
+                continue;
+            };
+
+            // Arrows always point forward in time
+            let (from, to) = match link.kind {
+                ItemLinkKind::Predecessor => (target, source),
+                ItemLinkKind::Successor => (source, target),
+            };
+            let start = from.right_center();
+            let stop = to.left_center();
+
+            const MIN_BEND: f32 = 20.0;
+            let bend = ((stop.x - start.x).abs() * 0.5).at_least(MIN_BEND);
+            let curve = egui::epaint::CubicBezierShape::from_points_stroke(
+                [
+                    start,
+                    start + Vec2::new(bend, 0.0),
+                    stop - Vec2::new(bend, 0.0),
+                    stop,
+                ],
+                false,
+                Color32::TRANSPARENT,
+                stroke,
+            );
+            ui.painter().add(curve);
+
+            const ARROW_SIZE: f32 = 6.0;
+            let tip = stop;
+            ui.painter()
+                .line_segment([tip - Vec2::new(ARROW_SIZE, ARROW_SIZE), tip], stroke);
+            ui.painter()
+                .line_segment([tip - Vec2::new(ARROW_SIZE, -ARROW_SIZE), tip], stroke);
+        }
+    }
+
+    fn node_selection(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        ui.subheading("Node Selection", cx);
+        let total = self.panel.slots.len().saturating_sub(1);
+        let response = ui
+            .add(
+                egui::TextEdit::singleline(&mut self.config.node_set_text).hint_text(format!(
+                    "e.g., 0-{},17 or 0-{}:2",
+                    total.min(3),
+                    total
+                )),
+            )
+            .on_hover_text("Comma separated nodes and ranges (with optional :step), or * for all");
+        if response.changed() {
+            match self.config.node_set_text.parse() {
+                Ok(node_set) => {
+                    self.config.node_set = node_set;
+                    self.config.node_set_error = None;
+                }
+                Err(e) => self.config.node_set_error = Some(e),
+            }
+        }
+        if let Some(error) = &self.config.node_set_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+
+    fn expand_collapse(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        ui.subheading("Expand/Collapse", cx);
+        // Only the slots in view fetch tiles, so even expanding everything
+        // fetches no more than a screenful at a time
+        ui.horizontal(|ui| {
+            if ui.button("Expand All").clicked() {
+                self.for_each_expandable(|_, expanded| *expanded = true);
+            }
+            if ui.button("Collapse All").clicked() {
+                self.for_each_expandable(|_, expanded| *expanded = false);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Show down to:");
+            let levels = [
+                (1, "Nodes", "Collapse everything"),
+                (2, "Kinds", "Expand nodes only"),
+                (3, "Slots", "Expand nodes and kinds, but not slots"),
+            ];
+            for (level, name, hover_text) in levels {
+                if ui.button(name).on_hover_text(hover_text).clicked() {
+                    self.for_each_expandable(|entry_id, expanded| {
+                        *expanded = entry_id.level() < level;
+                    });
+                }
+            }
+        });
+
+        let mut toggle_all = |label, toggle| {
+            for node in &mut self.panel.slots {
+                for kind in &mut node.slots {
+                    if kind.expanded == toggle && kind.label_text() == label {
+                        kind.toggle_expanded();
+                    }
+                }
+            }
+        };
+        ui.label("Expand by kind:");
+        ui.horizontal_wrapped(|ui| {
+            for kind in &self.kinds {
+                if ui.button(kind).clicked() {
+                    toggle_all(kind.to_lowercase(), false);
+                }
+            }
+        });
+        ui.label("Collapse by kind:");
+        ui.horizontal_wrapped(|ui| {
+            for kind in &self.kinds {
+                if ui.button(kind).clicked() {
+                    toggle_all(kind.to_lowercase(), true);
+                }
+            }
+        });
+    }
+
+    // A time in this profile, as wall-clock time if asked for (and known)
+    fn time_text(&self, time: Timestamp, cx: &Context) -> String {
+        match self.config.epoch.filter(|_| cx.wall_clock) {
+            Some(epoch) => time.wall_clock(epoch).to_string(),
+            None => time.to_string(),
+        }
+    }
+
+    // Short names of the panels down to the entry, e.g., n0/cpu/c1
+    fn entry_path(&self, entry_id: &EntryID) -> String {
+        let mut names = Vec::new();
+        let node_index = entry_id.slot_index(0);
+        if let Some(node) = node_index.and_then(|i| self.panel.slots.get(i as usize)) {
+            names.push(node.short_name.as_str());
+            let kind_index = entry_id.slot_index(1);
+            if let Some(kind) = kind_index.and_then(|i| node.slots.get(i as usize)) {
+                names.push(&kind.short_name);
+                let slot_index = entry_id.slot_index(2);
+                if let Some(slot) = slot_index.and_then(|i| kind.slots.get(i as usize)) {
+                    names.push(&slot.short_name);
+                }
+            }
+        }
+        names.join("/")
+    }
+
+    fn hidden_slots(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        ui.subheading("Hidden Slots", cx);
+        if self.config.hidden.is_empty() {
+            ui.label("None (hide slots from the menu on their labels)");
+            return;
+        }
+        let mut show = None;
+        for entry_id in &self.config.hidden {
+            ui.horizontal(|ui| {
+                if ui.small_button("Show").clicked() {
+                    show = Some(entry_id.clone());
+                }
+                ui.label(self.entry_path(entry_id));
+            });
+        }
+        if let Some(entry_id) = show {
+            self.config.hidden.remove(&entry_id);
+        }
+        if ui.button("Show All").clicked() {
+            self.config.hidden.clear();
+        }
+    }
+
+    // Reorders the visible nodes, and the slots of their expanded kinds, by
+    // activity in the view. Everything else stays where it was.
+    fn sort_slots(&mut self) -> DataSourceResult<()> {
+        let key = self.config.sort_key;
+        if key == SortKey::Original {
+            self.panel.sort(None);
+            for node in &mut self.panel.slots {
+                for kind in &mut node.slots {
+                    kind.sort(None);
+                }
+            }
+            return Ok(());
+        }
+
+        let view = self.config.view_interval;
+        let config = &mut self.config;
+        let mut node_activity = Vec::new();
+        for node in &mut self.panel.slots {
+            if !Panel::<Slot>::is_slot_visible(&node.entry_id, config) {
+                node_activity.push(0.0);
+                continue;
+            }
+            let utilization = if let Some(summary) = &node.summary {
+                fetch_utilization(config.data_source.as_mut(), &summary.entry_id, view)?
+            } else {
+                Vec::new()
+            };
+            node_activity.push(key.activity(&utilization, view));
+
+            for kind in node.slots.iter_mut().filter(|kind| kind.expanded) {
+                let mut activity = Vec::new();
+                for slot in &kind.slots {
+                    let density = slot.fetch_density(config.data_source.as_mut(), view)?;
+                    activity.push(key.activity(&density, view));
+                }
+                kind.sort(Some(activity));
+            }
+        }
+        self.panel.sort(Some(node_activity));
+        Ok(())
+    }
+
+    fn sort_controls(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        ui.subheading("Sort", cx);
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source(("sort_key", self.index))
+                .selected_text(self.config.sort_key.name())
+                .show_ui(ui, |ui| {
+                    for key in SortKey::ALL {
+                        changed |= ui
+                            .selectable_value(&mut self.config.sort_key, *key, key.name())
+                            .changed();
+                    }
+                });
+            let sorted = self.config.sort_key != SortKey::Original;
+            changed |= ui
+                .add_enabled(sorted, egui::Button::new("Update"))
+                .on_hover_text("Sort again for the current view")
+                .clicked();
+        });
+        if changed {
+            let result = self.sort_slots();
+            self.config.report_error(result);
+        }
+    }
+
+    // The application the profile is of, if the data source says
+    fn title(&self) -> String {
+        match &self.config.description.name {
+            Some(name) => name.clone(),
+            None => format!("Profile {}", self.index),
+        }
+    }
+
+    fn about(&self, ui: &mut egui::Ui) {
+        let description = &self.config.description;
+        let mut fields = Vec::new();
+        let mut add = |name: &str, value: Option<Field>| {
+            if let Some(value) = value {
+                fields.push((name.to_owned(), value));
+            }
+        };
+        add("Profile", Some(Field::U64(self.index)));
+        add("Application", description.name.clone().map(Field::String));
+        add("Machine", description.machine.clone().map(Field::String));
+        add("Nodes", description.nodes.map(Field::U64));
+        add(
+            "Legion",
+            description.legion_version.clone().map(Field::String),
+        );
+        add(
+            "Command",
+            description.command_line.clone().map(Field::String),
+        );
+        add("Duration", Some(Field::Interval(self.config.interval)));
+        add("Location", self.location.clone().map(Field::String));
+        fields.extend(description.fields.iter().cloned());
+        egui::CollapsingHeader::new("About this Profile")
+            .id_source(("about", self.index))
+            .show(ui, |ui| field_grid(ui, ("about", self.index), &fields));
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui, cx: &mut Context) {
+        const WIDGET_PADDING: f32 = 8.0;
+        ui.heading(format!("{}: Controls", self.title()));
+        ui.add_space(WIDGET_PADDING);
+        self.about(ui);
+        if let Some(error) = &self.config.last_error {
+            let mut dismiss = false;
+            ui.horizontal_wrapped(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", error));
+                dismiss = ui.small_button("Dismiss").clicked();
+            });
+            if dismiss {
+                self.config.last_error = None;
+            }
+            ui.add_space(WIDGET_PADDING);
+        }
+        self.node_selection(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        let mut split = self.split.is_some();
+        if ui
+            .checkbox(&mut split, "Split View")
+            .on_hover_text("Show a second view of this profile, zoomed independently")
+            .changed()
+        {
+            self.toggle_split(cx);
+        }
+        ui.add_space(WIDGET_PADDING);
+        self.expand_collapse(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        self.hidden_slots(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        self.sort_controls(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        self.critical_path_controls(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        self.longest_items(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        self.statistics(ui, cx);
+        ui.add_space(WIDGET_PADDING);
+        self.annotations(ui, cx);
+    }
+
+    fn annotations(&mut self, ui: &mut egui::Ui, cx: &mut Context) {
+        ui.subheading("Annotations", cx);
+
+        // Annotate the selection if there is one, otherwise the selected item
+        let offset = self.config.view_offset;
+        let selected = self.config.selected.as_ref();
+        let target = cx
+            .selection
+            .map(|selection| selection.translate(offset))
+            .or_else(|| selected.map(|s| s.item.interval));
+        ui.add(egui::TextEdit::singleline(&mut self.config.annotation_text).hint_text("Note"));
+        ui.horizontal(|ui| {
+            let add = egui::Button::new("Add");
+            if ui
+                .add_enabled(target.is_some(), add)
+                .on_hover_text("At the selection, or else the selected item")
+                .on_disabled_hover_text("Select an interval or an item first")
+                .clicked()
+            {
+                let entry_id = selected
+                    .filter(|_| self.config.annotate_slot)
+                    .map(|s| s.entry_id.clone());
+                let text = std::mem::take(&mut self.config.annotation_text);
+                self.config.annotations.push(Annotation {
+                    text,
+                    interval: target.unwrap(),
+                    entry_id,
+                });
+            }
+            ui.add_enabled(
+                selected.is_some(),
+                egui::Checkbox::new(&mut self.config.annotate_slot, "On Selected Slot"),
+            );
+        });
+
+        let mut remove = None;
+        let mut zoom = None;
+        for (i, annotation) in self.config.annotations.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                let text = if annotation.text.is_empty() {
+                    "(no text)"
+                } else {
+                    &annotation.text
+                };
+                if ui
+                    .link(text)
+                    .on_hover_text(annotation.interval.to_string())
+                    .clicked()
+                {
+                    zoom = Some(annotation.interval);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            self.config.annotations.remove(i);
+        }
+        if let Some(interval) = zoom {
+            // Leave some room around points and short intervals
+            let margin = (interval.duration_ns() / 2).max(self.config.interval.duration_ns() / 100);
+            self.zoom_to(cx, interval.grow(margin));
+        }
+
+        const FILE_NAME: &str = "legion_prof_annotations.json";
+        ui.horizontal(|ui| {
+            if ui.button("Export").clicked() {
+                let json = serde_json::to_vec_pretty(&self.config.annotations).unwrap();
+                self.config.annotation_status = Some(
+                    match export::save_file(FILE_NAME, "application/json", &json) {
+                        Ok(path) => format!("Saved to {}", path),
+                        Err(e) => format!("Export failed: {}", e),
+                    },
+                );
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui
+                .button("Import")
+                .on_hover_text(format!("Add the annotations in {}", FILE_NAME))
+                .clicked()
+            {
+                let loaded = export::load_file(FILE_NAME).and_then(|bytes| {
+                    serde_json::from_slice::<Vec<Annotation>>(&bytes).map_err(Into::into)
+                });
+                self.config.annotation_status = Some(match loaded {
+                    Ok(loaded) => {
+                        let count = loaded.len();
+                        for annotation in loaded {
+                            if !self.config.annotations.contains(&annotation) {
+                                self.config.annotations.push(annotation);
+                            }
+                        }
+                        format!("Imported {} annotations", count)
+                    }
+                    Err(e) => format!("Import failed: {}", e),
+                });
+            }
+        });
+        if let Some(status) = &self.config.annotation_status {
+            ui.label(status);
+        }
+    }
+
+    fn critical_path_controls(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        ui.subheading("Critical Path", cx);
+        ui.horizontal(|ui| {
+            if ui.button("Compute").clicked() {
+                self.compute_critical_path();
+            }
+            if ui.button("Clear").clicked() {
+                self.config.critical_path = None;
+            }
+        });
+        if let Some(path) = &self.config.critical_path {
+            ui.label(format!("{} items", path.items.len()));
+            if let Some(interval) = path.interval() {
+                ui.label(format!("Span: {}", Timestamp(interval.duration_ns())));
+                ui.label(format!("Busy: {}", Timestamp(path.busy_ns())));
+            }
+        } else {
+            ui.label("Ends at the selected task, or the last task in view.");
+        }
+    }
+
+    fn longest_items(&mut self, ui: &mut egui::Ui, cx: &mut Context) {
+        ui.subheading("Longest Items", cx);
+        ui.horizontal(|ui| {
+            ui.label("Show:");
+            let changed = ui
+                .add(egui::DragValue::new(&mut self.config.top_n).clamp_range(1..=100))
+                .changed();
+            if changed {
+                self.rebuild_longest();
+            }
+        });
+        if self.config.longest.is_empty() {
+            ui.label("No items loaded in view.");
+            return;
+        }
+
+        let mut selected = None;
+        for (i, long) in self.config.longest.iter().enumerate() {
+            let slot_name = self
+                .find_slot(&long.entry_id)
+                .map_or("", |slot| &slot.long_name);
+            if ui
+                .link(format!(
+                    "{} ({})",
+                    long.title,
+                    Timestamp(long.interval.duration_ns())
+                ))
+                .on_hover_text(format!("{}\n{}", slot_name, long.interval))
+                .clicked()
+            {
+                selected = Some(i);
+            }
+        }
+
+        if let Some(i) = selected {
+            let long = &self.config.longest[i];
+            let (entry_id, interval) = (long.entry_id.clone(), long.interval);
+            self.reveal(&entry_id);
+            self.zoom_to(cx, interval);
+        }
+    }
+
+    fn statistics(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        ui.subheading("Statistics", cx);
+        ui.horizontal(|ui| {
+            if ui
+                .button("Compute")
+                .on_hover_text("Over the selection, or the view if nothing is selected")
+                .clicked()
+            {
+                let interval = cx.selection.unwrap_or(cx.view_interval);
+                let items = self.items_in(interval);
+                let stats = self.config.report_error(items.map(|items| {
+                    let mut stats = analysis::name_stats(items.iter().map(|(_, item)| item));
+                    let (column, descending) = self.config.stats_sort;
+                    analysis::sort_stats(&mut stats, column, descending);
+                    Some((interval, stats))
+                }));
+                self.config.name_stats = stats;
+            }
+            if ui.button("Clear").clicked() {
+                self.config.name_stats = None;
+            }
+        });
+        let (interval, stats) = if let Some((interval, stats)) = &mut self.config.name_stats {
+            (*interval, stats)
+        } else {
+            ui.label("Count, total, mean and max time of items by name.");
+            return;
+        };
+        ui.label(format!("Over {}", interval));
+
+        const COLUMNS: &[(StatsColumn, &str)] = &[
+            (StatsColumn::Name, "Name"),
+            (StatsColumn::Count, "Count"),
+            (StatsColumn::Total, "Total"),
+            (StatsColumn::Mean, "Mean"),
+            (StatsColumn::Max, "Max"),
+        ];
+        let sort = &mut self.config.stats_sort;
+        ScrollArea::both()
+            .id_source(("name_stats", self.index))
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new(("name_stats_grid", self.index))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (column, name) in COLUMNS {
+                            let text = if sort.0 == *column {
+                                format!("{} {}", name, if sort.1 { "⏷" } else { "⏶" })
+                            } else {
+                                name.to_string()
+                            };
+                            if ui.selectable_label(sort.0 == *column, text).clicked() {
+                                // Clicking the sorted column flips the order
+                                *sort = if sort.0 == *column {
+                                    (*column, !sort.1)
+                                } else {
+                                    (*column, *column != StatsColumn::Name)
+                                };
+                                analysis::sort_stats(stats, sort.0, sort.1);
+                            }
+                        }
+                        ui.end_row();
+
+                        for row in stats.iter() {
+                            ui.label(&row.name);
+                            ui.label(row.count.to_string());
+                            ui.label(Timestamp(row.total_ns).to_string());
+                            ui.label(Timestamp(row.mean_ns()).to_string());
+                            ui.label(Timestamp(row.max_ns).to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    // Summaries only keep the curves their tiles make up, so they aren't counted
+    fn tiles_held(&self) -> usize {
+        let slots = self.panel.slots.iter().flat_map(|node| &node.slots);
+        slots
+            .flat_map(|kind| &kind.slots)
+            .map(|slot| {
+                slot.tiles.len()
+                    + slot.counter.as_ref().map_or(0, |c| c.tiles.len())
+                    + slot.events.as_ref().map_or(0, |e| e.tiles.len())
+            })
+            .sum()
+    }
+
+    fn stats(&mut self, ui: &mut egui::Ui) {
+        let fetch = self.config.data_source.fetch_stats();
+        let lookups = fetch.cache_hits + fetch.cache_misses;
+        let frame = &self.config.frame_stats;
+        let rows = [
+            ("Tiles held", self.tiles_held().to_string()),
+            ("Fetched this frame", frame.tiles_fetched.to_string()),
+            ("Items painted", frame.items_painted.get().to_string()),
+            ("Requests", fetch.requests.to_string()),
+            ("In flight", fetch.in_flight.to_string()),
+            ("Fetched", format_counter(fetch.bytes as f64, "B")),
+            (
+                "Cache hits",
+                if lookups > 0 {
+                    let rate = 100.0 * fetch.cache_hits as f64 / lookups as f64;
+                    format!("{:.0}% of {}", rate, lookups)
+                } else {
+                    "-".to_owned()
+                },
+            ),
+        ];
+        ui.strong(format!("Profile {}", self.index));
+        egui::Grid::new(("stats", self.index)).show(ui, |ui| {
+            for (name, value) in rows {
+                ui.label(name);
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+    }
+
+    // Upstream items above the selection, downstream ones below. Returns
+    // the item clicked on, if any.
+    fn dependency_graph(&mut self, ui: &mut egui::Ui, cx: &Context) -> Option<GraphNode> {
+        const NODE_SIZE: Vec2 = Vec2::new(120.0, 24.0);
+        const SPACING: Vec2 = Vec2::new(8.0, 32.0);
+        const ARROW_SIZE: f32 = 6.0;
+
+        ui.subheading(format!("Profile {}", self.index), cx);
+        let selected = if let Some(selected) = &self.config.selected {
+            selected
+        } else {
+            ui.label("Select an item to see what it depends on, and what depends on it.");
+            return None;
+        };
+        if selected.graph.is_none() {
+            let graph = DependencyGraph::new(self.config.data_source.as_mut(), selected);
+            let graph = self.config.report_error(graph);
+            self.config.selected.as_mut().unwrap().graph = Some(graph);
+        }
+        let graph = self.config.selected.as_ref()?.graph.as_ref()?;
+        if graph.nodes.len() == 1 {
+            ui.label("This item has no dependencies.");
+            return None;
+        }
+        if graph.truncated {
+            ui.weak(format!(
+                "Showing at most {} items per level.",
+                DependencyGraph::MAX_LAYER_WIDTH
+            ));
+        }
+
+        let mut layers: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        for (n, node) in graph.nodes.iter().enumerate() {
+            layers.entry(node.depth).or_default().push(n);
+        }
+        let widest = layers.values().map(Vec::len).max().unwrap_or(1) as f32;
+        let size = Vec2::new(
+            (widest * (NODE_SIZE.x + SPACING.x)).at_least(ui.available_width()),
+            layers.len() as f32 * (NODE_SIZE.y + SPACING.y),
+        );
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+        let mut rects = vec![Rect::NOTHING; graph.nodes.len()];
+        for (row, layer) in layers.values().enumerate() {
+            let width = layer.len() as f32 * (NODE_SIZE.x + SPACING.x) - SPACING.x;
+            let mut min = Pos2::new(
+                rect.center().x - width / 2.0,
+                rect.top() + row as f32 * (NODE_SIZE.y + SPACING.y),
+            );
+            for n in layer {
+                rects[*n] = Rect::from_min_size(min, NODE_SIZE);
+                min.x += NODE_SIZE.x + SPACING.x;
+            }
+        }
+
+        let visuals = ui.visuals();
+        let stroke = Stroke::new(1.0, visuals.weak_text_color());
+        for (from, to) in &graph.edges {
+            let (from, to) = (rects[*from], rects[*to]);
+            let (start, stop) = if from.bottom() <= to.top() {
+                (from.center_bottom(), to.center_top())
+            } else {
+                (from.center_top(), to.center_bottom())
+            };
+            let dir = (stop - start).normalized();
+            let side = dir.rot90() * ARROW_SIZE * 0.5;
+            ui.painter().line_segment([start, stop], stroke);
+            ui.painter()
+                .line_segment([stop - dir * ARROW_SIZE + side, stop], stroke);
+            ui.painter()
+                .line_segment([stop - dir * ARROW_SIZE - side, stop], stroke);
+        }
+
+        let mut clicked = None;
+        for (n, (node, node_rect)) in graph.nodes.iter().zip(&rects).enumerate() {
+            let id = ui.id().with(("dependency", self.index, n));
+            let response = ui.interact(*node_rect, id, egui::Sense::click());
+            let visuals = ui.visuals();
+            let (fill, node_stroke) = if n == 0 {
+                (visuals.selection.bg_fill, visuals.selection.stroke)
+            } else {
+                let widget = visuals.widgets.style(&response);
+                (widget.bg_fill, widget.bg_stroke)
+            };
+            ui.painter().rect(*node_rect, 3.0, fill, node_stroke);
+            ui.painter().with_clip_rect(node_rect.shrink(2.0)).text(
+                node_rect.center(),
+                Align2::CENTER_CENTER,
+                &node.title,
+                TextStyle::Small.resolve(ui.style()),
+                visuals.text_color(),
+            );
+            let response = response.on_hover_text(format!(
+                "{}\n{}\n{}",
+                node.title,
+                self.entry_path(&node.entry_id),
+                node.interval
+            ));
+            if response.clicked() {
+                clicked = Some(n);
+            }
+        }
+        clicked.map(|n| graph.nodes[n].clone())
+    }
+
+    fn histogram(&mut self, ui: &mut egui::Ui, cx: &Context) {
+        const HEIGHT: f32 = 120.0;
+
+        ui.subheading(format!("Profile {}", self.index), cx);
+        let (entry_id, title) = if let Some(selected) = &self.config.selected {
+            (selected.entry_id.clone(), selected.item.title.clone())
+        } else {
+            ui.label("Select an item to see durations in its slot or with its name.");
+            return;
+        };
+
+        let slot_name = self
+            .find_slot(&entry_id)
+            .map_or_else(String::new, |slot| slot.long_name.clone());
+        let hist = &mut self.config.histogram;
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut hist.by_name, false, format!("Slot: {}", slot_name));
+            ui.radio_value(&mut hist.by_name, true, format!("Name: {}", title));
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut hist.log_scale, "Log Scale");
+            if ui.button("Clear Brush").clicked() {
+                hist.brush = None;
+            }
+        });
+
+        // Items that straddle a tile boundary show up once per tile
+        let mut intervals: BTreeMap<ItemUID, Interval> = BTreeMap::new();
+        let by_name = self.config.histogram.by_name;
+        for (slot, _, item) in self.loaded_items() {
+            let source = if by_name {
+                item.title == title
+            } else {
+                slot.entry_id == entry_id
+            };
+            if source {
+                intervals
+                    .entry(item.item_uid)
+                    .and_modify(|i| *i = i.union(item.interval))
+                    .or_insert(item.interval);
+            }
+        }
+        let durations: Vec<i64> = intervals.values().map(|i| i.duration_ns()).collect();
+        let range = match (durations.iter().min(), durations.iter().max()) {
+            (Some(min), Some(max)) => (*min, *max),
+            _ => {
+                ui.label("No items loaded.");
+                return;
+            }
+        };
+
+        let hist = &mut self.config.histogram;
+        let counts = hist.counts(&durations, range);
+        let max_count = counts.iter().copied().max().unwrap_or(0).max(1);
+
+        let size = Vec2::new(ui.available_width(), HEIGHT);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+        let to_fraction = |x: f32| ((x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+
+        // Brushing: drag across the histogram to highlight those items
+        if response.drag_started() {
+            hist.brush_start = response.interact_pointer_pos().map(|p| to_fraction(p.x));
+        }
+        if let (Some(start), Some(pos)) = (hist.brush_start, response.interact_pointer_pos()) {
+            let end = to_fraction(pos.x);
+            hist.brush = Some((
+                hist.duration(start.min(end), range),
+                hist.duration(start.max(end), range),
+            ));
+        }
+        if response.drag_released() {
+            hist.brush_start = None;
+        }
+        if response.clicked() {
+            hist.brush = None;
+        }
+
+        ui.painter()
+            .rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+        let width = rect.width() / Histogram::BUCKETS as f32;
+        let hover = response.hover_pos();
+        for (i, count) in counts.iter().enumerate() {
+            let left = rect.left() + i as f32 * width;
+            let top = rect.bottom() - rect.height() * *count as f32 / max_count as f32;
+            let bar =
+                Rect::from_min_max(Pos2::new(left, top), Pos2::new(left + width, rect.bottom()));
+            let bucket = (
+                hist.duration(i as f32 / Histogram::BUCKETS as f32, range),
+                hist.duration((i + 1) as f32 / Histogram::BUCKETS as f32, range),
+            );
+            let color = if hist.is_brushed((bucket.0 + bucket.1) / 2) {
+                ui.visuals().selection.bg_fill
+            } else {
+                ui.visuals().widgets.inactive.bg_fill
+            };
+            ui.painter()
+                .rect_filled(bar.shrink2(Vec2::new(0.5, 0.0)), 0.0, color);
+
+            let column = Rect::from_x_y_ranges(bar.x_range(), rect.y_range());
+            if hover.map_or(false, |h| column.contains(h)) {
+                ui.show_tooltip(
+                    "histogram_tooltip",
+                    &column,
+                    format!(
+                        "{} to {}: {} items",
+                        Timestamp(bucket.0),
+                        Timestamp(bucket.1),
+                        count
+                    ),
+                );
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(Timestamp(range.0).to_string());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(Timestamp(range.1).to_string());
+            });
+        });
+        if let Some((min, max)) = hist.brush {
+            let brushed = durations.iter().filter(|d| hist.is_brushed(**d)).count();
+            ui.label(format!(
+                "Highlighting {} items from {} to {}",
+                brushed,
+                Timestamp(min),
+                Timestamp(max)
+            ));
+        }
+    }
+
+    // Stats for the selection, based on the tiles loaded for the current
+    // view. Recomputed as more tiles come in.
+    fn selection_stats(&mut self, selection: Interval) -> &SelectionStats {
+        let interval = selection.translate(self.config.view_offset);
+        let view = self.config.view_interval;
+        let fresh_slots = || {
+            self.panel
+                .slots
+                .iter()
+                .filter(|node| Panel::<Slot>::is_slot_visible(&node.entry_id, &self.config))
+                .flat_map(|node| &node.slots)
+                .flat_map(|kind| kind.slots.iter().map(move |slot| (kind, slot)))
+                .filter(move |(_, slot)| slot.last_view_interval == Some(view))
+        };
+        let tile_count = fresh_slots().map(|(_, slot)| slot.tiles.len()).sum();
+        let stale = self.config.selection_stats.as_ref().map_or(true, |s| {
+            s.interval != interval || s.tile_count != tile_count
+        });
+        if stale {
+            let mut items = BTreeSet::new();
+            let mut busy_ns = 0;
+            // (busy time, slots) of each kind
+            let mut kinds: BTreeMap<&str, (i64, u64)> = BTreeMap::new();
+            for (kind, slot) in fresh_slots() {
+                if slot.counter.is_some() || slot.events.is_some() {
+                    continue;
+                }
+                let mut intervals = Vec::new();
+                for tile in &slot.tiles {
+                    for item in tile.items.iter().flatten() {
+                        if interval.overlaps(item.interval) {
+                            items.insert(item.item_uid);
+                            intervals.push(item.interval.intersection(interval));
+                        }
+                    }
+                }
+                let busy = analysis::covered_ns(&mut intervals);
+                busy_ns += busy;
+                let entry = kinds.entry(&kind.short_name).or_default();
+                entry.0 += busy;
+                entry.1 += 1;
+            }
+            let duration = interval.duration_ns().max(1) as f32;
+            let kinds = kinds
+                .into_iter()
+                .map(|(name, (busy, slots))| {
+                    (name.to_owned(), busy as f32 / (duration * slots as f32))
+                })
+                .collect();
+            self.config.selection_stats = Some(SelectionStats {
+                interval,
+                tile_count,
+                items: items.len() as u64,
+                busy_ns,
+                kinds,
+            });
+        }
+        self.config.selection_stats.as_ref().unwrap()
+    }
+
+    // Recompute the longest items from the tiles already loaded for the view
+    fn rebuild_longest(&mut self) {
+        self.config.longest.clear();
+        let view = self.config.view_interval;
+        for node in &self.panel.slots {
+            for kind in &node.slots {
+                for slot in &kind.slots {
+                    if slot.last_view_interval != Some(view) {
+                        continue; // stale
+                    }
+                    for tile in &slot.tiles {
+                        self.config.record_longest(&slot.entry_id, tile);
+                    }
+                }
+            }
+        }
+    }
+
+    fn compute_critical_path(&mut self) {
+        // Start from the selected item if there is one, otherwise the item
+        // that finishes last among everything currently loaded
+        let end = if let Some(selected) = &self.config.selected {
+            Some(PathItem {
+                item_uid: selected.item.item_uid,
+                entry_id: selected.entry_id.clone(),
+                row: selected.row,
+                interval: selected.item.interval,
+                title: selected.item.title.clone(),
+            })
+        } else {
+            self.loaded_items()
+                .filter(|(_, _, item)| self.config.view_interval.overlaps(item.interval))
+                .max_by_key(|(_, _, item)| item.interval.stop)
+                .map(|(slot, row, item)| PathItem {
+                    item_uid: item.item_uid,
+                    entry_id: slot.entry_id.clone(),
+                    row,
+                    interval: item.interval,
+                    title: item.title.clone(),
+                })
+        };
+
+        let interval = self.config.view_interval;
+        let path = end
+            .map(|end| analysis::critical_path(self.config.data_source.as_mut(), interval, end))
+            .transpose();
+        self.config.critical_path = self.config.report_error(path);
+    }
+
+    fn find_slot_mut<'a>(
+        panel: &'a mut Panel<Panel<Panel<Slot>>>,
+        entry_id: &EntryID,
+    ) -> Option<&'a mut Slot> {
+        let node = panel.slots.get_mut(entry_id.slot_index(0)? as usize)?;
+        let kind = node.slots.get_mut(entry_id.slot_index(1)? as usize)?;
+        kind.slots.get_mut(entry_id.slot_index(2)? as usize)
+    }
+
+    // Draws the pinned slots, which stay put while the rest scroll
+    fn pinned_slots(&mut self, ui: &mut egui::Ui, cx: &mut Context) {
+        const ROW_PADDING: f32 = 4.0;
+
+        let pinned = &self.config.pinned;
+        let paths: Vec<_> = pinned.iter().map(|p| self.entry_path(p)).collect();
+        let config = &mut self.config;
+        let mut height = 0.0;
+        for entry_id in &config.pinned {
+            if let Some(slot) = Self::find_slot_mut(&mut self.panel, entry_id) {
+                height += slot.height(config, cx) + ROW_PADDING;
+            }
+        }
+        if height == 0.0 {
+            return;
+        }
+        // Line up with the scroll area (as of the last frame, since its
+        // scroll bar comes and goes), and with the slots in it, which are
+        // under the labels of their node and kind
+        let width = if config.scroll_width > 0.0 {
+            config.scroll_width.at_most(ui.available_width())
+        } else {
+            ui.available_width()
+        };
+        let (rect, _) = ui.allocate_exact_size(Vec2::new(width, height), egui::Sense::hover());
+        let indent = 2.0 * (config.label_width + Panel::<Slot>::COL_PADDING);
+        let slots_rect = Rect::from_min_max(Pos2::new(rect.min.x + indent, rect.min.y), rect.max);
+        let viewport = Rect::from_min_size(Pos2::ZERO, rect.size());
+        let mut y = rect.min.y;
+        for (entry_id, path) in config.pinned.clone().iter().zip(paths) {
+            if let Some(slot) = Self::find_slot_mut(&mut self.panel, entry_id) {
+                ui.painter().text(
+                    Pos2::new(rect.min.x, y) + ui.spacing().item_spacing,
+                    Align2::LEFT_TOP,
+                    path,
+                    TextStyle::Body.resolve(ui.style()),
+                    ui.visuals().weak_text_color(),
+                );
+                Panel::<Slot>::render(ui, slots_rect, viewport, slot, &mut y, config, cx);
+            }
+        }
+        ui.separator();
+    }
+
+    fn find_slot(&self, entry_id: &EntryID) -> Option<&Slot> {
+        let node = self.panel.slots.get(entry_id.slot_index(0)? as usize)?;
+        let kind = node.slots.get(entry_id.slot_index(1)? as usize)?;
+        kind.slots.get(entry_id.slot_index(2)? as usize)
+    }
+
+    // Make sure the given slot is visible (expanding anything on the way)
+    // and scroll to it on the next frame
+    fn reveal(&mut self, entry_id: &EntryID) {
+        if let Some(node_index) = entry_id.slot_index(0) {
+            if !self.config.node_set.contains(node_index) {
+                self.config.node_set.insert(node_index);
+                self.config.node_set_text = self.config.node_set.to_string();
+                self.config.node_set_error = None;
+            }
+
+            if let Some(node) = self.panel.slots.get_mut(node_index as usize) {
+                node.expanded = true;
+                let kind_index = entry_id.slot_index(1);
+                if let Some(kind) = kind_index.and_then(|i| node.slots.get_mut(i as usize)) {
+                    kind.expanded = true;
+                    let slot_index = entry_id.slot_index(2);
+                    if let Some(slot) = slot_index.and_then(|i| kind.slots.get_mut(i as usize)) {
+                        slot.expanded = true;
+                    }
+                }
+            }
+        }
+        self.config
+            .hidden
+            .retain(|hidden| !covers(hidden, entry_id));
+        self.config.scroll_target = Some(entry_id.clone());
+    }
+
+    fn search(&mut self, cx: &Context) {
+        const MAX_RESULTS: usize = 1000;
+
+        let mut results: Vec<SearchResult> = Vec::new();
+        if cx.search.query.trim().is_empty() {
+            self.config.search_results = results;
+            return;
+        }
+
+        // First search everything we've already loaded
+        for (slot, _, item) in self.loaded_items() {
+            if !cx.search.is_match(&slot.entry_id, item) {
+                continue;
+            }
+            // Items that straddle a tile boundary show up once per tile, so
+            // stitch them back together
+            if let Some(last) = results.last_mut() {
+                if last.entry_id == slot.entry_id
+                    && last.title == item.title
+                    && last.interval.stop == item.interval.start
+                {
+                    last.interval = last.interval.union(item.interval);
+                    continue;
+                }
+            }
+            results.push(SearchResult {
+                entry_id: slot.entry_id.clone(),
+                title: item.title.clone(),
+                interval: item.interval,
+            });
+        }
+
+        // Then see if the data source knows about anything else (it only
+        // understands plain text)
+        let found = if cx.search.parsed.is_none() {
+            self.config.data_source.search(&cx.search.query)
+        } else {
+            Ok(Vec::new())
+        };
+        for result in self.config.report_error(found) {
+            let duplicate = results.iter().any(|r| {
+                r.entry_id == result.entry_id
+                    && r.title == result.title
+                    && r.interval.overlaps(result.interval)
+            });
+            if !duplicate {
+                results.push(result);
+            }
+        }
+
+        results.truncate(MAX_RESULTS);
+        self.config.search_results = results;
+    }
+
+    // Every item in the tiles loaded so far, as (slot, row, item)
+    fn loaded_items(&self) -> impl Iterator<Item = (&Slot, u64, &Item)> {
+        self.panel
+            .slots
+            .iter()
+            .flat_map(|node| &node.slots)
+            .flat_map(|kind| &kind.slots)
+            .flat_map(|slot| {
+                slot.tiles.iter().flat_map(move |tile| {
+                    tile.items.iter().enumerate().flat_map(move |(row, items)| {
+                        items.iter().map(move |item| (slot, row as u64, item))
+                    })
+                })
+            })
+    }
+}
+
+impl Action {
+    fn description(self) -> &'static str {
+        match self {
+            Action::PanLeft => "Pan left",
+            Action::PanRight => "Pan right",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::ResetView => "Reset zoom level",
+            Action::ExpandAll => "Expand all",
+            Action::CollapseAll => "Collapse all",
+            Action::Search => "Search",
+            Action::ToggleHelp => "Show/hide this help",
+        }
+    }
+}
+
+impl Trigger {
+    fn pressed(self, ctx: &egui::Context) -> bool {
+        match self {
+            Trigger::Key(modifiers, key) => ctx.input_mut().consume_key(modifiers, key),
+            Trigger::Text(text) => ctx
+                .input()
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Text(t) if t == text)),
+        }
+    }
+}
+
+impl Default for FollowState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_ns: 1_000_000_000,
+            last_poll: f64::NEG_INFINITY,
+            view: None,
+        }
+    }
+}
+
+impl Default for FilterState {
+    fn default() -> Self {
+        Self {
+            filters: Vec::new(),
+            mode: FilterMode::default(),
+            subject: Subject::Name,
+            op: Op::Contains,
+            value: String::new(),
+            query: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl FilterState {
+    // Color to draw the item in, or None if it should be hidden
+    fn color(&self, entry_id: &EntryID, item: &Item, color: Color32) -> Option<Color32> {
+        if self
+            .filters
+            .iter()
+            .all(|query| query.matches(entry_id, item))
+        {
+            Some(color)
+        } else if self.mode == FilterMode::Dim {
+            Some(color.linear_multiply(DIM_FACTOR))
+        } else {
+            None
+        }
+    }
+}
+
+impl SearchState {
+    fn set_query(&mut self, query: String) {
+        self.query = query;
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.query_lower = self.query.to_lowercase();
+        self.parsed = None;
+        self.error = None;
+        if !Query::is_plain(&self.query) {
+            match self.query.parse() {
+                Ok(query) => self.parsed = Some(query),
+                // Fall back to searching for the text as is
+                Err(error) => self.error = Some(error),
+            }
+        }
+    }
+
+    fn is_match(&self, entry_id: &EntryID, item: &Item) -> bool {
+        if let Some(query) = &self.parsed {
+            query.matches(entry_id, item)
+        } else {
+            !self.query_lower.is_empty()
+                && filter::contains_lowercase(&item.title, &self.query_lower)
+        }
+    }
+}
+
+impl ProfApp {
+    /// Called once before the first frame.
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        data_source: Box<dyn DataSource>,
+        extra_source: Option<Box<dyn DataSource>>,
+        options: &StartOptions,
+    ) -> Self {
+        // This is also where you can customized the look at feel of egui using
+        // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
+
+        // Load previous app state (if any).
+        // Note that you must enable the `persistence` feature for this to work.
+        let mut result: Self = if let Some(storage) = cc.storage {
+            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
+        } else {
+            Default::default()
+        };
+
+        result.windows.clear();
+        match Window::new(data_source, 0) {
+            Ok(mut window) => {
+                result.cx.total_interval = window.config.interval;
+                result.cx.view_interval = result.cx.total_interval;
+
+                // Pick up where we left off if this profile has been seen before
+                if let Some(saved) = result.saved_views.get(&window.profile_key) {
+                    window.restore_view(saved);
+                    let view = saved.view_interval.intersection(result.cx.total_interval);
+                    if view.duration_ns() > 0 {
+                        result.cx.view_interval = view;
+                    }
+                }
+                if let Some(view) = options.view_interval {
+                    let view = view.intersection(result.cx.total_interval);
+                    if view.duration_ns() > 0 {
+                        result.cx.view_interval = view;
+                    }
+                }
+                if let Some(node_set) = &options.node_set {
+                    window.config.node_set = node_set.clone();
+                    window.config.node_set_text = node_set.to_string();
+                }
+                window.location = options.profile.clone();
+                result.windows.push(window);
+            }
+            Err(error) => {
+                result.status = Some(format!("Unable to open profile: {}", error));
+            }
+        }
+
+        result.extra_source = extra_source;
+        result.cx.sync_views = true;
+        if let Some(theme) = &options.theme {
+            result.cx.theme = theme.clone();
+        }
+        result.cx.color_overrides = options.color_overrides.clone();
+        result.cx.color_file = options.color_file.clone();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            result.last_update = Some(Instant::now());
+            result.config_file = options.config_file.clone();
+
+            // Notice profiles changing on disk (see loader::watch_file) even
+            // when nothing else is happening
+            let ctx = cc.egui_ctx.clone();
+            watch::set_waker(move || ctx.request_repaint());
+        }
+
+        // A shared link takes precedence over the saved view
+        #[cfg(target_arch = "wasm32")]
+        result.apply_location();
+
+        result
+    }
+
+    // Restores the view described by the URL fragment, if any
+    #[cfg(target_arch = "wasm32")]
+    fn apply_location(&mut self) {
+        let hash = web_sys::window().and_then(|w| w.location().hash().ok());
+        let hash = match hash {
+            Some(hash) if hash.len() > 1 => hash,
+            _ => return,
+        };
+        let link = match DeepLink::parse(&hash) {
+            Ok(link) => link,
+            Err(error) => {
+                self.status = Some(format!("Ignoring link: {}", error));
+                return;
+            }
+        };
+        if let Some(window) = self.windows.first_mut() {
+            window.set_toggled(&link.toggled);
+        }
+        if let Some(view) = link.view {
+            let view = view.intersection(self.cx.total_interval);
+            if view.duration_ns() > 0 {
+                self.cx.view_interval = view;
+            }
+        }
+        self.source_url = link.url;
+    }
+
+    // Keeps the URL fragment in sync with the view (of the first profile)
+    #[cfg(target_arch = "wasm32")]
+    fn update_location(&mut self) {
+        let window = if let Some(window) = self.windows.first_mut() {
+            window
+        } else {
+            return;
+        };
+        let link = DeepLink {
+            url: self.source_url.clone(),
+            view: Some(self.cx.view_interval),
+            toggled: window.toggled(),
+        };
+        let fragment = link.to_fragment();
+        if fragment == self.last_fragment {
+            return;
+        }
+        // Replace rather than push, so panning doesn't flood the history
+        if let Some(history) = web_sys::window().and_then(|w| w.history().ok()) {
+            let url = format!("#{}", fragment);
+            let null = wasm_bindgen::JsValue::NULL;
+            if history
+                .replace_state_with_url(&null, "", Some(&url))
+                .is_ok()
+            {
+                self.last_fragment = fragment;
+            }
+        }
+    }
+
+    // Most recent first. Files that fail to open are dropped from the list.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_recent_files(recent_files: &mut Vec<PathBuf>, path: PathBuf, opened: bool) {
+        const MAX_RECENT_FILES: usize = 10;
+        recent_files.retain(|p| *p != path);
+        if opened {
+            recent_files.insert(0, path);
+            recent_files.truncate(MAX_RECENT_FILES);
+        }
+    }
+
+    // Shows that files can be dropped while they're dragged over the window
+    fn drop_target(ctx: &egui::Context) {
+        let count = ctx.input().raw.hovered_files.len();
+        if count == 0 {
+            return;
+        }
+        let layer = egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_target"));
+        let painter = ctx.layer_painter(layer);
+        let screen = ctx.input().screen_rect();
+        painter.rect_filled(screen, 0.0, Color32::from_black_alpha(192));
+        let text = if count == 1 {
+            "Drop to open the profile".to_owned()
+        } else {
+            format!("Drop to open {} profiles", count)
+        };
+        painter.text(
+            screen.center(),
+            Align2::CENTER_CENTER,
+            text,
+            TextStyle::Heading.resolve(&ctx.style()),
+            Color32::WHITE,
+        );
+    }
+
+    // Opens a profile in a new window, after the existing ones
+    fn add_window(
+        windows: &mut Vec<Window>,
+        saved_views: &BTreeMap<u64, SavedView>,
+        cx: &mut Context,
+        data_source: Box<dyn DataSource>,
+        location: Option<String>,
+    ) -> DataSourceResult<()> {
+        // Windows may have been reordered, so find the largest index
+        let index = windows.iter().map(|w| w.index + 1).max().unwrap_or(0);
+        let mut window = Window::new(data_source, index)?;
+        window.location = location;
+        tracing::info!("opened {} ({})", window.title(), window.config.interval);
+        if let Some(saved) = saved_views.get(&window.profile_key) {
+            window.restore_view(saved);
+        }
+        cx.total_interval = if windows.is_empty() {
+            window.config.interval
+        } else {
+            cx.total_interval.union(window.config.interval)
+        };
+        cx.view_interval = cx.total_interval;
+        windows.push(window);
+        Ok(())
+    }
+
+    fn search_id() -> egui::Id {
+        egui::Id::new("search_query")
+    }
+
+    fn search(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        ui.heading("Search");
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut cx.search.query).id(Self::search_id()))
+                .changed();
+            // Loaded tiles change as the view moves, so allow a manual refresh
+            changed |= ui.button("Search").clicked();
+            let query = cx.search.query.trim();
+            if ui
+                .add_enabled(!query.is_empty(), egui::Button::new("Save"))
+                .on_hover_text("Save this query for later")
+                .clicked()
+                && !cx.saved_queries.iter().any(|q| q == query)
+            {
+                cx.saved_queries.push(query.to_owned());
+            }
+        });
+        if let Some(error) = &cx.search.error {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("{} (searching for plain text)", error),
+            );
+        }
+
+        if !cx.saved_queries.is_empty() {
+            let mut remove = None;
+            egui::CollapsingHeader::new("Saved Queries").show(ui, |ui| {
+                for (i, query) in cx.saved_queries.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.link(query).on_hover_text("Search").clicked() {
+                            cx.search.set_query(query.clone());
+                            changed = true;
+                        }
+                        if ui
+                            .small_button("Filter")
+                            .on_hover_text("Add as a filter")
+                            .clicked()
+                        {
+                            match query.parse() {
+                                Ok(query) => cx.filters.filters.push(query),
+                                Err(error) => cx.filters.error = Some(error),
+                            }
+                        }
+                        if ui.small_button("✖").on_hover_text("Forget").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+            });
+            if let Some(i) = remove {
+                cx.saved_queries.remove(i);
+            }
+        }
+
+        if changed {
+            cx.search.update();
+            for window in windows.iter_mut() {
+                window.search(cx);
+            }
+        }
+
+        let mut selected = None;
+        for (w, window) in windows.iter().enumerate() {
+            let results = &window.config.search_results;
+            if results.is_empty() {
+                continue;
+            }
+            ui.label(format!(
+                "Profile {}: {} results",
+                window.index,
+                results.len()
+            ));
+            ScrollArea::vertical()
+                .id_source(("search_results", window.index))
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for (r, result) in results.iter().enumerate() {
+                        let slot_name = window
+                            .find_slot(&result.entry_id)
+                            .map_or("", |slot| &slot.long_name);
+                        if ui
+                            .link(format!("{} ({})", result.title, slot_name))
+                            .on_hover_text(result.interval.to_string())
+                            .clicked()
+                        {
+                            selected = Some((w, r));
+                        }
+                    }
+                });
+        }
+
+        if let Some((w, r)) = selected {
+            let window = &mut windows[w];
+            let result = window.config.search_results[r].clone();
+            window.reveal(&result.entry_id);
+            window.zoom_to(cx, result.interval);
+        }
+    }
+
+    fn filters(ui: &mut egui::Ui, cx: &mut Context) {
+        ui.heading("Filters");
+        let state = &mut cx.filters;
+
+        ui.horizontal(|ui| {
+            let subject = match &state.subject {
+                Subject::Name => "Name",
+                Subject::Duration => "Duration",
+                Subject::Field(_) => "Field",
+            };
+            egui::ComboBox::from_id_source("filter_subject")
+                .selected_text(subject)
+                .width(80.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut state.subject, Subject::Name, "Name");
+                    ui.selectable_value(&mut state.subject, Subject::Duration, "Duration");
+                    if ui.selectable_label(subject == "Field", "Field").clicked()
+                        && subject != "Field"
+                    {
+                        state.subject = Subject::Field(String::new());
+                    }
+                });
+            if let Subject::Field(name) = &mut state.subject {
+                ui.add(
+                    egui::TextEdit::singleline(name)
+                        .hint_text("field")
+                        .desired_width(80.0),
+                );
+            }
+            egui::ComboBox::from_id_source("filter_op")
+                .selected_text(state.op.symbol())
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for op in Op::ALL {
+                        ui.selectable_value(&mut state.op, *op, op.symbol());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            let hint = if state.subject == Subject::Duration {
+                "e.g., 1ms"
+            } else {
+                "value"
+            };
+            let response = ui.add(egui::TextEdit::singleline(&mut state.value).hint_text(hint));
+            let submitted = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+            if ui.button("Add").clicked() || submitted {
+                match Filter::new(state.subject.clone(), state.op, &state.value) {
+                    Ok(filter) => {
+                        state.filters.push(Query::Filter(filter));
+                        state.value.clear();
+                        state.error = None;
+                    }
+                    Err(error) => state.error = Some(error),
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut state.query)
+                    .hint_text("or a query, e.g., name:fill AND dur>10us"),
+            );
+            let submitted = response.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+            if ui.button("Add").clicked() || submitted {
+                match state.query.parse() {
+                    Ok(query) => {
+                        state.filters.push(query);
+                        state.query.clear();
+                        state.error = None;
+                    }
+                    Err(error) => state.error = Some(error),
+                }
+            }
+        });
+        if let Some(error) = &state.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if state.filters.is_empty() {
+            ui.label("Items failing any filter are dimmed or hidden.");
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut state.mode, FilterMode::Dim, "Dim");
+            ui.radio_value(&mut state.mode, FilterMode::Hide, "Hide");
+            if ui.button("Clear All").clicked() {
+                state.filters.clear();
+            }
+        });
+        // One chip per active filter, click to remove
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, filter) in state.filters.iter().enumerate() {
+                if ui
+                    .small_button(format!("{} ✖", filter))
+                    .on_hover_text("Remove filter")
+                    .clicked()
+                {
+                    remove = Some(i);
+                }
+            }
+        });
+        if let Some(i) = remove {
+            state.filters.remove(i);
+        }
+    }
+
+    fn task_details(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        ui.heading("Task Details");
+
+        let mut navigate = None;
+        for (w, window) in windows.iter().enumerate() {
+            let selected = if let Some(selected) = &window.config.selected {
+                selected
+            } else {
+                continue;
+            };
+            if windows.len() > 1 {
+                ui.subheading(format!("Profile {}", window.index), cx);
+            }
+            ui.label(&selected.item.title);
+            ui.label(format!("Interval: {}", selected.item.interval));
+            if window.config.epoch.is_some() && cx.wall_clock {
+                let start = window.time_text(selected.item.interval.start, cx);
+                ui.label(format!("Started: {}", start));
+            }
+            show_all_fields(ui, w, &selected.item.fields);
+            show_endpoints(ui, &selected.item, &window.config);
+            // On the web, eframe hands this to the browser's clipboard API
+            ui.horizontal(|ui| {
+                let path = || window.entry_path(&selected.entry_id);
+                if ui.button("Copy Details").clicked() {
+                    ui.output().copied_text = item_details(&selected.item, &path(), false);
+                }
+                if ui.button("Copy as JSON").clicked() {
+                    ui.output().copied_text = item_details(&selected.item, &path(), true);
+                }
+            });
+
+            for (kind, label) in [
+                (ItemLinkKind::Predecessor, "Depends on:"),
+                (ItemLinkKind::Successor, "Dependents:"),
+            ] {
+                let mut links = selected.links.iter().filter(|l| l.kind == kind).peekable();
+                if links.peek().is_none() {
+                    continue;
+                }
+                ui.label(label);
+                for link in links {
+                    if ui
+                        .link(&link.title)
+                        .on_hover_text(link.interval.to_string())
+                        .clicked()
+                    {
+                        navigate = Some((w, link.entry_id.clone(), link.interval));
+                    }
+                }
+            }
+            if !selected.links.is_empty() && ui.button("Show Dependency Graph").clicked() {
+                cx.show_dependency_graph = true;
+            }
+        }
+        if windows.iter().all(|w| w.config.selected.is_none()) {
+            ui.label("Click on a task to see it displayed here.");
+        }
+
+        if let Some((w, entry_id, interval)) = navigate {
+            windows[w].reveal(&entry_id);
+            windows[w].zoom_to(cx, interval);
+        }
+    }
+
+    fn time_format(ui: &mut egui::Ui, cx: &mut Context) {
+        let format = &mut cx.time_format;
+        egui::ComboBox::from_label("Unit")
+            .selected_text(format.unit.name())
+            .show_ui(ui, |ui| {
+                for unit in TimeUnit::ALL {
+                    ui.selectable_value(&mut format.unit, *unit, unit.name());
+                }
+            });
+        ui.add(Slider::new(&mut format.precision, 0..=TimeFormat::MAX_PRECISION).text("Digits"));
+        ui.checkbox(&mut format.engineering, "Engineering Notation")
+            .on_hover_text("Powers of a thousand, e.g., 1.500e-3 s rather than 1.500 ms");
+        if ui.button("Reset").clicked() {
+            *format = TimeFormat::default();
+        }
+        // Takes effect now, rather than next frame
+        format.set();
+    }
+
+    fn density(ui: &mut egui::Ui, cx: &mut Context) {
+        let density = &mut cx.density;
+        ui.add(Slider::new(&mut density.ui_scale, Density::SCALES).text("UI Scale"))
+            .on_hover_text("Size of everything, applied once the slider is let go");
+        ui.add(Slider::new(&mut density.row_scale, Density::SCALES).text("Row Height"))
+            .on_hover_text("Height of the rows of items, relative to the text");
+        if ui.button("Reset").clicked() {
+            *density = Density::default();
+        }
+    }
+
+    fn go_to(ui: &mut egui::Ui, cx: &mut Context) {
+        ui.horizontal(|ui| {
+            ui.label("Go to:");
+            let response = ui
+                .text_edit_singleline(&mut cx.go_to_text)
+                .on_hover_text("A time to center the view on (e.g., 1.5ms), or an interval to show (e.g., 100us-250us)");
+            if !(response.lost_focus() && ui.input().key_pressed(egui::Key::Enter)) {
+                return;
+            }
+            let text = cx.go_to_text.trim();
+            let is_interval = text.contains("..") || text.get(1..).map_or(false, |t| t.contains('-'));
+            cx.go_to_error = None;
+            if is_interval {
+                match Interval::parse(text) {
+                    Ok(interval) if interval.duration_ns() > 0 => Self::zoom_in(cx, interval),
+                    Ok(_) => cx.go_to_error = Some("the interval is empty".to_owned()),
+                    Err(error) => cx.go_to_error = Some(error),
+                }
+            } else {
+                match Timestamp::parse(text) {
+                    Ok(time) => {
+                        let duration = cx.view_interval.duration_ns();
+                        Self::move_view(cx, Timestamp(time.0 - duration / 2), duration);
+                    }
+                    Err(error) => cx.go_to_error = Some(error),
+                }
+            }
+        });
+        if let Some(error) = &cx.go_to_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+
+    // Set the view to the given interval, no shorter than MIN_VIEW_NS and
+    // staying inside the profile as far as possible
+    fn set_view(cx: &mut Context, interval: Interval) {
+        cx.view_interval = interval.clamp_within(cx.total_interval, MIN_VIEW_NS);
+    }
+
+    // Set the view, and remember it in the breadcrumbs
+    fn zoom_in(cx: &mut Context, interval: Interval) {
+        Self::set_view(cx, interval);
+        cx.new_crumb = true;
+    }
+
+    fn reset_zoom(cx: &mut Context) {
+        cx.view_interval = cx.total_interval;
+        cx.crumbs.clear();
+    }
+
+    // The whole profile, then each zoom in turn (the last few, if there
+    // isn't room). Clicking one goes back to it.
+    fn breadcrumbs(ui: &mut egui::Ui, cx: &mut Context) {
+        const MAX_SHOWN: usize = 8;
+
+        if cx.new_crumb {
+            cx.new_crumb = false;
+            let view = cx.view_interval;
+            // Keep only the zooms this one is inside of
+            while let Some(last) = cx.crumbs.last() {
+                if last.start <= view.start && view.stop <= last.stop && *last != view {
+                    break;
+                }
+                cx.crumbs.pop();
+            }
+            cx.crumbs.push(view);
+        }
+
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            let current = cx.view_interval;
+            if ui
+                .selectable_label(current == cx.total_interval, "All")
+                .on_hover_text(cx.total_interval.to_string())
+                .clicked()
+            {
+                clicked = Some(None);
+            }
+            let skip = cx.crumbs.len().saturating_sub(MAX_SHOWN);
+            if skip > 0 {
+                ui.label("› …");
+            }
+            for (i, crumb) in cx.crumbs.iter().enumerate().skip(skip) {
+                ui.label("›");
+                let label = Timestamp(crumb.duration_ns()).to_string();
+                if ui
+                    .selectable_label(current == *crumb, label)
+                    .on_hover_text(crumb.to_string())
+                    .clicked()
+                {
+                    clicked = Some(Some(i));
+                }
+            }
+        });
+        match clicked {
+            Some(Some(i)) => {
+                cx.crumbs.truncate(i + 1);
+                Self::set_view(cx, cx.crumbs[i]);
+            }
+            Some(None) => Self::reset_zoom(cx),
+            None => {}
+        }
+    }
+
+    // Set the view to the given start and duration
+    fn move_view(cx: &mut Context, start: Timestamp, duration: i64) {
+        let stop = Timestamp(start.0.saturating_add(duration.max(0)));
+        Self::set_view(cx, Interval::new(start, stop));
+    }
+
+    fn pan(cx: &mut Context, fraction: f64) {
+        let view = cx.view_interval;
+        let delta = (view.duration_ns() as f64 * fraction) as i64;
+        Self::set_view(cx, view.translate(delta));
+    }
+
+    // Zoom around the center of the view
+    fn zoom(cx: &mut Context, factor: f64) {
+        let view = cx.view_interval;
+        let duration = (view.duration_ns() as f64 * factor) as i64;
+        let center = view.lerp(0.5);
+        Self::move_view(
+            cx,
+            Timestamp(center.0.saturating_sub(duration / 2)),
+            duration,
+        );
+    }
+
+    // Zoom to an interval, with some margin on either side
+    fn zoom_to(cx: &mut Context, interval: Interval) {
+        let margin = interval.duration_ns() / 10;
+        Self::zoom_in(cx, interval.grow(margin));
+    }
+
+    fn timeline(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        // Use body font to figure out how tall to draw rectangles.
+        let font_id = TextStyle::Body.resolve(ui.style());
+        let row_height = ui.fonts().row_height(&font_id);
+        // Just set this on every frame for now
+        cx.row_height = row_height * cx.density.row_scale;
+        cx.active_view_rect = None;
+
+        if cx.tabbed && windows.len() > 1 {
+            Self::tabs(windows, ui, cx);
+            return;
+        }
+
+        let mut remaining = windows.len();
+        // Only wrap in a frame if more than one profile
+        if remaining > 1 {
+            for window in windows.iter_mut() {
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.push_id(window.index, |ui| {
+                        ui.set_height(ui.available_height() / (remaining as f32));
+                        ui.set_width(ui.available_width());
+                        window.content(ui, cx);
+                        remaining -= 1;
+                    });
+                });
+            }
+        } else {
+            for window in windows.iter_mut() {
+                window.content(ui, cx);
+            }
+        }
+    }
+
+    fn tabs(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        // Fall back to the first tab if the current one was closed
+        let current = cx
+            .current_tab
+            .filter(|index| windows.iter().any(|w| w.index == *index))
+            .unwrap_or(windows[0].index);
+        cx.current_tab = Some(current);
+        ui.horizontal(|ui| {
+            for window in windows.iter() {
+                if ui
+                    .selectable_label(window.index == current, window.title())
+                    .on_hover_text(format!("Profile {}", window.index))
+                    .clicked()
+                {
+                    cx.current_tab = Some(window.index);
+                }
+            }
+        });
+        ui.separator();
+        if let Some(window) = windows.iter_mut().find(|w| w.index == current) {
+            ui.push_id(window.index, |ui| window.content(ui, cx));
+        }
+    }
+
+    // Whether the timeline shows the window (i.e., it isn't in another tab)
+    fn shown(windows: &[Window], window: &Window, cx: &Context) -> bool {
+        !cx.tabbed || windows.len() <= 1 || cx.current_tab == Some(window.index)
+    }
+
+    // Re-run the timeline in an offscreen context with the same size,
+    // style and scroll state as the real one, and rasterize the result.
+    fn render_png(
+        windows: &mut [Window],
+        ctx: &egui::Context,
+        cx: &mut Context,
+        size: Vec2,
+    ) -> std::io::Result<Vec<u8>> {
+        let pixels_per_point = ctx.pixels_per_point();
+        let offscreen = egui::Context::default();
+        offscreen.set_style(ctx.style());
+        *offscreen.memory() = ctx.memory().clone();
+
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, size)),
+            pixels_per_point: Some(pixels_per_point),
+            ..Default::default()
+        };
+        cx.fetch_all = true;
+        let output = offscreen.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                Self::timeline(windows, ui, cx);
+            });
+        });
+        cx.fetch_all = false;
+
+        let mut rasterizer = Rasterizer::default();
+        rasterizer.update_textures(&output.textures_delta);
+        rasterize_output(&offscreen, &rasterizer, output.shapes, size).encode_png()
+    }
+
+    fn export_png(
+        windows: &mut [Window],
+        ctx: &egui::Context,
+        cx: &mut Context,
+        size: Vec2,
+    ) -> String {
+        Self::export_status(
+            Self::render_png(windows, ctx, cx, size)
+                .and_then(|bytes| export::save_file("legion_prof.png", "image/png", &bytes)),
+        )
+    }
+
+    fn render_svg(windows: &mut [Window], cx: &mut Context, options: &SvgOptions) -> String {
+        const MARGIN: f32 = 16.0;
+        const TICKS: u32 = 8;
+
+        // Rows are sized by the label font rather than the UI font
+        let row_height = cx.row_height;
+        cx.row_height = options.label_font_size * 1.4;
+
+        let mut svg = SvgWriter::new(options.clone());
+        let mut y = MARGIN;
+        for window in windows.iter_mut() {
+            let global_view = std::mem::replace(&mut cx.view_interval, window.config.view_interval);
+            window.export_svg(&mut svg, MARGIN, options.width - MARGIN, &mut y, cx);
+            cx.view_interval = global_view;
+        }
+
+        // Time axis along the bottom, lined up with the slots
+        if let Some(timeline) = svg.timeline_rect() {
+            let font_size = options.label_font_size;
+            svg.line(
+                Pos2::new(timeline.min.x, y),
+                Pos2::new(timeline.max.x, y),
+                SVG_TEXT,
+                1.0,
+            );
+            for i in 0..=TICKS {
+                let fraction = i as f32 / TICKS as f32;
+                let x = timeline.min.x + fraction * timeline.width();
+                svg.line(
+                    Pos2::new(x, y),
+                    Pos2::new(x, y + font_size * 0.5),
+                    SVG_TEXT,
+                    1.0,
+                );
+                let anchor = if i == 0 {
+                    TextAnchor::Start
+                } else if i == TICKS {
+                    TextAnchor::End
+                } else {
+                    TextAnchor::Middle
+                };
+                let time = cx.view_interval.lerp(fraction as f64);
+                svg.text(
+                    Pos2::new(x, y + font_size * 1.75),
+                    font_size,
+                    anchor,
+                    SVG_TEXT,
+                    &time.to_string(),
+                    None,
+                );
+            }
+            y += font_size * 2.0;
+        }
+
+        cx.row_height = row_height;
+        svg.finish(options.width, y + MARGIN, Color32::WHITE)
+    }
+
+    fn export_svg(windows: &mut [Window], cx: &mut Context, options: &SvgOptions) -> String {
+        let svg = Self::render_svg(windows, cx, options);
+        Self::export_status(export::save_file(
+            "legion_prof.svg",
+            "image/svg+xml",
+            svg.as_bytes(),
+        ))
+    }
+
+    fn export_csv(windows: &mut [Window], interval: Interval) -> String {
+        let mut csv = "profile,name,entry,start_ns,stop_ns,duration_ns,fields\n".to_owned();
+        for window in windows.iter_mut() {
+            if let Err(error) = window.export_csv(&mut csv, interval) {
+                return format!("Export failed: {}", error);
+            }
+        }
+        Self::export_status(export::save_file(
+            "legion_prof_selection.csv",
+            "text/csv",
+            csv.as_bytes(),
+        ))
+    }
+
+    fn export_status(result: std::io::Result<String>) -> String {
+        match result {
+            Ok(location) => format!("Saved {}", location),
+            Err(e) => format!("Export failed: {}", e),
+        }
+    }
+
+    // Overview of the entire profile, with the current view highlighted.
+    // Click to jump, drag to pan.
+    fn minimap(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        const HEIGHT: f32 = 32.0;
+        const MIN_VIEW_WIDTH: f32 = 2.0;
+
+        let total = cx.total_interval;
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(ui.available_width(), HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+        if total.duration_ns() <= 0 {
+            return;
+        }
+
+        let visuals = ui.style().interact_selectable(&response, false);
+        ui.painter()
+            .rect(rect, 0.0, visuals.bg_fill, visuals.bg_stroke);
+
+        let time_to_x = |time: Timestamp| rect.lerp(Vec2::new(total.unlerp(time) as f32, 0.0)).x;
+        let x_to_time = |x: f32| total.lerp(((x - rect.left()) / rect.width()) as f64);
+
+        for window in windows.iter_mut() {
+            let interval = window.config.interval;
+            let overview = window.overview();
+            let points: Vec<_> = overview
+                .iter()
+                .enumerate()
+                .map(|(i, util)| {
+                    let time = interval.lerp((i as f64 + 0.5) / overview.len() as f64);
+                    Pos2::new(time_to_x(time), rect.bottom() - util * rect.height())
+                })
+                .collect();
+            ui.painter()
+                .add(egui::Shape::line(points, visuals.fg_stroke));
+        }
+
+        let view = cx.view_interval;
+        let mut view_rect = Rect::from_min_max(
+            Pos2::new(time_to_x(view.start), rect.top()),
+            Pos2::new(time_to_x(view.stop), rect.bottom()),
+        );
+        if view_rect.width() < MIN_VIEW_WIDTH {
+            view_rect = Rect::from_center_size(
+                view_rect.center(),
+                Vec2::new(MIN_VIEW_WIDTH, view_rect.height()),
+            );
+        }
+        let selection = ui.visuals().selection;
+        ui.painter().rect(
+            view_rect,
+            0.0,
+            selection.bg_fill.linear_multiply(0.3),
+            selection.stroke,
+        );
+
+        let pointer = response.interact_pointer_pos();
+        if response.clicked()
+            || (response.drag_started() && !pointer.map_or(false, |p| view_rect.contains(p)))
+        {
+            // Jump so that the view is centered on the pointer
+            if let Some(pos) = pointer {
+                let center = x_to_time(pos.x);
+                let start = Timestamp(center.0 - view.duration_ns() / 2);
+                Self::move_view(cx, start, view.duration_ns());
+            }
+        } else if response.dragged() {
+            let delta = response.drag_delta().x / rect.width() * total.duration_ns() as f32;
+            let start = Timestamp(view.start.0 + delta as i64);
+            Self::move_view(cx, start, view.duration_ns());
+        }
+    }
+
+    fn scrollbar(ui: &mut egui::Ui, cx: &mut Context) {
+        const MIN_THUMB_WIDTH: f32 = 16.0;
+
+        let total = cx.total_interval;
+        let height = ui.spacing().scroll_bar_width;
+        let (full_rect, response) = ui.allocate_exact_size(
+            Vec2::new(ui.available_width(), height),
+            egui::Sense::click_and_drag(),
+        );
+        // Line up with the timeline (as of the last frame), not the labels
+        let rect = cx.slot_rect.map_or(full_rect, |slot_rect| {
+            Rect::from_x_y_ranges(slot_rect.x_range(), full_rect.y_range())
+        });
+        if total.duration_ns() <= 0 || rect.width() <= MIN_THUMB_WIDTH {
+            return;
+        }
+
+        let view = cx.view_interval;
+        let visuals = ui.visuals();
+        ui.painter().rect_filled(
+            rect,
+            visuals.widgets.noninteractive.rounding,
+            visuals.extreme_bg_color,
+        );
+
+        // The thumb keeps a usable size when zoomed far in, so the track it
+        // moves along is that much shorter
+        let fraction = view.duration_ns() as f32 / total.duration_ns() as f32;
+        let thumb_width = (fraction * rect.width()).clamp(MIN_THUMB_WIDTH, rect.width());
+        let track = rect.width() - thumb_width;
+        let room = (total.duration_ns() - view.duration_ns()).max(1) as f32;
+        let position = (view.start.0 - total.start.0) as f32 / room;
+        let thumb_x = rect.left() + position.clamp(0.0, 1.0) * track;
+        let thumb = Rect::from_x_y_ranges(thumb_x..=thumb_x + thumb_width, rect.y_range());
+
+        let pointer = response.interact_pointer_pos();
+        let on_thumb = pointer.map_or(false, |p| thumb.contains(p));
+        let widget = if response.dragged() || (on_thumb && response.hovered()) {
+            &visuals.widgets.active
+        } else if response.hovered() {
+            &visuals.widgets.hovered
+        } else {
+            &visuals.widgets.inactive
+        };
+        ui.painter()
+            .rect_filled(thumb, widget.rounding, widget.bg_fill);
+
+        if response.clicked() && !on_thumb {
+            // Page by a whole view towards the pointer, like other scrollbars
+            if let Some(pos) = pointer {
+                let sign = if pos.x < thumb.left() { -1.0 } else { 1.0 };
+                Self::pan(cx, sign);
+            }
+        } else if response.dragged() && track > 0.0 {
+            let delta = response.drag_delta().x / track * room;
+            Self::set_view(cx, view.translate(delta as i64));
+        }
+    }
+
+    // Pinch to zoom (about the fingers) and pan with two fingers
+    fn touch(ui: &egui::Ui, cx: &mut Context, rect: Rect) {
+        let touch = match ui.input().multi_touch() {
+            Some(touch) if rect.contains(touch.start_pos) => touch,
+            _ => return,
+        };
+        // The first finger also moves the pointer, which isn't a drag to zoom
+        cx.drag_origin = None;
+
+        let view = cx.view_interval;
+        let anchor = ui.input().pointer.hover_pos().unwrap_or(touch.start_pos);
+        let fraction = ((anchor.x - rect.left()) / rect.width()).clamp(0.0, 1.0) as f64;
+        let duration = view.duration_ns() as f64 / touch.zoom_delta.max(f32::EPSILON) as f64;
+        let pan = -touch.translation_delta.x as f64 / rect.width() as f64 * duration;
+        let start = view.lerp(fraction).0 as f64 - fraction * duration + pan;
+        Self::move_view(cx, Timestamp(start as i64), duration as i64);
+    }
+
+    fn measure(ui: &mut egui::Ui, cx: &mut Context, rect: Rect, response: &egui::Response) {
+        let time_to_x = |time: Timestamp| {
+            rect.lerp(Vec2::new(cx.view_interval.unlerp(time) as f32, 0.0))
+                .x
+        };
+
+        if cx.measure_mode && response.clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let time = cx
+                    .view_interval
+                    .lerp(((pos.x - rect.left()) / rect.width()) as f64);
+                if let Some(start) = cx.measure_start.take() {
+                    cx.measurements
+                        .push(Interval::new(start.min(time), start.max(time)));
+                } else {
+                    cx.measure_start = Some(time);
+                }
+            }
+        }
+
+        let visuals = ui.visuals();
+        let stroke = Stroke::new(1.5, visuals.warn_fg_color);
+        let font_id = TextStyle::Body.resolve(ui.style());
+
+        // Stack the brackets so that overlapping measurements stay readable
+        let mut bracket_y = rect.top() + cx.row_height;
+        let mut draw_bracket = |interval: Interval| {
+            let left = time_to_x(interval.start);
+            let right = time_to_x(interval.stop);
+            if right < rect.left() || left > rect.right() {
+                return;
+            }
+            let painter = ui.painter();
+            for x in [left, right] {
+                painter.add(egui::Shape::dashed_line(
+                    &[Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                    stroke,
+                    4.0,
+                    4.0,
+                ));
+            }
+            painter.line_segment(
+                [Pos2::new(left, bracket_y), Pos2::new(right, bracket_y)],
+                stroke,
+            );
+            let duration = Timestamp(interval.duration_ns());
+            let label = Pos2::new((left + right) * 0.5, bracket_y);
+            let galley =
+                painter.layout_no_wrap(format!("{}", duration), font_id.clone(), stroke.color);
+            let label_rect =
+                Align2::CENTER_BOTTOM.anchor_rect(Rect::from_min_size(label, galley.size()));
+            painter.rect_filled(label_rect.expand(2.0), 2.0, ui.visuals().extreme_bg_color);
+            painter.galley(label_rect.min, galley);
+            bracket_y += cx.row_height * 1.5;
+        };
+        for interval in &cx.measurements {
+            draw_bracket(*interval);
+        }
+
+        // Preview the measurement in progress
+        if let Some(start) = cx.measure_start {
+            if let Some(hover) = response.hover_pos() {
+                let time = cx
+                    .view_interval
+                    .lerp(((hover.x - rect.left()) / rect.width()) as f64);
+                draw_bracket(Interval::new(start.min(time), start.max(time)));
+            } else {
+                draw_bracket(Interval::new(start, start));
+            }
+        }
+    }
+
+    fn markers(ui: &mut egui::Ui, cx: &mut Context, rect: Rect, response: &egui::Response) {
+        if cx.marker_mode && response.clicked() {
+            if let Some(pos) = response.interact_pointer_pos() {
+                let time = cx
+                    .view_interval
+                    .lerp(((pos.x - rect.left()) / rect.width()) as f64);
+                cx.markers_placed += 1;
+                let name = format!("M{}", cx.markers_placed);
+                let index = cx.markers.partition_point(|m| m.time <= time);
+                cx.markers.insert(index, Marker { name, time });
+            }
+        }
+
+        let stroke = Stroke::new(1.5, ui.visuals().hyperlink_color);
+        let font_id = TextStyle::Small.resolve(ui.style());
+        let painter = ui.painter();
+        for marker in &cx.markers {
+            if !cx.view_interval.contains(marker.time) {
+                continue;
+            }
+            let x = rect
+                .lerp(Vec2::new(cx.view_interval.unlerp(marker.time) as f32, 0.0))
+                .x;
+            painter.line_segment(
+                [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                stroke,
+            );
+            let galley = painter.layout_no_wrap(marker.name.clone(), font_id.clone(), stroke.color);
+            let label_rect = Align2::LEFT_TOP.anchor_rect(Rect::from_min_size(
+                Pos2::new(x + 2.0, rect.top()),
+                galley.size(),
+            ));
+            painter.rect_filled(label_rect.expand(1.0), 2.0, ui.visuals().extreme_bg_color);
+            painter.galley(label_rect.min, galley);
+        }
+    }
+
+    fn marker_list(ui: &mut egui::Ui, cx: &mut Context) {
+        ui.heading("Markers");
+        if cx.markers.is_empty() {
+            ui.label("Turn on Marker and click on the timeline to drop one.");
+            return;
+        }
+
+        let mut remove = None;
+        let mut center = None;
+        egui::Grid::new("markers").striped(true).show(ui, |ui| {
+            let mut previous: Option<Timestamp> = None;
+            for (i, marker) in cx.markers.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut marker.name).desired_width(60.0));
+                if ui
+                    .link(marker.time.to_string())
+                    .on_hover_text("Center the view here")
+                    .clicked()
+                {
+                    center = Some(marker.time);
+                }
+                // Time since the previous marker
+                if let Some(previous) = previous {
+                    ui.label(format!("+{}", Timestamp(marker.time.0 - previous.0)));
+                } else {
+                    ui.label("");
+                }
+                if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+                previous = Some(marker.time);
+            }
+        });
+        if ui.button("Clear Markers").clicked() {
+            cx.markers.clear();
+        }
+
+        if let Some(i) = remove {
+            cx.markers.remove(i);
+        }
+        if let Some(time) = center {
+            let duration = cx.view_interval.duration_ns();
+            Self::move_view(cx, Timestamp(time.0 - duration / 2), duration);
+        }
+    }
+
+    fn keyboard(ctx: &egui::Context, windows: &mut [Window], cx: &mut Context) {
+        const PAN_FRACTION: f64 = 0.1;
+        const ZOOM_FACTOR: f64 = 0.5;
+
+        // Don't interfere with typing into text fields
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let actions: Vec<_> = KEYBINDINGS
+            .iter()
+            .filter(|binding| binding.trigger.pressed(ctx))
+            .map(|binding| binding.action)
+            .collect();
+        for action in actions {
+            match action {
+                Action::PanLeft => Self::pan(cx, -PAN_FRACTION),
+                Action::PanRight => Self::pan(cx, PAN_FRACTION),
+                Action::ZoomIn => Self::zoom(cx, ZOOM_FACTOR),
+                Action::ZoomOut => Self::zoom(cx, 1.0 / ZOOM_FACTOR),
+                Action::ResetView => Self::reset_zoom(cx),
+                Action::ExpandAll => windows.iter_mut().for_each(|w| w.expand_all(true)),
+                Action::CollapseAll => windows.iter_mut().for_each(|w| w.expand_all(false)),
+                Action::Search => ctx.memory().request_focus(Self::search_id()),
+                Action::ToggleHelp => cx.show_help = !cx.show_help,
+            }
+        }
+    }
+
+    fn dependency_graph(ctx: &egui::Context, windows: &mut [Window], cx: &mut Context) {
+        let mut open = cx.show_dependency_graph;
+        let mut navigate = None;
+        egui::Window::new("Dependency Graph")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ScrollArea::both().show(ui, |ui| {
+                    for (w, window) in windows.iter_mut().enumerate() {
+                        if let Some(node) = window.dependency_graph(ui, cx) {
+                            navigate = Some((w, node));
+                        }
+                    }
+                });
+            });
+        cx.show_dependency_graph = open;
+
+        if let Some((w, node)) = navigate {
+            windows[w].reveal(&node.entry_id);
+            windows[w].zoom_to(cx, node.interval);
+        }
+    }
+
+    fn histogram(ctx: &egui::Context, windows: &mut [Window], cx: &mut Context) {
+        let mut open = cx.show_histogram;
+        egui::Window::new("Duration Histogram")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                for window in windows.iter_mut() {
+                    window.histogram(ui, cx);
+                }
+            });
+        cx.show_histogram = open;
+    }
+
+    fn item_legend(ctx: &egui::Context, windows: &mut [Window], cx: &mut Context) {
+        const SWATCH_SIZE: f32 = 10.0;
+
+        let mut open = cx.show_item_legend;
+        let mut highlight = None;
+        let mut filter = None;
+        let mut recolor = None;
+        egui::Window::new("Legend")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                // Profiles that aren't shown drew nothing, so still have
+                // the names from when they were
+                let mut names: BTreeMap<String, (Color32, u64)> = BTreeMap::new();
+                for window in windows.iter().filter(|w| Self::shown(windows, w, cx)) {
+                    for (name, (color, count)) in &window.config.legend.borrow().names {
+                        names.entry(name.clone()).or_insert((*color, 0)).1 += count;
+                    }
+                }
+                if names.is_empty() {
+                    ui.label("No items in view");
+                    return;
+                }
+                // Most common first
+                let mut names: Vec<_> = names.into_iter().collect();
+                names.sort_by(|(a, (_, m)), (b, (_, n))| n.cmp(m).then(a.cmp(b)));
+
+                ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("item_legend").striped(true).show(ui, |ui| {
+                        for (name, (color, count)) in names {
+                            let (swatch, _) = ui.allocate_exact_size(
+                                Vec2::splat(SWATCH_SIZE),
+                                egui::Sense::hover(),
+                            );
+                            ui.painter().rect_filled(swatch, 0.0, color);
+                            if ui
+                                .link(&name)
+                                .on_hover_text("Highlight these items")
+                                .clicked()
+                            {
+                                highlight = Some(name.clone());
+                            }
+                            ui.label(count.to_string());
+                            if ui
+                                .small_button("Filter")
+                                .on_hover_text("Add a filter for just these items")
+                                .clicked()
+                            {
+                                filter = Some(name.clone());
+                            }
+                            if ui
+                                .small_button("Color")
+                                .on_hover_text("Choose a color for these items")
+                                .clicked()
+                            {
+                                recolor = Some((name, color));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        cx.show_item_legend = open;
+
+        let by_name = |name: String| {
+            Query::Filter(Filter {
+                subject: Subject::Name,
+                op: Op::Eq,
+                value: Value::Text(name),
+            })
+        };
+        if let Some(name) = highlight {
+            cx.search.set_query(by_name(name).to_string());
+            for window in windows.iter_mut() {
+                window.search(cx);
+            }
+        }
+        if let Some(name) = filter {
+            cx.filters.filters.push(by_name(name));
+        }
+        if let Some((name, color)) = recolor {
+            let editor = &mut cx.color_editor;
+            editor.open = true;
+            editor.by_kind = false;
+            editor.key = name;
+            editor.color = color;
+        }
+    }
+
+    fn item_colors(ctx: &egui::Context, windows: &[Window], cx: &mut Context) {
+        let mut open = cx.color_editor.open;
+        egui::Window::new("Item Colors")
+            .open(&mut open)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                ui.label("Drawn in place of the profile's own colors, by name first");
+                let overrides = &mut cx.color_overrides;
+                let sections = [
+                    ("By Name", &mut overrides.names),
+                    ("By Kind", &mut overrides.kinds),
+                ];
+                for (heading, colors) in sections {
+                    ui.strong(heading);
+                    if colors.is_empty() {
+                        ui.weak("None");
+                    }
+                    let mut remove = None;
+                    egui::Grid::new(heading).show(ui, |ui| {
+                        for (key, color) in colors.iter_mut() {
+                            ui.color_edit_button_srgba(color);
+                            ui.label(key);
+                            if ui
+                                .small_button("✖")
+                                .on_hover_text("Go back to the profile's color")
+                                .clicked()
+                            {
+                                remove = Some(key.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(key) = remove {
+                        colors.remove(&key);
+                    }
+                }
+                ui.separator();
+
+                let editor = &mut cx.color_editor;
+                ui.horizontal(|ui| {
+                    ui.label("Add by");
+                    ui.radio_value(&mut editor.by_kind, false, "Name");
+                    ui.radio_value(&mut editor.by_kind, true, "Kind");
+                });
+                ui.horizontal(|ui| {
+                    if editor.by_kind {
+                        let kinds: BTreeSet<_> = windows
+                            .iter()
+                            .flat_map(|w| w.kinds.iter().map(|kind| kind.to_lowercase()))
+                            .collect();
+                        egui::ComboBox::from_id_source("color_kind")
+                            .selected_text(editor.key.as_str())
+                            .show_ui(ui, |ui| {
+                                for kind in kinds {
+                                    ui.selectable_value(&mut editor.key, kind.clone(), kind);
+                                }
+                            });
+                    } else {
+                        ui.text_edit_singleline(&mut editor.key)
+                            .on_hover_text("The item's name, exactly");
+                    }
+                    ui.color_edit_button_srgba(&mut editor.color);
+                    let key = editor.key.trim();
+                    if ui
+                        .add_enabled(!key.is_empty(), egui::Button::new("Add"))
+                        .clicked()
+                    {
+                        let colors = if editor.by_kind {
+                            &mut overrides.kinds
+                        } else {
+                            &mut overrides.names
+                        };
+                        colors.insert(key.to_owned(), editor.color);
+                        editor.key.clear();
+                    }
+                });
+
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(path) = &cx.color_file {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Save")
+                            .on_hover_text(format!("Keep these colors in {}", path.display()))
+                            .clicked()
+                        {
+                            editor.status = match settings::save_colors(path, overrides) {
+                                Ok(()) => Some("Saved".to_owned()),
+                                Err(error) => Some(format!("Unable to save: {}", error)),
+                            };
+                        }
+                        if let Some(status) = &editor.status {
+                            ui.label(status);
+                        }
+                    });
+                }
+            });
+        cx.color_editor.open = open;
+    }
+
+    // Scopes are only recorded while the profiler is open, so they cost
+    // next to nothing otherwise
+    #[cfg(feature = "puffin")]
+    fn profiler(ctx: &egui::Context, cx: &mut Context) {
+        puffin::GlobalProfiler::lock().new_frame();
+        puffin::set_scopes_on(cx.show_profiler);
+        if cx.show_profiler {
+            cx.show_profiler = puffin_egui::profiler_window(ctx);
+        }
+    }
+
+    fn stats_overlay(ctx: &egui::Context, windows: &mut [Window], cx: &Context) {
+        if !cx.show_stats {
+            return;
+        }
+        let frame_time = ctx.input().unstable_dt;
+        egui::Area::new("stats_overlay")
+            .anchor(Align2::RIGHT_BOTTOM, Vec2::new(-8.0, -8.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(format!("Frame time: {:.1} ms", frame_time * 1e3));
+                    for window in windows.iter_mut() {
+                        ui.separator();
+                        window.stats(ui);
+                    }
+                });
+            });
+    }
+
+    fn follow(ctx: &egui::Context, windows: &mut [Window], cx: &mut Context) {
+        const POLL_SECONDS: f64 = 1.0;
+
+        let follow = &mut cx.follow;
+        if !follow.enabled {
+            return;
+        }
+        // Anything else moving the view means the user wants to look around
+        if follow.view.map_or(false, |view| view != cx.view_interval) {
+            follow.enabled = false;
+            follow.view = None;
+            return;
+        }
+
+        let now = ctx.input().time;
+        if now - follow.last_poll >= POLL_SECONDS {
+            follow.last_poll = now;
+            for window in windows.iter_mut() {
+                match window.config.data_source.interval() {
+                    Ok(interval) => window.config.interval = interval,
+                    Err(error) => window.config.last_error = Some(error),
+                }
+            }
+            if let Some(total) = windows
+                .iter()
+                .map(|w| w.config.interval)
+                .reduce(Interval::union)
+            {
+                cx.total_interval = total;
+            }
+        }
+
+        let stop = cx.total_interval.stop;
+        let start = Timestamp((stop.0 - follow.window_ns).max(cx.total_interval.start.0));
+        cx.view_interval = Interval::new(start, stop);
+        follow.view = Some(cx.view_interval);
+        ctx.request_repaint_after(std::time::Duration::from_secs_f64(POLL_SECONDS));
+    }
+
+    fn poll_warnings(ctx: &egui::Context, windows: &mut [Window]) {
+        for window in windows.iter_mut() {
+            let warnings = window.config.data_source.poll_warnings();
+            if !warnings.is_empty() {
+                window.config.warnings.extend(warnings);
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    fn warnings(ctx: &egui::Context, windows: &mut [Window], cx: &mut Context) {
+        let mut open = cx.show_warnings;
+        let mut reveal = None;
+        egui::Window::new("Warnings")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (w, window) in windows.iter_mut().enumerate() {
+                        if window.config.warnings.is_empty() {
+                            continue;
+                        }
+                        ui.subheading(window.title(), cx);
+                        let mut dismiss = None;
+                        for (i, warning) in window.config.warnings.iter().enumerate() {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label(format!("⚠ {}", warning.message));
+                                if let Some(entry_id) = &warning.entry_id {
+                                    if ui.small_button("Show").clicked() {
+                                        reveal = Some((w, entry_id.clone()));
+                                    }
+                                }
+                                if ui.small_button("Dismiss").clicked() {
+                                    dismiss = Some(i);
+                                }
+                            });
+                        }
+                        if let Some(i) = dismiss {
+                            window.config.warnings.remove(i);
+                        }
+                    }
+                });
+                if ui.button("Dismiss All").clicked() {
+                    windows.iter_mut().for_each(|w| w.config.warnings.clear());
+                }
+            });
+        // Nothing left to show
+        cx.show_warnings = open && windows.iter().any(|w| !w.config.warnings.is_empty());
+
+        if let Some((w, entry_id)) = reveal {
+            windows[w].reveal(&entry_id);
+        }
+    }
+
+    // Opens a profile in a viewer of its own (i.e., another process, which
+    // can be moved to another screen), with the same configuration file
+    // and saved state as this one
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_viewer(&self, location: &str) -> std::io::Result<()> {
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        if let Some(path) = &self.config_file {
+            command.arg("--config").arg(path);
+        }
+        let mut child = command.arg(location).spawn()?;
+        // Reap it when it exits
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn open_viewer(&self, _location: &str) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "not available in the browser",
+        ))
+    }
+
+    // The whole profile changed (e.g., it was regenerated), so start over
+    // with what the data source says now, keeping what a saved view would
+    fn reload_window(windows: &mut Vec<Window>, i: usize, cx: &mut Context) {
+        let mut old = windows.remove(i);
+        let saved = old.save_view(cx);
+        let title = old.title();
+        match Window::open(old.config.data_source, old.index) {
+            Ok(mut window) => {
+                window.restore_view(&saved);
+                window.config.view_offset = old.config.view_offset;
+                window.config.warnings = old.config.warnings;
+                window.location = old.location;
+                windows.insert(i, window);
+            }
+            Err((error, data_source)) => {
+                // Keep showing what we had, since it's still readable
+                let message = format!("unable to reload {}: {}", title, error);
+                tracing::error!("{}", message);
+                old.config.data_source = data_source;
+                old.config.last_error = Some(DataSourceError::new(message));
+                windows.insert(i, old);
+            }
+        }
+        if let Some(total) = windows
+            .iter()
+            .map(|w| w.config.interval)
+            .reduce(Interval::union)
+        {
+            cx.total_interval = total;
+        }
+    }
+
+    fn log_console(ctx: &egui::Context, cx: &mut Context) {
+        const LEVELS: &[Level] = &[
+            Level::ERROR,
+            Level::WARN,
+            Level::INFO,
+            Level::DEBUG,
+            Level::TRACE,
+        ];
+
+        egui::Window::new("Log Console")
+            .open(&mut cx.show_log)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let selected = cx.log_level.map_or("All", |level| level.as_str());
+                    egui::ComboBox::from_label("Level")
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut cx.log_level, None, "All");
+                            for level in LEVELS {
+                                ui.selectable_value(
+                                    &mut cx.log_level,
+                                    Some(*level),
+                                    level.as_str(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text("The most verbose level to show");
+                    if ui.button("Clear").clicked() {
+                        console::clear();
+                    }
+                });
+                ui.separator();
+
+                // More verbose levels compare greater
+                let records = console::records();
+                let shown = records
+                    .iter()
+                    .filter(|r| cx.log_level.map_or(true, |level| r.level <= level));
+                ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for record in shown {
+                            let color = match record.level {
+                                Level::ERROR => ui.visuals().error_fg_color,
+                                Level::WARN => ui.visuals().warn_fg_color,
+                                _ => ui.visuals().weak_text_color(),
+                            };
+                            ui.horizontal_wrapped(|ui| {
+                                ui.colored_label(color, record.level.as_str());
+                                let mut target = record.target.clone();
+                                if !record.spans.is_empty() {
+                                    write!(target, " {}", record.spans).unwrap();
+                                }
+                                ui.weak(target);
+                                ui.label(&record.message);
+                            });
+                        }
+                    });
+            });
+    }
+
+    fn invalidate_tiles(ctx: &egui::Context, windows: &mut Vec<Window>, cx: &mut Context) {
+        for i in 0..windows.len() {
+            let window = &mut windows[i];
+            let invalidated = window.config.data_source.poll_invalidated();
+            if invalidated.is_empty() {
+                continue;
+            }
+            if invalidated
+                .iter()
+                .any(|(entry_id, _)| *entry_id == EntryID::root())
+            {
+                Self::reload_window(windows, i, cx);
+                ctx.request_repaint();
+                continue;
+            }
+            for (entry_id, interval) in &invalidated {
+                window.panel.invalidate(entry_id, *interval);
+            }
+            // The overview is computed from summaries, which may have changed
+            window.config.overview.clear();
+            ctx.request_repaint();
+        }
+    }
+
+    fn help(ctx: &egui::Context, cx: &mut Context) {
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut cx.show_help)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                    // Bindings for the same action are adjacent, list them together
+                    let mut i = 0;
+                    while i < KEYBINDINGS.len() {
+                        let action = KEYBINDINGS[i].action;
+                        let mut keys = Vec::new();
+                        while i < KEYBINDINGS.len() && KEYBINDINGS[i].action == action {
+                            keys.push(KEYBINDINGS[i].name);
+                            i += 1;
+                        }
+                        ui.strong(keys.join(" / "));
+                        ui.label(action.description());
+                        ui.end_row();
+                    }
+                });
+            });
+    }
+
+    // Controls for each window, plus handles to close and reorder them
+    fn window_controls(
+        windows: &mut Vec<Window>,
+        saved_views: &mut BTreeMap<u64, SavedView>,
+        ui: &mut egui::Ui,
+        cx: &mut Context,
+    ) -> Option<u64> {
+        let closable = windows.len() > 1;
+        let mut close = None;
+        let mut pop_out = None;
+        let mut group_rects = Vec::new();
+        for (i, window) in windows.iter_mut().enumerate() {
+            let group = egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                if closable {
+                    ui.horizontal(|ui| {
+                        let handle = ui
+                            .add(egui::Label::new("☰").sense(egui::Sense::drag()))
+                            .on_hover_text("Drag to reorder");
+                        if handle.drag_started() {
+                            cx.dragged_window = Some(i);
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("✖").on_hover_text("Close").clicked() {
+                                close = Some(i);
+                            }
+                            if window.location.is_some()
+                                && ui
+                                    .small_button("⧉")
+                                    .on_hover_text("Open in a window of its own")
+                                    .clicked()
+                            {
+                                pop_out = Some(window.index);
+                            }
+                        });
+                    });
+                }
+                window.controls(ui, cx);
+            });
+            group_rects.push(group.response.rect);
+        }
+
+        if let Some(from) = cx.dragged_window {
+            let pointer = ui.input().pointer.interact_pos();
+            // Insert before the first window whose center is below the pointer
+            let to = pointer.map(|pos| {
+                group_rects
+                    .iter()
+                    .position(|rect| pos.y < rect.center().y)
+                    .unwrap_or(group_rects.len())
+            });
+            if ui.input().pointer.any_released() {
+                cx.dragged_window = None;
+                if let Some(to) = to {
+                    let window = windows.remove(from);
+                    windows.insert(if to > from { to - 1 } else { to }, window);
+                }
+            } else if let Some(to) = to {
+                // Show where the window will end up
+                let y = group_rects
+                    .get(to)
+                    .map_or_else(|| group_rects.last().unwrap().bottom(), |r| r.top());
+                let x = ui.max_rect().x_range();
+                ui.painter().hline(x, y, ui.visuals().selection.stroke);
+            }
+        }
 
-                let rect = Rect::from_min_size(ui.min_rect().min, viewport.size());
+        if let Some(i) = close {
+            Self::close_window(windows, saved_views, i, cx);
+        }
+        // By Window::index, since the windows may have been reordered
+        pop_out
+    }
 
-                // Root panel has no label
-                self.panel.content(ui, rect, viewport, &mut self.config, cx);
-            });
+    fn close_window(
+        windows: &mut Vec<Window>,
+        saved_views: &mut BTreeMap<u64, SavedView>,
+        i: usize,
+        cx: &mut Context,
+    ) {
+        let mut window = windows.remove(i);
+        // Remember the state of the window in case it gets opened again
+        saved_views.insert(window.profile_key, window.save_view(cx));
+        if cx
+            .active_window
+            .map_or(false, |(index, _)| index == window.index)
+        {
+            cx.active_window = None;
+        }
+        // Dropping the window frees its data source and any cached tiles
+        drop(window);
+
+        cx.total_interval = windows
+            .iter()
+            .map(|w| w.config.interval)
+            .reduce(Interval::union)
+            .unwrap();
+        let view = cx.view_interval.intersection(cx.total_interval);
+        cx.view_interval = if view.duration_ns() > 0 {
+            view
+        } else {
+            cx.total_interval
+        };
     }
 
-    fn node_selection(&mut self, ui: &mut egui::Ui, cx: &Context) {
-        ui.subheading("Node Selection", cx);
-        let total = self.panel.slots.len().saturating_sub(1) as u64;
-        let min_node = &mut self.config.min_node;
-        let max_node = &mut self.config.max_node;
-        ui.add(Slider::new(min_node, 0..=total).text("First"));
-        if *min_node > *max_node {
-            *max_node = *min_node;
+    // Opens the window's profile in another viewer, and closes it here if
+    // that worked
+    fn pop_out(&mut self, index: u64, frame: &mut eframe::Frame) {
+        let i = match self.windows.iter().position(|w| w.index == index) {
+            Some(i) => i,
+            None => return,
+        };
+        let location = match &self.windows[i].location {
+            Some(location) => location.clone(),
+            None => return,
+        };
+        // The new viewer starts from the saved state (including the view
+        // of this window), so save it first
+        if let Some(storage) = frame.storage_mut() {
+            eframe::App::save(self, storage);
+            storage.flush();
         }
-        ui.add(Slider::new(max_node, 0..=total).text("Last"));
-        if *min_node > *max_node {
-            *min_node = *max_node;
+        match self.open_viewer(&location) {
+            Ok(()) => Self::close_window(&mut self.windows, &mut self.saved_views, i, &mut self.cx),
+            Err(error) => {
+                let message = format!("unable to open a new viewer: {}", error);
+                self.windows[i].config.last_error = Some(DataSourceError::new(message));
+            }
         }
     }
 
-    fn expand_collapse(&mut self, ui: &mut egui::Ui, cx: &Context) {
-        let mut toggle_all = |label, toggle| {
-            for node in &mut self.panel.slots {
-                for kind in &mut node.slots {
-                    if kind.expanded == toggle && kind.label_text() == label {
-                        kind.toggle_expanded();
-                    }
-                }
-            }
+    fn selection(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        let selection = if let Some(selection) = cx.selection {
+            selection
+        } else {
+            return;
         };
-
-        ui.subheading("Expand/Collapse", cx);
-        ui.label("Expand by kind:");
-        ui.horizontal_wrapped(|ui| {
-            for kind in &self.kinds {
-                if ui.button(kind).clicked() {
-                    toggle_all(kind.to_lowercase(), false);
-                }
-            }
-        });
-        ui.label("Collapse by kind:");
-        ui.horizontal_wrapped(|ui| {
-            for kind in &self.kinds {
-                if ui.button(kind).clicked() {
-                    toggle_all(kind.to_lowercase(), true);
+        ui.horizontal(|ui| {
+            ui.heading("Selection");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.small_button("Clear").clicked() {
+                    cx.selection = None;
                 }
-            }
+            });
         });
+        Self::selection_details(windows, ui, selection, cx);
     }
 
-    fn controls(&mut self, ui: &mut egui::Ui, cx: &Context) {
-        const WIDGET_PADDING: f32 = 8.0;
-        ui.heading(format!("Profile {}: Controls", self.index));
-        ui.add_space(WIDGET_PADDING);
-        self.node_selection(ui, cx);
-        ui.add_space(WIDGET_PADDING);
-        self.expand_collapse(ui, cx);
+    fn selection_details(
+        windows: &mut [Window],
+        ui: &mut egui::Ui,
+        selection: Interval,
+        cx: &Context,
+    ) {
+        ui.label(format!("Duration: {}", Timestamp(selection.duration_ns())));
+        let multiple = windows.len() > 1;
+        for window in windows.iter_mut() {
+            if multiple {
+                ui.subheading(format!("Profile {}", window.index), cx);
+            }
+            let stats = window.selection_stats(selection);
+            ui.label(format!("Items: {}", stats.items));
+            ui.label(format!("Busy: {}", Timestamp(stats.busy_ns)));
+            for (kind, util) in &stats.kinds {
+                ui.label(format!("{} utilization: {:.1}%", kind, util * 100.0));
+            }
+        }
     }
-}
 
-impl ProfApp {
-    /// Called once before the first frame.
-    pub fn new(
-        cc: &eframe::CreationContext<'_>,
-        data_source: Box<dyn DataSource>,
-        extra_source: Option<Box<dyn DataSource>>,
-    ) -> Self {
-        // This is also where you can customized the look at feel of egui using
-        // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
+    fn compare_controls(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
+        ui.heading("Compare Profiles");
+        ui.horizontal(|ui| {
+            ui.label("Layout:");
+            ui.radio_value(&mut cx.tabbed, false, "Stacked")
+                .on_hover_text("All profiles at once, one above the other");
+            ui.radio_value(&mut cx.tabbed, true, "Tabbed")
+                .on_hover_text("One profile at a time, using the whole timeline");
+        });
+        ui.checkbox(&mut cx.sync_views, "Lock Timelines");
+
+        let speed = (cx.total_interval.duration_ns() as f64 / 1000.0).max(1.0);
+        ui.label("Offset of each profile (ns):");
+        for window in windows.iter_mut() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Profile {}", window.index));
+                ui.add(egui::DragValue::new(&mut window.config.view_offset).speed(speed));
+            });
+        }
+        if ui.button("Align Starts").clicked() {
+            let start = windows[0].config.interval.start;
+            for window in windows.iter_mut() {
+                window.config.view_offset = window.config.interval.start.0 - start.0;
+            }
+        }
+    }
 
-        // Load previous app state (if any).
-        // Note that you must enable the `persistence` feature for this to work.
-        let mut result: Self = if let Some(storage) = cc.storage {
-            eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
-        } else {
-            Default::default()
+    // Offers to zoom to a selection made by dragging
+    fn selection_popup(windows: &mut [Window], ui: &mut egui::Ui, rect: Rect, cx: &mut Context) {
+        let (selection, pos) = match (cx.selection, cx.selection_popup) {
+            (Some(selection), Some(pos)) => (selection, pos),
+            _ => return,
         };
 
-        result.windows.clear();
-        result.windows.push(Window::new(data_source, 0));
-        let window = result.windows.last().unwrap();
-        result.cx.total_interval = window.config.interval;
-        result.cx.view_interval = result.cx.total_interval;
-
-        result.extra_source = extra_source;
-
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            result.last_update = Some(Instant::now());
+        // Shade the selection until a choice is made
+        let left = rect.lerp(Vec2::new(
+            cx.view_interval.unlerp(selection.start) as f32,
+            0.0,
+        ));
+        let right = rect.lerp(Vec2::new(
+            cx.view_interval.unlerp(selection.stop) as f32,
+            1.0,
+        ));
+        let shade = Rect::from_min_max(left, right).intersect(rect);
+        let color = ui.visuals().selection.bg_fill.linear_multiply(0.3);
+        ui.painter()
+            .rect(shade, 0.0, color, ui.visuals().selection.stroke);
+
+        let mut close = ui.input().key_pressed(egui::Key::Escape);
+        egui::Area::new("selection_popup")
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(selection.to_string());
+                    ui.horizontal(|ui| {
+                        if ui.button("Zoom").clicked() {
+                            Self::zoom_in(cx, selection);
+                            close = true;
+                        }
+                        ui.toggle_value(&mut cx.selection_popup_stats, "Stats");
+                        if ui.button("Cancel").clicked() {
+                            cx.selection = None;
+                            close = true;
+                        }
+                    });
+                    if cx.selection_popup_stats {
+                        ui.separator();
+                        Self::selection_details(windows, ui, selection, cx);
+                    }
+                });
+            });
+        if close {
+            cx.selection_popup = None;
         }
-
-        result
     }
 
-    fn cursor(ui: &mut egui::Ui, cx: &mut Context) {
+    fn cursor(windows: &mut [Window], ui: &mut egui::Ui, cx: &mut Context) {
         // Hack: the UI rect we have at this point is not where the
         // timeline is being drawn. So fish out the coordinates we
         // need to draw the correct rect.
         let ui_rect = ui.min_rect();
-        let slot_rect = cx.slot_rect.unwrap();
+        let slot_rect = if let Some(slot_rect) = cx.slot_rect {
+            slot_rect
+        } else {
+            return; // nothing has been drawn yet
+        };
         let rect = Rect::from_min_max(
             Pos2::new(slot_rect.min.x, ui_rect.min.y),
             Pos2::new(slot_rect.max.x, ui_rect.max.y),
         );
 
-        let response = ui.allocate_rect(rect, egui::Sense::drag());
+        // Only steal clicks from the items when measuring or placing markers
+        let sense = if cx.measure_mode || cx.marker_mode {
+            egui::Sense::click_and_drag()
+        } else {
+            egui::Sense::drag()
+        };
+        let response = ui.allocate_rect(rect, sense);
+
+        Self::measure(ui, cx, rect, &response);
+        Self::markers(ui, cx, rect, &response);
+        Self::selection_popup(windows, ui, rect, cx);
+        Self::touch(ui, cx, rect);
 
         // Handle drag detection
         let mut drag_interval = None;
@@ -848,17 +7548,18 @@ impl ProfApp {
             let max = origin.x.max(current.x);
 
             let start = (min - rect.left()) / rect.width();
-            let start = cx.view_interval.lerp(start);
+            let start = cx.view_interval.lerp(start as f64);
             let stop = (max - rect.left()) / rect.width();
-            let stop = cx.view_interval.lerp(stop);
+            let stop = cx.view_interval.lerp(stop as f64);
 
             let interval = Interval::new(start, stop);
 
             if is_active_drag {
                 // Still in drag, draw a rectangle to show the dragged region
+                let span = cx.active_view_rect.unwrap_or(rect);
                 let drag_rect =
-                    Rect::from_min_max(Pos2::new(min, rect.min.y), Pos2::new(max, rect.max.y));
-                let color = Color32::DARK_GRAY.linear_multiply(0.5);
+                    Rect::from_min_max(Pos2::new(min, span.min.y), Pos2::new(max, span.max.y));
+                let color = ui.visuals().selection.bg_fill.linear_multiply(0.5);
                 ui.painter().rect(drag_rect, 0.0, color, Stroke::NONE);
 
                 drag_interval = Some(interval);
@@ -866,23 +7567,43 @@ impl ProfApp {
                 // Only set view interval if the drag was a certain amount
                 const MIN_DRAG_DISTANCE: f32 = 4.0;
                 if max - min > MIN_DRAG_DISTANCE {
-                    cx.view_interval = interval;
+                    cx.selection = Some(interval);
+                    if cx.select_on_drag || ui.input().modifiers.shift {
+                        cx.selection_popup = Some(current);
+                        cx.selection_popup_stats = false;
+                    } else {
+                        Self::zoom_in(cx, interval);
+                        cx.selection_popup = None;
+                    }
                 }
 
                 cx.drag_origin = None;
             }
         }
 
+        // Linked to the other view of a split window (next frame, since
+        // that's already been drawn)
+        let cursor_time = response.hover_pos().map(|hover| {
+            cx.view_interval
+                .lerp(((hover.x - rect.left()) / rect.width()) as f64)
+        });
+        if cursor_time != cx.cursor_time {
+            cx.cursor_time = cursor_time;
+            ui.ctx().request_repaint();
+        }
+
         // Handle hover detection
         if let Some(hover) = response.hover_pos() {
             let visuals = ui.style().interact_selectable(&response, false);
 
-            // Draw vertical line through cursor
+            // Draw vertical line through cursor (in just the view it's over,
+            // if that's one of the views of a split window)
             const RADIUS: f32 = 12.0;
-            let top = Pos2::new(hover.x, ui.min_rect().min.y);
-            let mid_top = Pos2::new(hover.x, (hover.y - RADIUS).at_least(ui.min_rect().min.y));
-            let mid_bottom = Pos2::new(hover.x, (hover.y + RADIUS).at_most(ui.min_rect().max.y));
-            let bottom = Pos2::new(hover.x, ui.min_rect().max.y);
+            let span = cx.active_view_rect.unwrap_or_else(|| ui.min_rect());
+            let top = Pos2::new(hover.x, span.min.y);
+            let mid_top = Pos2::new(hover.x, (hover.y - RADIUS).at_least(span.min.y));
+            let mid_bottom = Pos2::new(hover.x, (hover.y + RADIUS).at_most(span.max.y));
+            let bottom = Pos2::new(hover.x, span.max.y);
             ui.painter().line_segment([top, mid_top], visuals.fg_stroke);
             ui.painter()
                 .line_segment([mid_bottom, bottom], visuals.fg_stroke);
@@ -890,13 +7611,20 @@ impl ProfApp {
             // Show timestamp popup
 
             const HOVER_PADDING: f32 = 8.0;
-            let time = (hover.x - rect.left()) / rect.width();
-            let time = cx.view_interval.lerp(time);
+            let fraction = (hover.x - rect.left()) / rect.width();
 
             // Hack: This avoids an issue where popups displayed normally are
             // forced to stack, even when an explicit position is
             // requested. Instead we display the popup manually via black magic
-            let popup_size = if drag_interval.is_some() { 300.0 } else { 90.0 };
+            let popup_size = if drag_interval.is_some() {
+                300.0
+            } else if cx.wall_clock {
+                320.0
+            } else if windows.len() > 1 && !cx.tabbed {
+                180.0
+            } else {
+                90.0
+            };
             let mut popup_rect = Rect::from_min_size(
                 Pos2::new(top.x + HOVER_PADDING, top.y),
                 Vec2::new(popup_size, 100.0),
@@ -918,7 +7646,25 @@ impl ProfApp {
                 if let Some(drag) = drag_interval {
                     ui.label(format!("{}", drag));
                 } else {
-                    ui.label(format!("t={}", time));
+                    // Each profile may be looking at a different time
+                    let shown: Vec<_> = windows
+                        .iter()
+                        .filter(|w| Self::shown(windows, w, cx))
+                        .collect();
+                    for window in &shown {
+                        let lower = cx.active_window == Some((window.index, true));
+                        let view = match &window.split {
+                            Some(split) if lower => split.view_interval,
+                            _ => window.config.view_interval,
+                        };
+                        let time = view.lerp(fraction as f64);
+                        let time = window.time_text(time, cx);
+                        if shown.len() > 1 {
+                            ui.label(format!("Profile {}: t={}", window.index, time));
+                        } else {
+                            ui.label(format!("t={}", time));
+                        }
+                    }
                 }
             });
 
@@ -930,19 +7676,27 @@ impl ProfApp {
 impl eframe::App for ProfApp {
     /// Called to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        for window in &mut self.windows {
+            let view = window.save_view(&self.cx);
+            self.saved_views.insert(window.profile_key, view);
+        }
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
     /// Called each time the UI needs repainting.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         let Self {
             windows,
             cx,
+            svg_options,
+            status,
             #[cfg(not(target_arch = "wasm32"))]
             last_update,
             ..
         } = self;
 
+        cx.time_format.set();
+
         let mut _fps = 0.0;
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -953,57 +7707,336 @@ impl eframe::App for ProfApp {
             *last_update = Some(now);
         }
 
+        let system_dark = frame
+            .info()
+            .system_theme
+            .map(|theme| theme == eframe::Theme::Dark);
+        let dark = cx.theme.appearance.is_dark(system_dark);
+        if ctx.style().visuals.dark_mode != dark {
+            ctx.set_visuals(if dark {
+                egui::Visuals::dark()
+            } else {
+                egui::Visuals::light()
+            });
+        }
+
+        // Not while dragging, or the slider would jump out from under the
+        // pointer
+        let pixels_per_point =
+            frame.info().native_pixels_per_point.unwrap_or(1.0) * cx.density.ui_scale;
+        if (ctx.pixels_per_point() - pixels_per_point).abs() > 1e-3
+            && !ctx.input().pointer.any_down()
+        {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+
+        #[cfg(feature = "puffin")]
+        Self::profiler(ctx, cx);
+        Self::keyboard(ctx, windows, cx);
+        Self::help(ctx, cx);
+        Self::histogram(ctx, windows, cx);
+        Self::item_legend(ctx, windows, cx);
+        Self::item_colors(ctx, windows, cx);
+        Self::dependency_graph(ctx, windows, cx);
+        Self::follow(ctx, windows, cx);
+        Self::invalidate_tiles(ctx, windows, cx);
+        Self::poll_warnings(ctx, windows);
+        Self::warnings(ctx, windows, cx);
+        Self::log_console(ctx, cx);
+
+        let mut export_requested = false;
+        let mut export_svg_requested = false;
+        let mut export_csv_requested = false;
         #[cfg(not(target_arch = "wasm32"))]
+        let mut open_requested = None;
+        let mut pop_out = None;
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        if ui.button("Open…").clicked() {
+                            open_requested = rfd::FileDialog::new()
+                                .add_filter("All Profiles", &["json", "gz", "lpv", "jsonl"])
+                                .add_filter("Chrome Trace", &["json", "gz"])
+                                .add_filter("Profile Archive", &["lpv"])
+                                .add_filter("Recording", &["jsonl"])
+                                .pick_file();
+                            ui.close_menu();
+                        }
+                        let recent_files = &mut self.recent_files;
+                        ui.add_enabled_ui(!recent_files.is_empty(), |ui| {
+                            ui.menu_button("Open Recent", |ui| {
+                                for path in recent_files.iter() {
+                                    if ui.button(path.display().to_string()).clicked() {
+                                        open_requested = Some(path.clone());
+                                        ui.close_menu();
+                                    }
+                                }
+                                ui.separator();
+                                if ui.button("Clear Recent").clicked() {
+                                    recent_files.clear();
+                                    ui.close_menu();
+                                }
+                            });
+                        });
+                        ui.separator();
+                    }
+                    if ui.button("Export View as PNG").clicked() {
+                        export_requested = true;
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Export View as SVG", |ui| {
+                        ui.add(Slider::new(&mut svg_options.width, 400.0..=4000.0).text("Width"));
+                        ui.add(
+                            Slider::new(&mut svg_options.label_font_size, 6.0..=24.0)
+                                .text("Label Font Size"),
+                        );
+                        ui.checkbox(&mut svg_options.include_tooltips, "Include Tooltips");
+                        if ui.button("Export").clicked() {
+                            export_svg_requested = true;
+                            ui.close_menu();
+                        }
+                    });
+                    let button = egui::Button::new("Export Selection as CSV");
+                    if ui
+                        .add_enabled(cx.selection.is_some(), button)
+                        .on_disabled_hover_text("Drag across the timeline to select an interval")
+                        .clicked()
+                    {
+                        export_csv_requested = true;
+                        ui.close_menu();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
                     if ui.button("Quit").clicked() {
-                        _frame.close();
+                        frame.close();
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut cx.show_histogram, "Duration Histogram");
+                    ui.checkbox(&mut cx.show_item_legend, "Legend")
+                        .on_hover_text("Names and colors of the items in view");
+                    ui.checkbox(&mut cx.color_editor.open, "Item Colors")
+                        .on_hover_text("Choose colors for items by name or kind");
+                    ui.checkbox(&mut cx.show_dependency_graph, "Dependency Graph");
+                    let known = windows.iter().any(|w| w.config.epoch.is_some());
+                    ui.add_enabled(
+                        known,
+                        egui::Checkbox::new(&mut cx.wall_clock, "Wall-Clock Time"),
+                    )
+                    .on_hover_text("Show times as the time of day (UTC) they happened at")
+                    .on_disabled_hover_text("The profile doesn't say when it was taken");
+                    ui.menu_button("Time Format", |ui| Self::time_format(ui, cx));
+                    ui.menu_button("Density", |ui| Self::density(ui, cx));
+                    ui.separator();
+                    ui.label("Color Items By:");
+                    ui.radio_value(&mut cx.color_mode, ColorMode::Data, "Profile Colors");
+                    ui.radio_value(&mut cx.color_mode, ColorMode::Duration, "Duration")
+                        .on_hover_text("Short items at the start of the ramp, long at the end");
+                    ui.add_enabled_ui(cx.color_mode == ColorMode::Duration, |ui| {
+                        egui::ComboBox::from_label("Ramp")
+                            .selected_text(cx.color_ramp.name())
+                            .show_ui(ui, |ui| {
+                                for ramp in ColorRamp::ALL {
+                                    ui.selectable_value(&mut cx.color_ramp, *ramp, ramp.name());
+                                }
+                            });
+                    });
+                    ui.separator();
+                    egui::ComboBox::from_label("Palette")
+                        .selected_text(cx.theme.palette.name())
+                        .show_ui(ui, |ui| {
+                            for palette in Palette::ALL {
+                                ui.selectable_value(
+                                    &mut cx.theme.palette,
+                                    *palette,
+                                    palette.name(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text("Remap profile colors, e.g., to a colorblind-safe palette");
+                    ui.add(Slider::new(&mut cx.theme.saturation, 0.0..=1.5).text("Saturation"));
+                });
+                ui.menu_button("Debug", |ui| {
+                    ui.checkbox(&mut cx.show_stats, "Statistics Overlay")
+                        .on_hover_text(
+                            "What each profile fetches and draws, e.g., to see why it's slow",
+                        );
+                    ui.checkbox(&mut cx.show_log, "Log Console")
+                        .on_hover_text("Recent log messages, e.g., about failed fetches");
+                    #[cfg(feature = "puffin")]
+                    ui.checkbox(&mut cx.show_profiler, "Profiler")
+                        .on_hover_text("Where the viewer spends its time in each frame");
+                });
+                ui.menu_button("Help", |ui| {
+                    if ui.button("Keyboard Shortcuts").clicked() {
+                        cx.show_help = true;
+                        ui.close_menu();
                     }
                 });
+                let warnings: usize = windows.iter().map(|w| w.config.warnings.len()).sum();
+                if warnings > 0 {
+                    let badge = egui::RichText::new(format!("⚠ {}", warnings))
+                        .color(ui.visuals().warn_fg_color);
+                    if ui
+                        .button(badge)
+                        .on_hover_text("Warnings about the profiles")
+                        .clicked()
+                    {
+                        cx.show_warnings = !cx.show_warnings;
+                    }
+                }
+                if let Some(status) = status {
+                    ui.label(status.as_str());
+                }
             });
         });
 
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = open_requested {
+            let location = Some(path.display().to_string());
+            let result = loader::watch_file(&path).and_then(|source| {
+                Self::add_window(windows, &self.saved_views, cx, source, location)
+            });
+            Self::update_recent_files(&mut self.recent_files, path, result.is_ok());
+            if let Err(error) = result {
+                *status = Some(format!("Unable to open profile: {}", error));
+            }
+        }
+
+        // Dropped files come with a path on native, and contents on the web
+        let dropped = std::mem::take(&mut ctx.input_mut().raw.dropped_files);
+        for file in dropped {
+            let (result, location) = match (file.path, file.bytes) {
+                (Some(path), _) => {
+                    let location = Some(path.display().to_string());
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let result = loader::watch_file(&path);
+                    #[cfg(target_arch = "wasm32")]
+                    let result = loader::open_file(&path);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Self::update_recent_files(&mut self.recent_files, path, result.is_ok());
+                    (result, location)
+                }
+                (None, Some(bytes)) => (loader::open_bytes(&file.name, &bytes), None),
+                (None, None) => continue,
+            };
+            let result = result.and_then(|source| {
+                Self::add_window(windows, &self.saved_views, cx, source, location)
+            });
+            if let Err(error) = result {
+                *status = Some(format!("Unable to open profile: {}", error));
+            }
+        }
+        Self::drop_target(ctx);
+
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
             let body = TextStyle::Body.resolve(ui.style()).size;
             let heading = TextStyle::Heading.resolve(ui.style()).size;
             // Just set this on every frame for now
             cx.subheading_size = (heading + body) * 0.5;
+            cx.dark_mode = ui.visuals().dark_mode;
 
             ui.heading("Legion Prof Tech Demo");
 
             const WIDGET_PADDING: f32 = 8.0;
             ui.add_space(WIDGET_PADDING);
 
-            for window in windows.iter_mut() {
-                egui::Frame::group(ui.style()).show(ui, |ui| {
-                    ui.set_width(ui.available_width());
-                    window.controls(ui, cx);
-                });
-            }
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                for appearance in Appearance::ALL {
+                    ui.selectable_value(&mut cx.theme.appearance, *appearance, appearance.name());
+                }
+            });
+
+            pop_out = Self::window_controls(windows, &mut self.saved_views, ui, cx);
 
             if self.extra_source.is_some() && ui.button("Add Another Profile").clicked() {
                 let extra = self.extra_source.take().unwrap();
-                let mut index = 0;
-                if let Some(last) = windows.last() {
-                    index = last.index + 1;
+                if let Err(error) = Self::add_window(windows, &self.saved_views, cx, extra, None) {
+                    *status = Some(format!("Unable to open profile: {}", error));
                 }
-                windows.push(Window::new(extra, index));
-                let window = windows.last_mut().unwrap();
-                cx.total_interval = cx.total_interval.union(window.config.interval);
-                cx.view_interval = cx.total_interval;
             }
 
             if ui.button("Reset Zoom Level").clicked() {
-                cx.view_interval = cx.total_interval;
+                Self::reset_zoom(cx);
+            }
+            Self::go_to(ui, cx);
+
+            ui.horizontal(|ui| {
+                let follow = &mut cx.follow;
+                if ui
+                    .toggle_value(&mut follow.enabled, "Follow")
+                    .on_hover_text("Keep the view on the end of the profile as it grows. Panning or zooming stops following.")
+                    .changed()
+                {
+                    follow.view = None;
+                    follow.last_poll = f64::NEG_INFINITY;
+                }
+                ui.label("Last");
+                let mut seconds = follow.window_ns as f64 * 1e-9;
+                let window = egui::DragValue::new(&mut seconds)
+                    .speed(0.1)
+                    .clamp_range(0.001..=3600.0)
+                    .suffix(" s");
+                if ui.add(window).changed() {
+                    follow.window_ns = (seconds * 1e9).round() as i64;
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui.toggle_value(&mut cx.measure_mode, "Measure").changed() {
+                    cx.measure_start = None;
+                    cx.marker_mode &= !cx.measure_mode;
+                }
+                if ui.button("Clear Measurements").clicked() {
+                    cx.measure_start = None;
+                    cx.measurements.clear();
+                }
+                // Clicks go to one tool at a time
+                if ui.toggle_value(&mut cx.marker_mode, "Marker").changed() && cx.marker_mode {
+                    cx.measure_mode = false;
+                    cx.measure_start = None;
+                }
+            });
+            ui.checkbox(&mut cx.select_on_drag, "Drag to Select")
+                .on_hover_text("Ask before zooming to a dragged interval (or hold Shift)");
+
+            if windows.len() > 1 {
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    Self::compare_controls(windows, ui, cx);
+                });
             }
 
             egui::Frame::group(ui.style()).show(ui, |ui| {
                 ui.set_width(ui.available_width());
-                ui.heading("Task Details");
-                ui.label("Click on a task to see it displayed here.");
+                Self::search(windows, ui, cx);
+            });
+
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                Self::filters(ui, cx);
+            });
+
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                Self::task_details(windows, ui, cx);
+            });
+
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                Self::marker_list(ui, cx);
             });
 
+            if cx.selection.is_some() {
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    Self::selection(windows, ui, cx);
+                });
+            }
+
             ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
                 ui.horizontal(|ui| {
                     ui.spacing_mut().item_spacing.x = 0.0;
@@ -1027,34 +8060,44 @@ impl eframe::App for ProfApp {
             });
         });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // Use body font to figure out how tall to draw rectangles.
-            let font_id = TextStyle::Body.resolve(ui.style());
-            let row_height = ui.fonts().row_height(&font_id);
-            // Just set this on every frame for now
-            cx.row_height = row_height;
+        egui::TopBottomPanel::top("minimap").show(ctx, |ui| {
+            Self::minimap(windows, ui, cx);
+        });
 
-            let mut remaining = windows.len();
-            // Only wrap in a frame if more than one profile
-            if remaining > 1 {
-                for window in windows.iter_mut() {
-                    egui::Frame::group(ui.style()).show(ui, |ui| {
-                        ui.push_id(window.index, |ui| {
-                            ui.set_height(ui.available_height() / (remaining as f32));
-                            ui.set_width(ui.available_width());
-                            window.content(ui, cx);
-                            remaining -= 1;
-                        });
-                    });
-                }
-            } else {
-                for window in windows.iter_mut() {
-                    window.content(ui, cx);
-                }
-            }
+        if !cx.crumbs.is_empty() || cx.new_crumb {
+            egui::TopBottomPanel::top("breadcrumbs").show(ctx, |ui| {
+                Self::breadcrumbs(ui, cx);
+            });
+        }
 
-            Self::cursor(ui, cx);
+        egui::TopBottomPanel::bottom("scrollbar").show(ctx, |ui| {
+            Self::scrollbar(ui, cx);
         });
+
+        let central = egui::CentralPanel::default().show(ctx, |ui| {
+            Self::timeline(windows, ui, cx);
+            Self::cursor(windows, ui, cx);
+        });
+        // After the timeline, so that it counts this frame
+        Self::stats_overlay(ctx, windows, cx);
+
+        if export_requested {
+            let size = central.response.rect.size();
+            *status = Some(Self::export_png(windows, ctx, cx, size));
+        }
+        if export_svg_requested {
+            *status = Some(Self::export_svg(windows, cx, svg_options));
+        }
+        if let Some(selection) = cx.selection.filter(|_| export_csv_requested) {
+            *status = Some(Self::export_csv(windows, selection));
+        }
+
+        if let Some(index) = pop_out {
+            self.pop_out(index, frame);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        self.update_location();
     }
 }
 
@@ -1132,34 +8175,211 @@ impl UiExtra for egui::Ui {
     }
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub fn start(data_source: Box<dyn DataSource>, extra_source: Option<Box<dyn DataSource>>) {
-    // Log to stdout (if you run with `RUST_LOG=debug`).
-    tracing_subscriber::fmt::init();
+impl ProfViewerState {
+    /// Opens the profile with the default view (all of it, and everything
+    /// but kinds expanded).
+    pub fn new(data_source: Box<dyn DataSource>) -> DataSourceResult<Self> {
+        let window = Window::new(data_source, 0)?;
+        let mut cx = Context::default();
+        cx.total_interval = window.config.interval;
+        cx.view_interval = cx.total_interval;
+        cx.sync_views = true;
+        Ok(Self {
+            windows: vec![window],
+            cx,
+        })
+    }
+
+    /// Shows another profile below the others, e.g., to compare runs.
+    pub fn add_profile(&mut self, data_source: Box<dyn DataSource>) -> DataSourceResult<()> {
+        let window = Window::new(data_source, self.windows.len() as u64)?;
+        self.cx.total_interval = self.cx.total_interval.union(window.config.interval);
+        self.windows.push(window);
+        Ok(())
+    }
+
+    pub fn view_interval(&self) -> Interval {
+        self.cx.view_interval
+    }
+
+    /// Zooms to part of the profiles (clipped to the whole of them).
+    pub fn set_view_interval(&mut self, interval: Interval) {
+        let view = interval.intersection(self.cx.total_interval);
+        if view.duration_ns() > 0 {
+            self.cx.view_interval = view;
+        }
+    }
+
+    pub fn reset_view(&mut self) {
+        ProfApp::reset_zoom(&mut self.cx);
+    }
+
+    pub fn expand_all(&mut self, expanded: bool) {
+        for window in &mut self.windows {
+            window.expand_all(expanded);
+        }
+    }
+}
+
+impl ProfViewerWidget {
+    /// Shows the timeline in the space available in the UI.
+    pub fn show(ui: &mut egui::Ui, state: &mut ProfViewerState) {
+        let ProfViewerState { windows, cx } = state;
+        ProfApp::invalidate_tiles(ui.ctx(), windows, cx);
+        // Keep clear of the IDs of whatever else is in the application
+        ui.push_id("prof_viewer", |ui| {
+            ProfApp::timeline(windows, ui, cx);
+            ProfApp::cursor(windows, ui, cx);
+        });
+    }
+}
+
+impl Headless {
+    /// Opens the profile with the default view, to be drawn at the given
+    /// size in points.
+    pub fn new(data_source: Box<dyn DataSource>, size: Vec2) -> DataSourceResult<Self> {
+        let mut state = ProfViewerState::new(data_source)?;
+        state.cx.fetch_all = true;
+        Ok(Self {
+            state,
+            ctx: egui::Context::default(),
+            rasterizer: Rasterizer::default(),
+            size,
+            pointer: None,
+        })
+    }
+
+    pub fn view_interval(&self) -> Interval {
+        self.state.view_interval()
+    }
+
+    pub fn set_view_interval(&mut self, interval: Interval) {
+        self.state.set_view_interval(interval);
+    }
+
+    pub fn expand_all(&mut self, expanded: bool) {
+        self.state.expand_all(expanded);
+    }
+
+    /// Moves the mouse over the timeline (or off it), to draw the cursor.
+    pub fn set_pointer(&mut self, pos: Option<Pos2>) {
+        self.pointer = pos;
+    }
+
+    /// Lays out one frame of the timeline, returning the shapes drawn (not
+    /// yet tessellated). Some layout depends on the previous frame (e.g.,
+    /// whether there's a scroll bar), so it takes a couple of frames for
+    /// the result to settle.
+    pub fn run(&mut self) -> egui::FullOutput {
+        let pointer = match self.pointer {
+            Some(pos) => egui::Event::PointerMoved(pos),
+            None => egui::Event::PointerGone,
+        };
+        let input = egui::RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, self.size)),
+            pixels_per_point: Some(1.0),
+            events: vec![pointer],
+            ..Default::default()
+        };
+        let state = &mut self.state;
+        let output = self.ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ProfViewerWidget::show(ui, state);
+            });
+        });
+        // Every frame, since fonts are only sent once
+        self.rasterizer.update_textures(&output.textures_delta);
+        output
+    }
+
+    /// Draws one frame of the timeline into an image, one pixel per point.
+    pub fn render(&mut self) -> Image {
+        let output = self.run();
+        rasterize_output(&self.ctx, &self.rasterizer, output.shapes, self.size)
+    }
+}
+
+// Tessellates and rasterizes the shapes of a frame run offscreen (with
+// the rasterizer's textures already up to date)
+fn rasterize_output(
+    ctx: &egui::Context,
+    rasterizer: &Rasterizer,
+    shapes: Vec<egui::epaint::ClippedShape>,
+    size: Vec2,
+) -> Image {
+    let pixels_per_point = ctx.pixels_per_point();
+    let primitives = ctx.tessellate(shapes);
+    let pixels = size * pixels_per_point;
+    let mut image = Image::new(
+        pixels.x.round() as usize,
+        pixels.y.round() as usize,
+        Color32::TRANSPARENT,
+    );
+    rasterizer.rasterize(&primitives, pixels_per_point, &mut image);
+    image
+}
+
+// Everything from the viewer itself, but only the more important messages
+// from the (chattier) libraries it uses
+fn console_filter() -> Targets {
+    Targets::new()
+        .with_default(Level::INFO)
+        .with_target("legion_prof_viewer", Level::DEBUG)
+}
 
-    let native_options = eframe::NativeOptions::default();
+#[cfg(not(target_arch = "wasm32"))]
+pub fn start(
+    data_source: Box<dyn DataSource>,
+    extra_source: Option<Box<dyn DataSource>>,
+    options: StartOptions,
+) {
+    // Log to stdout, and keep the viewer's own debug messages for the
+    // console (Debug > Log Console).
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_filter(tracing_subscriber::filter::LevelFilter::INFO),
+        )
+        .with(ConsoleLayer.with_filter(console_filter()))
+        .init();
+
+    let native_options = eframe::NativeOptions {
+        initial_window_size: options.window_size,
+        drag_and_drop_support: true,
+        ..Default::default()
+    };
     eframe::run_native(
         "Legion Prof",
         native_options,
-        Box::new(|cc| Box::new(ProfApp::new(cc, data_source, extra_source))),
+        Box::new(move |cc| Box::new(ProfApp::new(cc, data_source, extra_source, &options))),
     );
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn start(data_source: Box<dyn DataSource>, extra_source: Option<Box<dyn DataSource>>) {
+pub fn start(
+    data_source: Box<dyn DataSource>,
+    extra_source: Option<Box<dyn DataSource>>,
+    options: StartOptions,
+) {
     // Make sure panics are logged using `console.error`.
     console_error_panic_hook::set_once();
 
-    // Redirect tracing to console.log and friends:
-    tracing_wasm::set_as_global_default();
+    // Redirect tracing to console.log and friends, and to our own console
+    // (Debug > Log Console):
+    tracing_subscriber::registry()
+        .with(tracing_wasm::WASMLayer::new(
+            tracing_wasm::WASMLayerConfig::default(),
+        ))
+        .with(ConsoleLayer.with_filter(console_filter()))
+        .init();
 
     let web_options = eframe::WebOptions::default();
 
-    wasm_bindgen_futures::spawn_local(async {
+    wasm_bindgen_futures::spawn_local(async move {
         eframe::start_web(
             "the_canvas_id", // hardcode it
             web_options,
-            Box::new(|cc| Box::new(ProfApp::new(cc, data_source, extra_source))),
+            Box::new(move |cc| Box::new(ProfApp::new(cc, data_source, extra_source, &options))),
         )
         .await
         .expect("failed to start eframe");
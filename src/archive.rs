@@ -0,0 +1,392 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::data::{
+    CounterTile, DataSource, DataSourceError, DataSourceResult, Description, EntryID, EntryInfo,
+    EventTile, FetchStats, ItemLink, ItemUID, SearchResult, SlotTile, SummaryTile, TileID,
+    UtilPoint, Warning,
+};
+use crate::timestamp::{Interval, Timestamp};
+
+// An archive holds everything a viewer can ask of a profile, so that it
+// can be sent around as one file:
+//
+//   * MAGIC, then VERSION (one byte)
+//   * Blobs: tiles (and item links), each as deflated bincode
+//   * The Index, as a blob
+//   * Where the index is: its offset and length (u64 little endian each)
+//
+// Tiles are fetched from the original source over a fixed division of the
+// profile, so that a viewer only needs to decode the part it's showing.
+pub const MAGIC: &[u8; 8] = b"LPVARCH\0";
+const VERSION: u8 = 2;
+const HEADER: usize = MAGIC.len() + 1;
+const TRAILER: usize = 16;
+
+// Pieces each entry is divided into (the source may divide them further)
+const DIVISIONS: i64 = 64;
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+struct Blob {
+    offset: u64,
+    length: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Index {
+    interval: Interval,
+    epoch: Option<i64>,
+    description: Description,
+    warnings: Vec<Warning>, // handed out on the first poll
+    info: EntryInfo,
+    tiles: BTreeMap<EntryID, Vec<(TileID, Blob)>>, // sorted by start
+    item_links: Blob,                              // BTreeMap<ItemUID, Vec<ItemLink>>
+}
+
+// Keeps track of where blobs end up
+struct BlobWriter<W: Write> {
+    writer: W,
+    offset: u64,
+}
+
+impl<W: Write> BlobWriter<W> {
+    fn write(&mut self, value: &impl Serialize) -> DataSourceResult<Blob> {
+        let bytes = bincode::serialize(value).expect("data source types always serialize");
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&bytes)?;
+        let bytes = encoder.finish()?;
+        self.writer.write_all(&bytes)?;
+        let blob = Blob {
+            offset: self.offset,
+            length: bytes.len() as u64,
+        };
+        self.offset += blob.length;
+        Ok(blob)
+    }
+}
+
+/// Writes everything in a data source to an archive, which ArchiveDataSource
+/// (or loader::open_file) can open later without the original profile.
+pub fn write_archive(data_source: &mut dyn DataSource, writer: impl Write) -> DataSourceResult<()> {
+    let interval = data_source.interval()?;
+    let epoch = data_source.epoch()?;
+    let description = data_source.fetch_description()?;
+    let info = data_source.fetch_info()?.clone();
+
+    let mut writer = BlobWriter { writer, offset: 0 };
+    writer.writer.write_all(MAGIC)?;
+    writer.writer.write_all(&[VERSION])?;
+    writer.offset = HEADER as u64;
+
+    let divisions = DIVISIONS.min(interval.duration_ns()).max(1);
+    let mut tiles = BTreeMap::new();
+    let mut item_uids = BTreeSet::new();
+    for (entry_id, entry) in info.data_entries() {
+        let mut entry_tiles: Vec<(TileID, Blob)> = Vec::new();
+        for i in 0..divisions {
+            let division = Interval::new(
+                Timestamp(interval.start.0 + i * interval.duration_ns() / divisions),
+                Timestamp(interval.start.0 + (i + 1) * interval.duration_ns() / divisions),
+            );
+            for tile_id in data_source.request_tiles(&entry_id, division)? {
+                let blob = match entry {
+                    EntryInfo::Summary { .. } | EntryInfo::Aggregate { .. } => {
+                        let mut tile = data_source.fetch_summary_tile(&entry_id, tile_id)?;
+                        clip(&mut tile.utilization, tile_id.0);
+                        for series in &mut tile.series {
+                            clip(&mut series.utilization, tile_id.0);
+                        }
+                        writer.write(&tile)?
+                    }
+                    EntryInfo::Slot { .. } => {
+                        let tile = data_source.fetch_slot_tile(&entry_id, tile_id)?;
+                        item_uids.extend(tile.items.iter().flatten().map(|item| item.item_uid));
+                        writer.write(&tile)?
+                    }
+                    EntryInfo::Counter { .. } => {
+                        writer.write(&data_source.fetch_counter_tile(&entry_id, tile_id)?)?
+                    }
+                    EntryInfo::Events { .. } => {
+                        writer.write(&data_source.fetch_event_tile(&entry_id, tile_id)?)?
+                    }
+                    EntryInfo::Panel { .. } => unreachable!(),
+                };
+                entry_tiles.push((tile_id, blob));
+            }
+        }
+        entry_tiles.sort_by_key(|(tile_id, _)| tile_id.0.start);
+        tiles.insert(entry_id, entry_tiles);
+    }
+
+    let mut item_links = BTreeMap::new();
+    for item_uid in item_uids {
+        let links = data_source.fetch_item_links(item_uid)?;
+        if !links.is_empty() {
+            item_links.insert(item_uid, links);
+        }
+    }
+    let item_links = writer.write(&item_links)?;
+
+    let index = writer.write(&Index {
+        interval,
+        epoch,
+        description,
+        // Including any that came up while fetching everything above
+        warnings: data_source.poll_warnings(),
+        info,
+        tiles,
+        item_links,
+    })?;
+    writer.writer.write_all(&index.offset.to_le_bytes())?;
+    writer.writer.write_all(&index.length.to_le_bytes())?;
+    writer.writer.flush()?;
+    Ok(())
+}
+
+// Sources may include the points on either side of a tile, so that the
+// curve reaches its edges. That's fine for a tile covering the whole view,
+// but these are drawn side by side, so cut the curve at the edges instead.
+fn clip(points: &mut Vec<UtilPoint>, interval: Interval) {
+    let at = |time: Timestamp| {
+        let index = points.partition_point(|p| p.time < time);
+        match (index.checked_sub(1).map(|i| points[i]), points.get(index)) {
+            (_, Some(p)) if p.time == time => Some(*p),
+            (Some(p1), Some(p2)) => {
+                let ratio = Interval::new(p1.time, p2.time).unlerp(time) as f32;
+                let util = p1.util + (p2.util - p1.util) * ratio;
+                Some(UtilPoint { time, util })
+            }
+            _ => None,
+        }
+    };
+    let (start, stop) = (at(interval.start), at(interval.stop));
+    points.retain(|p| p.time > interval.start && p.time < interval.stop);
+    if let Some(start) = start {
+        points.insert(0, start);
+    }
+    points.extend(stop);
+}
+
+/// Reads an archive made by write_archive. Only the index is read up front:
+/// tiles are read (and decoded) when they're fetched, so an archive can be
+/// much larger than memory if it's read from a file.
+pub struct ArchiveDataSource<R: Read + Seek = Cursor<Vec<u8>>> {
+    reader: R,
+    length: u64,
+    index: Index,
+    item_links: Option<BTreeMap<ItemUID, Vec<ItemLink>>>, // once fetched
+    stats: FetchStats,
+}
+
+fn invalid(problem: impl std::fmt::Display) -> DataSourceError {
+    DataSourceError::new(format!("invalid archive: {}", problem))
+}
+
+fn read_blob<T: DeserializeOwned>(
+    reader: &mut (impl Read + Seek),
+    length: u64,
+    blob: Blob,
+) -> DataSourceResult<T> {
+    let stop = blob.offset.checked_add(blob.length);
+    if blob.offset < HEADER as u64 || stop.map_or(true, |stop| stop > length) {
+        return Err(invalid("blob out of bounds"));
+    }
+    reader.seek(SeekFrom::Start(blob.offset))?;
+    // The decoder buffers its input, so this doesn't need a BufReader
+    let decoder = DeflateDecoder::new(reader.take(blob.length));
+    bincode::deserialize_from(decoder).map_err(invalid)
+}
+
+impl ArchiveDataSource {
+    /// Reads an archive that's already in memory (e.g., in a browser).
+    pub fn new(bytes: Vec<u8>) -> DataSourceResult<Self> {
+        Self::from_reader(Cursor::new(bytes))
+    }
+}
+
+impl<R: Read + Seek> ArchiveDataSource<R> {
+    /// Reads an archive from anything that can seek, usually a File.
+    pub fn from_reader(mut reader: R) -> DataSourceResult<Self> {
+        let mut header = [0; HEADER];
+        let length = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        if length < (HEADER + TRAILER) as u64
+            || reader.read_exact(&mut header).is_err()
+            || !header.starts_with(MAGIC)
+        {
+            return Err(invalid("not an archive"));
+        }
+        if header[MAGIC.len()] != VERSION {
+            return Err(invalid(format!(
+                "version {} is not supported (expected {})",
+                header[MAGIC.len()],
+                VERSION
+            )));
+        }
+        let mut trailer = [0; TRAILER];
+        reader.seek(SeekFrom::Start(length - TRAILER as u64))?;
+        reader.read_exact(&mut trailer)?;
+        let index = Blob {
+            offset: u64::from_le_bytes(trailer[..8].try_into().unwrap()),
+            length: u64::from_le_bytes(trailer[8..].try_into().unwrap()),
+        };
+        let index = read_blob(&mut reader, length, index)?;
+        Ok(Self {
+            reader,
+            length,
+            index,
+            item_links: None,
+            stats: FetchStats::default(),
+        })
+    }
+
+    fn tile<T: DeserializeOwned>(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<T> {
+        let blob = self
+            .index
+            .tiles
+            .get(entry_id)
+            .and_then(|tiles| tiles.iter().find(|(t, _)| t.0 == tile_id.0))
+            .map(|(_, blob)| *blob)
+            .ok_or_else(|| {
+                DataSourceError::new(format!("no tile {} of {} in archive", tile_id.0, entry_id))
+            })?;
+        self.read(blob)
+    }
+
+    fn read<T: DeserializeOwned>(&mut self, blob: Blob) -> DataSourceResult<T> {
+        self.stats.requests += 1;
+        self.stats.bytes += blob.length;
+        read_blob(&mut self.reader, self.length, blob)
+    }
+}
+
+impl<R: Read + Seek> DataSource for ArchiveDataSource<R> {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        Ok(self.index.interval)
+    }
+
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        Ok(self.index.epoch)
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        Ok(self.index.description.clone())
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        Ok(&self.index.info)
+    }
+
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        let tiles = self
+            .index
+            .tiles
+            .get(entry_id)
+            .map_or(&[][..], Vec::as_slice);
+        Ok(tiles
+            .iter()
+            .map(|(tile_id, _)| *tile_id)
+            .filter(|tile_id| tile_id.0.overlaps(request_interval))
+            .collect())
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.tile(entry_id, tile_id)
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        self.tile(entry_id, tile_id)
+    }
+
+    // Reads every slot tile, but that's what searching the profile means
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        let query = query.to_lowercase();
+        let slots: Vec<(EntryID, Vec<Blob>)> = self
+            .index
+            .info
+            .data_entries()
+            .into_iter()
+            .filter(|(_, entry)| matches!(entry, EntryInfo::Slot { .. }))
+            .map(|(entry_id, _)| {
+                let tiles = self
+                    .index
+                    .tiles
+                    .get(&entry_id)
+                    .ok_or_else(|| invalid(format!("no tiles for {}", entry_id)))?;
+                let blobs = tiles.iter().map(|t| t.1).collect();
+                Ok((entry_id, blobs))
+            })
+            .collect::<DataSourceResult<_>>()?;
+        let mut results = Vec::new();
+        for (entry_id, blobs) in slots {
+            // Items that cross tiles are in each of them
+            let mut found = BTreeSet::new();
+            for blob in blobs {
+                let tile: SlotTile = self.read(blob)?;
+                for item in tile.items.into_iter().flatten() {
+                    if item.title.to_lowercase().contains(&query) && found.insert(item.item_uid) {
+                        results.push(SearchResult {
+                            entry_id: entry_id.clone(),
+                            title: item.title,
+                            interval: item.interval,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        if self.item_links.is_none() {
+            self.item_links = Some(self.read(self.index.item_links)?);
+        }
+        let links = self.item_links.as_ref().unwrap().get(&item_uid);
+        Ok(links.cloned().unwrap_or_default())
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        self.tile(entry_id, tile_id)
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        self.tile(entry_id, tile_id)
+    }
+
+    fn fetch_stats(&mut self) -> FetchStats {
+        self.stats
+    }
+
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.index.warnings)
+    }
+}
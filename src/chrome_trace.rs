@@ -0,0 +1,527 @@
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+
+use crate::colors;
+use crate::data::{
+    Color, DataSource, DataSourceError, DataSourceResult, EntryID, EntryInfo, Field, Item, ItemUID,
+    SearchResult, SlotTile, SummaryTile, TileID, UtilPoint,
+};
+use crate::timestamp::{Interval, Timestamp};
+
+/// Reads a Chrome Trace Event file (the JSON format used by
+/// chrome://tracing and Perfetto) so that the viewer can be used on
+/// non-Legion traces.
+///
+/// Processes become nodes, with a single kind ("threads") containing one
+/// slot per thread. Nested events on a thread are stacked into rows by
+/// depth.
+pub struct ChromeTraceDataSource {
+    info: EntryInfo,
+    interval: Interval,
+    processes: Vec<Process>,
+}
+
+struct Process {
+    threads: Vec<Vec<Vec<Item>>>, // thread -> row -> [item]
+    utilization: Vec<UtilPoint>,
+}
+
+// pid/tid are usually integers, but some tools emit strings
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(untagged)]
+enum TraceID {
+    Int(i64),
+    Str(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TraceFile {
+    Array(Vec<TraceEvent>),
+    Object {
+        #[serde(rename = "traceEvents")]
+        trace_events: Vec<TraceEvent>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceEvent {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    cat: String,
+    ph: String,
+    #[serde(default)]
+    ts: f64, // us
+    dur: Option<f64>, // us
+    pid: Option<TraceID>,
+    tid: Option<TraceID>,
+    #[serde(default)]
+    args: serde_json::Map<String, serde_json::Value>,
+}
+
+// An event before it has been assigned to a row
+struct Span {
+    name: String,
+    cat: String,
+    start: f64,
+    stop: f64,
+    args: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Default)]
+struct ThreadBuilder {
+    name: Option<String>,
+    spans: Vec<Span>,
+    open: Vec<Span>, // "B" events waiting for their "E"
+}
+
+#[derive(Default)]
+struct ProcessBuilder {
+    name: Option<String>,
+    threads: BTreeMap<TraceID, ThreadBuilder>,
+}
+
+impl ChromeTraceDataSource {
+    /// Opens a `.json` or `.json.gz` trace file. Compression is detected
+    /// from the file contents, not the extension.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::from_slice(&bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+        let file: TraceFile = if bytes.starts_with(&GZIP_MAGIC) {
+            serde_json::from_reader(GzDecoder::new(bytes))?
+        } else {
+            serde_json::from_slice(bytes)?
+        };
+        let events = match file {
+            TraceFile::Array(events) => events,
+            TraceFile::Object { trace_events } => trace_events,
+        };
+        Ok(Self::from_events(events))
+    }
+
+    fn from_events(events: Vec<TraceEvent>) -> Self {
+        let mut builders: BTreeMap<TraceID, ProcessBuilder> = BTreeMap::new();
+        for event in events {
+            let pid = event.pid.clone().unwrap_or(TraceID::Int(0));
+            let tid = event.tid.clone().unwrap_or(TraceID::Int(0));
+            let process = builders.entry(pid).or_default();
+
+            let meta_name = || {
+                event
+                    .args
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .map(str::to_owned)
+            };
+            match event.ph.as_str() {
+                "X" => {
+                    let stop = event.ts + event.dur.unwrap_or(0.0);
+                    process.threads.entry(tid).or_default().spans.push(Span {
+                        name: event.name,
+                        cat: event.cat,
+                        start: event.ts,
+                        stop,
+                        args: event.args,
+                    });
+                }
+                "B" => {
+                    process.threads.entry(tid).or_default().open.push(Span {
+                        name: event.name,
+                        cat: event.cat,
+                        start: event.ts,
+                        stop: event.ts,
+                        args: event.args,
+                    });
+                }
+                "E" => {
+                    let thread = process.threads.entry(tid).or_default();
+                    if let Some(mut span) = thread.open.pop() {
+                        span.stop = event.ts;
+                        span.args.extend(event.args);
+                        thread.spans.push(span);
+                    }
+                }
+                "M" if event.name == "process_name" => {
+                    process.name = meta_name();
+                }
+                "M" if event.name == "thread_name" => {
+                    process.threads.entry(tid).or_default().name = meta_name();
+                }
+                // Other event types (instant, counter, flow, ...) are not
+                // supported yet
+                _ => {}
+            }
+        }
+
+        // Timestamps are shifted so that the trace starts at zero
+        let all_spans = || {
+            builders
+                .values()
+                .flat_map(|p| p.threads.values())
+                .flat_map(|t| &t.spans)
+        };
+        let origin = all_spans().map(|s| s.start).fold(f64::INFINITY, f64::min);
+        let origin = if origin.is_finite() { origin } else { 0.0 };
+        let to_ns = |us: f64| Timestamp(((us - origin) * 1000.0).round() as i64);
+        let end = all_spans().map(|s| to_ns(s.stop)).max().unwrap_or_default();
+        let interval = Interval::new(Timestamp(0), end);
+
+        let mut next_uid = 0;
+        let mut processes = Vec::new();
+        let mut process_slots = Vec::new();
+        for (p, (pid, builder)) in builders.into_iter().enumerate() {
+            let process_name = builder
+                .name
+                .unwrap_or_else(|| format!("Process {}", trace_id_name(&pid)));
+
+            let mut threads = Vec::new();
+            let mut thread_slots = Vec::new();
+            for (t, (tid, mut thread)) in builder.threads.into_iter().enumerate() {
+                // Unterminated "B" events run until the end of the trace
+                for mut span in thread.open.drain(..) {
+                    span.stop = span.stop.max(origin + end.0 as f64 / 1000.0);
+                    thread.spans.push(span);
+                }
+
+                // Enclosing spans first, so that nesting depth gives the row
+                thread
+                    .spans
+                    .sort_by(|a, b| a.start.total_cmp(&b.start).then(b.stop.total_cmp(&a.stop)));
+                let mut rows: Vec<Vec<Item>> = Vec::new();
+                let mut stack: Vec<Timestamp> = Vec::new();
+                for span in thread.spans {
+                    let start = to_ns(span.start);
+                    let stop = to_ns(span.stop);
+                    while stack.last().map_or(false, |s| *s <= start) {
+                        stack.pop();
+                    }
+                    let depth = stack.len();
+                    stack.push(stop);
+
+                    if rows.len() <= depth {
+                        rows.resize_with(depth + 1, Vec::new);
+                    }
+                    rows[depth].push(Item {
+                        item_uid: ItemUID(next_uid),
+                        interval: Interval::new(start, stop),
+                        // The same function is always the same color
+                        color: colors::name_color(&span.name),
+                        title: span.name,
+                        fields: span_fields(&span.cat, span.args),
+                        segments: Vec::new(),
+                        endpoints: None,
+                        allocation: None,
+                    });
+                    next_uid += 1;
+                }
+
+                let thread_name = thread
+                    .name
+                    .unwrap_or_else(|| format!("Thread {}", trace_id_name(&tid)));
+                thread_slots.push(EntryInfo::Slot {
+                    short_name: format!("t{}", t),
+                    long_name: format!("{} {}", process_name, thread_name),
+                    max_rows: rows.len() as u64,
+                });
+                threads.push(rows);
+            }
+
+            let utilization = utilization(&threads, interval);
+            processes.push(Process {
+                threads,
+                utilization,
+            });
+
+            let kind = EntryInfo::Panel {
+                short_name: "threads".to_owned(),
+                long_name: format!("{} Threads", process_name),
+                summary: Some(Box::new(EntryInfo::Summary { color: Color::BLUE })),
+                slots: thread_slots,
+            };
+            process_slots.push(EntryInfo::Panel {
+                short_name: format!("p{}", p),
+                long_name: process_name,
+                summary: None,
+                slots: vec![kind],
+            });
+        }
+
+        let info = EntryInfo::Panel {
+            short_name: "root".to_owned(),
+            long_name: "root".to_owned(),
+            summary: None,
+            slots: process_slots,
+        };
+
+        Self {
+            info,
+            interval,
+            processes,
+        }
+    }
+
+    fn process(&self, entry_id: &EntryID) -> DataSourceResult<&Process> {
+        entry_id
+            .slot_index(0)
+            .and_then(|p| self.processes.get(p as usize))
+            .ok_or_else(|| DataSourceError::new(format!("no process for {:?}", entry_id)))
+    }
+}
+
+/// Writes the items of any data source as a Chrome trace, so it can be
+/// opened by other tools. Each node becomes a process, and each slot a
+/// thread. Summaries, counters and events are not included.
+pub fn write_chrome_trace(
+    data_source: &mut dyn DataSource,
+    writer: impl Write,
+) -> DataSourceResult<()> {
+    let interval = data_source.interval()?;
+    let info = data_source.fetch_info()?.clone();
+
+    let mut events = Vec::new();
+    let mut threads: BTreeMap<u64, u64> = BTreeMap::new(); // per process
+    for (entry_id, entry) in info.data_entries() {
+        let thread_name = match entry {
+            EntryInfo::Slot { long_name, .. } => long_name,
+            _ => continue,
+        };
+        let pid = entry_id.slot_index(0).unwrap_or(0);
+        let process_name = match info.get(&EntryID::root().child(pid)) {
+            Some(EntryInfo::Panel { long_name, .. }) => long_name.as_str(),
+            _ => "",
+        };
+        // Slot names usually repeat the node's, which readers add back
+        let thread_name = thread_name
+            .strip_prefix(process_name)
+            .map_or(thread_name.as_str(), str::trim_start);
+        let tid = threads.entry(pid).or_default();
+        if *tid == 0 {
+            events.push(json!({
+                "name": "process_name", "ph": "M", "pid": pid,
+                "args": { "name": process_name },
+            }));
+        }
+        *tid += 1;
+        events.push(json!({
+            "name": "thread_name", "ph": "M", "pid": pid, "tid": *tid,
+            "args": { "name": thread_name },
+        }));
+
+        // Items that cross tile boundaries come back in pieces
+        let mut items: BTreeMap<ItemUID, Item> = BTreeMap::new();
+        for tile_id in data_source.request_tiles(&entry_id, interval)? {
+            let tile = data_source.fetch_slot_tile(&entry_id, tile_id)?;
+            for item in tile.items.into_iter().flatten() {
+                items
+                    .entry(item.item_uid)
+                    .and_modify(|i| i.interval = i.interval.union(item.interval))
+                    .or_insert(item);
+            }
+        }
+        for item in items.into_values() {
+            let mut cat = String::new();
+            let mut args = serde_json::Map::new();
+            for (name, field) in item.fields {
+                let value = match field {
+                    Field::String(s) if name == "Category" => {
+                        cat = s;
+                        continue;
+                    }
+                    Field::I64(i) => json!(i),
+                    Field::U64(u) => json!(u),
+                    Field::String(s) => json!(s),
+                    Field::Interval(i) => json!(i.to_string()),
+                    Field::Empty => serde_json::Value::Null,
+                    Field::F64(f) | Field::Percent(f) => json!(f),
+                    Field::Bytes(b) => json!(b),
+                    Field::Link { url, .. } => json!(url),
+                };
+                args.insert(name, value);
+            }
+            events.push(json!({
+                "name": item.title, "cat": cat, "ph": "X", "pid": pid, "tid": *tid,
+                "ts": item.interval.start.0 as f64 / 1000.0,
+                "dur": item.interval.duration_ns() as f64 / 1000.0,
+                "args": args,
+            }));
+        }
+    }
+
+    let trace = json!({ "traceEvents": events, "displayTimeUnit": "ns" });
+    serde_json::to_writer(writer, &trace)
+        .map_err(|e| DataSourceError::new(format!("failed to write trace: {}", e)))
+}
+
+fn trace_id_name(id: &TraceID) -> String {
+    match id {
+        TraceID::Int(i) => i.to_string(),
+        TraceID::Str(s) => s.clone(),
+    }
+}
+
+fn span_fields(
+    cat: &str,
+    args: serde_json::Map<String, serde_json::Value>,
+) -> Vec<(String, Field)> {
+    let mut fields = Vec::new();
+    if !cat.is_empty() {
+        fields.push(("Category".to_owned(), Field::String(cat.to_owned())));
+    }
+    for (name, value) in args {
+        let field = match value {
+            serde_json::Value::Number(n) if n.is_i64() => Field::I64(n.as_i64().unwrap()),
+            serde_json::Value::Number(n) if n.is_u64() => Field::U64(n.as_u64().unwrap()),
+            serde_json::Value::Number(n) => Field::F64(n.as_f64().unwrap()),
+            serde_json::Value::String(s) => Field::String(s),
+            serde_json::Value::Null => Field::Empty,
+            value => Field::String(value.to_string()),
+        };
+        fields.push((name, field));
+    }
+    fields
+}
+
+// Fraction of threads in the process that are busy (i.e., have a top-level
+// span running), as a step function over time
+fn utilization(threads: &[Vec<Vec<Item>>], interval: Interval) -> Vec<UtilPoint> {
+    let mut changes: Vec<(Timestamp, i64)> = Vec::new();
+    for rows in threads {
+        for item in rows.first().into_iter().flatten() {
+            changes.push((item.interval.start, 1));
+            changes.push((item.interval.stop, -1));
+        }
+    }
+    changes.sort();
+
+    let count = threads.len().max(1) as f32;
+    let mut result = vec![UtilPoint {
+        time: interval.start,
+        util: 0.0,
+    }];
+    let mut active = 0;
+    for (time, delta) in changes {
+        let last = *result.last().unwrap();
+        active += delta;
+        let util = active as f32 / count;
+        if time == last.time {
+            result.last_mut().unwrap().util = util;
+        } else {
+            // Hold the previous value up to this point to draw a step
+            result.push(UtilPoint {
+                time,
+                util: last.util,
+            });
+            result.push(UtilPoint { time, util });
+        }
+    }
+    result.push(UtilPoint {
+        time: interval.stop,
+        util: result.last().unwrap().util,
+    });
+    result
+}
+
+impl DataSource for ChromeTraceDataSource {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        Ok(self.interval)
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        Ok(&self.info)
+    }
+
+    fn request_tiles(
+        &mut self,
+        _entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        // Everything is in memory, so there's no benefit to splitting
+        Ok(vec![TileID(request_interval)])
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        let points = &self.process(entry_id)?.utilization;
+
+        // Include the points on either side of the tile so that the plot
+        // doesn't get cut off at the edges
+        let first = points
+            .partition_point(|p| p.time < tile_id.0.start)
+            .saturating_sub(1);
+        let last = points
+            .partition_point(|p| p.time <= tile_id.0.stop)
+            .min(points.len() - 1);
+        Ok(SummaryTile {
+            tile_id,
+            utilization: points[first..=last].to_vec(),
+            series: Vec::new(),
+        })
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        let process = self.process(entry_id)?;
+        let rows = entry_id
+            .slot_index(2)
+            .and_then(|t| process.threads.get(t as usize))
+            .ok_or_else(|| DataSourceError::new(format!("no thread for {:?}", entry_id)))?;
+
+        let mut items = Vec::new();
+        for row in rows {
+            let mut slot_row = Vec::new();
+            for item in row {
+                // When the item straddles a tile boundary, it has to be
+                // sliced to fit
+                if tile_id.0.overlaps(item.interval) {
+                    let mut new_item = item.clone();
+                    new_item.interval = new_item.interval.intersection(tile_id.0);
+                    slot_row.push(new_item);
+                }
+            }
+            items.push(slot_row);
+        }
+
+        Ok(SlotTile { tile_id, items })
+    }
+
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        let query = query.to_lowercase();
+        let mut results = Vec::new();
+        for (p, process) in self.processes.iter().enumerate() {
+            let kind_id = EntryID::root().child(p as u64).child(0);
+            for (t, rows) in process.threads.iter().enumerate() {
+                for item in rows.iter().flatten() {
+                    if item.title.to_lowercase().contains(&query) {
+                        results.push(SearchResult {
+                            entry_id: kind_id.child(t as u64),
+                            title: item.title.clone(),
+                            interval: item.interval,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(results)
+    }
+}
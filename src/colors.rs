@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+
+use crate::data::Color;
+
+// Kept away from the extremes, so that items stand out against both light
+// and dark backgrounds, and labels on top stay readable
+const SATURATIONS: &[f32] = &[0.55, 0.7, 0.85];
+const LIGHTNESSES: &[f32] = &[0.45, 0.55, 0.65];
+
+// Colors closer than this (see Hsl::distance) are taken to look the same
+const MIN_DISTANCE: f32 = 0.12;
+// Other colors to try for a name that looks like one already assigned
+const MAX_ATTEMPTS: u64 = 16;
+
+/// Color for a name (e.g., of a task), the same in every run and every
+/// profile. Data sources that color items by name can use this so that
+/// their colors agree with each other.
+pub fn name_color(name: &str) -> Color {
+    Hsl::probe(name.as_bytes(), 0).to_rgb()
+}
+
+/// Color for a numeric ID, as for name_color.
+pub fn id_color(id: u64) -> Color {
+    Hsl::probe(&id.to_le_bytes(), 0).to_rgb()
+}
+
+/// Assigns colors to names like name_color, except that a name whose color
+/// looks like one already assigned gets another one instead (as far as
+/// possible). Names that don't clash keep the color from name_color, and
+/// the same names assigned in the same order always get the same colors.
+#[derive(Debug, Clone, Default)]
+pub struct ColorAssigner {
+    assigned: BTreeMap<String, Color>,
+    used: Vec<Hsl>,
+}
+
+impl ColorAssigner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn color(&mut self, name: &str) -> Color {
+        if let Some(color) = self.assigned.get(name) {
+            return *color;
+        }
+        let mut best = Hsl::probe(name.as_bytes(), 0);
+        let mut best_distance = self.distance(best);
+        for attempt in 1..MAX_ATTEMPTS {
+            if best_distance >= MIN_DISTANCE {
+                break;
+            }
+            let hsl = Hsl::probe(name.as_bytes(), attempt);
+            let distance = self.distance(hsl);
+            if distance > best_distance {
+                best = hsl;
+                best_distance = distance;
+            }
+        }
+        self.used.push(best);
+        let color = best.to_rgb();
+        self.assigned.insert(name.to_owned(), color);
+        color
+    }
+
+    // To the closest color assigned so far
+    fn distance(&self, hsl: Hsl) -> f32 {
+        self.used
+            .iter()
+            .map(|used| used.distance(hsl))
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Hsl {
+    hue: f32, // in turns, 0 to 1
+    saturation: f32,
+    lightness: f32,
+}
+
+impl Hsl {
+    fn probe(bytes: &[u8], attempt: u64) -> Self {
+        let hash = fnv1a(bytes, attempt);
+        let pick = |values: &[f32], bits: u64| values[(bits % values.len() as u64) as usize];
+        Self {
+            hue: (hash & 0xffff) as f32 / 65536.0,
+            saturation: pick(SATURATIONS, hash >> 16),
+            lightness: pick(LIGHTNESSES, hash >> 32),
+        }
+    }
+
+    // Roughly how different two colors look: mostly by hue, less so by
+    // lightness, and least by saturation. Each is scaled to 0 to 1.
+    fn distance(self, other: Hsl) -> f32 {
+        let hue = (self.hue - other.hue).abs();
+        let hue = hue.min(1.0 - hue) * 2.0;
+        let lightness = (self.lightness - other.lightness).abs() / 0.2;
+        let saturation = (self.saturation - other.saturation).abs() / 0.3;
+        (hue * hue + 0.25 * lightness * lightness + 0.0625 * saturation * saturation).sqrt()
+    }
+
+    fn to_rgb(self) -> Color {
+        let c = (1.0 - (2.0 * self.lightness - 1.0).abs()) * self.saturation;
+        let h = self.hue * 6.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let m = self.lightness - c / 2.0;
+        let channel = |v: f32| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Color::from_rgb(channel(r), channel(g), channel(b))
+    }
+}
+
+// FNV-1a, which (unlike std's DefaultHasher) is the same in every build, so
+// colors don't change from one version of the viewer to the next
+fn fnv1a(bytes: &[u8], salt: u64) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = OFFSET;
+    for byte in salt.to_le_bytes().iter().chain(bytes) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
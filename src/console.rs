@@ -0,0 +1,95 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// A log record kept for the in-app console (Debug > Log Console).
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub level: Level,
+    pub target: String,
+    pub spans: String, // names of the enclosing spans, outermost first
+    pub message: String,
+}
+
+// Oldest records are dropped past this
+const CAPACITY: usize = 1000;
+
+static RECORDS: Mutex<Vec<Record>> = Mutex::new(Vec::new());
+
+/// The most recent records, oldest first.
+pub fn records() -> Vec<Record> {
+    RECORDS.lock().unwrap().clone()
+}
+
+pub fn clear() {
+    RECORDS.lock().unwrap().clear();
+}
+
+/// Keeps the events it sees for the console. Add it to the subscriber
+/// (with a filter, if need be) alongside whatever else is logging.
+pub struct ConsoleLayer;
+
+impl<S> Layer<S> for ConsoleLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, cx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let spans = cx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| span.name())
+                    .collect::<Vec<_>>()
+                    .join(":")
+            })
+            .unwrap_or_default();
+        let record = Record {
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_owned(),
+            spans,
+            message: visitor.message,
+        };
+
+        let mut records = RECORDS.lock().unwrap();
+        if records.len() >= CAPACITY {
+            let excess = records.len() + 1 - CAPACITY;
+            records.drain(..excess);
+        }
+        records.push(record);
+    }
+}
+
+// Formats the message, followed by any other fields as name=value
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl MessageVisitor {
+    fn add(&mut self, field: &Field, value: fmt::Arguments<'_>) {
+        use std::fmt::Write;
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.message);
+            let _ = write!(self.message, "{}{}", value, fields);
+        } else {
+            let _ = write!(self.message, " {}={}", field.name(), value);
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.add(field, format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.add(field, format_args!("{:?}", value));
+    }
+}
@@ -1,13 +1,14 @@
-pub use egui::Color32;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::{fmt, io};
 
 use crate::timestamp::{Interval, Timestamp};
 
 // We encode EntryID as i64 because it allows us to pack Summary into the
 // value -1. Users shouldn't need to know about this and interact through the
 // methods below, or via EntryIndex.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 pub struct EntryID(Vec<i64>);
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -16,6 +17,12 @@ pub enum EntryIndex {
     Slot(u64),
 }
 
+// An sRGB color with premultiplied alpha. This is the same as egui's
+// Color32 (including how it's serialized), so that data sources can be
+// built without the GUI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+pub struct Color(pub [u8; 4]);
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum EntryInfo {
     Panel {
@@ -30,16 +37,53 @@ pub enum EntryInfo {
         max_rows: u64,
     },
     Summary {
-        color: Color32,
+        color: Color,
+    },
+    // Utilization of several kinds (e.g., CPU, GPU) stacked on top of each
+    // other in one row. Tiles carry a series for each layer (matched by
+    // name), holding that kind's share of the total utilization.
+    Aggregate {
+        layers: Vec<(String, Color)>, // bottom to top
+    },
+    // A track of arbitrary values over time (e.g., bytes in use, queue
+    // depth), fetched with fetch_counter_tile
+    Counter {
+        short_name: String,
+        long_name: String,
+        unit: String, // e.g., "B" (gets binary prefixes), "B/s", "" for a plain count
+        color: Color,
+        style: CounterStyle,
+    },
+    // Instantaneous events (e.g., barriers, checkpoints), fetched with
+    // fetch_event_tile
+    Events {
+        short_name: String,
+        long_name: String,
+        color: Color,
+        global: bool, // also draw each event as a line across all slots
     },
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CounterStyle {
+    Line,
+    Bar,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
 pub struct UtilPoint {
     pub time: Timestamp,
     pub util: f32,
 }
 
+// The value holds until the next point (or the end of the tile)
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default, Deserialize, Serialize)]
+pub struct CounterPoint {
+    pub time: Timestamp,
+    pub value: f64,
+}
+
+// New variants go at the end, so that existing archives still decode
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Field {
     I64(i64),
@@ -47,14 +91,64 @@ pub enum Field {
     String(String),
     Interval(Interval),
     Empty,
+    F64(f64),
+    Bytes(u64),
+    Percent(f64), // a fraction, shown as a percentage
+    Link { label: String, url: String },
+}
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
+pub struct ItemUID(pub u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ItemState {
+    Running,
+    Waiting,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct ItemSegment {
+    pub interval: Interval,
+    pub state: ItemState,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Item {
+    // Older servers don't send one, so their items all share the default
+    #[serde(default)]
+    pub item_uid: ItemUID,
     pub interval: Interval,
-    pub color: Color32,
+    pub color: Color,
     pub title: String,
     pub fields: Vec<(String, Field)>,
+    // Parts of the item not spent running (if any). Anything not covered
+    // is assumed to be running.
+    #[serde(default)]
+    pub segments: Vec<ItemSegment>,
+    // Optional: for copies (items in channels), the memories they move
+    // data between
+    #[serde(default)]
+    pub endpoints: Option<CopyEndpoints>,
+    // Optional: for instances (items in memories), where they live
+    #[serde(default)]
+    pub allocation: Option<Allocation>,
+}
+
+// In bytes, from the start of the memory
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct Allocation {
+    pub offset: u64,
+    pub size: u64,
+}
+
+// Memories are slots, as EntryIDs. Either may be unknown, e.g., fills
+// have no source.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CopyEndpoints {
+    pub source: Option<EntryID>,
+    pub target: Option<EntryID>,
 }
 
 #[derive(Debug, Copy, Clone, Deserialize, Serialize)]
@@ -63,21 +157,440 @@ pub struct TileID(pub Interval);
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SummaryTile {
     pub tile_id: TileID,
+    pub utilization: Vec<UtilPoint>, // average
+    // Optional: additional named curves. Series named UtilSeries::MIN and
+    // UtilSeries::MAX are drawn as a shaded band around the average.
+    #[serde(default)]
+    pub series: Vec<UtilSeries>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CounterTile {
+    pub tile_id: TileID,
+    pub points: Vec<CounterPoint>, // sorted, starting with the value at the tile start
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Event {
+    pub time: Timestamp,
+    pub title: String,
+    pub fields: Vec<(String, Field)>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EventTile {
+    pub tile_id: TileID,
+    pub events: Vec<Event>, // sorted by time
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct UtilSeries {
+    pub name: String,
     pub utilization: Vec<UtilPoint>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SlotTile {
     pub tile_id: TileID,
-    pub items: Vec<Vec<Item>>, // row -> [item]
+    pub items: Vec<Vec<Item>>, // row -> [item], sorted by start within a row (they may overlap)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ItemLinkKind {
+    Predecessor, // the linked item must run before this one
+    Successor,   // the linked item runs after this one
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ItemLink {
+    pub kind: ItemLinkKind,
+    pub item_uid: ItemUID,
+    pub entry_id: EntryID,
+    pub row: u64,
+    pub interval: Interval,
+    pub title: String,
+}
+
+// What a profile is of, for the viewer to show alongside it. Everything is
+// optional, since sources only know what their format records.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Description {
+    pub name: Option<String>, // of the application, used as the window title
+    pub machine: Option<String>,
+    pub nodes: Option<u64>,
+    pub legion_version: Option<String>,
+    pub command_line: Option<String>,
+    pub fields: Vec<(String, Field)>, // anything else worth knowing
+}
+
+// Something wrong with the profile that isn't worth failing over (e.g.,
+// missing logs for a node, or clock skew), for the viewer to point out
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Warning {
+    pub message: String,
+    pub entry_id: Option<EntryID>, // what it's about, if anything in particular
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchResult {
+    pub entry_id: EntryID,
+    pub title: String,
+    pub interval: Interval,
+}
+
+// Something went wrong fetching data (e.g., I/O or network failure, or
+// malformed profile data). Failures are shown in the UI rather than
+// bringing the viewer down.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DataSourceError {
+    pub message: String,
+}
+
+pub type DataSourceResult<T> = Result<T, DataSourceError>;
+
+// Counters a data source may keep about what it has fetched, for the
+// viewer's statistics overlay (e.g., to see why a profile is slow)
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FetchStats {
+    pub requests: u64,     // reads of the underlying file or server
+    pub bytes: u64,        // read or downloaded by those requests
+    pub cache_hits: u64,   // requests answered from a cache
+    pub cache_misses: u64, // requests a cache couldn't answer
+    pub in_flight: u64,    // requests started but not yet answered
 }
 
 pub trait DataSource {
-    fn interval(&mut self) -> Interval;
-    fn fetch_info(&mut self) -> &EntryInfo;
-    fn request_tiles(&mut self, entry_id: &EntryID, request_interval: Interval) -> Vec<TileID>;
-    fn fetch_summary_tile(&mut self, entry_id: &EntryID, tile_id: TileID) -> SummaryTile;
-    fn fetch_slot_tile(&mut self, entry_id: &EntryID, tile_id: TileID) -> SlotTile;
+    fn interval(&mut self) -> DataSourceResult<Interval>;
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo>;
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>>;
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile>;
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile>;
+
+    // Optional: how busy a slot is over a tile, for drawing slots that
+    // aren't expanded. The default computes it from the slot tile (see
+    // SlotTile::density), which sources that can do so more cheaply (e.g.,
+    // without reading every item) should override.
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        Ok(self.fetch_slot_tile(entry_id, tile_id)?.density())
+    }
+
+    // Optional: search the entire profile for items whose title contains
+    // the query (case-insensitive). Sources that can't do this efficiently
+    // return nothing, and the app falls back to searching loaded tiles.
+    fn search(&mut self, _query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        Ok(Vec::new())
+    }
+
+    // Optional: dependencies of an item, in either direction
+    fn fetch_item_links(&mut self, _item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        Ok(Vec::new())
+    }
+
+    // Optional: the wall-clock time of t = 0 (in ns since the Unix epoch),
+    // so that the viewer can show when things happened, e.g., to line the
+    // profile up with logs
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        Ok(None)
+    }
+
+    // Optional: what the profile is of (application, machine, etc.)
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        Ok(Description::default())
+    }
+
+    // Optional: only needed for sources that have EntryInfo::Counter entries
+    fn fetch_counter_tile(
+        &mut self,
+        _entry_id: &EntryID,
+        _tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        Err(DataSourceError::new("counters are not supported"))
+    }
+
+    // Optional: only needed for sources that have EntryInfo::Events entries
+    fn fetch_event_tile(
+        &mut self,
+        _entry_id: &EntryID,
+        _tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        Err(DataSourceError::new("events are not supported"))
+    }
+
+    // Optional: whether fetching a tile returned by request_tiles would
+    // return without waiting. Sources that fetch in the background say no
+    // until the tile arrives, and the app draws what it has until then.
+    fn tile_ready(&mut self, _entry_id: &EntryID, _tile_id: TileID) -> bool {
+        true
+    }
+
+    // Optional: counters of what the source has fetched so far
+    fn fetch_stats(&mut self) -> FetchStats {
+        FetchStats::default()
+    }
+
+    // Optional: notification that tiles returned by request_tiles will not
+    // be fetched after all (e.g., because the view moved on). Sources that
+    // prefetch in the background can use this to drop the work.
+    fn cancel_tiles(&mut self, _entry_id: &EntryID, _tile_ids: &[TileID]) {}
+
+    // Optional: tiles that have changed since they were fetched (e.g.,
+    // because background post-processing refined the data), given as the
+    // entry and interval affected. An entry covers all its descendants.
+    // Polled every frame; anything on screen that overlaps is fetched again.
+    // Invalidating the root means the whole profile changed (e.g., it was
+    // regenerated), entries and interval included, so it's opened again.
+    fn poll_invalidated(&mut self) -> Vec<(EntryID, Interval)> {
+        Vec::new()
+    }
+
+    // Optional: warnings about the profile, as they come up. Polled every
+    // frame; each warning is returned once.
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        Vec::new()
+    }
+}
+
+// So that wrappers generic over a data source also take boxed ones
+impl<T: DataSource + ?Sized> DataSource for Box<T> {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        (**self).interval()
+    }
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        (**self).fetch_info()
+    }
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        (**self).request_tiles(entry_id, request_interval)
+    }
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        (**self).fetch_summary_tile(entry_id, tile_id)
+    }
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        (**self).fetch_slot_tile(entry_id, tile_id)
+    }
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        (**self).fetch_slot_density_tile(entry_id, tile_id)
+    }
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        (**self).search(query)
+    }
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        (**self).fetch_item_links(item_uid)
+    }
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        (**self).epoch()
+    }
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        (**self).fetch_description()
+    }
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        (**self).fetch_counter_tile(entry_id, tile_id)
+    }
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        (**self).fetch_event_tile(entry_id, tile_id)
+    }
+    fn tile_ready(&mut self, entry_id: &EntryID, tile_id: TileID) -> bool {
+        (**self).tile_ready(entry_id, tile_id)
+    }
+    fn fetch_stats(&mut self) -> FetchStats {
+        (**self).fetch_stats()
+    }
+    fn cancel_tiles(&mut self, entry_id: &EntryID, tile_ids: &[TileID]) {
+        (**self).cancel_tiles(entry_id, tile_ids)
+    }
+    fn poll_invalidated(&mut self) -> Vec<(EntryID, Interval)> {
+        (**self).poll_invalidated()
+    }
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        (**self).poll_warnings()
+    }
+}
+
+// For sources made of several others
+impl std::ops::AddAssign for FetchStats {
+    fn add_assign(&mut self, other: Self) {
+        self.requests += other.requests;
+        self.bytes += other.bytes;
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+        self.in_flight += other.in_flight;
+    }
+}
+
+impl UtilSeries {
+    pub const MIN: &'static str = "min";
+    pub const MAX: &'static str = "max";
+}
+
+impl SlotTile {
+    /// Bins in a density tile. Density is only drawn a few pixels high, so
+    /// there's no point in more detail than this.
+    pub const DENSITY_BINS: i64 = 64;
+
+    /// Items in a row of a tile that overlap the interval, in order. Items
+    /// in a row may overlap each other (e.g., in an over-packed row), so
+    /// they can only be searched by start time; the rest are checked.
+    pub fn items_in(row_items: &[Item], interval: Interval) -> impl Iterator<Item = &Item> {
+        let end = row_items.partition_point(|item| item.interval.start < interval.stop);
+        row_items[..end]
+            .iter()
+            .filter(move |item| item.interval.stop >= interval.start)
+    }
+
+    /// The fraction of each bin of the tile during which any of its items
+    /// are running, as a step function.
+    pub fn density(&self) -> SummaryTile {
+        let interval = self.tile_id.0;
+        let mut items: Vec<Interval> = self
+            .items
+            .iter()
+            .flatten()
+            .map(|item| item.interval.intersection(interval))
+            .filter(|i| i.duration_ns() > 0)
+            .collect();
+        items.sort_by_key(|i| i.start);
+
+        let duration = interval.duration_ns();
+        let bins = Self::DENSITY_BINS.min(duration).max(1);
+        // Start of the i-th bin (and which bin a time falls in)
+        let bin = |i: i64| {
+            Timestamp(interval.start.0 + (duration as i128 * i as i128 / bins as i128) as i64)
+        };
+        let bin_of = |t: Timestamp| {
+            ((t.0 - interval.start.0) as i128 * bins as i128 / duration.max(1) as i128) as i64
+        };
+        let mut busy = vec![0; bins as usize];
+        let mut covered = interval.start; // time up to here is already counted
+        for item in items {
+            let start = item.start.max(covered);
+            if start >= item.stop {
+                continue;
+            }
+            covered = item.stop;
+            for i in bin_of(start)..=bin_of(item.stop).min(bins - 1) {
+                let overlap =
+                    Interval::new(bin(i), bin(i + 1)).intersection(Interval::new(start, item.stop));
+                busy[i as usize] += overlap.duration_ns().max(0);
+            }
+        }
+
+        let mut utilization = Vec::new();
+        for (i, busy) in busy.into_iter().enumerate() {
+            let (start, stop) = (bin(i as i64), bin(i as i64 + 1));
+            let util = busy as f32 / (stop.0 - start.0).max(1) as f32;
+            utilization.push(UtilPoint { time: start, util });
+            utilization.push(UtilPoint { time: stop, util });
+        }
+        SummaryTile {
+            tile_id: self.tile_id,
+            utilization,
+            series: Vec::new(),
+        }
+    }
+}
+
+impl DataSourceError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DataSourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DataSourceError {}
+
+impl From<io::Error> for DataSourceError {
+    fn from(error: io::Error) -> Self {
+        Self::new(error.to_string())
+    }
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::from_rgba_premultiplied(0, 0, 0, 0);
+    pub const BLACK: Color = Color::from_rgb(0, 0, 0);
+    pub const GRAY: Color = Color::from_rgb(160, 160, 160);
+    pub const WHITE: Color = Color::from_rgb(255, 255, 255);
+    pub const RED: Color = Color::from_rgb(255, 0, 0);
+    pub const LIGHT_RED: Color = Color::from_rgb(255, 128, 128);
+    pub const YELLOW: Color = Color::from_rgb(255, 255, 0);
+    pub const KHAKI: Color = Color::from_rgb(240, 230, 140);
+    pub const DARK_GREEN: Color = Color::from_rgb(0, 0x64, 0);
+    pub const GREEN: Color = Color::from_rgb(0, 255, 0);
+    pub const DARK_BLUE: Color = Color::from_rgb(0, 0, 0x8B);
+    pub const BLUE: Color = Color::from_rgb(0, 0, 255);
+
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self([r, g, b, 255])
+    }
+
+    pub const fn from_rgba_premultiplied(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self([r, g, b, a])
+    }
+
+    pub const fn to_array(self) -> [u8; 4] {
+        self.0
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<Color> for egui::Color32 {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.0;
+        egui::Color32::from_rgba_premultiplied(r, g, b, a)
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<egui::Color32> for Color {
+    fn from(color: egui::Color32) -> Self {
+        Self(color.to_array())
+    }
 }
 
 impl EntryID {
@@ -99,6 +612,10 @@ impl EntryID {
         result
     }
 
+    pub fn is_ancestor_of(&self, other: &EntryID) -> bool {
+        other.0.len() > self.0.len() && other.0.starts_with(&self.0)
+    }
+
     pub fn level(&self) -> u64 {
         self.0.len() as u64
     }
@@ -132,6 +649,43 @@ impl EntryID {
     }
 }
 
+// Written as the indices separated by dots, with s for a summary, e.g.,
+// 0.1.3 or 2.s (the root is the empty string)
+impl fmt::Display for EntryID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (level, index) in self.0.iter().enumerate() {
+            if level > 0 {
+                write!(f, ".")?;
+            }
+            if *index < 0 {
+                write!(f, "s")?;
+            } else {
+                write!(f, "{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EntryID {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut result = Self::root();
+        for index in s.split('.').filter(|_| !s.is_empty()) {
+            result = if index == "s" {
+                result.summary()
+            } else {
+                let index = index
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid entry '{}'", s))?;
+                result.child(index)
+            };
+        }
+        Ok(result)
+    }
+}
+
 impl EntryInfo {
     pub fn get(&self, entry_id: &EntryID) -> Option<&EntryInfo> {
         let mut result = self;
@@ -149,6 +703,30 @@ impl EntryInfo {
         Some(result)
     }
 
+    /// Every entry that has tiles of its own (i.e., everything but panels),
+    /// in depth-first order.
+    pub fn data_entries(&self) -> Vec<(EntryID, &EntryInfo)> {
+        fn visit<'a>(
+            info: &'a EntryInfo,
+            entry_id: EntryID,
+            result: &mut Vec<(EntryID, &'a EntryInfo)>,
+        ) {
+            if let EntryInfo::Panel { summary, slots, .. } = info {
+                if let Some(summary) = summary {
+                    result.push((entry_id.summary(), summary));
+                }
+                for (i, slot) in slots.iter().enumerate() {
+                    visit(slot, entry_id.child(i as u64), result);
+                }
+            } else {
+                result.push((entry_id, info));
+            }
+        }
+        let mut result = Vec::new();
+        visit(self, EntryID::root(), &mut result);
+        result
+    }
+
     pub fn nodes(&self) -> u64 {
         if let EntryInfo::Panel { slots, .. } = self {
             slots.len() as u64
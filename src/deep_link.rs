@@ -0,0 +1,102 @@
+use std::fmt::Write;
+
+use crate::data::EntryID;
+use crate::timestamp::{Interval, Timestamp};
+
+/// View state that can be shared as the fragment of a URL, e.g.,
+/// `#url=https%3A%2F%2Fexample.com%2Fprof&view=0..1000&toggled=0,0.1`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeepLink {
+    pub url: Option<String>, // where the profile was loaded from
+    pub view: Option<Interval>,
+
+    // Entries whose expansion state differs from the default
+    pub toggled: Vec<EntryID>,
+}
+
+impl DeepLink {
+    pub fn to_fragment(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(url) = &self.url {
+            parts.push(format!("url={}", encode(url)));
+        }
+        if let Some(view) = self.view {
+            parts.push(format!("view={}..{}", view.start.0, view.stop.0));
+        }
+        if !self.toggled.is_empty() {
+            let toggled: Vec<_> = self.toggled.iter().map(EntryID::to_string).collect();
+            parts.push(format!("toggled={}", toggled.join(",")));
+        }
+        parts.join("&")
+    }
+
+    /// Parses a fragment (with or without the leading `#`). Unknown keys
+    /// are ignored, so links keep working if more state is added later.
+    pub fn parse(fragment: &str) -> Result<Self, String> {
+        let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+        let mut result = Self::default();
+        for part in fragment.split('&').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').unwrap_or((part, ""));
+            match key {
+                "url" => result.url = Some(decode(value)?),
+                "view" => {
+                    let (start, stop) = value
+                        .split_once("..")
+                        .ok_or_else(|| format!("expected start..stop, got {:?}", value))?;
+                    let parse = |s: &str| {
+                        s.parse::<i64>()
+                            .map(Timestamp)
+                            .map_err(|_| format!("invalid timestamp {:?}", s))
+                    };
+                    let view = Interval::new(parse(start)?, parse(stop)?);
+                    if view.duration_ns() <= 0 {
+                        return Err(format!("empty view {:?}", value));
+                    }
+                    result.view = Some(view);
+                }
+                "toggled" => {
+                    result.toggled = value
+                        .split(',')
+                        .filter(|s| !s.is_empty())
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+}
+
+// Percent-encodes everything but the characters that are always safe
+pub(crate) fn encode(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            result.push(byte as char);
+        } else {
+            write!(result, "%{:02X}", byte).unwrap();
+        }
+    }
+    result
+}
+
+pub(crate) fn decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid escape in {:?}", text))?;
+            result.push(byte);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(result).map_err(|_| format!("invalid UTF-8 in {:?}", text))
+}
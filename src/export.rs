@@ -0,0 +1,49 @@
+use std::io;
+
+/// Saves a file on behalf of the user. On native this writes to the
+/// current directory; on the web it triggers a browser download. Returns a
+/// description of where the file went.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(file_name: &str, _mime_type: &str, bytes: &[u8]) -> io::Result<String> {
+    std::fs::write(file_name, bytes)?;
+    let path = std::env::current_dir()?.join(file_name);
+    Ok(path.display().to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(file_name: &str, mime_type: &str, bytes: &[u8]) -> io::Result<String> {
+    use wasm_bindgen::JsCast;
+
+    let js_error =
+        |e: wasm_bindgen::JsValue| io::Error::new(io::ErrorKind::Other, format!("{:?}", e));
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_(mime_type);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+    // The standard trick: click on an invisible link to the blob
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no document"))?;
+    let anchor = document
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|e| js_error(e.into()))?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)?;
+    Ok(format!("download of {}", file_name))
+}
+
+/// Reads back a file written by save_file, from the current directory.
+/// There is no equivalent on the web.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_file(file_name: &str) -> io::Result<Vec<u8>> {
+    std::fs::read(file_name)
+}
@@ -0,0 +1,452 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::data::{EntryID, Field, Item};
+use crate::node_set::NodeSet;
+use crate::timestamp::{TimeFormat, Timestamp};
+
+/// What part of an item a filter looks at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subject {
+    Name,
+    Duration,
+    Field(String), // by field name, e.g., "Provenance"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Contains,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Duration(i64), // nanoseconds
+    Number(f64),
+    Text(String),
+}
+
+/// A predicate over items. Items that fail any active filter are either
+/// dimmed or hidden in the timeline (see `FilterMode`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub subject: Subject,
+    pub op: Op,
+    pub value: Value,
+}
+
+/// A combination of filters, written in a small query language. For example:
+///
+///   * `fill` is any item with "fill" in its name
+///   * `name:fill AND dur>10us` also requires it to run for over 10 us
+///   * `node:3..7 (copy OR fill)` restricts that to nodes 3 through 7
+///   * `NOT Provenance:"foo.py"` excludes items with a matching field
+///
+/// Terms are `key op value`, where the key is `name`, `dur` (or
+/// `duration`), `node`, or else the name of a field, and the op is one of
+/// `: = != < <= > >=` (`:` meaning contains). A bare word matches names.
+/// Terms next to each other are combined with AND.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Filter(Filter),
+    Node(NodeSet),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    #[default]
+    Dim,
+    Hide,
+}
+
+impl Op {
+    pub const ALL: &'static [Op] = &[Op::Contains, Op::Eq, Op::Ne, Op::Lt, Op::Le, Op::Gt, Op::Ge];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Op::Contains => "contains",
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+
+    fn test(self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Op::Eq, Some(o)) => o == Ordering::Equal,
+            (Op::Ne, Some(o)) => o != Ordering::Equal,
+            (Op::Lt, Some(o)) => o == Ordering::Less,
+            (Op::Le, Some(o)) => o != Ordering::Greater,
+            (Op::Gt, Some(o)) => o == Ordering::Greater,
+            (Op::Ge, Some(o)) => o != Ordering::Less,
+            _ => false,
+        }
+    }
+}
+
+// Parses a duration with an optional unit suffix, e.g., "10us" or "1.5 ms".
+// Bare numbers are nanoseconds.
+pub fn parse_duration(text: &str) -> Option<i64> {
+    Timestamp::parse(text).ok().map(|time| time.0)
+}
+
+/// Whether the text contains `lower` (which is already lowercase), ignoring
+/// case. This is checked for every item in view on every frame, so ASCII
+/// text (i.e., most names) is compared without making a lowercase copy.
+pub fn contains_lowercase(text: &str, lower: &str) -> bool {
+    if !text.is_ascii() {
+        return text.to_lowercase().contains(lower);
+    }
+    let (text, lower) = (text.as_bytes(), lower.as_bytes());
+    lower.is_empty()
+        || text
+            .windows(lower.len())
+            .any(|w| w.eq_ignore_ascii_case(lower))
+}
+
+impl Filter {
+    pub fn new(subject: Subject, op: Op, value: &str) -> Result<Self, String> {
+        let value = match (&subject, op) {
+            (_, Op::Contains) => Value::Text(value.to_lowercase()),
+            (Subject::Name, _) => Value::Text(value.to_owned()),
+            (Subject::Duration, _) => Value::Duration(
+                parse_duration(value)
+                    .ok_or_else(|| format!("invalid duration '{}' (e.g., 10us)", value))?,
+            ),
+            (Subject::Field(_), _) => {
+                if let Ok(number) = value.trim().parse() {
+                    Value::Number(number)
+                } else if let Some(duration) = parse_duration(value) {
+                    Value::Duration(duration)
+                } else {
+                    Value::Text(value.to_owned())
+                }
+            }
+        };
+        if let Subject::Field(name) = &subject {
+            if name.is_empty() {
+                return Err("field name is empty".to_owned());
+            }
+        }
+        Ok(Self { subject, op, value })
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        match &self.subject {
+            Subject::Name => self.test_text(&item.title),
+            Subject::Duration => self.test_duration(item.interval.duration_ns()),
+            Subject::Field(name) => {
+                let field = item.fields.iter().find(|(n, _)| n == name);
+                match field.map(|(_, field)| field) {
+                    Some(Field::I64(value)) => self.test_number(*value as f64),
+                    Some(Field::U64(value)) => self.test_number(*value as f64),
+                    Some(Field::String(value)) => self.test_text(value),
+                    Some(Field::Interval(interval)) => self.test_duration(interval.duration_ns()),
+                    Some(Field::F64(value)) => self.test_number(*value),
+                    Some(Field::Bytes(value)) => self.test_number(*value as f64),
+                    // As shown, e.g., "Occupancy > 50" for over half
+                    Some(Field::Percent(value)) => self.test_number(value * 100.0),
+                    Some(Field::Link { label, .. }) => self.test_text(label),
+                    Some(Field::Empty) | None => false,
+                }
+            }
+        }
+    }
+
+    fn test_text(&self, text: &str) -> bool {
+        match (&self.value, self.op) {
+            (Value::Text(value), Op::Contains) => contains_lowercase(text, value),
+            (Value::Text(value), op) => op.test(Some(text.cmp(value))),
+            _ => false,
+        }
+    }
+
+    fn test_number(&self, number: f64) -> bool {
+        match &self.value {
+            Value::Number(value) => self.op.test(number.partial_cmp(value)),
+            Value::Duration(value) => self.op.test(number.partial_cmp(&(*value as f64))),
+            Value::Text(_) => self.test_text(&number.to_string()),
+        }
+    }
+
+    fn test_duration(&self, duration: i64) -> bool {
+        match &self.value {
+            Value::Duration(value) => self.op.test(Some(duration.cmp(value))),
+            Value::Number(value) => self.op.test((duration as f64).partial_cmp(value)),
+            Value::Text(_) => false,
+        }
+    }
+}
+
+impl Query {
+    pub fn matches(&self, entry_id: &EntryID, item: &Item) -> bool {
+        match self {
+            Query::Filter(filter) => filter.matches(item),
+            Query::Node(nodes) => entry_id.slot_index(0).map_or(false, |n| nodes.contains(n)),
+            Query::Not(query) => !query.matches(entry_id, item),
+            Query::And(queries) => queries.iter().all(|q| q.matches(entry_id, item)),
+            Query::Or(queries) => queries.iter().any(|q| q.matches(entry_id, item)),
+        }
+    }
+
+    // Text without any query syntax, which is best searched for verbatim
+    pub fn is_plain(text: &str) -> bool {
+        !text.contains([':', '=', '<', '>', '(', ')', '"'])
+            && !text
+                .split_whitespace()
+                .any(|word| matches!(word, "AND" | "OR" | "NOT"))
+    }
+}
+
+// Splits on whitespace and parentheses, keeping quoted text together
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if quoted => current.push(c),
+            '(' | ')' => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err("missing closing quote".to_owned());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+fn parse_term(token: &str) -> Result<Query, String> {
+    // Find the operator, if any (but not inside quotes)
+    let mut quoted = false;
+    let split = token.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        !quoted && matches!(c, ':' | '=' | '!' | '<' | '>')
+    });
+    let i = if let Some((i, _)) = split {
+        i
+    } else {
+        return Filter::new(Subject::Name, Op::Contains, unquote(token)).map(Query::Filter);
+    };
+
+    let (key, rest) = token.split_at(i);
+    let (op, value) = if let Some(value) = rest.strip_prefix(":") {
+        (None, value)
+    } else if let Some(op) = ["<=", ">=", "!=", "<", ">", "="]
+        .iter()
+        .find(|op| rest.starts_with(**op))
+    {
+        let op = Op::ALL.iter().find(|o| o.symbol() == *op).copied();
+        (op, &rest[op.map_or(0, |o| o.symbol().len())..])
+    } else {
+        return Err(format!("invalid operator in '{}'", token));
+    };
+    let (key, value) = (unquote(key), unquote(value));
+    if value.is_empty() {
+        return Err(format!("missing value for '{}'", key));
+    }
+
+    let subject = match key.to_lowercase().as_str() {
+        "name" | "title" => Subject::Name,
+        "dur" | "duration" => Subject::Duration,
+        "node" => {
+            if !matches!(op, None | Some(Op::Eq)) {
+                return Err("node only supports ':' or '='".to_owned());
+            }
+            return NodeSet::from_str(&value.replace("..", "-")).map(Query::Node);
+        }
+        _ => Subject::Field(key.to_owned()),
+    };
+    // Contains is the natural meaning of ':', except for durations
+    let op = op.unwrap_or(if subject == Subject::Duration {
+        Op::Eq
+    } else {
+        Op::Contains
+    });
+    Filter::new(subject, op, value).map(Query::Filter)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut queries = vec![self.and()?];
+        while self.eat("OR") {
+            queries.push(self.and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::Or(queries)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut queries = vec![self.unary()?];
+        loop {
+            if self.eat("AND") || !matches!(self.peek(), None | Some("OR") | Some(")")) {
+                queries.push(self.unary()?);
+            } else {
+                break;
+            }
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::And(queries)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, String> {
+        if self.eat("NOT") {
+            return Ok(Query::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let query = self.or()?;
+            if !self.eat(")") {
+                return Err("missing ')'".to_owned());
+            }
+            return Ok(query);
+        }
+        match self.peek() {
+            None => Err("query ends unexpectedly".to_owned()),
+            Some(token @ ("AND" | "OR" | ")")) => Err(format!("unexpected '{}'", token)),
+            Some(token) => {
+                let query = parse_term(token)?;
+                self.pos += 1;
+                Ok(query)
+            }
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Err("query is empty".to_owned());
+        }
+        let query = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected '{}'", token));
+        }
+        Ok(query)
+    }
+}
+
+// Quote anything that wouldn't come back as a single token
+fn quote(text: &str) -> String {
+    if text.is_empty() || text.contains(|c: char| c.is_whitespace() || "():=!<>\"".contains(c)) {
+        format!("\"{}\"", text)
+    } else {
+        text.to_owned()
+    }
+}
+
+// Filters print in query syntax, so they can be edited and parsed back
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.subject {
+            Subject::Name => write!(f, "name")?,
+            Subject::Duration => write!(f, "dur")?,
+            Subject::Field(name) => write!(f, "{}", quote(name))?,
+        }
+        match self.op {
+            Op::Contains => write!(f, ":")?,
+            op => write!(f, "{}", op.symbol())?,
+        }
+        match &self.value {
+            // In a format that parses back, whatever the user's settings
+            Value::Duration(ns) => {
+                let text = Timestamp(*ns).display(TimeFormat::default()).to_string();
+                write!(f, "{}", text.replace(' ', ""))
+            }
+            Value::Number(number) => write!(f, "{}", number),
+            Value::Text(text) => write!(f, "{}", quote(text)),
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, queries: &[Query], sep: &str| {
+            for (i, query) in queries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", sep)?;
+                }
+                match query {
+                    Query::And(_) | Query::Or(_) => write!(f, "({})", query)?,
+                    _ => write!(f, "{}", query)?,
+                }
+            }
+            Ok(())
+        };
+        match self {
+            Query::Filter(filter) => write!(f, "{}", filter),
+            Query::Node(nodes) => write!(f, "node:{}", nodes),
+            Query::Not(query) => match **query {
+                Query::And(_) | Query::Or(_) => write!(f, "NOT ({})", query),
+                _ => write!(f, "NOT {}", query),
+            },
+            Query::And(queries) => join(f, queries, "AND"),
+            Query::Or(queries) => join(f, queries, "OR"),
+        }
+    }
+}
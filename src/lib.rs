@@ -1,5 +1,44 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod analysis;
+#[cfg(feature = "client")]
 pub mod app;
+#[cfg(feature = "data")]
+pub mod archive;
+#[cfg(feature = "data")]
+pub mod chrome_trace;
+pub mod colors;
+#[cfg(feature = "client")]
+pub mod console;
 pub mod data;
+pub mod deep_link;
+pub mod export;
+pub mod filter;
+#[cfg(feature = "data")]
+pub mod loader;
+pub mod node_set;
+#[cfg(all(feature = "data", not(target_arch = "wasm32")))]
+pub mod parallel;
+#[cfg(feature = "data")]
+pub mod record;
+#[cfg(feature = "client")]
+pub mod remote;
+#[cfg(feature = "client")]
+pub mod render;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod server;
+#[cfg(all(feature = "client", not(target_arch = "wasm32")))]
+pub mod settings;
+#[cfg(feature = "client")]
+pub mod svg;
+#[cfg(feature = "data")]
+pub mod synthetic;
+#[cfg(feature = "client")]
+pub mod theme;
 pub mod timestamp;
+#[cfg(feature = "data")]
+pub mod validate;
+#[cfg(all(feature = "data", not(target_arch = "wasm32")))]
+pub mod watch;
+#[cfg(any(feature = "client", feature = "server"))]
+pub mod wire;
@@ -0,0 +1,95 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+use crate::archive::{self, ArchiveDataSource};
+use crate::chrome_trace::ChromeTraceDataSource;
+use crate::data::{DataSource, DataSourceError, DataSourceResult};
+use crate::record::ReplayDataSource;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::watch::WatchedDataSource;
+
+// Formats that profiles can be read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Archive, // made by archive::write_archive
+    ChromeTrace,
+    Recording, // made by RecordingDataSource
+}
+
+impl Format {
+    // By the contents if possible, otherwise by the name
+    fn detect(name: &str, bytes: &[u8]) -> Option<Self> {
+        const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+        if bytes.starts_with(archive::MAGIC) {
+            return Some(Format::Archive);
+        }
+        // Also JSON, so check before Chrome traces
+        if bytes.starts_with(b"{\"call\":") {
+            return Some(Format::Recording);
+        }
+        let text = bytes.iter().find(|b| !b.is_ascii_whitespace());
+        if matches!(text, Some(b'{' | b'[')) || bytes.starts_with(&GZIP_MAGIC) {
+            return Some(Format::ChromeTrace);
+        }
+        if name.ends_with(".jsonl") {
+            return Some(Format::Recording);
+        }
+        if name.ends_with(".json") || name.ends_with(".json.gz") {
+            return Some(Format::ChromeTrace);
+        }
+        if name.ends_with(".lpv") {
+            return Some(Format::Archive);
+        }
+        None
+    }
+}
+
+/// Opens a profile stored in a file: a Chrome trace (.json or .json.gz),
+/// an archive (.lpv), or a recording of another data source (.jsonl).
+pub fn open_file(path: &Path) -> DataSourceResult<Box<dyn DataSource>> {
+    let name = path.display().to_string();
+    let error = |e: &dyn std::fmt::Display| DataSourceError::new(format!("{}: {}", name, e));
+
+    // Archives can be larger than memory, so only they're read as needed
+    let mut file = File::open(path).map_err(|e| error(&e))?;
+    let mut magic = Vec::new();
+    (&mut file)
+        .take(archive::MAGIC.len() as u64)
+        .read_to_end(&mut magic)
+        .map_err(|e| error(&e))?;
+    if Format::detect(&name, &magic) == Some(Format::Archive) {
+        let source = ArchiveDataSource::from_reader(file).map_err(|e| error(&e))?;
+        return Ok(Box::new(source));
+    }
+
+    let bytes = std::fs::read(path).map_err(|e| error(&e))?;
+    open_bytes(&name, &bytes)
+}
+
+/// Like open_file, but opens the file again whenever it changes (e.g.,
+/// because the application was run again and regenerated its profile).
+#[cfg(not(target_arch = "wasm32"))]
+pub fn watch_file(path: &Path) -> DataSourceResult<Box<dyn DataSource>> {
+    let source = open_file(path)?;
+    let reopen = path.to_owned();
+    let open = Arc::new(move || open_file(&reopen));
+    Ok(Box::new(WatchedDataSource::new(source, path, open)?))
+}
+
+/// Opens a profile from the contents of a file (e.g., one dropped onto the
+/// viewer in a browser). The name is only used to help pick the format.
+pub fn open_bytes(name: &str, bytes: &[u8]) -> DataSourceResult<Box<dyn DataSource>> {
+    let error = |e: &dyn std::fmt::Display| DataSourceError::new(format!("{}: {}", name, e));
+    match Format::detect(name, bytes) {
+        Some(Format::ChromeTrace) => {
+            let source = ChromeTraceDataSource::from_slice(bytes).map_err(|e| error(&e))?;
+            Ok(Box::new(source))
+        }
+        Some(Format::Archive) => Ok(Box::new(ArchiveDataSource::new(bytes.to_vec())?)),
+        Some(Format::Recording) => Ok(Box::new(ReplayDataSource::new(bytes)?)),
+        None => Err(error(&"not a recognized profile format")),
+    }
+}
@@ -1,273 +1,491 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use egui::{Color32, NumExt};
-use rand::Rng;
-use std::collections::BTreeMap;
+use legion_prof_viewer::app::StartOptions;
+use legion_prof_viewer::synthetic::{RandomDataSource, RandomOptions};
 
-use legion_prof_viewer::data::{
-    DataSource, EntryID, EntryInfo, Field, Item, SlotTile, SummaryTile, TileID, UtilPoint,
-};
-use legion_prof_viewer::timestamp::{Interval, Timestamp};
+#[cfg(not(target_arch = "wasm32"))]
+mod cli {
+    use clap::{Args, Parser, Subcommand, ValueEnum};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde::Serialize;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
 
-fn main() {
-    legion_prof_viewer::app::start(
-        Box::<RandomDataSource>::default(),
-        Some(Box::<RandomDataSource>::default()),
-    );
-}
+    use legion_prof_viewer::app::Headless;
+    use legion_prof_viewer::archive::write_archive;
+    use legion_prof_viewer::chrome_trace::write_chrome_trace;
+    use legion_prof_viewer::data::{
+        DataSource, DataSourceError, DataSourceResult, EntryInfo, TileID,
+    };
+    use legion_prof_viewer::loader;
+    use legion_prof_viewer::node_set::NodeSet;
+    use legion_prof_viewer::parallel::{Open, ParallelDataSource};
+    use legion_prof_viewer::record::RecordingDataSource;
+    use legion_prof_viewer::remote::RemoteDataSource;
+    use legion_prof_viewer::server::ServeOptions;
+    use legion_prof_viewer::settings::Settings;
+    use legion_prof_viewer::timestamp::Interval;
+    use legion_prof_viewer::validate::ValidatingDataSource;
+    use legion_prof_viewer::watch::WatchedDataSource;
 
-#[derive(Default)]
-struct RandomDataSource {
-    info: Option<EntryInfo>,
-    interval: Option<Interval>,
-    summary_cache: BTreeMap<EntryID, Vec<UtilPoint>>,
-    slot_cache: BTreeMap<EntryID, Vec<Vec<Item>>>,
-    rng: rand::rngs::ThreadRng,
-}
+    use super::StartOptions;
 
-impl RandomDataSource {
-    fn generate_point(
-        &mut self,
-        first: UtilPoint,
-        last: UtilPoint,
-        level: i32,
-        max_level: i32,
-        utilization: &mut Vec<UtilPoint>,
-    ) {
-        let time = Timestamp((first.time.0 + last.time.0) / 2);
-        let util = (first.util + last.util) * 0.5;
-        let diff = (self.rng.gen::<f32>() - 0.5) / 1.2_f32.powi(max_level - level);
-        let util = (util + diff).at_least(0.0).at_most(1.0);
-        let point = UtilPoint { time, util };
-        if level > 0 {
-            self.generate_point(first, point, level - 1, max_level, utilization);
-        }
-        utilization.push(point);
-        if level > 0 {
-            self.generate_point(point, last, level - 1, max_level, utilization);
-        }
+    /// Interactive viewer for Legion profiles. Without a profile (here or
+    /// in the configuration file), shows randomly generated data.
+    #[derive(Parser)]
+    #[command(version, args_conflicts_with_subcommands = true)]
+    pub struct Cli {
+        #[command(subcommand)]
+        pub command: Option<Command>,
+
+        /// Configuration file to read defaults from, rather than
+        /// legion-prof-viewer/config.toml in the user's config directory
+        #[arg(long, global = true)]
+        pub config: Option<PathBuf>,
+
+        #[command(flatten)]
+        pub view: ViewArgs,
     }
 
-    fn generate_summary(&mut self, entry_id: &EntryID) -> &Vec<UtilPoint> {
-        if !self.summary_cache.contains_key(entry_id) {
-            const LEVELS: i32 = 8;
-            let first = UtilPoint {
-                time: self.interval().start,
-                util: self.rng.gen(),
-            };
-            let last = UtilPoint {
-                time: self.interval().stop,
-                util: self.rng.gen(),
-            };
-            let mut utilization = Vec::new();
-            utilization.push(first);
-            self.generate_point(first, last, LEVELS, LEVELS, &mut utilization);
-            utilization.push(last);
+    #[derive(Subcommand)]
+    pub enum Command {
+        /// Serve a profile over HTTP, so it can be opened by a viewer elsewhere
+        Serve(ServeArgs),
+        /// Print the entries, interval and tiles of a profile, e.g., to
+        /// debug a data source
+        Dump(DumpArgs),
+        /// Convert a profile to another format
+        Convert(ConvertArgs),
+        /// Draw the timeline of a profile to a PNG, without opening a window
+        Render(RenderArgs),
+    }
 
-            self.summary_cache.insert(entry_id.clone(), utilization);
-        }
-        self.summary_cache.get(entry_id).unwrap()
+    #[derive(Args)]
+    pub struct ViewArgs {
+        /// Profile to open: a Chrome trace (.json or .json.gz), an archive
+        /// (.lpv), or the URL of a profile being served
+        pub profile: Option<String>,
+
+        /// Initial view, e.g., 10ms..25ms (bare numbers are nanoseconds)
+        #[arg(long, value_parser = parse_interval)]
+        pub interval: Option<Interval>,
+
+        /// Nodes to show, e.g., 0-3,17 or 0-15:4
+        #[arg(long, value_parser = parse_node_set)]
+        pub nodes: Option<NodeSet>,
+
+        /// Window size in pixels, e.g., 1600x900
+        #[arg(long, value_parser = parse_size)]
+        pub window_size: Option<(f32, f32)>,
+
+        /// Record every request made of the profile to this file (.jsonl),
+        /// which can be opened later in place of the profile
+        #[arg(long)]
+        pub record: Option<PathBuf>,
+
+        /// Check everything read from the profile for mistakes (e.g., while
+        /// writing a new data source), showing them as errors
+        #[arg(long)]
+        pub validate: bool,
+
+        /// Fetch tiles of served profiles and archives on this many threads
+        #[arg(long, default_value = "8")]
+        pub threads: usize,
     }
 
-    fn generate_slot(&mut self, entry_id: &EntryID) -> &Vec<Vec<Item>> {
-        if !self.slot_cache.contains_key(entry_id) {
-            let entry = self.fetch_info().get(entry_id);
+    #[derive(Args)]
+    pub struct ServeArgs {
+        /// Profile to serve (same as for viewing)
+        pub profile: String,
 
-            let max_rows = if let EntryInfo::Slot { max_rows, .. } = entry.unwrap() {
-                max_rows
-            } else {
-                panic!("trying to fetch tile on something that is not a slot")
-            };
+        /// Address to listen on. Use 0.0.0.0 to accept remote connections.
+        #[arg(long, default_value = "127.0.0.1")]
+        pub address: String,
 
-            let mut items = Vec::new();
-            for row in 0..*max_rows {
-                let mut row_items = Vec::new();
-                const N: u64 = 1000;
-                for i in 0..N {
-                    let start = self.interval().lerp((i as f32 + 0.05) / (N as f32));
-                    let stop = self.interval().lerp((i as f32 + 0.95) / (N as f32));
-
-                    let color = match (row * N + i) % 7 {
-                        0 => Color32::BLUE,
-                        1 => Color32::GREEN,
-                        2 => Color32::RED,
-                        3 => Color32::YELLOW,
-                        4 => Color32::KHAKI,
-                        5 => Color32::DARK_GREEN,
-                        6 => Color32::DARK_BLUE,
-                        _ => Color32::WHITE,
-                    };
-
-                    row_items.push(Item {
-                        interval: Interval::new(start, stop),
-                        color,
-                        title: "Test Item".to_owned(),
-                        fields: vec![(
-                            "Interval".to_owned(),
-                            Field::Interval(Interval::new(start, stop)),
-                        )],
-                    });
-                }
-                items.push(row_items);
-            }
+        #[arg(long, default_value = "8080")]
+        pub port: u16,
 
-            self.slot_cache.insert(entry_id.clone(), items);
-        }
-        self.slot_cache.get(entry_id).unwrap()
+        /// Allow web pages from this origin to make requests (e.g., * for
+        /// any), for a viewer running in a browser
+        #[arg(long)]
+        pub cors_origin: Option<String>,
+    }
+
+    #[derive(Args)]
+    pub struct DumpArgs {
+        /// Profile to dump (same as for viewing)
+        pub profile: String,
+
+        #[arg(long, value_enum, default_value = "json")]
+        pub format: Format,
+    }
+
+    #[derive(Args)]
+    pub struct ConvertArgs {
+        /// Profile to convert (same as for viewing)
+        pub input: String,
+
+        /// File to write. The format is picked by the extension: .json or
+        /// .json.gz for a Chrome trace, or .lpv for an archive (which keeps
+        /// everything the viewer shows, in one file).
+        pub output: PathBuf,
+    }
+
+    #[derive(Args)]
+    pub struct RenderArgs {
+        /// Profile to draw (same as for viewing)
+        pub profile: String,
+
+        /// PNG file to write
+        pub output: PathBuf,
+
+        /// Part of the profile to draw, e.g., 10ms..25ms
+        #[arg(long, value_parser = parse_interval)]
+        pub interval: Option<Interval>,
+
+        /// Image size in pixels
+        #[arg(long, value_parser = parse_size, default_value = "1600x900")]
+        pub size: (f32, f32),
+
+        /// Expand every panel and slot, rather than just the defaults
+        #[arg(long)]
+        pub expand_all: bool,
+    }
+
+    #[derive(Clone, Copy, ValueEnum)]
+    pub enum Format {
+        Json,
+        Yaml,
     }
-}
 
-impl DataSource for RandomDataSource {
-    fn interval(&mut self) -> Interval {
-        if let Some(interval) = self.interval {
-            return interval;
+    #[derive(Serialize)]
+    struct Dump {
+        interval: Interval,
+        info: EntryInfo,
+        tiles: Vec<TileInventory>,
+    }
+
+    // Tiles the source offers for an entry, over the whole profile
+    #[derive(Serialize)]
+    struct TileInventory {
+        entry_id: String,
+        tiles: Vec<TileID>,
+    }
+
+    impl ViewArgs {
+        // Wrap the data source as asked for on the command line
+        pub fn wrap(
+            &self,
+            mut data_source: Box<dyn DataSource>,
+            settings: &Settings,
+        ) -> Result<Box<dyn DataSource>, String> {
+            let cache_dir = settings.cache_dir();
+            if let Some(profile) = &self.profile {
+                data_source = parallel(data_source, profile, self.threads, cache_dir.clone());
+            }
+            // Open files again (with the same threads) when they change
+            if let Some(profile) = self.profile.clone().filter(|p| !is_url(p)) {
+                let threads = self.threads;
+                let path = PathBuf::from(&profile);
+                let open: Open = Arc::new(move || {
+                    let data_source = open_profile(&profile, cache_dir.as_deref())
+                        .map_err(DataSourceError::new)?;
+                    Ok(parallel(data_source, &profile, threads, cache_dir.clone()))
+                });
+                let watched = WatchedDataSource::new(data_source, &path, open);
+                data_source = Box::new(watched.map_err(|e| e.to_string())?);
+            }
+            if self.validate {
+                data_source = Box::new(ValidatingDataSource::new(data_source));
+            }
+            let path = match &self.record {
+                Some(path) => path,
+                None => return Ok(data_source),
+            };
+            let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            Ok(Box::new(RecordingDataSource::new(
+                data_source,
+                BufWriter::new(file),
+            )))
+        }
+
+        pub fn options(
+            &self,
+            settings: &Settings,
+            config_file: Option<&Path>,
+        ) -> Result<StartOptions, String> {
+            Ok(StartOptions {
+                view_interval: self.interval,
+                node_set: self.nodes.clone(),
+                window_size: self.window_size.map(|(w, h)| egui::Vec2::new(w, h)),
+                theme: settings.theme.clone(),
+                color_overrides: settings.color_overrides()?,
+                color_file: Settings::colors_path(),
+                profile: self.profile.clone(),
+                config_file: config_file.map(Path::to_owned),
+            })
         }
-        let interval = Interval::new(
-            Timestamp(0),
-            Timestamp(self.rng.gen_range(1_000_000..2_000_000)),
-        );
-        self.interval = Some(interval);
-        interval
     }
 
-    fn fetch_info(&mut self) -> &EntryInfo {
-        if let Some(ref info) = self.info {
-            return info;
+    impl ServeArgs {
+        pub fn options(&self) -> ServeOptions {
+            ServeOptions {
+                address: self.address.clone(),
+                port: self.port,
+                cors_origin: self.cors_origin.clone(),
+            }
         }
+    }
 
-        let kinds = vec![
-            "CPU".to_string(),
-            "GPU".to_string(),
-            "OMP".to_string(),
-            "Py".to_string(),
-            "Util".to_string(),
-            "Chan".to_string(),
-            "SysMem".to_string(),
-        ];
-
-        const NODES: i32 = 8192;
-        const PROCS: i32 = 8;
-        let mut node_slots = Vec::new();
-        for node in 0..NODES {
-            let mut kind_slots = Vec::new();
-            let colors = &[Color32::BLUE, Color32::GREEN, Color32::RED, Color32::YELLOW];
-            for (i, kind) in kinds.iter().enumerate() {
-                let color = colors[i % colors.len()];
-                let mut proc_slots = Vec::new();
-                for proc in 0..PROCS {
-                    let rows: u64 = self.rng.gen_range(0..64);
-                    proc_slots.push(EntryInfo::Slot {
-                        short_name: format!(
-                            "{}{}",
-                            kind.chars().next().unwrap().to_lowercase(),
-                            proc
-                        ),
-                        long_name: format!("Node {} {} {}", node, kind, proc),
-                        max_rows: rows,
-                    });
-                }
-                kind_slots.push(EntryInfo::Panel {
-                    short_name: kind.to_lowercase(),
-                    long_name: format!("Node {} {}", node, kind),
-                    summary: Some(Box::new(EntryInfo::Summary { color })),
-                    slots: proc_slots,
+    impl DumpArgs {
+        pub fn dump(&self, data_source: &mut dyn DataSource) -> DataSourceResult<String> {
+            let interval = data_source.interval()?;
+            let info = data_source.fetch_info()?.clone();
+            let mut tiles = Vec::new();
+            for (entry_id, _) in info.data_entries() {
+                tiles.push(TileInventory {
+                    entry_id: entry_id.to_string(),
+                    tiles: data_source.request_tiles(&entry_id, interval)?,
                 });
             }
-            node_slots.push(EntryInfo::Panel {
-                short_name: format!("n{}", node),
-                long_name: format!("Node {}", node),
-                summary: None,
-                slots: kind_slots,
-            });
+            let dump = Dump {
+                interval,
+                info,
+                tiles,
+            };
+            Ok(match self.format {
+                Format::Json => serde_json::to_string_pretty(&dump).unwrap(),
+                Format::Yaml => serde_yaml::to_string(&dump).unwrap(),
+            })
         }
-        self.info = Some(EntryInfo::Panel {
-            short_name: "root".to_owned(),
-            long_name: "root".to_owned(),
-            summary: None,
-            slots: node_slots,
-        });
-        self.info.as_ref().unwrap()
     }
 
-    fn request_tiles(&mut self, _entry_id: &EntryID, request_interval: Interval) -> Vec<TileID> {
-        let duration = request_interval.duration_ns();
-
-        const TILES: i64 = 3;
+    impl ConvertArgs {
+        pub fn convert(&self, data_source: &mut dyn DataSource) -> Result<(), String> {
+            let name = self.output.to_string_lossy();
+            let (archive, compress) = if name.ends_with(".lpv") {
+                (true, false)
+            } else if name.ends_with(".json.gz") {
+                (false, true)
+            } else if name.ends_with(".json") {
+                (false, false)
+            } else {
+                return Err(format!(
+                    "{}: unknown output format (expected .json, .json.gz or .lpv)",
+                    name
+                ));
+            };
 
-        let mut tiles = Vec::new();
-        for i in 0..TILES {
-            let start = Timestamp(i * duration / TILES + request_interval.start.0);
-            let stop = Timestamp((i + 1) * duration / TILES + request_interval.start.0);
-            tiles.push(TileID(Interval::new(start, stop)));
+            let error = |e: &dyn std::fmt::Display| format!("{}: {}", name, e);
+            let mut file = BufWriter::new(File::create(&self.output).map_err(|e| error(&e))?);
+            if archive {
+                write_archive(data_source, &mut file).map_err(|e| error(&e))?;
+            } else if compress {
+                let mut encoder = GzEncoder::new(&mut file, Compression::default());
+                write_chrome_trace(data_source, &mut encoder).map_err(|e| error(&e))?;
+                encoder.finish().map_err(|e| error(&e))?;
+            } else {
+                write_chrome_trace(data_source, &mut file).map_err(|e| error(&e))?;
+            }
+            file.flush().map_err(|e| error(&e))
         }
-        tiles
     }
 
-    fn fetch_summary_tile(&mut self, entry_id: &EntryID, tile_id: TileID) -> SummaryTile {
-        let utilization = self.generate_summary(entry_id);
-
-        let mut tile_utilization = Vec::new();
-        let mut last_point = None;
-        for point in utilization {
-            let UtilPoint { time, util } = *point;
-            if let Some(last_point) = last_point {
-                let UtilPoint {
-                    time: last_time,
-                    util: last_util,
-                } = last_point;
-
-                let last_interval = Interval::new(last_time, time);
-                if last_interval.contains(tile_id.0.start) {
-                    let relative = last_interval.unlerp(tile_id.0.start);
-                    let start_util = (last_util - util) * relative + last_util;
-                    tile_utilization.push(UtilPoint {
-                        time: tile_id.0.start,
-                        util: start_util,
-                    });
-                }
-                if tile_id.0.contains(time) {
-                    tile_utilization.push(*point);
-                }
-                if last_interval.contains(tile_id.0.stop) {
-                    let relative = last_interval.unlerp(tile_id.0.stop);
-                    let stop_util = (last_util - util) * relative + last_util;
-                    tile_utilization.push(UtilPoint {
-                        time: tile_id.0.stop,
-                        util: stop_util,
-                    });
-                }
+    impl RenderArgs {
+        pub fn render(&self, data_source: Box<dyn DataSource>) -> Result<(), String> {
+            let (width, height) = self.size;
+            let mut headless = Headless::new(data_source, egui::Vec2::new(width, height))
+                .map_err(|e| e.to_string())?;
+            if let Some(interval) = self.interval {
+                headless.set_view_interval(interval);
             }
+            if self.expand_all {
+                headless.expand_all(true);
+            }
+            // Let the layout settle
+            headless.run();
+            headless.run();
 
-            last_point = Some(*point);
+            let error = |e: &dyn std::fmt::Display| format!("{}: {}", self.output.display(), e);
+            let bytes = headless.render().encode_png().map_err(|e| error(&e))?;
+            std::fs::write(&self.output, bytes).map_err(|e| error(&e))
         }
-        SummaryTile {
-            tile_id,
-            utilization: tile_utilization,
+    }
+
+    // Fetch tiles on several threads, for profiles that can take it
+    fn parallel(
+        data_source: Box<dyn DataSource>,
+        profile: &str,
+        threads: usize,
+        cache_dir: Option<PathBuf>,
+    ) -> Box<dyn DataSource> {
+        if !reopens_quickly(profile) || threads <= 1 {
+            return data_source;
         }
+        let profile = profile.to_owned();
+        let open = Arc::new(move || {
+            open_profile(&profile, cache_dir.as_deref()).map_err(DataSourceError::new)
+        });
+        Box::new(ParallelDataSource::new(data_source, open, threads))
+    }
+
+    fn is_url(profile: &str) -> bool {
+        profile.starts_with("http://") || profile.starts_with("https://")
     }
 
-    fn fetch_slot_tile(&mut self, entry_id: &EntryID, tile_id: TileID) -> SlotTile {
-        let items = self.generate_slot(entry_id);
-
-        let mut slot_items = Vec::new();
-        for row in items {
-            let mut slot_row = Vec::new();
-            for item in row {
-                // When the item straddles a tile boundary, it has to be
-                // sliced to fit
-                if tile_id.0.overlaps(item.interval) {
-                    let mut new_item = item.clone();
-                    new_item.interval = new_item.interval.intersection(tile_id.0);
-                    slot_row.push(new_item);
-                }
+    // Opening these again only means connecting, or reading an archive's
+    // index, so each thread fetching tiles can have its own
+    fn reopens_quickly(profile: &str) -> bool {
+        is_url(profile) || profile.ends_with(".lpv")
+    }
+
+    pub fn open_profile(
+        profile: &str,
+        cache_dir: Option<&Path>,
+    ) -> Result<Box<dyn DataSource>, String> {
+        if is_url(profile) {
+            let mut source = RemoteDataSource::new(profile);
+            if let Some(dir) = cache_dir {
+                source = source.with_cache(dir);
             }
-            slot_items.push(slot_row);
+            // Fail now, rather than with an empty window
+            source
+                .interval()
+                .map_err(|e| format!("{}: {}", profile, e))?;
+            return Ok(Box::new(source));
+        }
+        loader::open_file(Path::new(profile)).map_err(|e| e.to_string())
+    }
+
+    fn parse_interval(text: &str) -> Result<Interval, String> {
+        let interval = Interval::parse(text)?;
+        if interval.duration_ns() <= 0 {
+            return Err("interval must not be empty".to_owned());
         }
+        Ok(interval)
+    }
+
+    fn parse_node_set(text: &str) -> Result<NodeSet, String> {
+        text.parse()
+    }
+
+    fn parse_size(text: &str) -> Result<(f32, f32), String> {
+        let error = || format!("expected WIDTHxHEIGHT, got '{}'", text);
+        let (width, height) = text.split_once('x').ok_or_else(error)?;
+        let width: f32 = width.trim().parse().map_err(|_| error())?;
+        let height: f32 = height.trim().parse().map_err(|_| error())?;
+        Ok((width, height))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use clap::Parser;
+    use cli::{Cli, Command};
+    use legion_prof_viewer::settings::Settings;
+
+    let exit = |error: String| -> ! {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    };
 
-        SlotTile {
-            tile_id,
-            items: slot_items,
+    let mut cli = Cli::parse();
+    let settings = Settings::load(cli.config.as_deref()).unwrap_or_else(|e| exit(e));
+    let cache_dir = settings.cache_dir();
+    let open = |profile: &str| {
+        cli::open_profile(profile, cache_dir.as_deref()).unwrap_or_else(|e| exit(e))
+    };
+    if cli.view.profile.is_none() {
+        cli.view.profile = settings.server.clone();
+    }
+    match &cli.command {
+        Some(Command::Serve(args)) => {
+            // Problems with requests are logged, rather than failing
+            tracing_subscriber::fmt()
+                .with_writer(std::io::stderr)
+                .init();
+            let data_source = open(&args.profile);
+            let options = args.options();
+            println!(
+                "Serving {} at http://{}:{}",
+                args.profile, options.address, options.port
+            );
+            if let Err(error) = legion_prof_viewer::server::serve(data_source, &options) {
+                exit(error.to_string());
+            }
+        }
+        Some(Command::Dump(args)) => {
+            let mut data_source = open(&args.profile);
+            match args.dump(data_source.as_mut()) {
+                Ok(dump) => println!("{}", dump),
+                Err(error) => exit(error.to_string()),
+            }
+        }
+        Some(Command::Convert(args)) => {
+            let mut data_source = open(&args.input);
+            if let Err(error) = args.convert(data_source.as_mut()) {
+                exit(error);
+            }
+        }
+        Some(Command::Render(args)) => {
+            let data_source = open(&args.profile);
+            if let Err(error) = args.render(data_source) {
+                exit(error);
+            }
+        }
+        None => {
+            let options = cli
+                .view
+                .options(&settings, cli.config.as_deref())
+                .unwrap_or_else(|e| exit(e));
+            let wrap = |data_source| {
+                cli.view
+                    .wrap(data_source, &settings)
+                    .unwrap_or_else(|e| exit(e))
+            };
+            if let Some(profile) = &cli.view.profile {
+                let data_source = open(profile);
+                legion_prof_viewer::app::start(wrap(data_source), None, options);
+            } else {
+                legion_prof_viewer::app::start(
+                    wrap(Box::<RandomDataSource>::default()),
+                    Some(Box::new(RandomDataSource::new(RandomOptions {
+                        seed: 1,
+                        ..Default::default()
+                    }))),
+                    options,
+                );
+            }
         }
     }
 }
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use legion_prof_viewer::deep_link::DeepLink;
+    use legion_prof_viewer::remote::RemoteDataSource;
+
+    // A link to a served profile opens it, otherwise show the demo
+    let hash = web_sys::window().and_then(|w| w.location().hash().ok());
+    let link = hash.and_then(|hash| DeepLink::parse(&hash).ok());
+    if let Some(url) = link.and_then(|link| link.url) {
+        legion_prof_viewer::app::start(
+            Box::new(RemoteDataSource::new(url)),
+            None,
+            StartOptions::default(),
+        );
+    } else {
+        legion_prof_viewer::app::start(
+            Box::<RandomDataSource>::default(),
+            Some(Box::new(RandomDataSource::new(RandomOptions {
+                seed: 1,
+                ..Default::default()
+            }))),
+            StartOptions::default(),
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A set of nodes, written as a comma separated list of node numbers and
+/// (inclusive) ranges, each with an optional step. For example:
+///
+///   * `0-3,17` is nodes 0, 1, 2, 3 and 17
+///   * `0-15:4` is nodes 0, 4, 8 and 12
+///   * `*` (or nothing at all) is every node
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeSet {
+    ranges: Option<Vec<NodeRange>>, // None means all nodes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NodeRange {
+    first: u64,
+    last: u64,
+    step: u64,
+}
+
+impl NodeSet {
+    pub fn all() -> Self {
+        Self { ranges: None }
+    }
+
+    pub fn contains(&self, node: u64) -> bool {
+        if let Some(ranges) = &self.ranges {
+            ranges.iter().any(|r| r.contains(node))
+        } else {
+            true
+        }
+    }
+
+    pub fn insert(&mut self, node: u64) {
+        if let Some(ranges) = &mut self.ranges {
+            if !ranges.iter().any(|r| r.contains(node)) {
+                ranges.push(NodeRange {
+                    first: node,
+                    last: node,
+                    step: 1,
+                });
+            }
+        }
+    }
+}
+
+impl NodeRange {
+    fn contains(&self, node: u64) -> bool {
+        self.first <= node && node <= self.last && (node - self.first) % self.step == 0
+    }
+}
+
+impl FromStr for NodeSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s == "*" {
+            return Ok(Self::all());
+        }
+
+        let parse_node = |n: &str| {
+            n.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid node number '{}'", n.trim()))
+        };
+
+        let mut ranges = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (range, step) = match part.split_once(':') {
+                Some((range, step)) => (range, parse_node(step)?),
+                None => (part, 1),
+            };
+            let (first, last) = match range.split_once('-') {
+                Some((first, last)) => (parse_node(first)?, parse_node(last)?),
+                None => {
+                    let node = parse_node(range)?;
+                    (node, node)
+                }
+            };
+            if first > last {
+                return Err(format!("range '{}' is backwards", range.trim()));
+            }
+            if step == 0 {
+                return Err(format!("step in '{}' must be at least 1", part));
+            }
+            ranges.push(NodeRange { first, last, step });
+        }
+        Ok(Self {
+            ranges: Some(ranges),
+        })
+    }
+}
+
+impl fmt::Display for NodeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges = if let Some(ranges) = &self.ranges {
+            ranges
+        } else {
+            return write!(f, "*");
+        };
+        for (i, range) in ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}", range.first)?;
+            if range.last != range.first {
+                write!(f, "-{}", range.last)?;
+            }
+            if range.step != 1 {
+                write!(f, ":{}", range.step)?;
+            }
+        }
+        Ok(())
+    }
+}
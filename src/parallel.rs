@@ -0,0 +1,324 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::data::{
+    CounterTile, DataSource, DataSourceError, DataSourceResult, Description, EntryID, EntryInfo,
+    EventTile, FetchStats, ItemLink, ItemUID, SearchResult, SlotTile, SummaryTile, TileID, Warning,
+};
+use crate::timestamp::Interval;
+
+/// Opens another copy of a data source, for a thread of a ParallelDataSource.
+pub type Open = Arc<dyn Fn() -> DataSourceResult<Box<dyn DataSource>> + Send + Sync>;
+
+/// Wraps a data source, fetching the tiles returned by request_tiles on a
+/// pool of threads, ahead of the app asking for them. Until a tile arrives,
+/// tile_ready says so, and the app draws what it has in the meantime.
+///
+/// Each thread has its own copy of the data source (made by `open`), so
+/// this is for sources that are quick to open again and slow to fetch
+/// from, e.g., remote ones (where each fetch waits on the network) or
+/// archives (which only read their index up front).
+pub struct ParallelDataSource {
+    inner: Box<dyn DataSource>, // for everything but fetching tiles
+    jobs: Sender<Job>,
+    results: Receiver<Done>,
+    fetches: BTreeMap<Key, Option<DataSourceResult<Tile>>>, // None until it arrives
+    cancelled: Arc<Mutex<BTreeSet<Key>>>,                   // for the threads to skip
+    stats: Vec<FetchStats>,                                 // of each thread's copy
+}
+
+// TileIDs aren't Ord, but their intervals are
+type Key = (EntryID, Interval);
+
+struct Job {
+    key: Key,
+    kind: Kind,
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Kind {
+    Summary,
+    Slot,
+    Counter,
+    Events,
+}
+
+struct Done {
+    key: Key,
+    result: DataSourceResult<Tile>,
+    thread: usize,
+    stats: FetchStats,
+}
+
+enum Tile {
+    Summary(SummaryTile),
+    Slot(SlotTile),
+    Counter(CounterTile),
+    Events(EventTile),
+}
+
+fn fetch(data_source: &mut dyn DataSource, job: &Job) -> DataSourceResult<Tile> {
+    let (entry_id, tile_id) = (&job.key.0, TileID(job.key.1));
+    Ok(match job.kind {
+        Kind::Summary => Tile::Summary(data_source.fetch_summary_tile(entry_id, tile_id)?),
+        Kind::Slot => Tile::Slot(data_source.fetch_slot_tile(entry_id, tile_id)?),
+        Kind::Counter => Tile::Counter(data_source.fetch_counter_tile(entry_id, tile_id)?),
+        Kind::Events => Tile::Events(data_source.fetch_event_tile(entry_id, tile_id)?),
+    })
+}
+
+fn work(
+    thread: usize,
+    open: &Open,
+    jobs: &Mutex<Receiver<Job>>,
+    results: &Sender<Done>,
+    cancelled: &Mutex<BTreeSet<Key>>,
+) {
+    let mut data_source = open();
+    loop {
+        // Fails once the ParallelDataSource is dropped
+        let job = match jobs.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        if cancelled.lock().unwrap().remove(&job.key) {
+            continue;
+        }
+        let _span =
+            tracing::debug_span!("fetch", thread, entry = %job.key.0, tile = %job.key.1).entered();
+        let (result, stats) = match &mut data_source {
+            Ok(data_source) => (fetch(data_source.as_mut(), &job), data_source.fetch_stats()),
+            Err(error) => (Err(error.clone()), FetchStats::default()),
+        };
+        let done = Done {
+            key: job.key,
+            result,
+            thread,
+            stats,
+        };
+        if results.send(done).is_err() {
+            return;
+        }
+    }
+}
+
+impl ParallelDataSource {
+    pub fn new(inner: Box<dyn DataSource>, open: Open, threads: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let cancelled = Arc::new(Mutex::new(BTreeSet::new()));
+        let threads = threads.max(1);
+        for i in 0..threads {
+            let open = open.clone();
+            let jobs = job_receiver.clone();
+            let results = result_sender.clone();
+            let cancelled = cancelled.clone();
+            thread::Builder::new()
+                .name(format!("fetch {}", i))
+                .spawn(move || work(i, &open, &jobs, &results, &cancelled))
+                .expect("failed to start a thread for fetching tiles");
+        }
+        Self {
+            inner,
+            jobs,
+            results,
+            fetches: BTreeMap::new(),
+            cancelled,
+            stats: vec![FetchStats::default(); threads],
+        }
+    }
+
+    fn arrived(&mut self, done: Done) {
+        self.stats[done.thread] = done.stats;
+        // Otherwise it was cancelled (or invalidated) along the way
+        if let Some(fetch) = self.fetches.get_mut(&done.key) {
+            *fetch = Some(done.result);
+        }
+    }
+
+    fn receive(&mut self) {
+        while let Ok(done) = self.results.try_recv() {
+            self.arrived(done);
+        }
+    }
+
+    // The tile, once it arrives, or None if it isn't being fetched here
+    fn take(&mut self, entry_id: &EntryID, tile_id: TileID) -> Option<DataSourceResult<Tile>> {
+        let key = (entry_id.clone(), tile_id.0);
+        self.receive();
+        while let Some(None) = self.fetches.get(&key) {
+            match self.results.recv() {
+                Ok(done) => self.arrived(done),
+                Err(_) => {
+                    self.fetches.remove(&key);
+                    return Some(Err(DataSourceError::new(
+                        "the threads fetching tiles have stopped",
+                    )));
+                }
+            }
+        }
+        self.fetches.remove(&key)?
+    }
+}
+
+impl DataSource for ParallelDataSource {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        self.inner.interval()
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        self.inner.fetch_info()
+    }
+
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        let tile_ids = self.inner.request_tiles(entry_id, request_interval)?;
+        let kind = match self.inner.fetch_info()?.get(entry_id) {
+            Some(EntryInfo::Summary { .. } | EntryInfo::Aggregate { .. }) => Kind::Summary,
+            Some(EntryInfo::Slot { .. }) => Kind::Slot,
+            Some(EntryInfo::Counter { .. }) => Kind::Counter,
+            Some(EntryInfo::Events { .. }) => Kind::Events,
+            _ => return Ok(tile_ids), // fetched here, if at all
+        };
+        for tile_id in &tile_ids {
+            let key = (entry_id.clone(), tile_id.0);
+            if self.fetches.contains_key(&key) {
+                continue;
+            }
+            self.cancelled.lock().unwrap().remove(&key);
+            let job = Job {
+                key: key.clone(),
+                kind,
+            };
+            if self.jobs.send(job).is_ok() {
+                self.fetches.insert(key, None);
+            }
+        }
+        Ok(tile_ids)
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        match self.take(entry_id, tile_id) {
+            Some(Ok(Tile::Summary(tile))) => Ok(tile),
+            Some(Err(error)) => Err(error),
+            _ => self.inner.fetch_summary_tile(entry_id, tile_id),
+        }
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        match self.take(entry_id, tile_id) {
+            Some(Ok(Tile::Slot(tile))) => Ok(tile),
+            Some(Err(error)) => Err(error),
+            _ => self.inner.fetch_slot_tile(entry_id, tile_id),
+        }
+    }
+
+    // Slot tiles are fetched in the background either way, so this just
+    // saves the app from holding on to the items
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        match self.take(entry_id, tile_id) {
+            Some(Ok(Tile::Slot(tile))) => Ok(tile.density()),
+            Some(Err(error)) => Err(error),
+            _ => self.inner.fetch_slot_density_tile(entry_id, tile_id),
+        }
+    }
+
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        self.inner.search(query)
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        self.inner.fetch_item_links(item_uid)
+    }
+
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        self.inner.epoch()
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        self.inner.fetch_description()
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        match self.take(entry_id, tile_id) {
+            Some(Ok(Tile::Counter(tile))) => Ok(tile),
+            Some(Err(error)) => Err(error),
+            _ => self.inner.fetch_counter_tile(entry_id, tile_id),
+        }
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        match self.take(entry_id, tile_id) {
+            Some(Ok(Tile::Events(tile))) => Ok(tile),
+            Some(Err(error)) => Err(error),
+            _ => self.inner.fetch_event_tile(entry_id, tile_id),
+        }
+    }
+
+    fn tile_ready(&mut self, entry_id: &EntryID, tile_id: TileID) -> bool {
+        self.receive();
+        !matches!(self.fetches.get(&(entry_id.clone(), tile_id.0)), Some(None))
+    }
+
+    fn fetch_stats(&mut self) -> FetchStats {
+        self.receive();
+        let mut stats = self.inner.fetch_stats();
+        for thread in &self.stats {
+            stats += *thread;
+        }
+        stats.in_flight += self.fetches.values().filter(|f| f.is_none()).count() as u64;
+        stats
+    }
+
+    fn cancel_tiles(&mut self, entry_id: &EntryID, tile_ids: &[TileID]) {
+        let mut cancelled = self.cancelled.lock().unwrap();
+        for tile_id in tile_ids {
+            let key = (entry_id.clone(), tile_id.0);
+            if let Some(None) = self.fetches.remove(&key) {
+                cancelled.insert(key);
+            }
+        }
+        drop(cancelled);
+        self.inner.cancel_tiles(entry_id, tile_ids);
+    }
+
+    fn poll_invalidated(&mut self) -> Vec<(EntryID, Interval)> {
+        let invalidated = self.inner.poll_invalidated();
+        for (entry_id, interval) in &invalidated {
+            self.fetches.retain(|(e, i), _| {
+                !((entry_id == e || entry_id.is_ancestor_of(e)) && interval.overlaps(*i))
+            });
+        }
+        invalidated
+    }
+
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        self.inner.poll_warnings()
+    }
+}
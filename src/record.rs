@@ -0,0 +1,438 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Write};
+
+use crate::data::{
+    CounterTile, DataSource, DataSourceError, DataSourceResult, Description, EntryID, EntryInfo,
+    EventTile, FetchStats, ItemLink, ItemUID, SearchResult, SlotTile, SummaryTile, TileID, Warning,
+};
+use crate::timestamp::Interval;
+
+// A request made of a data source, with its arguments
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Call {
+    Interval,
+    FetchInfo,
+    RequestTiles {
+        entry_id: EntryID,
+        interval: Interval,
+    },
+    FetchSummaryTile {
+        entry_id: EntryID,
+        tile_id: TileID,
+    },
+    FetchSlotTile {
+        entry_id: EntryID,
+        tile_id: TileID,
+    },
+    FetchSlotDensityTile {
+        entry_id: EntryID,
+        tile_id: TileID,
+    },
+    FetchCounterTile {
+        entry_id: EntryID,
+        tile_id: TileID,
+    },
+    FetchEventTile {
+        entry_id: EntryID,
+        tile_id: TileID,
+    },
+    Search {
+        query: String,
+    },
+    FetchItemLinks {
+        item_uid: ItemUID,
+    },
+    Epoch,
+    FetchDescription,
+    PollWarnings,
+}
+
+impl Call {
+    // Calls with the same arguments get the same key
+    fn key(&self) -> String {
+        serde_json::to_string(self).expect("calls always serialize")
+    }
+}
+
+// One line of a recording, as written
+#[derive(Serialize)]
+struct Record<'a, T> {
+    call: &'a Call,
+    result: Result<&'a T, &'a DataSourceError>,
+}
+
+// Likewise, as read back (the type of the response depends on the call)
+#[derive(Deserialize)]
+struct Recorded {
+    call: Call,
+    result: Result<serde_json::Value, DataSourceError>,
+}
+
+/// Wraps a data source, writing every request made of it (and the answer)
+/// to a recording that ReplayDataSource can serve later without the
+/// original profile, e.g., to reproduce a bug report.
+///
+/// The recording is JSON Lines, one `{"call": ..., "result": ...}` object
+/// per distinct request. Lines are flushed as they are written, so a
+/// recording is still usable if the viewer crashes.
+///
+/// Warnings come a few at a time, so each time there are more, all of them
+/// so far are written again (the last line for a call is the one replayed).
+pub struct RecordingDataSource<W: Write> {
+    inner: Box<dyn DataSource>,
+    writer: W,
+    recorded: BTreeSet<String>,
+    warnings: Vec<Warning>,
+}
+
+impl<W: Write> RecordingDataSource<W> {
+    pub fn new(inner: Box<dyn DataSource>, writer: W) -> Self {
+        Self {
+            inner,
+            writer,
+            recorded: BTreeSet::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+// Not a method, so that the result can still borrow from the inner source
+fn record<T: Serialize>(
+    writer: &mut impl Write,
+    recorded: &mut BTreeSet<String>,
+    call: &Call,
+    result: &DataSourceResult<T>,
+) -> DataSourceResult<()> {
+    if !recorded.insert(call.key()) {
+        return Ok(()); // the answer is already in the recording
+    }
+    let line = Record {
+        call,
+        result: result.as_ref(),
+    };
+    let error = |e: &dyn std::fmt::Display| DataSourceError::new(format!("recording: {}", e));
+    serde_json::to_writer(&mut *writer, &line).map_err(|e| error(&e))?;
+    writeln!(writer).map_err(|e| error(&e))?;
+    writer.flush().map_err(|e| error(&e))
+}
+
+impl<W: Write> DataSource for RecordingDataSource<W> {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        let result = self.inner.interval();
+        record(
+            &mut self.writer,
+            &mut self.recorded,
+            &Call::Interval,
+            &result,
+        )?;
+        result
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        let result = self.inner.fetch_info();
+        record(
+            &mut self.writer,
+            &mut self.recorded,
+            &Call::FetchInfo,
+            &result,
+        )?;
+        result
+    }
+
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        let result = self.inner.request_tiles(entry_id, request_interval);
+        let call = Call::RequestTiles {
+            entry_id: entry_id.clone(),
+            interval: request_interval,
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        let result = self.inner.fetch_summary_tile(entry_id, tile_id);
+        let call = Call::FetchSummaryTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        let result = self.inner.fetch_slot_tile(entry_id, tile_id);
+        let call = Call::FetchSlotTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        let result = self.inner.fetch_slot_density_tile(entry_id, tile_id);
+        let call = Call::FetchSlotDensityTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        let result = self.inner.epoch();
+        record(&mut self.writer, &mut self.recorded, &Call::Epoch, &result)?;
+        result
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        let result = self.inner.fetch_description();
+        let call = Call::FetchDescription;
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        let result = self.inner.search(query);
+        let call = Call::Search {
+            query: query.to_owned(),
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        let result = self.inner.fetch_item_links(item_uid);
+        let call = Call::FetchItemLinks { item_uid };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        let result = self.inner.fetch_counter_tile(entry_id, tile_id);
+        let call = Call::FetchCounterTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        let result = self.inner.fetch_event_tile(entry_id, tile_id);
+        let call = Call::FetchEventTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        };
+        record(&mut self.writer, &mut self.recorded, &call, &result)?;
+        result
+    }
+
+    fn tile_ready(&mut self, entry_id: &EntryID, tile_id: TileID) -> bool {
+        self.inner.tile_ready(entry_id, tile_id)
+    }
+
+    fn fetch_stats(&mut self) -> FetchStats {
+        self.inner.fetch_stats()
+    }
+
+    fn cancel_tiles(&mut self, entry_id: &EntryID, tile_ids: &[TileID]) {
+        self.inner.cancel_tiles(entry_id, tile_ids);
+    }
+
+    fn poll_invalidated(&mut self) -> Vec<(EntryID, Interval)> {
+        self.inner.poll_invalidated()
+    }
+
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        let mut warnings = self.inner.poll_warnings();
+        if warnings.is_empty() {
+            return warnings;
+        }
+        self.warnings.extend(warnings.iter().cloned());
+        let call = Call::PollWarnings;
+        self.recorded.remove(&call.key());
+        let result: DataSourceResult<_> = Ok(&self.warnings);
+        // There's no other way to report it
+        if let Err(error) = record(&mut self.writer, &mut self.recorded, &call, &result) {
+            warnings.push(Warning {
+                message: error.to_string(),
+                entry_id: None,
+            });
+        }
+        warnings
+    }
+}
+
+/// Serves a recording made by RecordingDataSource. Answers depend only on
+/// the request, not the order requests are made in, so the same view
+/// always draws the same way. Requests that were never recorded fail.
+pub struct ReplayDataSource {
+    responses: BTreeMap<String, Result<serde_json::Value, DataSourceError>>,
+    info: Option<EntryInfo>,
+    warned: bool, // warnings are only reported once
+}
+
+impl ReplayDataSource {
+    pub fn new(reader: impl BufRead) -> DataSourceResult<Self> {
+        let mut responses = BTreeMap::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let recorded: Recorded = serde_json::from_str(&line)
+                .map_err(|e| DataSourceError::new(format!("line {}: {}", i + 1, e)))?;
+            responses.insert(recorded.call.key(), recorded.result);
+        }
+        Ok(Self {
+            responses,
+            info: None,
+            warned: false,
+        })
+    }
+
+    fn replay<T: for<'de> Deserialize<'de>>(&self, call: Call) -> DataSourceResult<T> {
+        let key = call.key();
+        match self.responses.get(&key) {
+            Some(Ok(value)) => {
+                T::deserialize(value).map_err(|e| DataSourceError::new(format!("{}: {}", key, e)))
+            }
+            Some(Err(error)) => Err(error.clone()),
+            None => Err(DataSourceError::new(format!("{}: not in recording", key))),
+        }
+    }
+}
+
+impl DataSource for ReplayDataSource {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        self.replay(Call::Interval)
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        if self.info.is_none() {
+            self.info = Some(self.replay(Call::FetchInfo)?);
+        }
+        Ok(self.info.as_ref().unwrap())
+    }
+
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        self.replay(Call::RequestTiles {
+            entry_id: entry_id.clone(),
+            interval: request_interval,
+        })
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.replay(Call::FetchSummaryTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        })
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        self.replay(Call::FetchSlotTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        })
+    }
+
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.replay(Call::FetchSlotDensityTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        })
+    }
+
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        // Recordings made before this was asked for don't have it
+        Ok(self.replay(Call::Epoch).unwrap_or(None))
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        // Likewise
+        Ok(self.replay(Call::FetchDescription).unwrap_or_default())
+    }
+
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        self.replay(Call::Search {
+            query: query.to_owned(),
+        })
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        self.replay(Call::FetchItemLinks { item_uid })
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        self.replay(Call::FetchCounterTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        })
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        self.replay(Call::FetchEventTile {
+            entry_id: entry_id.clone(),
+            tile_id,
+        })
+    }
+
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        if std::mem::replace(&mut self.warned, true) {
+            return Vec::new();
+        }
+        // Recordings without any warnings don't have this
+        self.replay(Call::PollWarnings).unwrap_or_default()
+    }
+}
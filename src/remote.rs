@@ -0,0 +1,352 @@
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::data::{
+    CounterTile, DataSource, DataSourceError, DataSourceResult, Description, EntryID, EntryInfo,
+    EventTile, FetchStats, ItemLink, ItemUID, SearchResult, SlotTile, SummaryTile, TileID, Warning,
+};
+use crate::deep_link::encode;
+use crate::timestamp::Interval;
+use crate::wire::{hash, Compression, Format, Handshake, DESCRIPTION, EPOCH, ITEM_LINKS, WARNINGS};
+
+/// Reads a profile served over HTTP, e.g., by `legion_prof_viewer serve`.
+/// After a `/handshake` (see wire.rs), each DataSource method is a GET
+/// request, answered in the Format given by the `format` parameter:
+///
+///   * `/interval` and `/info`
+///   * `/tiles?entry=0.1&start=0&stop=1000`, the TileIDs in an interval
+///   * `/summary_tile`, `/slot_tile`, `/counter_tile` and `/event_tile`,
+///     each with the same parameters as `/tiles`
+///   * `/search?query=text` and `/item_links?uid=42`
+///   * `/epoch`, `/description` and `/warnings`
+///
+/// Failures are answered with an error status and a DataSourceError.
+/// Successful responses have an ETag, so that a client can keep them (see
+/// with_cache) and later ask for them again only if they've changed.
+pub struct RemoteDataSource {
+    base_url: String,
+    server: Option<Handshake>, // None until the handshake succeeds
+    format: Format,
+    info: Option<EntryInfo>,
+    cache: Option<Cache>,
+    stats: FetchStats,
+    warnings_fetched: bool,
+}
+
+// Responses kept on disk, one file per request (under a directory per
+// profile), each holding the ETag on the first line and then the body
+struct Cache {
+    dir: PathBuf,
+}
+
+enum HttpResponse {
+    Ok { body: Vec<u8>, etag: Option<String> },
+    NotModified, // the body we have is still good
+    Error(Vec<u8>),
+}
+
+impl RemoteDataSource {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_owned();
+        Self {
+            base_url,
+            server: None,
+            format: Format::Json,
+            info: None,
+            cache: None,
+            stats: FetchStats::default(),
+            warnings_fetched: false,
+        }
+    }
+
+    /// Keeps responses in this directory, so that reopening the profile
+    /// (or going back to part of it) doesn't download tiles again unless
+    /// they've changed. The cache can be deleted at any time. In the
+    /// browser, its own cache does this instead.
+    pub fn with_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        let profile = hash(self.base_url.as_bytes());
+        self.cache = Some(Cache {
+            dir: dir.into().join(format!("{:016x}", profile)),
+        });
+        self
+    }
+
+    fn handshake(&mut self) -> DataSourceResult<&Handshake> {
+        if self.server.is_none() {
+            let url = format!("{}/handshake", self.base_url);
+            let server: Handshake = match http_get(&url, None)? {
+                HttpResponse::Ok { body, .. } => Format::Json
+                    .decode(&body)
+                    .map_err(|e| DataSourceError::new(format!("{}: {}", url, e)))?,
+                HttpResponse::NotModified => unreachable!("nothing was cached"),
+                HttpResponse::Error(body) => {
+                    return Err(DataSourceError::new(format!(
+                        "{}: no handshake, so the server is probably older than this viewer: {}",
+                        url,
+                        error_body(Format::Json, &body)
+                    )))
+                }
+            };
+            server.check()?;
+            // Tiles are compressed where both sides can
+            self.format = Format::Binary(Compression::best(&server));
+            self.server = Some(server);
+        }
+        Ok(self.server.as_ref().unwrap())
+    }
+
+    fn get<T: DeserializeOwned>(&mut self, path: &str) -> DataSourceResult<T> {
+        self.handshake()?;
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let url = format!(
+            "{}{}{}format={}",
+            self.base_url,
+            path,
+            separator,
+            self.format.name()
+        );
+        let _span = tracing::debug_span!("get", path).entered();
+        tracing::debug!("{}", url);
+        let cached = self.cache.as_ref().and_then(|cache| cache.load(&url));
+        let etag = cached.as_ref().map(|(etag, _)| etag.as_str());
+        self.stats.requests += 1;
+        let body = match http_get(&url, etag)? {
+            HttpResponse::Ok { body, etag } => {
+                self.stats.bytes += body.len() as u64;
+                if let Some(cache) = &self.cache {
+                    self.stats.cache_misses += 1;
+                    if let Some(etag) = etag {
+                        cache.store(&url, &etag, &body);
+                    }
+                }
+                body
+            }
+            HttpResponse::NotModified => {
+                self.stats.cache_hits += 1;
+                cached.map(|(_, body)| body).unwrap_or_default()
+            }
+            HttpResponse::Error(body) => {
+                let error = error_body(self.format, &body);
+                tracing::warn!("{}: {}", url, error);
+                return Err(error);
+            }
+        };
+        self.format
+            .decode(&body)
+            .map_err(|e| DataSourceError::new(format!("{}: {}", url, e)))
+    }
+}
+
+// The cache is only there to save time, so it's fine for it to fail (e.g.,
+// if the disk is full). The worst that happens is downloading tiles again.
+impl Cache {
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", hash(url.as_bytes())))
+    }
+
+    fn load(&self, url: &str) -> Option<(String, Vec<u8>)> {
+        let mut contents = fs::read(self.path(url)).ok()?;
+        let newline = contents.iter().position(|b| *b == b'\n')?;
+        let body = contents.split_off(newline + 1);
+        contents.truncate(newline);
+        Some((String::from_utf8(contents).ok()?, body))
+    }
+
+    fn store(&self, url: &str, etag: &str, body: &[u8]) {
+        let path = self.path(url);
+        // Written next to the file, then moved into place, so that another
+        // viewer never reads part of it
+        let temp = path.with_extension("tmp");
+        let mut contents = Vec::with_capacity(etag.len() + 1 + body.len());
+        contents.extend(etag.as_bytes());
+        contents.push(b'\n');
+        contents.extend(body);
+        let _ = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temp, contents))
+            .and_then(|_| fs::rename(&temp, &path));
+    }
+}
+
+// Proxies and the like may send errors as text
+fn error_body(format: Format, body: &[u8]) -> DataSourceError {
+    format
+        .decode(body)
+        .unwrap_or_else(|_| DataSourceError::new(String::from_utf8_lossy(body)))
+}
+
+impl DataSource for RemoteDataSource {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        self.get("/interval")
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        if self.info.is_none() {
+            self.info = Some(self.get("/info")?);
+        }
+        Ok(self.info.as_ref().unwrap())
+    }
+
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        self.get(&format!(
+            "/tiles?{}",
+            tile_query(entry_id, request_interval)
+        ))
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.get(&format!(
+            "/summary_tile?{}",
+            tile_query(entry_id, tile_id.0)
+        ))
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        self.get(&format!("/slot_tile?{}", tile_query(entry_id, tile_id.0)))
+    }
+
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        self.get(&format!("/search?query={}", encode(query)))
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        if !self.handshake()?.supports(ITEM_LINKS) {
+            return Ok(Vec::new());
+        }
+        self.get(&format!("/item_links?uid={}", item_uid.0))
+    }
+
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        if !self.handshake()?.supports(EPOCH) {
+            return Ok(None);
+        }
+        self.get("/epoch")
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        if !self.handshake()?.supports(DESCRIPTION) {
+            return Ok(Description::default());
+        }
+        self.get("/description")
+    }
+
+    // Fetched once, when the profile is opened, rather than every frame
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        if std::mem::replace(&mut self.warnings_fetched, true) {
+            return Vec::new();
+        }
+        let supported = self
+            .handshake()
+            .map_or(false, |server| server.supports(WARNINGS));
+        if !supported {
+            return Vec::new();
+        }
+        self.get("/warnings").unwrap_or_else(|error| {
+            vec![Warning {
+                message: format!("unable to fetch warnings: {}", error),
+                entry_id: None,
+            }]
+        })
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        self.get(&format!(
+            "/counter_tile?{}",
+            tile_query(entry_id, tile_id.0)
+        ))
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        self.get(&format!("/event_tile?{}", tile_query(entry_id, tile_id.0)))
+    }
+
+    fn fetch_stats(&mut self) -> FetchStats {
+        self.stats
+    }
+}
+
+// Errors are for requests that never got an answer, e.g., if the server is
+// down. The ETag is that of the body we have, if any.
+#[cfg(not(target_arch = "wasm32"))]
+fn http_get(url: &str, etag: Option<&str>) -> DataSourceResult<HttpResponse> {
+    use std::io::Read;
+
+    let read_body = |response: ureq::Response| {
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+        Ok::<_, DataSourceError>(body)
+    };
+    let mut request = ureq::get(url);
+    if let Some(etag) = etag {
+        request = request.set("If-None-Match", etag);
+    }
+    match request.call() {
+        Ok(response) if response.status() == 304 => Ok(HttpResponse::NotModified),
+        Ok(response) => {
+            let etag = response.header("ETag").map(str::to_owned);
+            let body = read_body(response)?;
+            Ok(HttpResponse::Ok { body, etag })
+        }
+        Err(ureq::Error::Status(_, response)) => Ok(HttpResponse::Error(read_body(response)?)),
+        Err(error) => Err(DataSourceError::new(format!("{}: {}", url, error))),
+    }
+}
+
+// The browser keeps responses (and checks their ETags) itself, so this
+// never sees a 304
+#[cfg(target_arch = "wasm32")]
+fn http_get(url: &str, _etag: Option<&str>) -> DataSourceResult<HttpResponse> {
+    let js_error = |e: wasm_bindgen::JsValue| DataSourceError::new(format!("{}: {:?}", url, e));
+
+    // DataSource is synchronous, so the request has to be too
+    let request = web_sys::XmlHttpRequest::new().map_err(js_error)?;
+    request
+        .open_with_async("GET", url, false)
+        .map_err(js_error)?;
+    // Synchronous requests can't ask for an ArrayBuffer, but with this
+    // charset each byte of the body comes back as one character (with
+    // the byte in the low 8 bits)
+    request
+        .override_mime_type("text/plain; charset=x-user-defined")
+        .map_err(js_error)?;
+    request.send().map_err(js_error)?;
+    let body = request
+        .response_text()
+        .map_err(js_error)?
+        .unwrap_or_default()
+        .chars()
+        .map(|c| c as u32 as u8)
+        .collect();
+    if request.status().map_err(js_error)? == 200 {
+        Ok(HttpResponse::Ok { body, etag: None })
+    } else {
+        Ok(HttpResponse::Error(body))
+    }
+}
+
+fn tile_query(entry_id: &EntryID, interval: Interval) -> String {
+    format!(
+        "entry={}&start={}&stop={}",
+        entry_id, interval.start.0, interval.stop.0
+    )
+}
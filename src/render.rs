@@ -0,0 +1,223 @@
+//! A minimal software rasterizer for egui output.
+//!
+//! egui doesn't give us a way to read back what the GPU drew, so to export
+//! images we run the UI into an offscreen `egui::Context`, tessellate the
+//! resulting shapes, and rasterize the meshes here. Quality is roughly that
+//! of the on-screen rendering without anti-aliasing beyond what egui's
+//! feathering already provides.
+
+use egui::epaint::{ClippedPrimitive, ImageData, Primitive, TextureId, Vertex};
+use egui::{Color32, Pos2, Rect};
+use std::collections::BTreeMap;
+use std::io;
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color32>, // row-major, premultiplied
+}
+
+struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>,
+}
+
+#[derive(Default)]
+pub struct Rasterizer {
+    textures: BTreeMap<TextureId, Texture>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Color32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn encode_png(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        // PNG wants straight (not premultiplied) alpha
+        let data: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|c| {
+                let [r, g, b, a] = c.to_array();
+                if a == 0 {
+                    [0, 0, 0, 0]
+                } else {
+                    let unmultiply = |v: u8| ((v as u32 * 255 + a as u32 / 2) / a as u32).min(255);
+                    [
+                        unmultiply(r) as u8,
+                        unmultiply(g) as u8,
+                        unmultiply(b) as u8,
+                        a,
+                    ]
+                }
+            })
+            .collect();
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+
+    fn blend(&mut self, x: usize, y: usize, src: Color32) {
+        // Premultiplied "over" operator
+        let dst = &mut self.pixels[y * self.width + x];
+        let inv = 255 - src.a() as u32;
+        let mix = |s: u8, d: u8| (s as u32 + (d as u32 * inv + 127) / 255).min(255) as u8;
+        *dst = Color32::from_rgba_premultiplied(
+            mix(src.r(), dst.r()),
+            mix(src.g(), dst.g()),
+            mix(src.b(), dst.b()),
+            mix(src.a(), dst.a()),
+        );
+    }
+}
+
+impl Texture {
+    fn sample(&self, uv: Pos2) -> Color32 {
+        // Nearest neighbor is plenty for font glyphs at 1:1 scale
+        let x = ((uv.x * self.width as f32) as usize).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Rasterizer {
+    /// Apply texture updates (e.g., the font atlas) from a frame's output.
+    pub fn update_textures(&mut self, delta: &egui::TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            let (width, height, pixels): (usize, usize, Vec<Color32>) = match &image_delta.image {
+                ImageData::Color(image) => (image.width(), image.height(), image.pixels.clone()),
+                ImageData::Font(image) => (
+                    image.width(),
+                    image.height(),
+                    image.srgba_pixels(1.0).collect(),
+                ),
+            };
+            if let (Some([x0, y0]), Some(texture)) = (image_delta.pos, self.textures.get_mut(id)) {
+                // Partial update of an existing texture
+                for y in 0..height {
+                    for x in 0..width {
+                        let (tx, ty) = (x0 + x, y0 + y);
+                        if tx < texture.width && ty < texture.height {
+                            texture.pixels[ty * texture.width + tx] = pixels[y * width + x];
+                        }
+                    }
+                }
+            } else {
+                self.textures.insert(
+                    *id,
+                    Texture {
+                        width,
+                        height,
+                        pixels,
+                    },
+                );
+            }
+        }
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    /// Rasterize tessellated primitives into the image. Coordinates are in
+    /// points and get scaled by pixels_per_point.
+    pub fn rasterize(
+        &self,
+        primitives: &[ClippedPrimitive],
+        pixels_per_point: f32,
+        image: &mut Image,
+    ) {
+        let bounds = Rect::from_min_max(
+            Pos2::ZERO,
+            Pos2::new(image.width as f32, image.height as f32),
+        );
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let mesh = if let Primitive::Mesh(mesh) = primitive {
+                mesh
+            } else {
+                continue; // paint callbacks can't be rendered offscreen
+            };
+            let texture = self.textures.get(&mesh.texture_id);
+            let clip = Rect::from_min_max(
+                (clip_rect.min.to_vec2() * pixels_per_point).to_pos2(),
+                (clip_rect.max.to_vec2() * pixels_per_point).to_pos2(),
+            )
+            .intersect(bounds);
+            for triangle in mesh.indices.chunks_exact(3) {
+                let v = |i: u32| {
+                    let mut vertex = mesh.vertices[i as usize];
+                    vertex.pos = (vertex.pos.to_vec2() * pixels_per_point).to_pos2();
+                    vertex
+                };
+                Self::triangle(
+                    [v(triangle[0]), v(triangle[1]), v(triangle[2])],
+                    texture,
+                    clip,
+                    image,
+                );
+            }
+        }
+    }
+
+    fn triangle(v: [Vertex; 3], texture: Option<&Texture>, clip: Rect, image: &mut Image) {
+        let [a, b, c] = v.map(|v| v.pos);
+        let area = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).max(clip.min.x).floor() as i64;
+        let max_x = a.x.max(b.x).max(c.x).min(clip.max.x).ceil() as i64;
+        let min_y = a.y.min(b.y).min(c.y).max(clip.min.y).floor() as i64;
+        let max_y = a.y.max(b.y).max(c.y).min(clip.max.y).ceil() as i64;
+
+        for y in min_y.max(0)..max_y {
+            for x in min_x.max(0)..max_x {
+                // Sample at the pixel center
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = ((b.x - p.x) * (c.y - p.y) - (b.y - p.y) * (c.x - p.x)) / area;
+                let w1 = ((c.x - p.x) * (a.y - p.y) - (c.y - p.y) * (a.x - p.x)) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let lerp =
+                    |f: &dyn Fn(&Vertex) -> f32| f(&v[0]) * w0 + f(&v[1]) * w1 + f(&v[2]) * w2;
+                let channel = |i: usize| lerp(&|v: &Vertex| v.color.to_array()[i] as f32);
+                let mut color = Color32::from_rgba_premultiplied(
+                    channel(0) as u8,
+                    channel(1) as u8,
+                    channel(2) as u8,
+                    channel(3) as u8,
+                );
+                if let Some(texture) = texture {
+                    let uv = Pos2::new(lerp(&|v| v.uv.x), lerp(&|v| v.uv.y));
+                    let t = texture.sample(uv);
+                    let mul = |c: u8, t: u8| ((c as u32 * t as u32 + 127) / 255) as u8;
+                    color = Color32::from_rgba_premultiplied(
+                        mul(color.r(), t.r()),
+                        mul(color.g(), t.g()),
+                        mul(color.b(), t.b()),
+                        mul(color.a(), t.a()),
+                    );
+                }
+                image.blend(x as usize, y as usize, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::io;
+use tiny_http::{Header, Method, Response, Server};
+
+use crate::data::{
+    DataSource, DataSourceError, DataSourceResult, EntryID, ItemUID, TileID, Warning,
+};
+use crate::deep_link::decode;
+use crate::timestamp::{Interval, Timestamp};
+use crate::wire::{hash, Format, Handshake};
+
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub address: String,
+    pub port: u16,
+
+    // Origin allowed to make requests from a web page (e.g., "*" for any),
+    // for when the viewer is served from somewhere else
+    pub cors_origin: Option<String>,
+}
+
+/// Serves a profile over HTTP, in the protocol read by RemoteDataSource.
+/// Requests are handled one at a time, until the process is killed.
+pub fn serve(mut data_source: Box<dyn DataSource>, options: &ServeOptions) -> io::Result<()> {
+    let server = Server::http((options.address.as_str(), options.port))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    // Everything the data source has warned about so far, for /warnings
+    let mut warnings: Vec<Warning> = Vec::new();
+    for request in server.incoming_requests() {
+        warnings.extend(data_source.poll_warnings());
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+        let query = Query::parse(query);
+        // Errors in the format itself can only be answered in JSON
+        let format = match &query {
+            Ok(query) => query.format().unwrap_or(Format::Json),
+            Err(_) => Format::Json,
+        };
+        let (status, body) = if *request.method() == Method::Options {
+            // CORS preflight
+            (204, Vec::new())
+        } else if *request.method() != Method::Get {
+            let error = DataSourceError::new("only GET is supported");
+            (405, format.encode(&error))
+        } else {
+            let result =
+                query.and_then(|query| respond(data_source.as_mut(), &warnings, path, &query));
+            match result {
+                Ok(body) => (200, body),
+                Err(error) => (500, format.encode(&error)),
+            }
+        };
+
+        // Clients keep responses, and only need them again if they change
+        // (e.g., the profile is served again after being regenerated)
+        let etag = if status == 200 {
+            Some(format!("\"{:016x}\"", hash(&body)))
+        } else {
+            None
+        };
+        let not_modified = etag.as_ref().map_or(false, |etag| {
+            request
+                .headers()
+                .iter()
+                .any(|h| h.field.equiv("If-None-Match") && h.value.as_str() == etag)
+        });
+        let (status, body) = if not_modified {
+            (304, Vec::new())
+        } else {
+            (status, body)
+        };
+
+        let mut response = Response::from_data(body)
+            .with_status_code(status)
+            .with_header(header("Content-Type", format.content_type()));
+        if let Some(etag) = &etag {
+            response.add_header(header("ETag", etag));
+            response.add_header(header("Cache-Control", "no-cache"));
+        }
+        if let Some(origin) = &options.cors_origin {
+            response.add_header(header("Access-Control-Allow-Origin", origin));
+            response.add_header(header("Access-Control-Allow-Methods", "GET, OPTIONS"));
+        }
+        if let Err(error) = request.respond(response) {
+            tracing::warn!(%error, "failed to respond");
+        }
+    }
+    Ok(())
+}
+
+fn respond(
+    data_source: &mut dyn DataSource,
+    warnings: &[Warning],
+    path: &str,
+    query: &Query,
+) -> DataSourceResult<Vec<u8>> {
+    let format = query.format()?;
+    match path {
+        "/handshake" => Ok(Format::Json.encode(&Handshake::current())),
+        "/interval" => Ok(format.encode(&data_source.interval()?)),
+        "/info" => Ok(format.encode(data_source.fetch_info()?)),
+        "/tiles" => {
+            let (entry_id, interval) = query.tile()?;
+            Ok(format.encode(&data_source.request_tiles(&entry_id, interval)?))
+        }
+        "/summary_tile" => {
+            let (entry_id, interval) = query.tile()?;
+            let tile = data_source.fetch_summary_tile(&entry_id, TileID(interval))?;
+            Ok(format.encode(&tile))
+        }
+        "/slot_tile" => {
+            let (entry_id, interval) = query.tile()?;
+            Ok(format.encode(&data_source.fetch_slot_tile(&entry_id, TileID(interval))?))
+        }
+        "/counter_tile" => {
+            let (entry_id, interval) = query.tile()?;
+            let tile = data_source.fetch_counter_tile(&entry_id, TileID(interval))?;
+            Ok(format.encode(&tile))
+        }
+        "/event_tile" => {
+            let (entry_id, interval) = query.tile()?;
+            Ok(format.encode(&data_source.fetch_event_tile(&entry_id, TileID(interval))?))
+        }
+        "/search" => Ok(format.encode(&data_source.search(query.get("query")?)?)),
+        "/item_links" => Ok(format.encode(&data_source.fetch_item_links(query.item_uid()?)?)),
+        "/epoch" => Ok(format.encode(&data_source.epoch()?)),
+        "/description" => Ok(format.encode(&data_source.fetch_description()?)),
+        "/warnings" => Ok(format.encode(warnings)),
+        _ => Err(DataSourceError::new(format!("unknown request '{}'", path))),
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+// Parameters of a request, as sent by RemoteDataSource
+struct Query(BTreeMap<String, String>);
+
+impl Query {
+    fn parse(query: &str) -> DataSourceResult<Self> {
+        let mut result = BTreeMap::new();
+        for part in query.split('&').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').unwrap_or((part, ""));
+            result.insert(key.to_owned(), decode(value).map_err(DataSourceError::new)?);
+        }
+        Ok(Self(result))
+    }
+
+    fn get(&self, key: &str) -> DataSourceResult<&str> {
+        self.0
+            .get(key)
+            .map(String::as_str)
+            .ok_or_else(|| DataSourceError::new(format!("missing parameter '{}'", key)))
+    }
+
+    fn number(&self, key: &str) -> DataSourceResult<i64> {
+        let value = self.get(key)?;
+        value
+            .parse()
+            .map_err(|_| DataSourceError::new(format!("invalid {} '{}'", key, value)))
+    }
+
+    // JSON unless asked otherwise, see wire::Format
+    fn format(&self) -> DataSourceResult<Format> {
+        self.0
+            .get("format")
+            .map_or(Ok(Format::Json), |name| Format::parse(name))
+    }
+
+    fn item_uid(&self) -> DataSourceResult<ItemUID> {
+        Ok(ItemUID(self.number("uid")? as u64))
+    }
+
+    // Inverse of tile_query in remote.rs
+    fn tile(&self) -> DataSourceResult<(EntryID, Interval)> {
+        let entry_id = self.get("entry")?.parse().map_err(DataSourceError::new)?;
+        let start = Timestamp(self.number("start")?);
+        let stop = Timestamp(self.number("stop")?);
+        Ok((entry_id, Interval::new(start, stop)))
+    }
+}
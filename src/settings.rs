@@ -0,0 +1,151 @@
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::theme::{self, ColorOverrides, Theme};
+
+/// Defaults read from a configuration file, e.g.:
+///
+/// ```toml
+/// server = "http://cluster:8080"   # opened when no profile is given
+/// cache_dir = "/scratch/me/cache"  # for served profiles
+///
+/// [theme]                          # as in the View menu
+/// appearance = "Dark"
+/// palette = "OkabeIto"
+///
+/// [colors]                         # items by processor kind
+/// gpu = "#44aa99"
+/// ```
+///
+/// The command line takes precedence over the file, which takes precedence
+/// over settings saved from the UI the last time the viewer ran.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub server: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub theme: Option<Theme>,
+    colors: BTreeMap<String, String>,
+}
+
+impl Settings {
+    /// Where the file is read from unless told otherwise, e.g.,
+    /// ~/.config/legion-prof-viewer/config.toml on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("legion-prof-viewer").join("config.toml"))
+    }
+
+    /// Where the colors chosen in the viewer (View > Item Colors) are kept,
+    /// next to the default configuration file.
+    pub fn colors_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("legion-prof-viewer").join("colors.toml"))
+    }
+
+    /// Reads the given file, or the one at the default path if there is
+    /// one (otherwise the defaults).
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None => match Self::default_path().filter(|path| path.exists()) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+        let text = std::fs::read_to_string(&path).map_err(|e| error(&e))?;
+        let settings = Self::parse(&text).map_err(|e| error(&e))?;
+        Ok(settings)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let settings: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        // Catch mistakes now, rather than ignoring them later
+        settings.kind_colors()?;
+        Ok(settings)
+    }
+
+    /// Colors to draw items in, by processor kind (in lowercase).
+    pub fn kind_colors(&self) -> Result<BTreeMap<String, Color32>, String> {
+        self.colors
+            .iter()
+            .map(|(kind, color)| {
+                let color = theme::parse_color(color).map_err(|e| format!("{}: {}", kind, e))?;
+                Ok((kind.to_lowercase(), color))
+            })
+            .collect()
+    }
+
+    /// Colors by kind from this file, and then anything chosen in the
+    /// viewer (see colors_path), which takes precedence.
+    pub fn color_overrides(&self) -> Result<ColorOverrides, String> {
+        let mut colors = ColorOverrides {
+            kinds: self.kind_colors()?,
+            ..Default::default()
+        };
+        if let Some(path) = Self::colors_path().filter(|path| path.exists()) {
+            colors.extend(load_colors(&path)?);
+        }
+        Ok(colors)
+    }
+
+    /// Where to cache served profiles, if anywhere.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir
+            .clone()
+            .or_else(|| dirs::cache_dir().map(|dir| dir.join("legion_prof_viewer")))
+    }
+}
+
+// As kept in colors.toml, e.g.:
+//
+//   [names]
+//   "fill instance" = "#4477aa"
+//
+//   [kinds]
+//   gpu = "#44aa99"
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+struct ColorFile {
+    names: BTreeMap<String, String>,
+    kinds: BTreeMap<String, String>,
+}
+
+pub fn load_colors(path: &Path) -> Result<ColorOverrides, String> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let text = std::fs::read_to_string(path).map_err(|e| error(&e))?;
+    let file: ColorFile = toml::from_str(&text).map_err(|e| error(&e))?;
+    let parse = |colors: BTreeMap<String, String>, lowercase: bool| {
+        colors
+            .into_iter()
+            .map(|(key, color)| {
+                let color = theme::parse_color(&color).map_err(|e| error(&e))?;
+                Ok((if lowercase { key.to_lowercase() } else { key }, color))
+            })
+            .collect::<Result<BTreeMap<_, _>, String>>()
+    };
+    Ok(ColorOverrides {
+        names: parse(file.names, false)?,
+        kinds: parse(file.kinds, true)?,
+    })
+}
+
+pub fn save_colors(path: &Path, colors: &ColorOverrides) -> Result<(), String> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let format = |colors: &BTreeMap<String, Color32>| {
+        colors
+            .iter()
+            .map(|(key, color)| (key.clone(), theme::format_color(*color)))
+            .collect()
+    };
+    let file = ColorFile {
+        names: format(&colors.names),
+        kinds: format(&colors.kinds),
+    };
+    let text = toml::to_string(&file).map_err(|e| error(&e))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| error(&e))?;
+    }
+    std::fs::write(path, text).map_err(|e| error(&e))
+}
@@ -0,0 +1,225 @@
+use egui::{Color32, Pos2, Rect};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SvgOptions {
+    pub width: f32, // total width of the image, in pixels
+    pub label_font_size: f32,
+    pub include_tooltips: bool, // emit <title> elements for items
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            width: 1200.0,
+            label_font_size: 12.0,
+            include_tooltips: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// Accumulates SVG elements. Coordinates are in the same space as egui
+/// (y grows downward), so layout code can be shared with the UI.
+pub struct SvgWriter {
+    options: SvgOptions,
+    body: String,
+    clip_count: u64,
+
+    // Where the most recent slot was drawn, used to place the time axis
+    timeline_rect: Option<Rect>,
+}
+
+impl SvgWriter {
+    pub fn new(options: SvgOptions) -> Self {
+        Self {
+            options,
+            body: String::new(),
+            clip_count: 0,
+            timeline_rect: None,
+        }
+    }
+
+    pub fn options(&self) -> &SvgOptions {
+        &self.options
+    }
+
+    pub fn timeline_rect(&self) -> Option<Rect> {
+        self.timeline_rect
+    }
+
+    pub fn set_timeline_rect(&mut self, rect: Rect) {
+        self.timeline_rect = Some(rect);
+    }
+
+    pub fn rect(
+        &mut self,
+        rect: Rect,
+        fill: Color32,
+        stroke: Option<Color32>,
+        title: Option<&str>,
+    ) {
+        write!(
+            self.body,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}"{}"#,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            paint("fill", fill)
+        )
+        .unwrap();
+        if let Some(stroke) = stroke {
+            self.body.push_str(&paint("stroke", stroke));
+        }
+        if let Some(title) = title.filter(|_| self.options.include_tooltips) {
+            writeln!(self.body, "><title>{}</title></rect>", escape(title)).unwrap();
+        } else {
+            self.body.push_str("/>\n");
+        }
+    }
+
+    pub fn line(&mut self, p1: Pos2, p2: Pos2, color: Color32, width: f32) {
+        writeln!(
+            self.body,
+            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-width="{}"{}/>"#,
+            p1.x,
+            p1.y,
+            p2.x,
+            p2.y,
+            width,
+            paint("stroke", color)
+        )
+        .unwrap();
+    }
+
+    pub fn polyline(&mut self, points: &[Pos2], color: Color32, width: f32) {
+        let points: Vec<_> = points
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+            .collect();
+        writeln!(
+            self.body,
+            r#"<polyline points="{}" fill="none" stroke-width="{}"{}/>"#,
+            points.join(" "),
+            width,
+            paint("stroke", color)
+        )
+        .unwrap();
+    }
+
+    pub fn polygon(&mut self, points: &[Pos2], fill: Color32) {
+        let points: Vec<_> = points
+            .iter()
+            .map(|p| format!("{:.2},{:.2}", p.x, p.y))
+            .collect();
+        writeln!(
+            self.body,
+            r#"<polygon points="{}"{}/>"#,
+            points.join(" "),
+            paint("fill", fill)
+        )
+        .unwrap();
+    }
+
+    // Draws text with the baseline at pos
+    pub fn text(
+        &mut self,
+        pos: Pos2,
+        font_size: f32,
+        anchor: TextAnchor,
+        color: Color32,
+        text: &str,
+        title: Option<&str>,
+    ) {
+        let anchor = match anchor {
+            TextAnchor::Start => "start",
+            TextAnchor::Middle => "middle",
+            TextAnchor::End => "end",
+        };
+        write!(
+            self.body,
+            r#"<text x="{:.2}" y="{:.2}" font-size="{}" text-anchor="{}"{}>{}"#,
+            pos.x,
+            pos.y,
+            font_size,
+            anchor,
+            paint("fill", color),
+            escape(text)
+        )
+        .unwrap();
+        if let Some(title) = title.filter(|_| self.options.include_tooltips) {
+            write!(self.body, "<title>{}</title>", escape(title)).unwrap();
+        }
+        self.body.push_str("</text>\n");
+    }
+
+    // Everything drawn until the matching end_clip is clipped to rect
+    pub fn begin_clip(&mut self, rect: Rect) {
+        self.clip_count += 1;
+        writeln!(
+            self.body,
+            r#"<clipPath id="clip{0}"><rect x="{1:.2}" y="{2:.2}" width="{3:.2}" height="{4:.2}"/></clipPath><g clip-path="url(#clip{0})">"#,
+            self.clip_count,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height()
+        )
+        .unwrap();
+    }
+
+    pub fn end_clip(&mut self) {
+        self.body.push_str("</g>\n");
+    }
+
+    pub fn finish(self, width: f32, height: f32, background: Color32) -> String {
+        let mut result = String::new();
+        writeln!(
+            result,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0:.0}" height="{1:.0}" viewBox="0 0 {0:.0} {1:.0}" font-family="sans-serif">"#,
+            width, height
+        )
+        .unwrap();
+        writeln!(
+            result,
+            r#"<rect width="100%" height="100%"{}/>"#,
+            paint("fill", background)
+        )
+        .unwrap();
+        result.push_str(&self.body);
+        result.push_str("</svg>\n");
+        result
+    }
+}
+
+fn paint(attr: &str, color: Color32) -> String {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut result = format!(r#" {}="rgb({},{},{})""#, attr, r, g, b);
+    if a != 255 {
+        write!(result, r#" {}-opacity="{:.3}""#, attr, a as f32 / 255.0).unwrap();
+    }
+    result
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
@@ -0,0 +1,649 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+use crate::data::{
+    Allocation, Color, CopyEndpoints, CounterPoint, CounterStyle, CounterTile, DataSource,
+    DataSourceResult, Description, EntryID, EntryInfo, Event, EventTile, Field, Item, ItemLink,
+    ItemLinkKind, ItemSegment, ItemState, ItemUID, SlotTile, SummaryTile, TileID, UtilPoint,
+    UtilSeries, Warning,
+};
+use crate::timestamp::{Interval, Timestamp};
+
+// Processor kinds stacked in each node's summary, with the share of the
+// node's utilization that each one accounts for
+const STACKED_KINDS: &[(&str, f32)] = &[
+    ("CPU", 0.4),
+    ("GPU", 0.3),
+    ("OMP", 0.15),
+    ("Py", 0.1),
+    ("Util", 0.05),
+];
+
+// Processor kinds (and channels and memories) of each node
+const KINDS: &[&str] = &["CPU", "GPU", "OMP", "Py", "Util", "Chan", "SysMem"];
+
+// Names given to items, so that search and coloring have something to work
+// with. Items with the same name share a color.
+const ITEM_NAMES: &[&str] = &[
+    "init_mesh",
+    "compute_fluxes",
+    "apply_boundary",
+    "exchange_ghosts",
+    "reduce_residual",
+    "update_solution",
+    "copy",
+    "fill",
+    "partition_by_field",
+    "checkpoint",
+];
+
+const ITEM_COLORS: &[Color] = &[
+    Color::BLUE,
+    Color::GREEN,
+    Color::RED,
+    Color::YELLOW,
+    Color::KHAKI,
+    Color::DARK_GREEN,
+    Color::DARK_BLUE,
+];
+
+/// Shape of the profile generated by RandomDataSource.
+#[derive(Debug, Clone)]
+pub struct RandomOptions {
+    // Sources with the same options generate the same profile
+    pub seed: u64,
+    pub nodes: u64,
+    pub procs_per_kind: u64,
+    pub max_rows: u64, // each slot gets somewhere below this many rows
+    pub items_per_row: u64,
+}
+
+impl Default for RandomOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            nodes: 8192,
+            procs_per_kind: 8,
+            max_rows: 64,
+            items_per_row: 1000,
+        }
+    }
+}
+
+/// Generates a synthetic profile (e.g., for demos and benchmarks) with
+/// every kind of entry: stacked and banded summaries, slots, counters and
+/// events. Everything is derived from the seed and the entry, so the data
+/// doesn't depend on the order it's fetched in.
+pub struct RandomDataSource {
+    options: RandomOptions,
+    info: Option<EntryInfo>,
+    interval: Option<Interval>,
+    summary_cache: BTreeMap<EntryID, Vec<UtilPoint>>,
+    slot_cache: BTreeMap<EntryID, Vec<Vec<Item>>>,
+    counter_cache: BTreeMap<EntryID, Vec<CounterPoint>>,
+    event_cache: BTreeMap<EntryID, Vec<Event>>,
+    item_locations: BTreeMap<ItemUID, (EntryID, usize, usize)>, // slot, row, index
+    warned: bool,
+}
+
+impl Default for RandomDataSource {
+    fn default() -> Self {
+        Self::new(RandomOptions::default())
+    }
+}
+
+impl RandomDataSource {
+    pub fn new(options: RandomOptions) -> Self {
+        Self {
+            options,
+            info: None,
+            interval: None,
+            summary_cache: BTreeMap::new(),
+            slot_cache: BTreeMap::new(),
+            counter_cache: BTreeMap::new(),
+            event_cache: BTreeMap::new(),
+            item_locations: BTreeMap::new(),
+            warned: false,
+        }
+    }
+
+    // A generator for one part of the profile (FNV-1a of the name, mixed
+    // with the seed)
+    fn rng(&self, name: &str) -> StdRng {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64 ^ self.options.seed;
+        for byte in name.bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        StdRng::seed_from_u64(hash)
+    }
+
+    fn generate_point(
+        rng: &mut StdRng,
+        first: UtilPoint,
+        last: UtilPoint,
+        level: i32,
+        max_level: i32,
+        utilization: &mut Vec<UtilPoint>,
+    ) {
+        let time = Timestamp((first.time.0 + last.time.0) / 2);
+        let util = (first.util + last.util) * 0.5;
+        let diff = (rng.gen::<f32>() - 0.5) / 1.2_f32.powi(max_level - level);
+        let util = (util + diff).clamp(0.0, 1.0);
+        let point = UtilPoint { time, util };
+        if level > 0 {
+            Self::generate_point(rng, first, point, level - 1, max_level, utilization);
+        }
+        utilization.push(point);
+        if level > 0 {
+            Self::generate_point(rng, point, last, level - 1, max_level, utilization);
+        }
+    }
+
+    fn generate_summary(&mut self, entry_id: &EntryID) -> &Vec<UtilPoint> {
+        if !self.summary_cache.contains_key(entry_id) {
+            const LEVELS: i32 = 8;
+            let interval = self.generate_interval();
+            let mut rng = self.rng(&format!("summary {}", entry_id));
+            let first = UtilPoint {
+                time: interval.start,
+                util: rng.gen(),
+            };
+            let last = UtilPoint {
+                time: interval.stop,
+                util: rng.gen(),
+            };
+            let mut utilization = Vec::new();
+            utilization.push(first);
+            Self::generate_point(&mut rng, first, last, LEVELS, LEVELS, &mut utilization);
+            utilization.push(last);
+
+            self.summary_cache.insert(entry_id.clone(), utilization);
+        }
+        self.summary_cache.get(entry_id).unwrap()
+    }
+
+    fn generate_counter(&mut self, entry_id: &EntryID) -> &Vec<CounterPoint> {
+        if !self.counter_cache.contains_key(entry_id) {
+            let entry = self.generate_info().get(entry_id);
+
+            let max_value = if let Some(EntryInfo::Counter { unit, .. }) = entry {
+                if unit == "B" {
+                    16.0 * (1u64 << 30) as f64
+                } else {
+                    32.0
+                }
+            } else {
+                unreachable!();
+            };
+
+            const N: u64 = 1000;
+            let interval = self.generate_interval();
+            let mut rng = self.rng(&format!("counter {}", entry_id));
+            let mut value = rng.gen::<f64>() * max_value;
+            let mut points = Vec::new();
+            for i in 0..N {
+                let step = (rng.gen::<f64>() - 0.5) * max_value * 0.1;
+                value = (value + step).clamp(0.0, max_value).round();
+                points.push(CounterPoint {
+                    time: interval.lerp(i as f64 / N as f64),
+                    value,
+                });
+            }
+
+            self.counter_cache.insert(entry_id.clone(), points);
+        }
+        self.counter_cache.get(entry_id).unwrap()
+    }
+
+    fn generate_events(&mut self, entry_id: &EntryID) -> &Vec<Event> {
+        if !self.event_cache.contains_key(entry_id) {
+            const N: u64 = 20;
+            let interval = self.generate_interval();
+            let mut rng = self.rng(&format!("events {}", entry_id));
+            let mut times: Vec<_> = (0..N).map(|_| interval.lerp(rng.gen())).collect();
+            times.sort();
+            let events = times
+                .into_iter()
+                .enumerate()
+                .map(|(i, time)| Event {
+                    time,
+                    title: format!("Barrier {}", i),
+                    fields: vec![("Participants".to_owned(), Field::U64(rng.gen_range(1..64)))],
+                })
+                .collect();
+
+            self.event_cache.insert(entry_id.clone(), events);
+        }
+        self.event_cache.get(entry_id).unwrap()
+    }
+
+    fn generate_slot(&mut self, entry_id: &EntryID) -> &Vec<Vec<Item>> {
+        if !self.slot_cache.contains_key(entry_id) {
+            let entry = self.generate_info().get(entry_id);
+
+            let max_rows = if let EntryInfo::Slot { max_rows, .. } = entry.unwrap() {
+                *max_rows
+            } else {
+                panic!("trying to fetch tile on something that is not a slot")
+            };
+
+            let interval = self.generate_interval();
+            let mut rng = self.rng(&format!("slot {}", entry_id));
+            // Each slot gets its own range of UIDs, numbered by position
+            let slot = (0..entry_id.level())
+                .map(|level| entry_id.slot_index(level).unwrap())
+                .fold(0, |acc, index| {
+                    acc * (self.options.procs_per_kind + KINDS.len() as u64) + index
+                });
+            let first_uid = slot << 32;
+
+            // Copies go from this node's system memory to the next node's
+            // (and some are fills, with no source)
+            let kind_index = |name| KINDS.iter().position(|k| *k == name).unwrap() as u64;
+            let is_channel = entry_id.slot_index(1) == Some(kind_index("Chan"));
+            let is_memory = entry_id.slot_index(1) == Some(kind_index("SysMem"));
+            let node = entry_id.slot_index(0).unwrap();
+            let memory = |node: u64, i: u64| {
+                EntryID::root()
+                    .child(node % self.options.nodes)
+                    .child(kind_index("SysMem"))
+                    .child(i % self.options.procs_per_kind)
+            };
+
+            let n = self.options.items_per_row;
+            let mut items = Vec::new();
+            for row in 0..max_rows {
+                let mut row_items = Vec::new();
+                for i in 0..n {
+                    let start = interval.lerp((i as f64 + 0.05) / (n as f64));
+                    let stop = interval.lerp((i as f64 + 0.95) / (n as f64));
+
+                    let name = rng.gen_range(0..ITEM_NAMES.len());
+                    let color = ITEM_COLORS[name % ITEM_COLORS.len()];
+
+                    let item_uid = ItemUID(first_uid + row * n + i);
+                    self.item_locations
+                        .insert(item_uid, (entry_id.clone(), row as usize, i as usize));
+
+                    // Some items spend part of their time waiting
+                    let interval = Interval::new(start, stop);
+                    let mut segments = Vec::new();
+                    if i % 4 == 0 {
+                        segments.push(ItemSegment {
+                            interval: Interval::new(interval.lerp(0.3), interval.lerp(0.6)),
+                            state: ItemState::Waiting,
+                        });
+                    }
+
+                    // Instances in each row get their own range of addresses,
+                    // so that they never overlap
+                    const ROW_BYTES: u64 = 64 << 20;
+                    let allocation = is_memory.then(|| Allocation {
+                        offset: row * ROW_BYTES + (i % 3) * ROW_BYTES / 4,
+                        size: ROW_BYTES / 4 * (1 + i % 2),
+                    });
+                    let mut fields = vec![
+                        ("Interval".to_owned(), Field::Interval(interval)),
+                        ("Memory".to_owned(), Field::Bytes(256 << (i % 24))),
+                        (
+                            "Efficiency".to_owned(),
+                            Field::Percent((i % 20) as f64 / 20.0),
+                        ),
+                    ];
+                    if let Some(allocation) = allocation {
+                        fields.push(("Size".to_owned(), Field::Bytes(allocation.size)));
+                    }
+
+                    row_items.push(Item {
+                        item_uid,
+                        interval,
+                        color,
+                        title: ITEM_NAMES[name].to_owned(),
+                        fields,
+                        segments,
+                        endpoints: is_channel.then(|| CopyEndpoints {
+                            source: (i % 8 != 0).then(|| memory(node, i)),
+                            target: Some(memory(node + 1, i)),
+                        }),
+                        allocation,
+                    });
+                }
+                items.push(row_items);
+            }
+
+            self.slot_cache.insert(entry_id.clone(), items);
+        }
+        self.slot_cache.get(entry_id).unwrap()
+    }
+}
+
+impl RandomDataSource {
+    fn generate_interval(&mut self) -> Interval {
+        if let Some(interval) = self.interval {
+            return interval;
+        }
+        let mut rng = self.rng("interval");
+        let interval = Interval::new(Timestamp(0), Timestamp(rng.gen_range(1_000_000..2_000_000)));
+        self.interval = Some(interval);
+        interval
+    }
+
+    fn generate_info(&mut self) -> &EntryInfo {
+        if let Some(ref info) = self.info {
+            return info;
+        }
+
+        let colors = &[Color::BLUE, Color::GREEN, Color::RED, Color::YELLOW];
+        let mut rng = self.rng("info");
+        let mut node_slots = Vec::new();
+        for node in 0..self.options.nodes {
+            let mut kind_slots = Vec::new();
+            for (i, &kind) in KINDS.iter().enumerate() {
+                let color = colors[i % colors.len()];
+                let mut proc_slots = Vec::new();
+                for proc in 0..self.options.procs_per_kind {
+                    let rows: u64 = rng.gen_range(0..self.options.max_rows.max(1));
+                    proc_slots.push(EntryInfo::Slot {
+                        short_name: format!(
+                            "{}{}",
+                            kind.chars().next().unwrap().to_lowercase(),
+                            proc
+                        ),
+                        long_name: format!("Node {} {} {}", node, kind, proc),
+                        max_rows: rows,
+                    });
+                }
+                let counter = match kind {
+                    "Chan" => Some(("queue", "Queue Depth", "", CounterStyle::Line)),
+                    "SysMem" => Some(("used", "Usage", "B", CounterStyle::Bar)),
+                    _ => None,
+                };
+                if kind == "Util" {
+                    proc_slots.push(EntryInfo::Events {
+                        short_name: "ev".to_owned(),
+                        long_name: format!("Node {} Barriers", node),
+                        color: Color::LIGHT_RED,
+                        global: true,
+                    });
+                }
+                if let Some((short_name, long_name, unit, style)) = counter {
+                    proc_slots.push(EntryInfo::Counter {
+                        short_name: short_name.to_owned(),
+                        long_name: format!("Node {} {} {}", node, kind, long_name),
+                        unit: unit.to_owned(),
+                        color,
+                        style,
+                    });
+                }
+                kind_slots.push(EntryInfo::Panel {
+                    short_name: kind.to_lowercase(),
+                    long_name: format!("Node {} {}", node, kind),
+                    summary: Some(Box::new(EntryInfo::Summary { color })),
+                    slots: proc_slots,
+                });
+            }
+            node_slots.push(EntryInfo::Panel {
+                short_name: format!("n{}", node),
+                long_name: format!("Node {}", node),
+                summary: Some(Box::new(EntryInfo::Aggregate {
+                    layers: STACKED_KINDS
+                        .iter()
+                        .zip(colors.iter().cycle())
+                        .map(|((kind, _), color)| (kind.to_string(), *color))
+                        .collect(),
+                })),
+                slots: kind_slots,
+            });
+        }
+        self.info = Some(EntryInfo::Panel {
+            short_name: "root".to_owned(),
+            long_name: "root".to_owned(),
+            summary: None,
+            slots: node_slots,
+        });
+        self.info.as_ref().unwrap()
+    }
+}
+
+impl DataSource for RandomDataSource {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        Ok(self.generate_interval())
+    }
+
+    // Midnight (UTC) on January 1, 2023, so that the wall-clock time is
+    // easy to check
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        Ok(Some(1_672_531_200 * 1_000_000_000))
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        let options = &self.options;
+        // No application name, so the window keeps its usual title
+        Ok(Description {
+            machine: Some("synthetic".to_owned()),
+            nodes: Some(options.nodes),
+            fields: vec![
+                ("Seed".to_owned(), Field::U64(options.seed)),
+                (
+                    "Items per Row".to_owned(),
+                    Field::U64(options.items_per_row),
+                ),
+            ],
+            ..Default::default()
+        })
+    }
+
+    // One made-up warning, to show what they look like
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        if self.options.nodes < 2 || std::mem::replace(&mut self.warned, true) {
+            return Vec::new();
+        }
+        vec![Warning {
+            message: "clock skew of up to 2 us detected on node 1".to_owned(),
+            entry_id: Some(EntryID::root().child(1)),
+        }]
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        Ok(self.generate_info())
+    }
+
+    fn request_tiles(
+        &mut self,
+        _entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        let duration = request_interval.duration_ns();
+
+        const TILES: i64 = 3;
+
+        let mut tiles = Vec::new();
+        for i in 0..TILES {
+            let start = Timestamp(i * duration / TILES + request_interval.start.0);
+            let stop = Timestamp((i + 1) * duration / TILES + request_interval.start.0);
+            tiles.push(TileID(Interval::new(start, stop)));
+        }
+        Ok(tiles)
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        let utilization = self.generate_summary(entry_id);
+
+        let mut tile_utilization = Vec::new();
+        let mut last_point = None;
+        for point in utilization {
+            let UtilPoint { time, util } = *point;
+            if let Some(last_point) = last_point {
+                let UtilPoint {
+                    time: last_time,
+                    util: last_util,
+                } = last_point;
+
+                let last_interval = Interval::new(last_time, time);
+                if last_interval.contains(tile_id.0.start) {
+                    let relative = last_interval.unlerp(tile_id.0.start) as f32;
+                    let start_util = (util - last_util) * relative + last_util;
+                    tile_utilization.push(UtilPoint {
+                        time: tile_id.0.start,
+                        util: start_util,
+                    });
+                }
+                if tile_id.0.contains(time) {
+                    tile_utilization.push(*point);
+                }
+                if last_interval.contains(tile_id.0.stop) {
+                    let relative = last_interval.unlerp(tile_id.0.stop) as f32;
+                    let stop_util = (util - last_util) * relative + last_util;
+                    tile_utilization.push(UtilPoint {
+                        time: tile_id.0.stop,
+                        util: stop_util,
+                    });
+                }
+            }
+
+            last_point = Some(*point);
+        }
+        // Node summaries are stacked by kind, everything else has a band
+        let is_node = entry_id.level() == 2;
+        let layers = STACKED_KINDS.iter().map(|(kind, share)| UtilSeries {
+            name: kind.to_string(),
+            utilization: tile_utilization
+                .iter()
+                .map(|p| UtilPoint {
+                    time: p.time,
+                    util: p.util * share,
+                })
+                .collect(),
+        });
+
+        // Pretend the nodes under this summary vary around the average
+        let band = |name: &str, f: fn(f32) -> f32| UtilSeries {
+            name: name.to_owned(),
+            utilization: tile_utilization
+                .iter()
+                .map(|p| UtilPoint {
+                    time: p.time,
+                    util: f(p.util),
+                })
+                .collect(),
+        };
+        let series = if is_node {
+            layers.collect()
+        } else {
+            vec![
+                band(UtilSeries::MIN, |util| util * 0.5),
+                band(UtilSeries::MAX, |util| util + (1.0 - util) * 0.5),
+            ]
+        };
+
+        Ok(SummaryTile {
+            tile_id,
+            utilization: tile_utilization,
+            series,
+        })
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        let items = self.generate_slot(entry_id);
+
+        let mut slot_items = Vec::new();
+        for row in items {
+            let mut slot_row = Vec::new();
+            for item in row {
+                // When the item straddles a tile boundary, it has to be
+                // sliced to fit
+                if tile_id.0.overlaps(item.interval) {
+                    let mut new_item = item.clone();
+                    new_item.interval = new_item.interval.intersection(tile_id.0);
+                    slot_row.push(new_item);
+                }
+            }
+            slot_items.push(slot_row);
+        }
+
+        Ok(SlotTile {
+            tile_id,
+            items: slot_items,
+        })
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        let points = self.generate_counter(entry_id);
+
+        // Start with whatever value is in effect at the start of the tile
+        let first = points.partition_point(|p| p.time <= tile_id.0.start);
+        let mut tile_points = Vec::new();
+        if let Some(p) = first.checked_sub(1).map(|i| points[i]) {
+            tile_points.push(CounterPoint {
+                time: tile_id.0.start,
+                value: p.value,
+            });
+        }
+        tile_points.extend(
+            points[first..]
+                .iter()
+                .take_while(|p| p.time < tile_id.0.stop),
+        );
+
+        Ok(CounterTile {
+            tile_id,
+            points: tile_points,
+        })
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        let events = self
+            .generate_events(entry_id)
+            .iter()
+            .filter(|event| tile_id.0.contains(event.time))
+            .cloned()
+            .collect();
+        Ok(EventTile { tile_id, events })
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        let (entry_id, row, index) = if let Some(location) = self.item_locations.get(&item_uid) {
+            location.clone()
+        } else {
+            return Ok(Vec::new());
+        };
+        let items = &self.slot_cache[&entry_id][row];
+
+        // Pretend each item depends on the previous item in the same row
+        let mut links = Vec::new();
+        let mut add_link = |kind, item: &Item| {
+            links.push(ItemLink {
+                kind,
+                item_uid: item.item_uid,
+                entry_id: entry_id.clone(),
+                row: row as u64,
+                interval: item.interval,
+                title: item.title.clone(),
+            });
+        };
+        if let Some(prev) = index.checked_sub(1).and_then(|i| items.get(i)) {
+            add_link(ItemLinkKind::Predecessor, prev);
+        }
+        if let Some(next) = items.get(index + 1) {
+            add_link(ItemLinkKind::Successor, next);
+        }
+        Ok(links)
+    }
+}
@@ -0,0 +1,206 @@
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Categorical palettes that colors from the data source can be remapped
+/// onto. All but the original are chosen to remain distinguishable with
+/// the common forms of color blindness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Palette {
+    #[default]
+    Original,
+    OkabeIto,
+    TolBright,
+    TolMuted,
+}
+
+/// Whether the UI is drawn dark or light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Appearance {
+    #[default]
+    System, // follow the OS (or browser), if it says
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Theme {
+    pub appearance: Appearance,
+    pub palette: Palette,
+    pub saturation: f32, // 1.0 leaves colors as they are, 0.0 is grayscale
+}
+
+const OKABE_ITO: &[Color32] = &[
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(240, 228, 66),
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(204, 121, 167),
+    Color32::from_rgb(153, 153, 153), // instead of black
+];
+
+const TOL_BRIGHT: &[Color32] = &[
+    Color32::from_rgb(68, 119, 170),
+    Color32::from_rgb(102, 204, 238),
+    Color32::from_rgb(34, 136, 51),
+    Color32::from_rgb(204, 187, 68),
+    Color32::from_rgb(238, 102, 119),
+    Color32::from_rgb(170, 51, 119),
+    Color32::from_rgb(187, 187, 187),
+];
+
+const TOL_MUTED: &[Color32] = &[
+    Color32::from_rgb(51, 34, 136),
+    Color32::from_rgb(136, 204, 238),
+    Color32::from_rgb(68, 170, 153),
+    Color32::from_rgb(17, 119, 51),
+    Color32::from_rgb(153, 153, 51),
+    Color32::from_rgb(221, 204, 119),
+    Color32::from_rgb(204, 102, 119),
+    Color32::from_rgb(136, 34, 85),
+    Color32::from_rgb(170, 68, 153),
+];
+
+impl Palette {
+    pub const ALL: &'static [Palette] = &[
+        Palette::Original,
+        Palette::OkabeIto,
+        Palette::TolBright,
+        Palette::TolMuted,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Original => "Original",
+            Palette::OkabeIto => "Okabe-Ito",
+            Palette::TolBright => "Tol Bright",
+            Palette::TolMuted => "Tol Muted",
+        }
+    }
+
+    fn colors(self) -> &'static [Color32] {
+        match self {
+            Palette::Original => &[],
+            Palette::OkabeIto => OKABE_ITO,
+            Palette::TolBright => TOL_BRIGHT,
+            Palette::TolMuted => TOL_MUTED,
+        }
+    }
+}
+
+impl Appearance {
+    pub const ALL: &'static [Appearance] =
+        &[Appearance::System, Appearance::Dark, Appearance::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Appearance::System => "System",
+            Appearance::Dark => "Dark",
+            Appearance::Light => "Light",
+        }
+    }
+
+    // Dark unless told otherwise, since that's egui's default
+    pub fn is_dark(self, system_dark: Option<bool>) -> bool {
+        match self {
+            Appearance::System => system_dark.unwrap_or(true),
+            Appearance::Dark => true,
+            Appearance::Light => false,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            appearance: Appearance::System,
+            palette: Palette::Original,
+            saturation: 1.0,
+        }
+    }
+}
+
+/// Parses a color written as in CSS, e.g., #4477aa (the # is optional).
+pub fn parse_color(text: &str) -> Result<Color32, String> {
+    let error = || format!("expected a color like #4477aa, got '{}'", text);
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(error());
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| error());
+    Ok(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Formats a color so that parse_color reads it back.
+pub fn format_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// Colors chosen by the user for items, in place of those from the data
+/// source: by the item's name, or else by its processor kind.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColorOverrides {
+    pub names: BTreeMap<String, Color32>,
+    pub kinds: BTreeMap<String, Color32>, // by short name, in lowercase
+}
+
+impl ColorOverrides {
+    pub fn get(&self, name: &str, kind: Option<&str>) -> Option<Color32> {
+        let by_kind = || kind.and_then(|kind| self.kinds.get(kind));
+        self.names.get(name).or_else(by_kind).copied()
+    }
+
+    /// Adds the other's colors, which take precedence.
+    pub fn extend(&mut self, other: ColorOverrides) {
+        self.names.extend(other.names);
+        self.kinds.extend(other.kinds);
+    }
+}
+
+impl Theme {
+    // Vivid colors glare against a dark background, so tone them down there
+    const DARK_SATURATION: f32 = 0.8;
+
+    /// Text color that stays readable on top of the given color.
+    pub fn text_on(color: Color32) -> Color32 {
+        let [r, g, b, _] = color.to_srgba_unmultiplied();
+        let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+        if luma > 140.0 {
+            Color32::BLACK
+        } else {
+            Color32::WHITE
+        }
+    }
+
+    /// Remaps a color from the data source for display. The same input
+    /// color always maps to the same palette entry, so items that shared a
+    /// color still do.
+    pub fn apply(&self, color: Color32, dark_mode: bool) -> Color32 {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        let colors = self.palette.colors();
+        let [r, g, b] = if colors.is_empty() {
+            [r, g, b]
+        } else {
+            let hash = u32::from_le_bytes([r, g, b, 0]).wrapping_mul(0x9e37_79b1) >> 16;
+            let c = colors[hash as usize % colors.len()];
+            [c.r(), c.g(), c.b()]
+        };
+
+        let saturation = if dark_mode {
+            self.saturation * Self::DARK_SATURATION
+        } else {
+            self.saturation
+        };
+        // Mix with the gray of the same luma
+        let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+        let mix = |c: u8| {
+            (luma + (c as f32 - luma) * saturation)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        Color32::from_rgba_unmultiplied(mix(r), mix(g), mix(b), a)
+    }
+}
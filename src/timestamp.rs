@@ -1,41 +1,246 @@
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
 pub struct Timestamp(pub i64 /* ns */);
 
 impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.display(TimeFormat::current()).fmt(f)
+    }
+}
+
+/// A Timestamp displayed in a given format, rather than the thread's.
+pub struct FormattedTimestamp(Timestamp, TimeFormat);
+
+impl fmt::Display for FormattedTimestamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Time is stored in nanoseconds. But display in larger units if possible.
-        let ns = self.0;
-        let ns_per_us = 1_000;
-        let ns_per_ms = 1_000_000;
-        let ns_per_s = 1_000_000_000;
-        let divisor;
-        let remainder_divisor;
-        let mut unit_name = "ns";
-        if ns >= ns_per_s {
-            divisor = ns_per_s;
-            remainder_divisor = divisor / 1_000;
-            unit_name = "s";
-        } else if ns >= ns_per_ms {
-            divisor = ns_per_ms;
-            remainder_divisor = divisor / 1_000;
-            unit_name = "ms";
-        } else if ns >= ns_per_us {
-            divisor = ns_per_us;
-            remainder_divisor = divisor / 1_000;
-            unit_name = "us";
-        } else {
-            return write!(f, "{} {}", ns, unit_name);
+        let FormattedTimestamp(time, format) = self;
+        let unit = format.unit_for(time.0.unsigned_abs());
+        format.write_number(f, time.0, unit)?;
+        write!(f, " {}", unit.suffix())
+    }
+}
+
+/// How times are written out. Display uses the format set for the thread
+/// (see TimeFormat::set), so that the viewer's settings apply everywhere.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TimeFormat {
+    pub unit: TimeUnit,
+    pub precision: u32,    // digits after the decimal point, at most 9
+    pub engineering: bool, // e.g., 1.500e-3 s rather than 1.500 ms
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TimeUnit {
+    Auto, // the largest unit that the time is at least one of
+    Seconds,
+    Milliseconds,
+    Microseconds,
+    Nanoseconds,
+}
+
+thread_local! {
+    static CURRENT_FORMAT: Cell<TimeFormat> = Cell::new(TimeFormat::default());
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self {
+            unit: TimeUnit::Auto,
+            precision: 3,
+            engineering: false,
         }
-        let units = ns / divisor;
-        let remainder = (ns % divisor) / remainder_divisor;
-        write!(f, "{}.{:0>3} {}", units, remainder, unit_name)
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Deserialize, Serialize)]
+impl TimeFormat {
+    pub const MAX_PRECISION: u32 = 9;
+
+    pub fn current() -> Self {
+        CURRENT_FORMAT.with(Cell::get)
+    }
+
+    /// Use this format for displaying times on this thread from now on.
+    pub fn set(self) {
+        CURRENT_FORMAT.with(|format| format.set(self));
+    }
+
+    // By magnitude, so that negative times get the same unit as positive ones
+    fn unit_for(self, magnitude: u64) -> TimeUnit {
+        match self.unit {
+            TimeUnit::Auto if self.engineering => TimeUnit::Seconds,
+            TimeUnit::Auto if magnitude >= TimeUnit::Seconds.ns() => TimeUnit::Seconds,
+            TimeUnit::Auto if magnitude >= TimeUnit::Milliseconds.ns() => TimeUnit::Milliseconds,
+            TimeUnit::Auto if magnitude >= TimeUnit::Microseconds.ns() => TimeUnit::Microseconds,
+            TimeUnit::Auto => TimeUnit::Nanoseconds,
+            unit => unit,
+        }
+    }
+
+    // Just the number, in the given unit
+    fn write_number(self, f: &mut fmt::Formatter<'_>, ns: i64, unit: TimeUnit) -> fmt::Result {
+        let precision = self.precision.min(Self::MAX_PRECISION);
+        if self.engineering {
+            let value = ns as f64 / unit.ns() as f64;
+            let mut exponent = if value == 0.0 {
+                0
+            } else {
+                (value.abs().log10() / 3.0).floor() as i32 * 3
+            };
+            let mut mantissa = value / 10f64.powi(exponent);
+            // Rounding may carry over into the next power of a thousand
+            let scale = 10f64.powi(precision as i32);
+            if (mantissa.abs() * scale).round() / scale >= 1000.0 {
+                mantissa /= 1000.0;
+                exponent += 3;
+            }
+            return write!(f, "{:.*}e{}", precision as usize, mantissa, exponent);
+        }
+        // Nanoseconds are whole numbers
+        if unit == TimeUnit::Nanoseconds {
+            return write!(f, "{}", ns);
+        }
+        // Digits are of the magnitude, so that, e.g., -0.5 ms keeps its sign
+        let sign = if ns < 0 { "-" } else { "" };
+        let magnitude = ns.unsigned_abs();
+        let divisor = unit.ns();
+        let units = magnitude / divisor;
+        if precision == 0 {
+            return write!(f, "{}{}", sign, units);
+        }
+        let fraction = (magnitude % divisor) as u128 * 10u128.pow(precision) / divisor as u128;
+        write!(
+            f,
+            "{}{}.{:0>width$}",
+            sign,
+            units,
+            fraction,
+            width = precision as usize
+        )
+    }
+}
+
+impl TimeUnit {
+    pub const ALL: &'static [TimeUnit] = &[
+        TimeUnit::Auto,
+        TimeUnit::Seconds,
+        TimeUnit::Milliseconds,
+        TimeUnit::Microseconds,
+        TimeUnit::Nanoseconds,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeUnit::Auto => "Auto",
+            TimeUnit::Seconds => "Seconds",
+            TimeUnit::Milliseconds => "Milliseconds",
+            TimeUnit::Microseconds => "Microseconds",
+            TimeUnit::Nanoseconds => "Nanoseconds",
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            TimeUnit::Auto | TimeUnit::Nanoseconds => "ns",
+            TimeUnit::Seconds => "s",
+            TimeUnit::Milliseconds => "ms",
+            TimeUnit::Microseconds => "us",
+        }
+    }
+
+    fn ns(self) -> u64 {
+        match self {
+            TimeUnit::Auto | TimeUnit::Nanoseconds => 1,
+            TimeUnit::Seconds => 1_000_000_000,
+            TimeUnit::Milliseconds => 1_000_000,
+            TimeUnit::Microseconds => 1_000,
+        }
+    }
+}
+
+impl Timestamp {
+    pub fn display(self, format: TimeFormat) -> FormattedTimestamp {
+        FormattedTimestamp(self, format)
+    }
+
+    /// Parses a time with an optional unit (ns, us, ms or s), e.g., "1.5ms"
+    /// or "2.3 s". Bare numbers are nanoseconds.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (number, unit) = split_unit(text);
+        Self::parse_with_unit(number, unit)
+            .ok_or_else(|| format!("invalid time '{}' (e.g., 1.5ms)", text.trim()))
+    }
+
+    fn parse_with_unit(number: &str, unit: &str) -> Option<Self> {
+        let number: f64 = number.parse().ok()?;
+        let scale = match unit {
+            "" | "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            _ => return None,
+        };
+        Some(Timestamp((number * scale).round() as i64))
+    }
+
+    /// Shows the timestamp as the date and time (UTC) it happened at, given
+    /// the wall-clock time of t = 0 in ns since the Unix epoch (as returned
+    /// by DataSource::epoch).
+    pub fn wall_clock(self, epoch: i64) -> WallClock {
+        WallClock(self.0.saturating_add(epoch))
+    }
+}
+
+/// A time in ns since the Unix epoch, displayed as, e.g.,
+/// `2023-01-01 12:34:56.789012345 UTC`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct WallClock(pub i64);
+
+impl fmt::Display for WallClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const NS_PER_S: i64 = 1_000_000_000;
+        const S_PER_DAY: i64 = 86_400;
+        let seconds = self.0.div_euclid(NS_PER_S);
+        let ns = self.0.rem_euclid(NS_PER_S);
+        let days = seconds.div_euclid(S_PER_DAY);
+        let time = seconds.rem_euclid(S_PER_DAY);
+
+        // Days since the epoch to a date in the proleptic Gregorian calendar,
+        // counting from March so that leap days come at the end of the year
+        // (http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153; // from March
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:09} UTC",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            ns
+        )
+    }
+}
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Deserialize, Serialize,
+)]
 pub struct Interval {
     pub start: Timestamp,
     pub stop: Timestamp, // exclusive
@@ -43,60 +248,68 @@ pub struct Interval {
 
 impl fmt::Display for Interval {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Time is stored in nanoseconds. But display in larger units if possible.
-        let start_ns = self.start.0;
-        let stop_ns = self.stop.0;
-        let ns_per_us = 1_000;
-        let ns_per_ms = 1_000_000;
-        let ns_per_s = 1_000_000_000;
-        let divisor;
-        let remainder_divisor;
-        let mut unit_name = "ns";
-        if stop_ns >= ns_per_s {
-            divisor = ns_per_s;
-            remainder_divisor = divisor / 1_000;
-            unit_name = "s";
-        } else if stop_ns >= ns_per_ms {
-            divisor = ns_per_ms;
-            remainder_divisor = divisor / 1_000;
-            unit_name = "ms";
-        } else if stop_ns >= ns_per_us {
-            divisor = ns_per_us;
-            remainder_divisor = divisor / 1_000;
-            unit_name = "us";
-        } else {
-            return write!(
-                f,
-                "from {} to {} {} (duration: {})",
-                start_ns,
-                stop_ns,
-                unit_name,
-                Timestamp(self.duration_ns())
-            );
-        }
-        let start_units = start_ns / divisor;
-        let start_remainder = (start_ns % divisor) / remainder_divisor;
-        let stop_units = stop_ns / divisor;
-        let stop_remainder = (stop_ns % divisor) / remainder_divisor;
+        // Both ends in the same unit, picked by the larger one
+        let format = TimeFormat::current();
+        let magnitude = self.start.0.unsigned_abs().max(self.stop.0.unsigned_abs());
+        let unit = format.unit_for(magnitude);
+        write!(f, "from ")?;
+        format.write_number(f, self.start.0, unit)?;
+        write!(f, " to ")?;
+        format.write_number(f, self.stop.0, unit)?;
         write!(
             f,
-            "from {}.{:0>3} to {}.{:0>3} {} (duration: {})",
-            start_units,
-            start_remainder,
-            stop_units,
-            stop_remainder,
-            unit_name,
+            " {} (duration: {})",
+            unit.suffix(),
             Timestamp(self.duration_ns())
         )
     }
 }
 
+// The number and the unit after it (if any), trimmed
+fn split_unit(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    let split = text
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || *c == '.' || (*i == 0 && *c == '-')))
+        .map_or(text.len(), |(i, _)| i);
+    let (number, unit) = text.split_at(split);
+    (number.trim(), unit.trim())
+}
+
 impl Interval {
     pub fn new(start: Timestamp, stop: Timestamp) -> Self {
         Self { start, stop }
     }
+
+    /// Parses "start-stop" or "start..stop", e.g., "100us-250us". If only
+    /// the stop has a unit, it applies to both, as in "100-250us".
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        // Skip the first character, in case the start is negative
+        let (start, stop) = text
+            .split_once("..")
+            .or_else(|| {
+                let dash = text.get(1..)?.find('-')? + 1;
+                Some((&text[..dash], &text[dash + 1..]))
+            })
+            .ok_or_else(|| format!("invalid interval '{}' (e.g., 100us-250us)", text))?;
+        let start = match (split_unit(start), split_unit(stop)) {
+            ((number, ""), (_, unit)) => Timestamp::parse_with_unit(number, unit)
+                .ok_or_else(|| format!("invalid time '{}' (e.g., 1.5ms)", start.trim()))?,
+            _ => Timestamp::parse(start)?,
+        };
+        let stop = Timestamp::parse(stop)?;
+        if stop < start {
+            return Err(format!("interval '{}' ends before it starts", text));
+        }
+        Ok(Self::new(start, stop))
+    }
     pub fn duration_ns(self) -> i64 {
-        self.stop.0 - self.start.0
+        self.stop.0.saturating_sub(self.start.0)
+    }
+    // The duration, or None if it doesn't fit in an i64
+    pub fn checked_duration_ns(self) -> Option<i64> {
+        self.stop.0.checked_sub(self.start.0)
     }
     pub fn contains(self, point: Timestamp) -> bool {
         point >= self.start && point < self.stop
@@ -116,12 +329,58 @@ impl Interval {
             stop: Timestamp(self.stop.0.max(other.stop.0)),
         }
     }
-    // Convert a timestamp into [0,1] relative space
-    pub fn unlerp(self, time: Timestamp) -> f32 {
-        (time.0 - self.start.0) as f32 / (self.duration_ns() as f32)
+    // Extend the interval by amount ns on both sides
+    pub fn grow(self, amount: i64) -> Self {
+        Self {
+            start: Timestamp(self.start.0.saturating_sub(amount)),
+            stop: Timestamp(self.stop.0.saturating_add(amount)),
+        }
+    }
+    // Shift the interval by amount ns
+    pub fn translate(self, amount: i64) -> Self {
+        Self {
+            start: Timestamp(self.start.0.saturating_add(amount)),
+            stop: Timestamp(self.stop.0.saturating_add(amount)),
+        }
+    }
+    // Shift the interval by amount ns, or None if it would overflow
+    pub fn checked_translate(self, amount: i64) -> Option<Self> {
+        Some(Self {
+            start: Timestamp(self.start.0.checked_add(amount)?),
+            stop: Timestamp(self.stop.0.checked_add(amount)?),
+        })
+    }
+    // Grow the interval (about its middle) to at least min_duration ns, then
+    // shift it to lie within bounds, shrinking it only if bounds is shorter
+    pub fn clamp_within(self, bounds: Interval, min_duration: i64) -> Self {
+        if bounds.stop <= bounds.start {
+            return bounds;
+        }
+        let duration = self
+            .duration_ns()
+            .max(min_duration)
+            .min(bounds.duration_ns());
+        let middle = ((self.start.0 as i128 + self.stop.0 as i128) / 2) as i64;
+        let start = middle
+            .saturating_sub(duration / 2)
+            .clamp(bounds.start.0, bounds.stop.0 - duration);
+        Self::new(Timestamp(start), Timestamp(start + duration))
+    }
+    // Convert a timestamp into [0,1] relative space. Note: this is done in
+    // f64 because f32 can't resolve single nanoseconds past about 16 ms.
+    // An empty interval maps everything to 0, rather than NaN.
+    pub fn unlerp(self, time: Timestamp) -> f64 {
+        let duration = self.duration_ns();
+        if duration <= 0 {
+            return 0.0;
+        }
+        (time.0 as f64 - self.start.0 as f64) / duration as f64
     }
     // Convert [0,1] relative space into a timestamp
-    pub fn lerp(self, value: f32) -> Timestamp {
-        Timestamp((value * (self.duration_ns() as f32)).round() as i64 + self.start.0)
+    pub fn lerp(self, value: f64) -> Timestamp {
+        // Float to int casts saturate, so this can't overflow
+        Timestamp(
+            ((value * (self.duration_ns() as f64)).round() as i64).saturating_add(self.start.0),
+        )
     }
 }
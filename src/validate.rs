@@ -0,0 +1,393 @@
+use std::fmt::Display;
+
+use crate::data::{
+    CounterTile, DataSource, DataSourceError, DataSourceResult, Description, EntryID, EntryIndex,
+    EntryInfo, EventTile, FetchStats, Item, ItemLink, ItemUID, SearchResult, SlotTile, SummaryTile,
+    TileID, UtilPoint, Warning,
+};
+use crate::timestamp::{Interval, Timestamp};
+
+/// Wraps a data source (e.g., one written for a new profile format) and
+/// checks that what it returns follows the rules laid out in data.rs.
+/// Violations are returned as errors describing the problem, which the
+/// viewer shows in place of the data, instead of being drawn wrong.
+pub struct ValidatingDataSource<T: DataSource> {
+    inner: T,
+    info: Option<EntryInfo>, // once checked
+}
+
+// Utilization is computed in floating point, so allow for rounding
+const UTIL_TOLERANCE: f32 = 1e-4;
+
+impl<T: DataSource> ValidatingDataSource<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner, info: None }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn info(&mut self) -> DataSourceResult<&EntryInfo> {
+        if self.info.is_none() {
+            let info = self.inner.fetch_info()?.clone();
+            check_info(&info, &EntryID::root())?;
+            self.info = Some(info);
+        }
+        Ok(self.info.as_ref().unwrap())
+    }
+
+    // The entry as described by fetch_info, if it's of the expected kind
+    fn entry(
+        &mut self,
+        entry_id: &EntryID,
+        kind: &str,
+        matches: fn(&EntryInfo) -> bool,
+    ) -> DataSourceResult<&EntryInfo> {
+        let what = || format!("entry {}", entry_id);
+        match lookup(self.info()?, entry_id) {
+            Some(entry) if matches(entry) => Ok(entry),
+            Some(_) => Err(invalid(what(), format!("not a {} in fetch_info", kind))),
+            None => Err(invalid(what(), "not in fetch_info")),
+        }
+    }
+
+    // Rows of the slot, for checking where items are said to be
+    fn slot_rows(&mut self, entry_id: &EntryID) -> DataSourceResult<u64> {
+        match self.entry(entry_id, "slot", is_slot)? {
+            EntryInfo::Slot { max_rows, .. } => Ok(*max_rows),
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn invalid(what: impl Display, problem: impl Display) -> DataSourceError {
+    DataSourceError::new(format!("invalid {}: {}", what, problem))
+}
+
+// Like EntryInfo::get, but doesn't assume the EntryID is valid
+fn lookup<'a>(info: &'a EntryInfo, entry_id: &EntryID) -> Option<&'a EntryInfo> {
+    let mut result = info;
+    for level in 0..entry_id.level() {
+        result = match (entry_id.index(level)?, result) {
+            (EntryIndex::Summary, EntryInfo::Panel { summary, .. }) => summary.as_deref()?,
+            (EntryIndex::Slot(i), EntryInfo::Panel { slots, .. }) => slots.get(i as usize)?,
+            _ => return None,
+        };
+    }
+    Some(result)
+}
+
+fn is_slot(info: &EntryInfo) -> bool {
+    matches!(info, EntryInfo::Slot { .. })
+}
+
+fn check_info(info: &EntryInfo, entry_id: &EntryID) -> DataSourceResult<()> {
+    let what = || format!("entry {} from fetch_info", entry_id);
+    match info {
+        EntryInfo::Panel { summary, slots, .. } => {
+            if let Some(summary) = summary {
+                if !matches!(
+                    **summary,
+                    EntryInfo::Summary { .. } | EntryInfo::Aggregate { .. }
+                ) {
+                    return Err(invalid(what(), "summary is not a Summary or Aggregate"));
+                }
+            }
+            for (i, slot) in slots.iter().enumerate() {
+                if matches!(
+                    slot,
+                    EntryInfo::Summary { .. } | EntryInfo::Aggregate { .. }
+                ) {
+                    return Err(invalid(what(), format!("slot {} is a summary", i)));
+                }
+                check_info(slot, &entry_id.child(i as u64))?;
+            }
+        }
+        _ if entry_id.level() == 0 => return Err(invalid(what(), "root is not a Panel")),
+        _ => {}
+    }
+    Ok(())
+}
+
+fn check_tile_id(what: &str, requested: TileID, returned: TileID) -> DataSourceResult<()> {
+    if requested.0 != returned.0 {
+        return Err(invalid(
+            what,
+            format!("returned tile {} instead", returned.0),
+        ));
+    }
+    Ok(())
+}
+
+// Tiles include the point at their stop, so that curves can be drawn up
+// to the edge
+fn check_time(what: &str, tile_id: TileID, time: Timestamp) -> DataSourceResult<()> {
+    if time < tile_id.0.start || time > tile_id.0.stop {
+        return Err(invalid(
+            what,
+            format!("point at {} is outside the tile", time),
+        ));
+    }
+    Ok(())
+}
+
+fn check_sorted(what: &str, times: impl Iterator<Item = Timestamp>) -> DataSourceResult<()> {
+    let mut last = None;
+    for time in times {
+        if last.map_or(false, |last| time < last) {
+            return Err(invalid(what, format!("point at {} is out of order", time)));
+        }
+        last = Some(time);
+    }
+    Ok(())
+}
+
+fn check_utilization(what: &str, tile_id: TileID, points: &[UtilPoint]) -> DataSourceResult<()> {
+    check_sorted(what, points.iter().map(|p| p.time))?;
+    for point in points {
+        check_time(what, tile_id, point.time)?;
+        if !(-UTIL_TOLERANCE..=1.0 + UTIL_TOLERANCE).contains(&point.util) {
+            return Err(invalid(
+                what,
+                format!(
+                    "utilization {} at {} is outside [0, 1]",
+                    point.util, point.time
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_slot_tile(what: &str, tile: &SlotTile, max_rows: u64) -> DataSourceResult<()> {
+    if tile.items.len() as u64 > max_rows {
+        return Err(invalid(
+            what,
+            format!("{} rows, but max_rows is {}", tile.items.len(), max_rows),
+        ));
+    }
+    for (row, items) in tile.items.iter().enumerate() {
+        let mut last: Option<&Item> = None;
+        for item in items {
+            if item.interval.stop < item.interval.start {
+                return Err(invalid(
+                    what,
+                    format!("item '{}' in row {} ends before it starts", item.title, row),
+                ));
+            }
+            if item.interval.start < tile.tile_id.0.start
+                || item.interval.stop > tile.tile_id.0.stop
+            {
+                return Err(invalid(
+                    what,
+                    format!(
+                        "item '{}' in row {} ({}) is not sliced to fit the tile",
+                        item.title, row, item.interval
+                    ),
+                ));
+            }
+            if let Some(last) = last {
+                if item.interval.start < last.interval.start {
+                    return Err(invalid(
+                        what,
+                        format!("items in row {} are not sorted (at '{}')", row, item.title),
+                    ));
+                }
+            }
+            last = Some(item);
+        }
+    }
+    Ok(())
+}
+
+fn check_endpoints(what: &str, tile: &SlotTile, info: &EntryInfo) -> DataSourceResult<()> {
+    for item in tile.items.iter().flatten() {
+        let endpoints = item.endpoints.iter();
+        for memory in endpoints.flat_map(|e| [&e.source, &e.target]).flatten() {
+            if !lookup(info, memory).map_or(false, is_slot) {
+                return Err(invalid(
+                    what,
+                    format!(
+                        "item '{}' copies to or from {}, which is not a slot",
+                        item.title, memory
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<T: DataSource> DataSource for ValidatingDataSource<T> {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        let interval = self.inner.interval()?;
+        if interval.stop < interval.start {
+            return Err(invalid(
+                "interval",
+                format!("{} ends before it starts", interval),
+            ));
+        }
+        Ok(interval)
+    }
+
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        self.info()
+    }
+
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        let tiles = self.inner.request_tiles(entry_id, request_interval)?;
+        for tile_id in &tiles {
+            if !tile_id.0.overlaps(request_interval) {
+                return Err(invalid(
+                    format!("tiles of {} for {}", entry_id, request_interval),
+                    format!("tile {} is outside the requested interval", tile_id.0),
+                ));
+            }
+        }
+        Ok(tiles)
+    }
+
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.entry(entry_id, "summary", |info| {
+            matches!(
+                info,
+                EntryInfo::Summary { .. } | EntryInfo::Aggregate { .. }
+            )
+        })?;
+        let tile = self.inner.fetch_summary_tile(entry_id, tile_id)?;
+        let what = format!("summary tile {} of {}", tile_id.0, entry_id);
+        check_tile_id(&what, tile_id, tile.tile_id)?;
+        check_utilization(&what, tile_id, &tile.utilization)?;
+        for series in &tile.series {
+            let what = format!("{} (series '{}')", what, series.name);
+            check_utilization(&what, tile_id, &series.utilization)?;
+        }
+        Ok(tile)
+    }
+
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        let max_rows = self.slot_rows(entry_id)?;
+        let tile = self.inner.fetch_slot_tile(entry_id, tile_id)?;
+        let what = format!("slot tile {} of {}", tile_id.0, entry_id);
+        check_tile_id(&what, tile_id, tile.tile_id)?;
+        check_slot_tile(&what, &tile, max_rows)?;
+        check_endpoints(&what, &tile, self.info()?)?;
+        Ok(tile)
+    }
+
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.slot_rows(entry_id)?;
+        let tile = self.inner.fetch_slot_density_tile(entry_id, tile_id)?;
+        let what = format!("slot density tile {} of {}", tile_id.0, entry_id);
+        check_tile_id(&what, tile_id, tile.tile_id)?;
+        check_utilization(&what, tile_id, &tile.utilization)?;
+        Ok(tile)
+    }
+
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        self.inner.epoch()
+    }
+
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        self.inner.fetch_description()
+    }
+
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        let results = self.inner.search(query)?;
+        for result in &results {
+            self.entry(&result.entry_id, "slot", is_slot)
+                .map_err(|e| invalid(format!("search result '{}'", result.title), e))?;
+        }
+        Ok(results)
+    }
+
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        let links = self.inner.fetch_item_links(item_uid)?;
+        for link in &links {
+            let what = format!("link to '{}'", link.title);
+            let max_rows = self
+                .slot_rows(&link.entry_id)
+                .map_err(|e| invalid(&what, e))?;
+            if link.row >= max_rows {
+                return Err(invalid(
+                    what,
+                    format!("row {}, but max_rows is {}", link.row, max_rows),
+                ));
+            }
+        }
+        Ok(links)
+    }
+
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        self.entry(entry_id, "counter", |info| {
+            matches!(info, EntryInfo::Counter { .. })
+        })?;
+        let tile = self.inner.fetch_counter_tile(entry_id, tile_id)?;
+        let what = format!("counter tile {} of {}", tile_id.0, entry_id);
+        check_tile_id(&what, tile_id, tile.tile_id)?;
+        check_sorted(&what, tile.points.iter().map(|p| p.time))?;
+        for point in &tile.points {
+            check_time(&what, tile_id, point.time)?;
+        }
+        Ok(tile)
+    }
+
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        self.entry(entry_id, "event track", |info| {
+            matches!(info, EntryInfo::Events { .. })
+        })?;
+        let tile = self.inner.fetch_event_tile(entry_id, tile_id)?;
+        let what = format!("event tile {} of {}", tile_id.0, entry_id);
+        check_tile_id(&what, tile_id, tile.tile_id)?;
+        check_sorted(&what, tile.events.iter().map(|e| e.time))?;
+        for event in &tile.events {
+            check_time(&what, tile_id, event.time)?;
+        }
+        Ok(tile)
+    }
+
+    fn tile_ready(&mut self, entry_id: &EntryID, tile_id: TileID) -> bool {
+        self.inner.tile_ready(entry_id, tile_id)
+    }
+
+    fn fetch_stats(&mut self) -> FetchStats {
+        self.inner.fetch_stats()
+    }
+
+    fn cancel_tiles(&mut self, entry_id: &EntryID, tile_ids: &[TileID]) {
+        self.inner.cancel_tiles(entry_id, tile_ids);
+    }
+
+    fn poll_invalidated(&mut self) -> Vec<(EntryID, Interval)> {
+        self.inner.poll_invalidated()
+    }
+
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        self.inner.poll_warnings()
+    }
+}
@@ -0,0 +1,202 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::data::{
+    CounterTile, DataSource, DataSourceError, DataSourceResult, Description, EntryID, EntryInfo,
+    EventTile, FetchStats, ItemLink, ItemUID, SearchResult, SlotTile, SummaryTile, TileID, Warning,
+};
+use crate::parallel::Open;
+use crate::timestamp::Interval;
+
+// Files are often written a piece at a time, so wait until this long has
+// passed without a change before reading it again
+const QUIET: Duration = Duration::from_millis(500);
+
+static WAKER: Mutex<Option<Box<dyn Fn() + Send>>> = Mutex::new(None);
+
+/// Called (on another thread) when a watched file has changed, e.g., to
+/// have the app repaint, and so poll the data source for the change.
+pub fn set_waker(waker: impl Fn() + Send + 'static) {
+    *WAKER.lock().unwrap() = Some(Box::new(waker));
+}
+
+/// Wraps a data source read from a file, opening it again (with `open`)
+/// whenever the file changes, e.g., because the application was run again
+/// and regenerated its profile. Each reload invalidates the root entry, so
+/// that the app starts over with the new entries and interval.
+///
+/// If the file can't be read again, the old copy is kept, with a warning.
+pub struct WatchedDataSource {
+    inner: Box<dyn DataSource>,
+    path: PathBuf,
+    open: Open,
+    _watcher: RecommendedWatcher, // stops watching once dropped
+    reloads: Receiver<()>,
+    warnings: Vec<Warning>,
+}
+
+impl WatchedDataSource {
+    pub fn new(inner: Box<dyn DataSource>, path: &Path, open: Open) -> DataSourceResult<Self> {
+        let error =
+            |e: &dyn std::fmt::Display| DataSourceError::new(format!("{}: {}", path.display(), e));
+
+        // Watch the directory rather than the file, which may be replaced
+        // (rather than written) when the profile is regenerated
+        let name = path.file_name().map(|name| name.to_owned());
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let (changes, changed) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<_>| {
+            let event: notify::Event = match event {
+                Ok(event) => event,
+                Err(_) => return,
+            };
+            let ours = event.paths.iter().any(|p| p.file_name() == name.as_deref());
+            if ours && !matches!(event.kind, EventKind::Access(_)) {
+                let _ = changes.send(());
+            }
+        })
+        .map_err(|e| error(&e))?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| error(&e))?;
+
+        let (reload, reloads) = mpsc::channel();
+        thread::spawn(move || {
+            // Ends once the watcher (and so the sender) is dropped
+            while changed.recv().is_ok() {
+                while changed.recv_timeout(QUIET).is_ok() {}
+                if reload.send(()).is_err() {
+                    return;
+                }
+                if let Some(wake) = &*WAKER.lock().unwrap() {
+                    wake();
+                }
+            }
+        });
+
+        Ok(Self {
+            inner,
+            path: path.to_owned(),
+            open,
+            _watcher: watcher,
+            reloads,
+            warnings: Vec::new(),
+        })
+    }
+
+    // Swaps in a new copy of the file, returning all of both copies'
+    // intervals. The new copy must at least say what's in it.
+    fn reload(&mut self) -> DataSourceResult<Interval> {
+        let mut inner = (self.open)()?;
+        let interval = inner.interval()?;
+        inner.fetch_info()?;
+        let old = self.inner.interval().unwrap_or(interval);
+        self.inner = inner;
+        Ok(old.union(interval))
+    }
+}
+
+impl DataSource for WatchedDataSource {
+    fn interval(&mut self) -> DataSourceResult<Interval> {
+        self.inner.interval()
+    }
+    fn fetch_info(&mut self) -> DataSourceResult<&EntryInfo> {
+        self.inner.fetch_info()
+    }
+    fn request_tiles(
+        &mut self,
+        entry_id: &EntryID,
+        request_interval: Interval,
+    ) -> DataSourceResult<Vec<TileID>> {
+        self.inner.request_tiles(entry_id, request_interval)
+    }
+    fn fetch_summary_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.inner.fetch_summary_tile(entry_id, tile_id)
+    }
+    fn fetch_slot_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SlotTile> {
+        self.inner.fetch_slot_tile(entry_id, tile_id)
+    }
+    fn fetch_slot_density_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<SummaryTile> {
+        self.inner.fetch_slot_density_tile(entry_id, tile_id)
+    }
+    fn search(&mut self, query: &str) -> DataSourceResult<Vec<SearchResult>> {
+        self.inner.search(query)
+    }
+    fn fetch_item_links(&mut self, item_uid: ItemUID) -> DataSourceResult<Vec<ItemLink>> {
+        self.inner.fetch_item_links(item_uid)
+    }
+    fn epoch(&mut self) -> DataSourceResult<Option<i64>> {
+        self.inner.epoch()
+    }
+    fn fetch_description(&mut self) -> DataSourceResult<Description> {
+        self.inner.fetch_description()
+    }
+    fn fetch_counter_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<CounterTile> {
+        self.inner.fetch_counter_tile(entry_id, tile_id)
+    }
+    fn fetch_event_tile(
+        &mut self,
+        entry_id: &EntryID,
+        tile_id: TileID,
+    ) -> DataSourceResult<EventTile> {
+        self.inner.fetch_event_tile(entry_id, tile_id)
+    }
+    fn tile_ready(&mut self, entry_id: &EntryID, tile_id: TileID) -> bool {
+        self.inner.tile_ready(entry_id, tile_id)
+    }
+    fn fetch_stats(&mut self) -> FetchStats {
+        self.inner.fetch_stats()
+    }
+    fn cancel_tiles(&mut self, entry_id: &EntryID, tile_ids: &[TileID]) {
+        self.inner.cancel_tiles(entry_id, tile_ids)
+    }
+    fn poll_invalidated(&mut self) -> Vec<(EntryID, Interval)> {
+        // Several changes in a row only need one reload
+        if self.reloads.try_iter().count() == 0 {
+            return self.inner.poll_invalidated();
+        }
+        match self.reload() {
+            Ok(interval) => {
+                tracing::info!("reloaded {}", self.path.display());
+                vec![(EntryID::root(), interval)]
+            }
+            Err(error) => {
+                tracing::warn!("unable to reload {}: {}", self.path.display(), error);
+                self.warnings.push(Warning {
+                    message: format!("Unable to reload the profile: {}", error),
+                    entry_id: None,
+                });
+                self.inner.poll_invalidated()
+            }
+        }
+    }
+    fn poll_warnings(&mut self) -> Vec<Warning> {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.extend(self.inner.poll_warnings());
+        warnings
+    }
+}
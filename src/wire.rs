@@ -0,0 +1,198 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{DataSourceError, DataSourceResult};
+
+/// Version of the protocol: the requests served, and how responses are
+/// encoded. Binary messages start with this, so that a client can tell a
+/// server speaking a newer format from a corrupt response. Bump it whenever
+/// either changes (including the encoding of the data model).
+pub const VERSION: u8 = 2;
+
+/// Capabilities a server may or may not have. Clients only use what both
+/// sides support, so these can be added without bumping the VERSION.
+pub const ZSTD: &str = "zstd"; // Compression::Zstd
+pub const ITEM_LINKS: &str = "item_links"; // answers /item_links
+pub const EPOCH: &str = "epoch"; // answers /epoch
+pub const DESCRIPTION: &str = "description"; // answers /description
+pub const WARNINGS: &str = "warnings"; // answers /warnings
+
+/// The answer to `/handshake`, which clients ask for before anything else,
+/// so that a viewer and server of different versions fail with a clear
+/// message instead of misreading each other. This is always JSON (and only
+/// ever gains fields with defaults), so that any version can read it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Handshake {
+    pub version: u8,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl Handshake {
+    /// What this build speaks and supports.
+    pub fn current() -> Self {
+        let mut capabilities = vec![
+            ITEM_LINKS.to_owned(),
+            EPOCH.to_owned(),
+            DESCRIPTION.to_owned(),
+            WARNINGS.to_owned(),
+        ];
+        if cfg!(not(target_arch = "wasm32")) {
+            capabilities.push(ZSTD.to_owned());
+        }
+        Self {
+            version: VERSION,
+            capabilities,
+        }
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Checks that a server (whose handshake this is) can be read.
+    pub fn check(&self) -> DataSourceResult<()> {
+        if self.version != VERSION {
+            return Err(DataSourceError::new(format!(
+                "the server speaks protocol version {}, but this viewer speaks version {} \
+                 (use the same version of the viewer to serve the profile)",
+                self.version, VERSION
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How responses of the server (`server::serve`) are encoded, as asked for
+/// by the `format` parameter of each request. JSON is the default, so that
+/// the server can be poked at with a browser or curl, but tiles with
+/// millions of items are much smaller (and faster to decode) in binary.
+///
+/// Binary messages are a version byte, a Compression byte, then the value
+/// as bincode (compressed, if asked for).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Binary(Compression),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    None = 0,
+    #[cfg(not(target_arch = "wasm32"))]
+    Zstd = 1, // not in the browser: zstd doesn't build for wasm
+}
+
+impl Compression {
+    /// The best that both this build and a server (whose handshake this
+    /// is) support.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn best(server: &Handshake) -> Self {
+        if server.supports(ZSTD) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn best(_server: &Handshake) -> Self {
+        Compression::None
+    }
+
+    fn from_byte(byte: u8) -> DataSourceResult<Self> {
+        match byte {
+            0 => Ok(Compression::None),
+            #[cfg(not(target_arch = "wasm32"))]
+            1 => Ok(Compression::Zstd),
+            #[cfg(target_arch = "wasm32")]
+            1 => Err(DataSourceError::new("zstd is not available in the browser")),
+            _ => Err(DataSourceError::new(format!(
+                "unknown compression {}",
+                byte
+            ))),
+        }
+    }
+}
+
+impl Format {
+    pub fn parse(name: &str) -> DataSourceResult<Self> {
+        match name {
+            "json" => Ok(Format::Json),
+            "binary" => Ok(Format::Binary(Compression::None)),
+            #[cfg(not(target_arch = "wasm32"))]
+            "zstd" => Ok(Format::Binary(Compression::Zstd)),
+            _ => Err(DataSourceError::new(format!("unknown format '{}'", name))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary(Compression::None) => "binary",
+            #[cfg(not(target_arch = "wasm32"))]
+            Format::Binary(Compression::Zstd) => "zstd",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Binary(_) => "application/octet-stream",
+        }
+    }
+
+    pub fn encode<T: Serialize + ?Sized>(self, value: &T) -> Vec<u8> {
+        match self {
+            Format::Json => serde_json::to_vec(value).expect("data source types always serialize"),
+            Format::Binary(compression) => {
+                let payload =
+                    bincode::serialize(value).expect("data source types always serialize");
+                let mut result = vec![VERSION, compression as u8];
+                match compression {
+                    Compression::None => result.extend(payload),
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Compression::Zstd => result.extend(
+                        zstd::encode_all(&payload[..], 0)
+                            .expect("compressing in memory can't fail"),
+                    ),
+                }
+                result
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> DataSourceResult<T> {
+        let error = |e: &dyn std::fmt::Display| DataSourceError::new(e.to_string());
+        match self {
+            Format::Json => serde_json::from_slice(bytes).map_err(|e| error(&e)),
+            Format::Binary(_) => match bytes {
+                [VERSION, compression, payload @ ..] => {
+                    match Compression::from_byte(*compression)? {
+                        Compression::None => bincode::deserialize(payload).map_err(|e| error(&e)),
+                        #[cfg(not(target_arch = "wasm32"))]
+                        Compression::Zstd => {
+                            let payload = zstd::decode_all(payload)?;
+                            bincode::deserialize(&payload).map_err(|e| error(&e))
+                        }
+                    }
+                }
+                [version, ..] => Err(error(&format!(
+                    "binary format version {} is not supported (expected {})",
+                    version, VERSION
+                ))),
+                [] => Err(error(&"empty response")),
+            },
+        }
+    }
+}
+
+// FNV-1a, for ETags and cache keys, which have to be the same from one run
+// (and build) to the next
+pub(crate) fn hash(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in bytes {
+        hash = (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
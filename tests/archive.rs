@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+
+use legion_prof_viewer::archive::{write_archive, ArchiveDataSource};
+use legion_prof_viewer::data::{DataSource, EntryID, EntryInfo, Item, ItemUID};
+use legion_prof_viewer::loader;
+use legion_prof_viewer::validate::ValidatingDataSource;
+
+mod common;
+use common::{json, random};
+
+fn archive(data_source: &mut dyn DataSource) -> ArchiveDataSource {
+    let mut bytes = Vec::new();
+    write_archive(data_source, &mut bytes).unwrap();
+    ArchiveDataSource::new(bytes).unwrap()
+}
+
+// Every item in the slot, put back together from the pieces in each tile
+fn items(data_source: &mut dyn DataSource, entry_id: &EntryID) -> BTreeMap<ItemUID, Item> {
+    let interval = data_source.interval().unwrap();
+    let mut items: BTreeMap<ItemUID, Item> = BTreeMap::new();
+    for tile_id in data_source.request_tiles(entry_id, interval).unwrap() {
+        let tile = data_source.fetch_slot_tile(entry_id, tile_id).unwrap();
+        for item in tile.items.into_iter().flatten() {
+            items
+                .entry(item.item_uid)
+                .and_modify(|i| i.interval = i.interval.union(item.interval))
+                .or_insert(item);
+        }
+    }
+    items
+}
+
+#[test]
+fn same_as_original() {
+    let mut original = random();
+    let mut archive = archive(&mut original);
+    assert_eq!(archive.interval().unwrap(), original.interval().unwrap());
+    let info = original.fetch_info().unwrap().clone();
+    assert_eq!(json(archive.fetch_info().unwrap()), json(&info));
+    assert_eq!(archive.epoch().unwrap(), original.epoch().unwrap());
+    assert_eq!(
+        json(&archive.fetch_description().unwrap()),
+        json(&original.fetch_description().unwrap())
+    );
+
+    for (entry_id, entry) in info.data_entries() {
+        if let EntryInfo::Slot { .. } = entry {
+            let expected = items(&mut original, &entry_id);
+            assert_eq!(json(&items(&mut archive, &entry_id)), json(&expected));
+            for item_uid in expected.keys() {
+                assert_eq!(
+                    json(&archive.fetch_item_links(*item_uid).unwrap()),
+                    json(&original.fetch_item_links(*item_uid).unwrap())
+                );
+            }
+        }
+    }
+
+    // The original can't search, but the archive can
+    let slot = EntryID::root().child(0).child(0).child(0);
+    let title = items(&mut original, &slot)
+        .into_values()
+        .next()
+        .unwrap()
+        .title;
+    let results = archive.search(&title.to_uppercase()).unwrap();
+    assert!(results.iter().any(|r| r.entry_id == slot));
+    assert!(results.iter().all(|r| r.title == title));
+}
+
+#[test]
+fn tiles_are_valid() {
+    let mut archive = ValidatingDataSource::new(archive(&mut random()));
+    let info = archive.fetch_info().unwrap().clone();
+    let interval = archive.interval().unwrap();
+    // Zoomed in, only some of the tiles are needed
+    let view = interval.intersection(interval.grow(-interval.duration_ns() * 4 / 10));
+    for (entry_id, entry) in info.data_entries() {
+        let tile_ids = archive.request_tiles(&entry_id, view).unwrap();
+        let all = archive.request_tiles(&entry_id, interval).unwrap();
+        assert!(tile_ids.len() < all.len());
+        for tile_id in all {
+            match entry {
+                EntryInfo::Slot { .. } => {
+                    archive.fetch_slot_tile(&entry_id, tile_id).unwrap();
+                }
+                EntryInfo::Counter { .. } => {
+                    archive.fetch_counter_tile(&entry_id, tile_id).unwrap();
+                }
+                EntryInfo::Events { .. } => {
+                    archive.fetch_event_tile(&entry_id, tile_id).unwrap();
+                }
+                _ => {
+                    archive.fetch_summary_tile(&entry_id, tile_id).unwrap();
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn from_file() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("from_file.lpv");
+    let mut original = random();
+    write_archive(&mut original, File::create(&path).unwrap()).unwrap();
+
+    let mut archive = loader::open_file(&path).unwrap();
+    let info = original.fetch_info().unwrap().clone();
+    assert_eq!(json(archive.fetch_info().unwrap()), json(&info));
+    let slot = EntryID::root().child(0).child(0).child(0);
+    assert_eq!(
+        json(&items(archive.as_mut(), &slot)),
+        json(&items(&mut original, &slot))
+    );
+    let stats = archive.fetch_stats();
+    assert!(stats.requests > 0 && stats.bytes > 0, "{:?}", stats);
+}
+
+#[test]
+fn not_an_archive() {
+    assert!(ArchiveDataSource::new(b"{}".to_vec()).is_err());
+    let mut bytes = Vec::new();
+    write_archive(&mut random(), &mut bytes).unwrap();
+    bytes.truncate(bytes.len() / 2);
+    assert!(ArchiveDataSource::new(bytes).is_err());
+}
+
+#[test]
+fn warnings() {
+    let mut archive = archive(&mut random());
+    let warnings = archive.poll_warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].entry_id, Some(EntryID::root().child(1)));
+    // Each warning is only handed out once
+    assert!(archive.poll_warnings().is_empty());
+}
@@ -0,0 +1,27 @@
+use legion_prof_viewer::colors::{self, ColorAssigner};
+use legion_prof_viewer::data::Color;
+
+#[test]
+fn stable() {
+    // Changing these changes every profile's colors, so only on purpose
+    assert_eq!(colors::name_color("fill"), colors::name_color("fill"));
+    assert_ne!(colors::name_color("fill"), colors::name_color("copy"));
+    assert_eq!(colors::name_color("fill"), Color::from_rgb(167, 117, 215));
+}
+
+#[test]
+fn assigned_colors_differ() {
+    let mut assigner = ColorAssigner::new();
+    let names: Vec<_> = (0..8).map(|i| format!("task_{}", i)).collect();
+    let colors: Vec<_> = names.iter().map(|name| assigner.color(name)).collect();
+    for (i, a) in colors.iter().enumerate() {
+        for b in &colors[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+    // Asking again doesn't change anything
+    assert_eq!(assigner.color(&names[3]), colors[3]);
+
+    // The first name has nothing to clash with
+    assert_eq!(colors[0], colors::name_color(&names[0]));
+}
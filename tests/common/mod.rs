@@ -0,0 +1,27 @@
+//! Fixtures shared by the tests (each of which uses only some of them).
+#![allow(dead_code)]
+
+use serde::Serialize;
+
+use legion_prof_viewer::synthetic::{RandomDataSource, RandomOptions};
+
+/// A small profile, with enough items per row to span several tiles. Every
+/// copy is the same, since the data only depends on the seed.
+pub fn random() -> RandomDataSource {
+    RandomDataSource::new(RandomOptions {
+        nodes: 2,
+        procs_per_kind: 2,
+        max_rows: 4,
+        items_per_row: 200,
+        ..Default::default()
+    })
+}
+
+/// The data model doesn't implement PartialEq, so compare it as JSON.
+pub fn json(value: &impl Serialize) -> serde_json::Value {
+    serde_json::to_value(value).unwrap()
+}
+
+pub fn assert_same(a: &impl Serialize, b: &impl Serialize) {
+    assert_eq!(json(a), json(b));
+}
@@ -0,0 +1,55 @@
+use legion_prof_viewer::data::{Color, Item, ItemUID, SlotTile};
+use legion_prof_viewer::timestamp::{Interval, Timestamp};
+
+fn interval(start: i64, stop: i64) -> Interval {
+    Interval::new(Timestamp(start), Timestamp(stop))
+}
+
+fn item(uid: u64, start: i64, stop: i64) -> Item {
+    Item {
+        item_uid: ItemUID(uid),
+        interval: interval(start, stop),
+        color: Color::GRAY,
+        title: uid.to_string(),
+        fields: Vec::new(),
+        segments: Vec::new(),
+        endpoints: None,
+        allocation: None,
+    }
+}
+
+fn uids(row: &[Item], view: Interval) -> Vec<u64> {
+    SlotTile::items_in(row, view)
+        .map(|i| i.item_uid.0)
+        .collect()
+}
+
+#[test]
+fn items_in_overlapping_row() {
+    // A long item with short ones on top of it, then one after it
+    let row = [
+        item(0, 0, 100),
+        item(1, 10, 20),
+        item(2, 30, 40),
+        item(3, 120, 130),
+    ];
+    assert_eq!(uids(&row, interval(50, 60)), [0]);
+    assert_eq!(uids(&row, interval(15, 35)), [0, 1, 2]);
+    assert_eq!(uids(&row, interval(90, 125)), [0, 3]);
+    assert_eq!(uids(&row, interval(101, 110)), [] as [u64; 0]);
+    assert_eq!(uids(&row, interval(0, 200)), [0, 1, 2, 3]);
+}
+
+#[test]
+fn item_without_uid() {
+    // As sent by servers from before items had UIDs
+    let json = r#"{
+        "interval": {"start": 10, "stop": 20},
+        "color": [128, 128, 128, 255],
+        "title": "task",
+        "fields": []
+    }"#;
+    let item: Item = serde_json::from_str(json).unwrap();
+    assert_eq!(item.item_uid, ItemUID::default());
+    assert_eq!(item.interval, interval(10, 20));
+}
@@ -0,0 +1,122 @@
+use legion_prof_viewer::filter::{contains_lowercase, Filter, Op, Query, Subject, Value};
+use legion_prof_viewer::node_set::NodeSet;
+
+fn parse(text: &str) -> Query {
+    text.parse()
+        .unwrap_or_else(|e| panic!("'{}' didn't parse: {}", text, e))
+}
+
+fn name(text: &str) -> Query {
+    Query::Filter(Filter::new(Subject::Name, Op::Contains, text).unwrap())
+}
+
+#[test]
+fn terms() {
+    assert_eq!(parse("Fill"), name("fill"));
+    assert_eq!(parse("name:fill"), name("fill"));
+    assert_eq!(
+        parse("name=Fill"),
+        Query::Filter(Filter::new(Subject::Name, Op::Eq, "Fill").unwrap())
+    );
+
+    let dur = parse("dur>10us");
+    let expected = Filter::new(Subject::Duration, Op::Gt, "10us").unwrap();
+    assert_eq!(expected.value, Value::Duration(10_000));
+    assert_eq!(dur, Query::Filter(expected));
+
+    let field = Subject::Field("Occupancy".to_owned());
+    assert_eq!(
+        parse("Occupancy>=50"),
+        Query::Filter(Filter::new(field, Op::Ge, "50").unwrap())
+    );
+    assert_eq!(
+        parse("node:3..7"),
+        Query::Node("3-7".parse::<NodeSet>().unwrap())
+    );
+}
+
+#[test]
+fn precedence() {
+    // AND (written or not) binds tighter than OR, and NOT tighter still
+    assert_eq!(
+        parse("a b OR c"),
+        Query::Or(vec![Query::And(vec![name("a"), name("b")]), name("c")])
+    );
+    assert_eq!(parse("a AND b OR c"), parse("a b OR c"));
+    assert_eq!(
+        parse("a (b OR c)"),
+        Query::And(vec![name("a"), Query::Or(vec![name("b"), name("c")])])
+    );
+    assert_eq!(
+        parse("NOT a b"),
+        Query::And(vec![Query::Not(Box::new(name("a"))), name("b")])
+    );
+    assert_eq!(
+        parse("NOT (a OR b)"),
+        Query::Not(Box::new(Query::Or(vec![name("a"), name("b")])))
+    );
+}
+
+#[test]
+fn quoting() {
+    assert_eq!(parse("\"fill instance\""), name("fill instance"));
+    assert_eq!(parse("name:\"a (b) OR c\""), name("a (b) or c"));
+    let field = Subject::Field("Source Line".to_owned());
+    assert_eq!(
+        parse("\"Source Line\":\"foo.py:12\""),
+        Query::Filter(Filter::new(field, Op::Contains, "foo.py:12").unwrap())
+    );
+}
+
+#[test]
+fn mistakes() {
+    for text in [
+        "",
+        "   ",
+        "\"fill",
+        "(fill",
+        "fill)",
+        "AND fill",
+        "fill OR",
+        "NOT",
+        "name:",
+        "name!fill",
+        "dur>soon",
+        "node<3",
+        "node:3-1",
+        "\"\":fill",
+    ] {
+        assert!(text.parse::<Query>().is_err(), "'{}' parsed", text);
+    }
+}
+
+#[test]
+fn display_round_trip() {
+    for text in [
+        "fill",
+        "name=Fill",
+        "name:\"fill instance\"",
+        "dur>=1.5ms",
+        "dur<10us OR dur>1s",
+        "Occupancy>50",
+        "\"Source Line\":\"foo.py:12\"",
+        "node:0-3,7 (copy OR fill)",
+        "NOT (a OR b) c",
+        "NOT NOT a",
+        "a (b (c OR d))",
+    ] {
+        let query = parse(text);
+        let shown = query.to_string();
+        assert_eq!(parse(&shown), query, "'{}' showed as '{}'", text, shown);
+        assert_eq!(parse(&shown).to_string(), shown);
+    }
+}
+
+#[test]
+fn ignoring_case() {
+    assert!(contains_lowercase("Fill Instance", "fill i"));
+    assert!(contains_lowercase("Fill", ""));
+    assert!(!contains_lowercase("Fill", "fills"));
+    assert!(contains_lowercase("Über Task", "über"));
+    assert!(!contains_lowercase("Task", "über"));
+}
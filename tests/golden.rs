@@ -0,0 +1,120 @@
+//! Renders the timeline offscreen and compares it against images checked
+//! in under tests/golden. After an intended change to the drawing, review
+//! the new images and update them with `UPDATE_GOLDEN=1 cargo test`.
+
+use egui::{Pos2, Vec2};
+use std::path::{Path, PathBuf};
+
+use legion_prof_viewer::app::Headless;
+use legion_prof_viewer::timestamp::Interval;
+
+mod common;
+
+const SIZE: Vec2 = Vec2::new(640.0, 360.0);
+
+// Channels can be off by this much (e.g., from float rounding on another
+// platform) before a pixel counts as different
+const CHANNEL_TOLERANCE: u8 = 2;
+// And this fraction of pixels can differ before the image does
+const PIXEL_TOLERANCE: f64 = 0.001;
+
+fn headless() -> Headless {
+    Headless::new(Box::new(common::random()), SIZE).unwrap()
+}
+
+fn zoom(headless: &mut Headless, start: f64, stop: f64) {
+    let view = headless.view_interval();
+    headless.set_view_interval(Interval::new(view.lerp(start), view.lerp(stop)));
+}
+
+fn decode_png(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    data.truncate(info.buffer_size());
+    (info.width, info.height, data)
+}
+
+fn check(name: &str, headless: &mut Headless) {
+    // Let the layout settle
+    headless.run();
+    headless.run();
+    let actual = headless.render().encode_png().unwrap();
+
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&golden).unwrap_or_else(|e| {
+        panic!(
+            "{}: {} (run with UPDATE_GOLDEN=1 to create it)",
+            golden.display(),
+            e
+        )
+    });
+
+    let (width, height, actual_data) = decode_png(&actual);
+    let (expected_width, expected_height, expected_data) = decode_png(&expected);
+    let differing = if (width, height) == (expected_width, expected_height) {
+        actual_data
+            .chunks(4)
+            .zip(expected_data.chunks(4))
+            .filter(|(a, e)| {
+                a.iter()
+                    .zip(e.iter())
+                    .any(|(a, e)| a.abs_diff(*e) > CHANNEL_TOLERANCE)
+            })
+            .count()
+    } else {
+        actual_data.len() / 4
+    };
+    let total = (width * height) as f64;
+    if differing as f64 > total * PIXEL_TOLERANCE {
+        let actual_path =
+            PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.actual.png", name));
+        std::fs::write(&actual_path, &actual).unwrap();
+        panic!(
+            "{}: {} of {} pixels differ, see {}",
+            golden.display(),
+            differing,
+            total,
+            actual_path.display()
+        );
+    }
+}
+
+#[test]
+fn summaries() {
+    // Kinds start collapsed, so this is mostly summaries
+    let mut headless = headless();
+    check("summaries", &mut headless);
+}
+
+#[test]
+fn slots() {
+    let mut headless = headless();
+    headless.expand_all(true);
+    zoom(&mut headless, 0.25, 0.27);
+    check("slots", &mut headless);
+}
+
+#[test]
+fn slots_merged() {
+    // Items too small to see get merged into blocks
+    let mut headless = headless();
+    headless.expand_all(true);
+    check("slots_merged", &mut headless);
+}
+
+#[test]
+fn cursor() {
+    let mut headless = headless();
+    headless.expand_all(true);
+    zoom(&mut headless, 0.25, 0.27);
+    headless.set_pointer(Some(Pos2::new(SIZE.x * 0.6, SIZE.y * 0.5)));
+    check("cursor", &mut headless);
+}
@@ -0,0 +1,54 @@
+use legion_prof_viewer::node_set::NodeSet;
+
+fn parse(text: &str) -> NodeSet {
+    text.parse()
+        .unwrap_or_else(|e| panic!("'{}' didn't parse: {}", text, e))
+}
+
+fn nodes(set: &NodeSet, below: u64) -> Vec<u64> {
+    (0..below).filter(|n| set.contains(*n)).collect()
+}
+
+#[test]
+fn ranges() {
+    assert_eq!(nodes(&parse("0-3,7"), 10), [0, 1, 2, 3, 7]);
+    assert_eq!(nodes(&parse(" 2 , 5-5 ,"), 10), [2, 5]);
+    assert_eq!(nodes(&parse("0-15:4"), 20), [0, 4, 8, 12]);
+    assert_eq!(nodes(&parse("3:2"), 10), [3]);
+    assert!(parse("18446744073709551615").contains(u64::MAX));
+}
+
+#[test]
+fn everything() {
+    for text in ["", "  ", "*"] {
+        let set = parse(text);
+        assert_eq!(set, NodeSet::all());
+        assert!(set.contains(0) && set.contains(u64::MAX));
+    }
+}
+
+#[test]
+fn mistakes() {
+    for text in [
+        "5-2",                  // backwards
+        "0-7:0",                // no step
+        "18446744073709551616", // too big
+        "-1",
+        "1-",
+        "a",
+        "1,b",
+        "0-3:x",
+        "1.5",
+    ] {
+        assert!(text.parse::<NodeSet>().is_err(), "'{}' parsed", text);
+    }
+}
+
+#[test]
+fn display_round_trip() {
+    for text in ["*", "0-3,17", "0-15:4", "7", "1,3-5:2,9"] {
+        let set = parse(text);
+        assert_eq!(set.to_string(), text);
+        assert_eq!(parse(&set.to_string()), set);
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use legion_prof_viewer::data::{DataSource, EntryID, EntryInfo};
+use legion_prof_viewer::parallel::{Open, ParallelDataSource};
+
+mod common;
+use common::{json, random};
+
+fn parallel(threads: usize) -> ParallelDataSource {
+    let open: Open = Arc::new(|| Ok(Box::new(random())));
+    ParallelDataSource::new(Box::new(random()), open, threads)
+}
+
+#[test]
+fn same_as_original() {
+    let mut original = random();
+    let mut parallel = parallel(4);
+    let interval = original.interval().unwrap();
+    let info = original.fetch_info().unwrap().clone();
+
+    // Request everything first, so that it's fetched in parallel
+    let mut requested = Vec::new();
+    for (entry_id, entry) in info.data_entries() {
+        let tile_ids = parallel.request_tiles(&entry_id, interval).unwrap();
+        requested.push((entry_id, entry.clone(), tile_ids));
+    }
+    for (entry_id, entry, tile_ids) in requested {
+        for tile_id in tile_ids {
+            let (expected, actual) = match entry {
+                EntryInfo::Slot { .. } => (
+                    json(&original.fetch_slot_tile(&entry_id, tile_id).unwrap()),
+                    json(&parallel.fetch_slot_tile(&entry_id, tile_id).unwrap()),
+                ),
+                _ => (
+                    json(&original.fetch_summary_tile(&entry_id, tile_id).unwrap()),
+                    json(&parallel.fetch_summary_tile(&entry_id, tile_id).unwrap()),
+                ),
+            };
+            assert_eq!(actual, expected);
+        }
+    }
+}
+
+#[test]
+fn ready_and_cancel() {
+    let mut parallel = parallel(2);
+    let interval = parallel.interval().unwrap();
+    let slot = EntryID::root().child(0).child(0).child(0);
+    let tile_ids = parallel.request_tiles(&slot, interval).unwrap();
+    let first = tile_ids[0];
+    while !parallel.tile_ready(&slot, first) {
+        std::thread::yield_now();
+    }
+    parallel.fetch_slot_tile(&slot, first).unwrap();
+
+    // Cancelled tiles can still be fetched (just not in the background)
+    parallel.cancel_tiles(&slot, &tile_ids[1..]);
+    for tile_id in &tile_ids[1..] {
+        assert!(parallel.tile_ready(&slot, *tile_id));
+        parallel.fetch_slot_tile(&slot, *tile_id).unwrap();
+    }
+}
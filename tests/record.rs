@@ -0,0 +1,79 @@
+use serde_json::Value;
+
+use legion_prof_viewer::data::{DataSource, EntryInfo};
+use legion_prof_viewer::loader;
+use legion_prof_viewer::record::{RecordingDataSource, ReplayDataSource};
+
+mod common;
+use common::{json, random};
+
+// Everything the viewer might ask for, in order, as JSON (errors included,
+// since they're recorded too)
+fn fetch_everything(data_source: &mut dyn DataSource) -> Vec<Value> {
+    let mut responses = vec![
+        json(&data_source.interval()),
+        json(&data_source.epoch()),
+        json(&data_source.fetch_description()),
+        json(&data_source.search("fill")),
+        json(&data_source.poll_warnings()),
+    ];
+    let interval = data_source.interval().unwrap();
+    let info = data_source.fetch_info().unwrap().clone();
+    responses.push(json(&info));
+    for (entry_id, entry) in info.data_entries() {
+        let tile_ids = data_source.request_tiles(&entry_id, interval).unwrap();
+        responses.push(json(&tile_ids));
+        for tile_id in tile_ids {
+            match entry {
+                EntryInfo::Slot { .. } => {
+                    let tile = data_source.fetch_slot_tile(&entry_id, tile_id).unwrap();
+                    if let Some(item) = tile.items.iter().flatten().next() {
+                        responses.push(json(&data_source.fetch_item_links(item.item_uid)));
+                    }
+                    let density = data_source.fetch_slot_density_tile(&entry_id, tile_id);
+                    responses.extend([json(&tile), json(&density)]);
+                }
+                EntryInfo::Counter { .. } => {
+                    responses.push(json(&data_source.fetch_counter_tile(&entry_id, tile_id)));
+                }
+                EntryInfo::Events { .. } => {
+                    responses.push(json(&data_source.fetch_event_tile(&entry_id, tile_id)));
+                }
+                _ => {
+                    responses.push(json(&data_source.fetch_summary_tile(&entry_id, tile_id)));
+                }
+            }
+        }
+    }
+    // Only reported once
+    responses.push(json(&data_source.poll_warnings()));
+    responses
+}
+
+#[test]
+fn replays_the_original() {
+    let expected = fetch_everything(&mut random());
+    let mut recording = Vec::new();
+    let mut recorder = RecordingDataSource::new(Box::new(random()), &mut recording);
+    assert_eq!(fetch_everything(&mut recorder), expected);
+    drop(recorder);
+
+    let mut replay = ReplayDataSource::new(&recording[..]).unwrap();
+    assert_eq!(fetch_everything(&mut replay), expected);
+    let mut opened = loader::open_bytes("recording.jsonl", &recording).unwrap();
+    assert_eq!(fetch_everything(opened.as_mut()), expected);
+}
+
+#[test]
+fn only_what_was_recorded() {
+    let mut recording = Vec::new();
+    let mut recorder = RecordingDataSource::new(Box::new(random()), &mut recording);
+    let interval = recorder.interval().unwrap();
+    drop(recorder);
+
+    let mut replay = ReplayDataSource::new(&recording[..]).unwrap();
+    assert_eq!(replay.interval().unwrap(), interval);
+    assert!(replay.fetch_info().is_err());
+    assert!(replay.poll_warnings().is_empty());
+    assert!(ReplayDataSource::new(&b"{\"call\":"[..]).is_err());
+}
@@ -0,0 +1,61 @@
+use egui::Color32;
+use std::path::PathBuf;
+
+use legion_prof_viewer::settings::{self, Settings};
+use legion_prof_viewer::theme::{ColorOverrides, Palette};
+
+#[test]
+fn parse() {
+    let settings = Settings::parse(
+        r##"
+        server = "http://cluster:8080"
+
+        [theme]
+        palette = "OkabeIto"
+
+        [colors]
+        GPU = "#44aa99"
+        "##,
+    )
+    .unwrap();
+    assert_eq!(settings.server.as_deref(), Some("http://cluster:8080"));
+    let theme = settings.theme.as_ref().unwrap();
+    assert_eq!(theme.palette, Palette::OkabeIto);
+    assert_eq!(theme.saturation, 1.0);
+    let colors = settings.kind_colors().unwrap();
+    assert_eq!(
+        colors.get("gpu"),
+        Some(&Color32::from_rgb(0x44, 0xaa, 0x99))
+    );
+}
+
+#[test]
+fn mistakes() {
+    assert!(Settings::parse("servr = \"http://cluster:8080\"").is_err());
+    assert!(Settings::parse("[colors]\ncpu = \"blue\"").is_err());
+    assert!(Settings::load(Some("no/such/config.toml".as_ref())).is_err());
+}
+
+#[test]
+fn colors_round_trip() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("colors.toml");
+    let mut colors = ColorOverrides::default();
+    colors
+        .names
+        .insert("fill instance".to_owned(), Color32::from_rgb(1, 2, 3));
+    colors
+        .kinds
+        .insert("gpu".to_owned(), Color32::from_rgb(4, 5, 6));
+    settings::save_colors(&path, &colors).unwrap();
+    assert_eq!(settings::load_colors(&path).unwrap(), colors);
+
+    assert_eq!(
+        colors.get("fill instance", Some("gpu")),
+        Some(Color32::from_rgb(1, 2, 3))
+    );
+    assert_eq!(
+        colors.get("copy", Some("gpu")),
+        Some(Color32::from_rgb(4, 5, 6))
+    );
+    assert_eq!(colors.get("copy", Some("cpu")), None);
+}
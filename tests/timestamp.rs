@@ -0,0 +1,171 @@
+use legion_prof_viewer::timestamp::{Interval, TimeFormat, TimeUnit, Timestamp, WallClock};
+
+const NS_PER_S: i64 = 1_000_000_000;
+
+// Every nanosecond in the interval should survive a trip through relative
+// space and back, otherwise items jitter when zoomed in
+fn assert_round_trip(interval: Interval) {
+    let step = (interval.duration_ns() / 1000).max(1) as usize;
+    for ns in (interval.start.0..=interval.stop.0).step_by(step) {
+        let time = Timestamp(ns);
+        assert_eq!(interval.lerp(interval.unlerp(time)), time, "{}", interval);
+    }
+}
+
+#[test]
+fn round_trip_full_profile() {
+    assert_round_trip(Interval::new(Timestamp(0), Timestamp(3600 * NS_PER_S)));
+}
+
+#[test]
+fn round_trip_deep_zoom() {
+    // A 100 ns view, ten seconds into the profile
+    let start = Timestamp(10 * NS_PER_S);
+    assert_round_trip(Interval::new(start, Timestamp(start.0 + 100)));
+
+    // A 1 ns view, an hour into the profile
+    let start = Timestamp(3600 * NS_PER_S);
+    assert_round_trip(Interval::new(start, Timestamp(start.0 + 1)));
+}
+
+#[test]
+fn unlerp_resolves_nanoseconds() {
+    let interval = Interval::new(Timestamp(0), Timestamp(60 * NS_PER_S));
+    let a = interval.unlerp(Timestamp(30 * NS_PER_S));
+    let b = interval.unlerp(Timestamp(30 * NS_PER_S + 1));
+    assert!(b > a);
+}
+
+#[test]
+fn lerp_endpoints() {
+    let interval = Interval::new(Timestamp(5 * NS_PER_S), Timestamp(7 * NS_PER_S));
+    assert_eq!(interval.lerp(0.0), interval.start);
+    assert_eq!(interval.lerp(1.0), interval.stop);
+    assert_eq!(interval.lerp(0.5), Timestamp(6 * NS_PER_S));
+    assert_eq!(interval.unlerp(interval.start), 0.0);
+    assert_eq!(interval.unlerp(interval.stop), 1.0);
+}
+
+#[test]
+fn wall_clock() {
+    assert_eq!(
+        WallClock(0).to_string(),
+        "1970-01-01 00:00:00.000000000 UTC"
+    );
+    // A leap day, 1.5 s and a nanosecond after the profile starts
+    let epoch = 951_782_400 * NS_PER_S; // 2000-02-29 00:00:00
+    let time = Timestamp(NS_PER_S + NS_PER_S / 2 + 1);
+    assert_eq!(
+        time.wall_clock(epoch).to_string(),
+        "2000-02-29 00:00:01.500000001 UTC"
+    );
+    assert_eq!(
+        WallClock(-1).to_string(),
+        "1969-12-31 23:59:59.999999999 UTC"
+    );
+    // A nonsense epoch doesn't overflow
+    assert_eq!(
+        Timestamp(NS_PER_S).wall_clock(i64::MAX),
+        WallClock(i64::MAX)
+    );
+    assert!(!WallClock(i64::MAX).to_string().is_empty());
+}
+
+#[test]
+fn parse() {
+    assert_eq!(Timestamp::parse("1.5ms"), Ok(Timestamp(1_500_000)));
+    assert_eq!(Timestamp::parse(" 2.3 s "), Ok(Timestamp(2_300_000_000)));
+    assert_eq!(Timestamp::parse("42"), Ok(Timestamp(42)));
+    assert_eq!(Timestamp::parse("-10us"), Ok(Timestamp(-10_000)));
+    assert!(Timestamp::parse("1.5 fortnights").is_err());
+    assert!(Timestamp::parse("ms").is_err());
+
+    let interval = Interval::new(Timestamp(100_000), Timestamp(250_000));
+    assert_eq!(Interval::parse("100us-250us"), Ok(interval));
+    assert_eq!(Interval::parse("100 - 250us"), Ok(interval));
+    assert_eq!(Interval::parse("100us..250us"), Ok(interval));
+    assert_eq!(
+        Interval::parse("-1ms-1ms"),
+        Ok(Interval::new(Timestamp(-1_000_000), Timestamp(1_000_000)))
+    );
+    assert!(Interval::parse("250us-100us").is_err());
+    assert!(Interval::parse("100us").is_err());
+}
+
+#[test]
+fn formats() {
+    let time = Timestamp(1_234_567);
+    let interval = Interval::new(Timestamp(500_000), time);
+    assert_eq!(time.to_string(), "1.234 ms");
+    assert_eq!(Timestamp(42).to_string(), "42 ns");
+    assert_eq!(
+        interval.to_string(),
+        "from 0.500 to 1.234 ms (duration: 734.567 us)"
+    );
+
+    // Each test runs on a thread of its own, so this doesn't leak
+    let with = |unit, precision, engineering| {
+        TimeFormat {
+            unit,
+            precision,
+            engineering,
+        }
+        .set();
+        time.to_string()
+    };
+    assert_eq!(with(TimeUnit::Microseconds, 1, false), "1234.5 us");
+    assert_eq!(with(TimeUnit::Seconds, 6, false), "0.001234 s");
+    assert_eq!(with(TimeUnit::Nanoseconds, 3, false), "1234567 ns");
+    assert_eq!(with(TimeUnit::Auto, 0, false), "1 ms");
+    assert_eq!(with(TimeUnit::Auto, 2, true), "1.23e-3 s");
+    assert_eq!(with(TimeUnit::Microseconds, 3, true), "1.235e3 us");
+}
+
+#[test]
+fn negative() {
+    assert_eq!(Timestamp(-1_500_000).to_string(), "-1.500 ms");
+    assert_eq!(Timestamp(-500_000).to_string(), "-500.000 us");
+    assert_eq!(Timestamp(-42).to_string(), "-42 ns");
+    assert_eq!(Timestamp(i64::MIN).to_string(), "-9223372036.854 s");
+    assert_eq!(
+        Interval::new(Timestamp(-2 * NS_PER_S), Timestamp(500_000)).to_string(),
+        "from -2.000 to 0.000 s (duration: 2.000 s)"
+    );
+
+    // Events before the start of the profile
+    let interval = Interval::new(Timestamp(-10 * NS_PER_S), Timestamp(NS_PER_S));
+    assert_round_trip(interval);
+    assert_eq!(interval.unlerp(Timestamp(-10 * NS_PER_S)), 0.0);
+    assert!(interval.contains(Timestamp(-1)));
+    let before = Interval::new(Timestamp(-20 * NS_PER_S), Timestamp(-5 * NS_PER_S));
+    assert!(interval.overlaps(before));
+    assert!(!interval.overlaps(before.translate(-6 * NS_PER_S)));
+}
+
+#[test]
+fn clamp_and_overflow() {
+    let total = Interval::new(Timestamp(0), Timestamp(1_000));
+
+    // Empty intervals don't produce NaN
+    let empty = Interval::new(Timestamp(500), Timestamp(500));
+    assert_eq!(empty.unlerp(Timestamp(700)), 0.0);
+
+    // Grown about the middle to the minimum duration
+    let view = empty.clamp_within(total, 10);
+    assert_eq!(view, Interval::new(Timestamp(495), Timestamp(505)));
+
+    // Shifted back inside, and cut only if longer than the bounds
+    let view = Interval::new(Timestamp(990), Timestamp(1_100)).clamp_within(total, 10);
+    assert_eq!(view, Interval::new(Timestamp(890), Timestamp(1_000)));
+    let view = Interval::new(Timestamp(-50), Timestamp(2_000)).clamp_within(total, 10);
+    assert_eq!(view, total);
+
+    // Arithmetic near the limits saturates instead of overflowing
+    let huge = Interval::new(Timestamp(i64::MIN), Timestamp(i64::MAX));
+    assert_eq!(huge.duration_ns(), i64::MAX);
+    assert_eq!(huge.checked_duration_ns(), None);
+    assert_eq!(huge.grow(10), huge);
+    assert_eq!(huge.translate(1).stop, Timestamp(i64::MAX));
+    assert_eq!(total.checked_translate(i64::MAX), None);
+    assert_eq!(huge.clamp_within(total, 10), total);
+}
@@ -0,0 +1,64 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use legion_prof_viewer::archive::write_archive;
+use legion_prof_viewer::data::{DataSource, EntryID};
+use legion_prof_viewer::loader;
+use legion_prof_viewer::synthetic::{RandomDataSource, RandomOptions};
+use legion_prof_viewer::timestamp::Interval;
+
+fn write(path: &Path, nodes: u64) {
+    let mut data_source = RandomDataSource::new(RandomOptions {
+        nodes,
+        procs_per_kind: 1,
+        max_rows: 2,
+        items_per_row: 10,
+        ..Default::default()
+    });
+    write_archive(&mut data_source, File::create(path).unwrap()).unwrap();
+}
+
+fn nodes(data_source: &mut dyn DataSource) -> u64 {
+    data_source.fetch_info().unwrap().nodes()
+}
+
+// Changes are noticed on another thread, after the file settles
+fn poll(data_source: &mut dyn DataSource) -> Vec<(EntryID, Interval)> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let invalidated = data_source.poll_invalidated();
+        if !invalidated.is_empty() || Instant::now() > deadline {
+            return invalidated;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn reloads_when_changed() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("reloads_when_changed.lpv");
+    write(&path, 1);
+    let mut watched = loader::watch_file(&path).unwrap();
+    assert_eq!(nodes(watched.as_mut()), 1);
+
+    write(&path, 2);
+    let invalidated = poll(watched.as_mut());
+    assert!(invalidated.iter().any(|(e, _)| *e == EntryID::root()));
+    assert_eq!(nodes(watched.as_mut()), 2);
+}
+
+#[test]
+fn keeps_old_copy_when_unreadable() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("keeps_old_copy.lpv");
+    write(&path, 2);
+    let mut watched = loader::watch_file(&path).unwrap();
+
+    std::fs::write(&path, b"not a profile").unwrap();
+    thread::sleep(Duration::from_secs(2));
+    assert!(watched.poll_invalidated().is_empty());
+    assert_eq!(nodes(watched.as_mut()), 2);
+    let warnings = watched.poll_warnings();
+    assert!(warnings.iter().any(|w| w.message.contains("reload")));
+}
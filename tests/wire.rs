@@ -0,0 +1,57 @@
+use legion_prof_viewer::data::{DataSource, EntryID, EntryInfo, SlotTile, TileID};
+use legion_prof_viewer::synthetic::RandomDataSource;
+use legion_prof_viewer::wire::{Compression, Format, VERSION};
+
+mod common;
+use common::assert_same;
+
+const FORMATS: &[Format] = &[
+    Format::Json,
+    Format::Binary(Compression::None),
+    Format::Binary(Compression::Zstd),
+];
+
+fn slot_tile(data_source: &mut RandomDataSource) -> SlotTile {
+    let slot = EntryID::root().child(0).child(0).child(0);
+    let interval = data_source.interval().unwrap();
+    let tile_id = data_source.request_tiles(&slot, interval).unwrap()[0];
+    data_source.fetch_slot_tile(&slot, tile_id).unwrap()
+}
+
+#[test]
+fn round_trip() {
+    let mut data_source = RandomDataSource::default();
+    let info = data_source.fetch_info().unwrap().clone();
+    let tile = slot_tile(&mut data_source);
+    assert!(!tile.items.is_empty());
+
+    for format in FORMATS {
+        let decoded: SlotTile = format.decode(&format.encode(&tile)).unwrap();
+        assert_same(&decoded, &tile);
+        let decoded: EntryInfo = format.decode(&format.encode(&info)).unwrap();
+        assert_same(&info, &decoded);
+        assert_eq!(Format::parse(format.name()).unwrap(), *format);
+    }
+}
+
+#[test]
+fn binary_is_smaller() {
+    let tile = slot_tile(&mut RandomDataSource::default());
+    let json = Format::Json.encode(&tile).len();
+    let binary = Format::Binary(Compression::None).encode(&tile).len();
+    let zstd = Format::Binary(Compression::Zstd).encode(&tile).len();
+    assert!(binary < json, "{} vs {}", binary, json);
+    assert!(zstd < binary, "{} vs {}", zstd, binary);
+}
+
+#[test]
+fn other_version() {
+    let format = Format::Binary(Compression::None);
+    let mut bytes = format.encode(&TileID(Default::default()));
+    bytes[0] = VERSION + 1;
+    let error = format.decode::<TileID>(&bytes).unwrap_err();
+    assert!(error.message.contains("version"), "{}", error);
+
+    let error = format.decode::<TileID>(&[]).unwrap_err();
+    assert!(!error.message.is_empty());
+}